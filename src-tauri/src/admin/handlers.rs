@@ -0,0 +1,223 @@
+//! 管理 API 请求处理器
+//!
+//! 各处理器直接复用 Tauri 命令背后的实现，返回结构与桌面端一致
+
+use super::{schema, AdminState};
+use crate::commands::{self, CrossAppMcpSyncInput, CrossAppMcpSyncResult, McpServerItem, UnifiedProviderOutput};
+use crate::config::ConfigManager;
+use crate::database::schema::{UsageSummary, UsageTrend};
+use crate::error::AppError;
+use crate::pricing::billing::{self, ConvertCurrency};
+use crate::proxy::{ProxyService, ProxyStatus, ProxyTakeoverStatus};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+/// 管理 API 错误（以 {"error": "..."} 返回）
+pub struct ApiError(StatusCode, String);
+
+impl ApiError {
+    fn bad_request(message: impl Into<String>) -> Self {
+        Self(StatusCode::BAD_REQUEST, message.into())
+    }
+
+    fn not_found(message: impl Into<String>) -> Self {
+        Self(StatusCode::NOT_FOUND, message.into())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(json!({ "error": self.1 }))).into_response()
+    }
+}
+
+impl From<AppError> for ApiError {
+    fn from(e: AppError) -> Self {
+        Self(StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+    }
+}
+
+type ApiResult<T> = Result<Json<T>, ApiError>;
+
+/// 健康检查（无需认证）
+pub async fn health() -> Json<Value> {
+    Json(json!({
+        "status": "healthy",
+        "version": env!("CARGO_PKG_VERSION"),
+    }))
+}
+
+// ==================== Schema ====================
+
+/// 列出可用的 JSON Schema
+pub async fn list_schemas() -> Json<Vec<&'static str>> {
+    Json(schema::SCHEMA_NAMES.to_vec())
+}
+
+/// 获取指定资源的 JSON Schema
+pub async fn get_schema(Path(name): Path<String>) -> ApiResult<Value> {
+    schema::get(&name)
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found(format!("未知的 schema: {name}")))
+}
+
+// ==================== 服务商 ====================
+
+/// 列出统一服务商（API Key 已脱敏）
+pub async fn list_providers() -> ApiResult<Vec<UnifiedProviderOutput>> {
+    let providers = commands::get_open_switch_providers()
+        .await
+        .map_err(|e| ApiError(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    Ok(Json(providers))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApplyProviderBody {
+    pub app: String,
+}
+
+/// 将服务商应用到指定工具
+pub async fn apply_provider(
+    Path(id): Path<String>,
+    Json(body): Json<ApplyProviderBody>,
+) -> ApiResult<Value> {
+    if !matches!(body.app.as_str(), "opencode" | "claude" | "codex" | "gemini") {
+        return Err(ApiError::bad_request(format!("不支持的应用: {}", body.app)));
+    }
+    commands::apply_open_switch_provider(id.clone(), body.app.clone())
+        .await
+        .map_err(ApiError::bad_request)?;
+    Ok(Json(json!({ "id": id, "app": body.app, "applied": true })))
+}
+
+// ==================== MCP ====================
+
+/// 列出 MCP 服务器
+pub async fn list_mcp() -> ApiResult<Vec<McpServerItem>> {
+    let manager = ConfigManager::new().map_err(AppError::from)?;
+    Ok(Json(commands::list_mcp_servers(&manager)?))
+}
+
+/// 同步 MCP 服务器到各应用
+pub async fn sync_mcp(Json(input): Json<CrossAppMcpSyncInput>) -> ApiResult<Vec<CrossAppMcpSyncResult>> {
+    let manager = ConfigManager::new().map_err(AppError::from)?;
+    Ok(Json(commands::sync_mcp_to_apps_with(&manager, &input)?))
+}
+
+// ==================== 使用统计 ====================
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageQuery {
+    pub period: Option<String>,
+    pub provider_id: Option<String>,
+    /// 费用换算货币（默认 USD）
+    pub currency: Option<String>,
+    /// 项目路径筛选
+    pub project: Option<String>,
+}
+
+impl UsageQuery {
+    fn period(&self) -> Result<&str, ApiError> {
+        match self.period.as_deref().unwrap_or("7d") {
+            p @ ("24h" | "7d" | "30d" | "all") => Ok(p),
+            other => Err(ApiError::bad_request(format!(
+                "不支持的时间范围: {other}（可选: 24h, 7d, 30d, all）"
+            ))),
+        }
+    }
+}
+
+/// 使用量摘要
+pub async fn usage_summary(
+    State(state): State<AdminState>,
+    Query(query): Query<UsageQuery>,
+) -> ApiResult<UsageSummary> {
+    let (start, end) = commands::get_time_range(query.period()?);
+    let rate = billing::report_rate(&state.db, query.currency.as_deref())?;
+    let mut summary = state.db.get_usage_summary(start, end, query.project.as_deref())?;
+    summary.convert_currency(rate);
+    Ok(Json(summary))
+}
+
+/// 使用趋势
+pub async fn usage_trend(
+    State(state): State<AdminState>,
+    Query(query): Query<UsageQuery>,
+) -> ApiResult<Vec<UsageTrend>> {
+    let period = query.period()?;
+    let (start, end) = commands::get_time_range(period);
+    let rate = billing::report_rate(&state.db, query.currency.as_deref())?;
+    let mut trend = state
+        .db
+        .get_usage_trend(
+            start,
+            end,
+            period,
+            query.provider_id.as_deref(),
+            query.project.as_deref(),
+        )?;
+    trend.convert_currency(rate);
+    Ok(Json(trend))
+}
+
+// ==================== 代理 ====================
+
+/// 代理运行状态
+pub async fn proxy_status(State(state): State<AdminState>) -> Json<ProxyStatus> {
+    let guard = state.proxy.read().await;
+    match guard.as_ref() {
+        Some(service) => Json(service.get_status().await),
+        None => Json(ProxyStatus::default()),
+    }
+}
+
+/// 各应用的接管状态
+pub async fn takeover_status(State(state): State<AdminState>) -> ApiResult<ProxyTakeoverStatus> {
+    Ok(Json(takeover_from_db(&state)?))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetTakeoverBody {
+    pub enabled: bool,
+}
+
+/// 开启/关闭指定应用的接管
+pub async fn set_takeover(
+    State(state): State<AdminState>,
+    Path(app): Path<String>,
+    Json(body): Json<SetTakeoverBody>,
+) -> ApiResult<ProxyTakeoverStatus> {
+    if !matches!(app.as_str(), "claude" | "codex" | "gemini") {
+        return Err(ApiError::bad_request(format!("不支持接管的应用: {app}")));
+    }
+
+    // 桌面端尚未初始化代理服务时，按需创建（与 init_proxy_service 一致）
+    {
+        let mut guard = state.proxy.write().await;
+        if guard.is_none() {
+            *guard = Some(ProxyService::new(state.db.clone()));
+        }
+    }
+
+    let guard = state.proxy.read().await;
+    if let Some(service) = guard.as_ref() {
+        service.set_takeover_for_app(&app, body.enabled).await?;
+    }
+    Ok(Json(takeover_from_db(&state)?))
+}
+
+fn takeover_from_db(state: &AdminState) -> Result<ProxyTakeoverStatus, AppError> {
+    let config = state.db.get_proxy_config()?;
+    Ok(ProxyTakeoverStatus {
+        claude: config.takeover_claude,
+        codex: config.takeover_codex,
+        gemini: config.takeover_gemini,
+    })
+}
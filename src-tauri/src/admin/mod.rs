@@ -0,0 +1,114 @@
+//! 本地管理 API 模块
+//!
+//! 在独立端口上提供带 Token 认证的 REST API，镜像桌面端的核心命令
+//! （服务商、MCP、使用统计、代理状态与接管），供脚本和编辑器插件集成
+
+pub mod handlers;
+pub mod schema;
+pub mod server;
+
+pub use server::{AdminServer, AdminState};
+
+use crate::error::AppError;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// 管理 API 配置（保存在 ~/.ai-switch/admin-api.json）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdminApiConfig {
+    /// 是否随应用启动
+    #[serde(default)]
+    pub enabled: bool,
+    /// 监听地址
+    #[serde(default = "default_listen_address")]
+    pub listen_address: String,
+    /// 监听端口
+    #[serde(default = "default_listen_port")]
+    pub listen_port: u16,
+    /// 访问令牌（请求头 Authorization: Bearer <token>）
+    #[serde(default)]
+    pub token: String,
+}
+
+fn default_listen_address() -> String {
+    "127.0.0.1".to_string()
+}
+
+fn default_listen_port() -> u16 {
+    15722
+}
+
+impl Default for AdminApiConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen_address: default_listen_address(),
+            listen_port: default_listen_port(),
+            token: String::new(),
+        }
+    }
+}
+
+impl AdminApiConfig {
+    /// 配置文件路径
+    pub fn path() -> Result<PathBuf, AppError> {
+        let home = dirs::home_dir().ok_or_else(|| AppError::Custom("无法获取用户目录".to_string()))?;
+        Ok(home.join(".ai-switch").join("admin-api.json"))
+    }
+
+    /// 读取配置，令牌为空时自动生成并保存
+    pub fn load() -> Result<Self, AppError> {
+        let path = Self::path()?;
+        let mut config = if path.exists() {
+            let content = std::fs::read_to_string(&path)?;
+            serde_json::from_str(&content)
+                .map_err(|e| AppError::Custom(format!("解析管理 API 配置失败: {e}")))?
+        } else {
+            Self::default()
+        };
+
+        if config.token.is_empty() {
+            config.token = generate_token();
+            config.save()?;
+        }
+        Ok(config)
+    }
+
+    /// 保存配置（Unix 下以 0600 权限创建，避免令牌被其他用户读取）
+    pub fn save(&self) -> Result<(), AppError> {
+        use std::io::Write;
+
+        let path = Self::path()?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let content = serde_json::to_string_pretty(self)?;
+
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options.open(&path)?;
+        // 创建时的权限只对新文件生效，之前保存的文件在写入令牌前收紧
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+        }
+        file.write_all(content.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// 生成随机访问令牌
+pub fn generate_token() -> String {
+    format!(
+        "{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    )
+}
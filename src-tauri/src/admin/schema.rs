@@ -0,0 +1,293 @@
+//! 管理 API 的 JSON Schema（draft 2020-12）
+//!
+//! 字段与 Rust 结构体的序列化结果保持一致，由下方测试校验
+
+use serde_json::{json, Value};
+
+/// 可用的 schema 名称
+pub const SCHEMA_NAMES: &[&str] = &[
+    "Provider",
+    "ApplyProviderRequest",
+    "McpServer",
+    "McpSyncRequest",
+    "McpSyncResult",
+    "UsageSummary",
+    "UsageTrend",
+    "ProxyStatus",
+    "TakeoverStatus",
+    "SetTakeoverRequest",
+    "Error",
+];
+
+/// 获取指定名称的 schema
+pub fn get(name: &str) -> Option<Value> {
+    let schema = match name {
+        "Provider" => object(
+            "统一服务商（API Key 已脱敏）",
+            json!({
+                "id": string(),
+                "name": string(),
+                "base_url": string(),
+                "api_key_masked": string(),
+                "apps": {
+                    "type": "object",
+                    "properties": {
+                        "opencode": boolean(),
+                        "claude": boolean(),
+                        "codex": boolean(),
+                        "gemini": boolean()
+                    }
+                },
+                "models": { "type": "object", "description": "各应用的模型配置" },
+                "website_url": nullable("string"),
+                "notes": nullable("string"),
+                "icon": nullable("string"),
+                "icon_color": nullable("string"),
+                "created_at": nullable("integer"),
+                "sort_index": nullable("integer")
+            }),
+            &["id", "name", "base_url", "api_key_masked", "apps", "models"],
+        ),
+        "ApplyProviderRequest" => object(
+            "POST /api/v1/providers/{id}/apply 请求体",
+            json!({ "app": { "type": "string", "enum": ["opencode", "claude", "codex", "gemini"] } }),
+            &["app"],
+        ),
+        "McpServer" => object(
+            "MCP 服务器",
+            json!({
+                "name": string(),
+                "server_type": { "type": "string", "enum": ["local", "remote"] },
+                "enabled": boolean(),
+                "url": nullable("string"),
+                "command": { "type": ["array", "null"], "items": string() },
+                "install_path": string(),
+                "package_name": nullable("string")
+            }),
+            &["name", "server_type", "enabled", "install_path"],
+        ),
+        "McpSyncRequest" => object(
+            "POST /api/v1/mcp/sync 请求体（server_names 为空表示全部已启用的服务器）",
+            json!({
+                "server_names": { "type": "array", "items": string() },
+                "targets": {
+                    "type": "array",
+                    "items": { "type": "string", "enum": ["opencode", "claudecode", "codex", "gemini", "cursor"] }
+                }
+            }),
+            &["server_names", "targets"],
+        ),
+        "McpSyncResult" => object(
+            "单个目标的 MCP 同步结果",
+            json!({
+                "target": string(),
+                "success": boolean(),
+                "message": string(),
+                "synced_count": integer()
+            }),
+            &["target", "success", "message", "synced_count"],
+        ),
+        "UsageSummary" => object(
+            "使用量摘要（GET /api/v1/usage/summary?period=24h|7d|30d|all&currency=USD）",
+            json!({
+                "totalRequests": integer(),
+                "totalCost": { "type": "string", "description": "currency 指定的货币（默认 USD），十进制字符串" },
+                "totalInputTokens": integer(),
+                "totalOutputTokens": integer(),
+                "totalCacheCreationTokens": integer(),
+                "totalCacheReadTokens": integer(),
+                "measuredTokens": { "type": "integer", "description": "来自用量数据的 token" },
+                "estimatedTokens": { "type": "integer", "description": "按文本估算的 token（没有用量数据的本地日志）" },
+                "successRate": number()
+            }),
+            &[
+                "totalRequests",
+                "totalCost",
+                "totalInputTokens",
+                "totalOutputTokens",
+                "totalCacheCreationTokens",
+                "totalCacheReadTokens",
+                "successRate",
+            ],
+        ),
+        "UsageTrend" => object(
+            "使用趋势中的一个时间桶（GET /api/v1/usage/trend 返回数组）",
+            json!({
+                "period": string(),
+                "requestCount": integer(),
+                "totalCost": number(),
+                "inputTokens": integer(),
+                "outputTokens": integer(),
+                "topModel": nullable("string")
+            }),
+            &["period", "requestCount", "totalCost", "inputTokens", "outputTokens"],
+        ),
+        "ProxyStatus" => object(
+            "代理运行状态",
+            json!({
+                "running": boolean(),
+                "address": string(),
+                "port": integer(),
+                "totalRequests": integer(),
+                "successRequests": integer(),
+                "failedRequests": integer(),
+                "uptimeSeconds": integer(),
+                "pid": { "type": "integer", "description": "代理所在进程的 PID" }
+            }),
+            &["running", "address", "port"],
+        ),
+        "TakeoverStatus" => object(
+            "各应用的接管状态",
+            json!({ "claude": boolean(), "codex": boolean(), "gemini": boolean() }),
+            &["claude", "codex", "gemini"],
+        ),
+        "SetTakeoverRequest" => object(
+            "PUT /api/v1/proxy/takeover/{app} 请求体",
+            json!({ "enabled": boolean() }),
+            &["enabled"],
+        ),
+        "Error" => object("错误响应", json!({ "error": string() }), &["error"]),
+        _ => return None,
+    };
+
+    let mut schema = schema;
+    schema["$schema"] = json!("https://json-schema.org/draft/2020-12/schema");
+    schema["title"] = json!(name);
+    Some(schema)
+}
+
+fn object(description: &str, properties: Value, required: &[&str]) -> Value {
+    json!({
+        "type": "object",
+        "description": description,
+        "properties": properties,
+        "required": required,
+    })
+}
+
+fn string() -> Value {
+    json!({ "type": "string" })
+}
+
+fn boolean() -> Value {
+    json!({ "type": "boolean" })
+}
+
+fn integer() -> Value {
+    json!({ "type": "integer", "minimum": 0 })
+}
+
+fn number() -> Value {
+    json!({ "type": "number" })
+}
+
+fn nullable(ty: &str) -> Value {
+    json!({ "type": [ty, "null"] })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::{CrossAppMcpSyncResult, McpServerItem, UnifiedProviderOutput};
+    use crate::database::schema::{UsageSummary, UsageTrend};
+    use crate::proxy::{ProxyStatus, ProxyTakeoverStatus};
+    use serde::Serialize;
+    use std::collections::BTreeSet;
+
+    /// schema 声明的属性必须与序列化结果的字段完全一致
+    fn assert_matches<T: Serialize>(name: &str, value: &T) {
+        let schema = get(name).unwrap();
+        let declared: BTreeSet<String> = schema["properties"]
+            .as_object()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect();
+        let actual: BTreeSet<String> = serde_json::to_value(value)
+            .unwrap()
+            .as_object()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect();
+        assert_eq!(declared, actual, "schema {name} 与结构体字段不一致");
+    }
+
+    #[test]
+    fn schemas_match_serialized_types() {
+        assert_matches(
+            "Provider",
+            &UnifiedProviderOutput {
+                id: "p".into(),
+                name: "p".into(),
+                base_url: "https://example.com".into(),
+                api_key_masked: "****".into(),
+                apps: Default::default(),
+                models: Default::default(),
+                website_url: None,
+                notes: None,
+                icon: None,
+                icon_color: None,
+                created_at: None,
+                sort_index: None,
+            },
+        );
+        assert_matches(
+            "McpServer",
+            &McpServerItem {
+                name: "m".into(),
+                server_type: "local".into(),
+                enabled: true,
+                url: None,
+                command: None,
+                install_path: String::new(),
+                package_name: None,
+            },
+        );
+        assert_matches(
+            "McpSyncResult",
+            &CrossAppMcpSyncResult {
+                target: "OpenCode".into(),
+                success: true,
+                message: String::new(),
+                synced_count: 0,
+            },
+        );
+        assert_matches(
+            "UsageSummary",
+            &UsageSummary {
+                total_requests: 0,
+                total_cost: "0".into(),
+                total_input_tokens: 0,
+                total_output_tokens: 0,
+                total_cache_creation_tokens: 0,
+                total_cache_read_tokens: 0,
+                measured_tokens: 0,
+                estimated_tokens: 0,
+                success_rate: 0.0,
+            },
+        );
+        assert_matches(
+            "UsageTrend",
+            &UsageTrend {
+                period: "2026-01-01".into(),
+                request_count: 0,
+                total_cost: 0.0,
+                input_tokens: 0,
+                output_tokens: 0,
+                top_model: None,
+            },
+        );
+        assert_matches("ProxyStatus", &ProxyStatus::default());
+        assert_matches("TakeoverStatus", &ProxyTakeoverStatus::default());
+    }
+
+    #[test]
+    fn every_listed_schema_resolves() {
+        for name in SCHEMA_NAMES {
+            let schema = get(name).unwrap();
+            assert_eq!(schema["title"], json!(name));
+        }
+        assert!(get("Unknown").is_none());
+    }
+}
@@ -0,0 +1,204 @@
+//! 管理 API HTTP 服务器
+//!
+//! 基于 Axum，所有 /api/v1 路由（健康检查除外）均需 Bearer Token 认证
+
+use super::{handlers, AdminApiConfig};
+use crate::database::Database;
+use crate::error::AppError;
+use crate::proxy::ProxyService;
+use axum::{
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post, put},
+    Json, Router,
+};
+use serde_json::json;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::sync::{oneshot, RwLock};
+
+/// 管理 API 共享状态
+#[derive(Clone)]
+pub struct AdminState {
+    pub db: Arc<Database>,
+    /// 与桌面端共享的代理服务（未初始化时按需创建）
+    pub proxy: Arc<RwLock<Option<ProxyService>>>,
+    pub token: Arc<String>,
+}
+
+/// 管理 API 服务器
+pub struct AdminServer {
+    config: AdminApiConfig,
+    state: AdminState,
+    shutdown_tx: Arc<RwLock<Option<oneshot::Sender<()>>>>,
+}
+
+impl AdminServer {
+    /// 创建管理 API 服务器
+    pub fn new(
+        config: AdminApiConfig,
+        db: Arc<Database>,
+        proxy: Arc<RwLock<Option<ProxyService>>>,
+    ) -> Self {
+        let state = AdminState {
+            db,
+            proxy,
+            token: Arc::new(config.token.clone()),
+        };
+        Self {
+            config,
+            state,
+            shutdown_tx: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    /// 启动服务器，返回实际监听地址
+    pub async fn start(&self) -> Result<SocketAddr, AppError> {
+        if self.shutdown_tx.read().await.is_some() {
+            return Err(AppError::Custom("管理 API 已在运行".to_string()));
+        }
+        if self.config.token.is_empty() {
+            return Err(AppError::Custom("管理 API 令牌为空".to_string()));
+        }
+
+        let addr: SocketAddr = format!("{}:{}", self.config.listen_address, self.config.listen_port)
+            .parse()
+            .map_err(|e| AppError::Custom(format!("无效的地址: {e}")))?;
+
+        let listener = tokio::net::TcpListener::bind(&addr)
+            .await
+            .map_err(|e| AppError::Custom(format!("绑定端口失败: {e}")))?;
+        let local_addr = listener.local_addr()?;
+
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        *self.shutdown_tx.write().await = Some(shutdown_tx);
+
+        let app = build_router(self.state.clone());
+        tokio::spawn(async move {
+            axum::serve(listener, app)
+                .with_graceful_shutdown(async {
+                    shutdown_rx.await.ok();
+                })
+                .await
+                .ok();
+        });
+
+        Ok(local_addr)
+    }
+
+    /// 停止服务器
+    pub async fn stop(&self) {
+        if let Some(tx) = self.shutdown_tx.write().await.take() {
+            let _ = tx.send(());
+        }
+    }
+
+    /// 当前配置
+    pub fn config(&self) -> &AdminApiConfig {
+        &self.config
+    }
+}
+
+/// 构建路由
+pub fn build_router(state: AdminState) -> Router {
+    let protected = Router::new()
+        .route("/schemas", get(handlers::list_schemas))
+        .route("/schemas/:name", get(handlers::get_schema))
+        .route("/providers", get(handlers::list_providers))
+        .route("/providers/:id/apply", post(handlers::apply_provider))
+        .route("/mcp", get(handlers::list_mcp))
+        .route("/mcp/sync", post(handlers::sync_mcp))
+        .route("/usage/summary", get(handlers::usage_summary))
+        .route("/usage/trend", get(handlers::usage_trend))
+        .route("/proxy/status", get(handlers::proxy_status))
+        .route("/proxy/takeover", get(handlers::takeover_status))
+        .route("/proxy/takeover/:app", put(handlers::set_takeover))
+        .route_layer(middleware::from_fn_with_state(state.clone(), require_token));
+
+    Router::new()
+        .route("/api/v1/health", get(handlers::health))
+        .nest("/api/v1", protected)
+        .with_state(state)
+}
+
+/// Bearer Token 认证中间件
+async fn require_token(State(state): State<AdminState>, req: Request, next: Next) -> Response {
+    let provided = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::trim);
+
+    match provided {
+        Some(token) if constant_time_eq(token.as_bytes(), state.token.as_bytes()) => {
+            next.run(req).await
+        }
+        _ => (
+            StatusCode::UNAUTHORIZED,
+            Json(json!({ "error": "未授权：缺少或错误的访问令牌" })),
+        )
+            .into_response(),
+    }
+}
+
+/// 常量时间比较，避免通过响应时间推测令牌
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn start_server() -> (AdminServer, String) {
+        let config = AdminApiConfig {
+            enabled: true,
+            listen_address: "127.0.0.1".to_string(),
+            listen_port: 0,
+            token: "secret-token".to_string(),
+        };
+        let db = Arc::new(Database::memory().unwrap());
+        let server = AdminServer::new(config, db, Arc::new(RwLock::new(None)));
+        let addr = server.start().await.unwrap();
+        (server, format!("http://{addr}/api/v1/schemas"))
+    }
+
+    async fn status(url: &str, auth: Option<&str>) -> u16 {
+        let mut req = reqwest::Client::new().get(url);
+        if let Some(auth) = auth {
+            req = req.header(header::AUTHORIZATION, auth);
+        }
+        req.send().await.unwrap().status().as_u16()
+    }
+
+    #[tokio::test]
+    async fn rejects_missing_token() {
+        let (server, url) = start_server().await;
+        assert_eq!(status(&url, None).await, 401);
+        assert_eq!(status(&url, Some("secret-token")).await, 401);
+        server.stop().await;
+    }
+
+    #[tokio::test]
+    async fn rejects_wrong_token() {
+        let (server, url) = start_server().await;
+        assert_eq!(status(&url, Some("Bearer wrong-token")).await, 401);
+        assert_eq!(status(&url, Some("Bearer secret-token-x")).await, 401);
+        server.stop().await;
+    }
+
+    #[tokio::test]
+    async fn accepts_correct_token() {
+        let (server, url) = start_server().await;
+        assert_eq!(status(&url, Some("Bearer secret-token")).await, 200);
+        // 健康检查无需认证
+        assert_eq!(status(&url.replace("/schemas", "/health"), None).await, 200);
+        server.stop().await;
+    }
+}
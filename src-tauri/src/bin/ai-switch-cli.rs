@@ -0,0 +1,11 @@
+// ai-switch-cli 命令行入口
+// 桌面端主程序已占用 ai-switch 二进制名，CLI 以 ai-switch-cli 发布
+
+#[tokio::main]
+async fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Err(e) = ai_switch::cli::run(args).await {
+        eprintln!("错误: {e}");
+        std::process::exit(1);
+    }
+}
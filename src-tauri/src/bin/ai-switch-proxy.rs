@@ -0,0 +1,55 @@
+// ai-switch-proxy 无界面代理守护进程入口
+// 用法: ai-switch-proxy [--config <path>]
+
+use ai_switch::proxy::daemon::{self, DaemonConfig};
+use std::path::PathBuf;
+
+const USAGE: &str = "用法: ai-switch-proxy [--config <path>]
+
+选项:
+  -c, --config <path>  JSON 配置文件（默认 ~/.ai-switch/proxy-daemon.json）
+  -h, --help           显示帮助
+
+环境变量:
+  AI_SWITCH_PROXY_CONFIG    配置文件路径
+  AI_SWITCH_PROXY_ADDRESS   监听地址
+  AI_SWITCH_PROXY_PORT      监听端口
+  AI_SWITCH_PROXY_TAKEOVER  启动时接管的应用，逗号分隔（claude,codex,gemini）
+  AI_SWITCH_PROXY_RESTORE   退出时是否恢复接管的配置（默认 true）";
+
+#[tokio::main]
+async fn main() {
+    let mut config_path: Option<PathBuf> = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-c" | "--config" => match args.next() {
+                Some(path) => config_path = Some(PathBuf::from(path)),
+                None => exit_with_usage("--config 需要一个路径参数"),
+            },
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return;
+            }
+            other => exit_with_usage(&format!("未知参数: {other}")),
+        }
+    }
+
+    let config = match DaemonConfig::load(config_path.as_deref()) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("加载配置失败: {e}");
+            std::process::exit(1);
+        }
+    };
+
+    if let Err(e) = daemon::run(config).await {
+        eprintln!("代理守护进程异常退出: {e}");
+        std::process::exit(1);
+    }
+}
+
+fn exit_with_usage(message: &str) -> ! {
+    eprintln!("{message}\n\n{USAGE}");
+    std::process::exit(2);
+}
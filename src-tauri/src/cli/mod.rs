@@ -0,0 +1,1263 @@
+//! 命令行接口
+//!
+//! 将 `commands/` 中的核心操作暴露给终端，便于脚本化环境配置和通过 SSH 使用。
+//! 所有子命令直接复用 Tauri 命令背后的实现，与桌面端共享同一份配置和数据库。
+
+pub mod output;
+
+use crate::commands::{self, CrossAppMcpSyncInput, McpSyncTarget, LOG_SOURCES};
+use crate::config::open_switch_manager::OpenSwitchConfigManager;
+use crate::config::ConfigManager;
+use crate::database::Database;
+use crate::pricing::matching::MatchKind;
+use crate::pricing::billing;
+use crate::pricing::{self, ConvertCurrency, PricingImportOptions, ProviderBilling, RecalcFilter};
+use crate::proxy::daemon::{self, DaemonConfig, DaemonState};
+use crate::report::anomaly::AnomalyOptions;
+use crate::report::cache::CacheStats;
+use crate::report::productivity::ProductivityRow;
+use crate::report::render::render;
+use crate::report::sessions::SessionListOptions;
+use crate::report::{self, ReportFormat, ReportOptions};
+use output::{check_mark, format_count, print_json, print_pairs, print_table};
+use std::collections::HashMap;
+use std::path::Path;
+
+pub const USAGE: &str = "用法: ai-switch-cli [--json] <命令> [参数]
+
+服务商:
+  provider list [--app <app>]              列出统一服务商（app: opencode/claude/codex/gemini）
+  provider apply <id> --app <app>          将服务商应用到指定工具
+
+代理:
+  proxy start [--address <addr>] [--port <port>] [--takeover claude,codex,gemini] [--no-restore]
+                                           在前台启动代理（Ctrl+C 停止）
+  proxy stop                               停止由 proxy start / ai-switch-proxy 启动的代理
+  proxy status [--config <file>]           查看代理状态
+
+MCP / 规则 / 技能:
+  mcp list                                 列出 MCP 服务器
+  mcp sync [name...] [--targets <list>]    同步 MCP 到应用（opencode/claude/codex/gemini/cursor）
+  rule list                                列出规则及各应用部署状态
+  rule sync <name> --apps <list>           将规则同步到指定应用
+  skill list                               列出技能及各工具安装状态
+  skill sync <name> --tools <list>         将技能同步到指定工具
+
+日志与统计:
+  logs import [--sources <list>]           导入本地日志（默认全部来源）
+  usage summary [--period 24h|7d|30d|all] [--project <path>]
+                                           使用量摘要
+  usage trend [--period <p>] [--provider <id>] [--project <path>]
+                                           使用趋势
+  usage providers [--period <p>]           各服务商统计
+  usage projects [--period <p>]            各项目统计
+                                           以上命令均可加 --currency <code> 按汇率换算费用
+  usage export [--format csv|jsonl|md] [--output <file>] [--from <date>] [--to <date>]
+               [--app <app>] [--provider <id>] [--model <id>] [--source proxy|local|manual]
+               [--project <path>] [--period day|week|month] [--by provider|model|app|session|project]
+               [--currency <code>]
+                                           导出使用报表（未指定 --output 时输出到终端）
+  usage cache [筛选同 usage export] [--period <p>] [--by <dimension>]
+                                           缓存效率：命中率、缓存节省和缓存写入开销
+  usage productivity [筛选同 usage export] [--period <p>] [--by <dimension>]
+                                           生产力：每百行改动费用、每次编辑 token、工具调用构成、思考时间占比
+  usage forecast [--currency <code>]       预测本月月末费用
+  usage anomalies [--days <n>]             检测最近 n 天（默认 7）的费用 / 用量异常
+  usage sessions [--from <date>] [--to <date>] [--app <app>] [--project <path>] [--model <id>]
+                 [--search <text>] [--offset <n>] [--limit <n>] [--currency <code>]
+                                           按最后活动时间列出会话
+  usage session <id> [--currency <code>]   查看会话的请求时间线和工具调用
+
+定价:
+  pricing import <file> [--overwrite-manual]
+                                           从 LiteLLM 定价 JSON 或 CSV 导入/刷新模型定价
+  pricing unmatched                        列出使用记录中没有定价的模型
+  pricing matches                          列出使用记录中各模型的定价匹配方式
+  pricing bind <model> <model_id>          将使用记录中的模型名绑定到已有定价
+  pricing unbind <model>                   解除模型名绑定
+  pricing rate <currency> <rate>           设置汇率（1 USD 可兑换的该货币数量）
+  pricing billing <provider_id> [--currency <code>] [--multiplier <m>]
+                                           设置服务商的计费货币和倍率
+  pricing recalc [--from <date>] [--to <date>] [--provider <id>] [--model <id>] [--source <app_type>] [--include-unversioned-local] [--apply]
+                                           按当前定价重算历史成本（默认仅预览变更，--apply 写入）
+
+全局选项:
+  --json                                   以 JSON 输出
+  -h, --help                               显示帮助";
+
+/// 解析后的命令行参数
+#[derive(Debug, Default)]
+struct Args {
+    positional: Vec<String>,
+    options: HashMap<String, String>,
+    flags: Vec<String>,
+    json: bool,
+}
+
+/// 不带值的开关
+const SWITCHES: &[&str] = &["json", "no-restore", "overwrite-manual", "include-unversioned-local", "apply", "help"];
+
+impl Args {
+    fn parse(raw: Vec<String>) -> Result<Self, String> {
+        let mut args = Args::default();
+        let mut iter = raw.into_iter();
+        while let Some(arg) = iter.next() {
+            if arg == "-h" {
+                args.flags.push("help".to_string());
+            } else if let Some(name) = arg.strip_prefix("--") {
+                if let Some((key, value)) = name.split_once('=') {
+                    args.options.insert(key.to_string(), value.to_string());
+                } else if SWITCHES.contains(&name) {
+                    if name == "json" {
+                        args.json = true;
+                    } else {
+                        args.flags.push(name.to_string());
+                    }
+                } else {
+                    let value = iter.next().ok_or_else(|| format!("--{name} 需要一个值"))?;
+                    args.options.insert(name.to_string(), value);
+                }
+            } else {
+                args.positional.push(arg);
+            }
+        }
+        Ok(args)
+    }
+
+    fn option(&self, key: &str) -> Option<&str> {
+        self.options.get(key).map(|s| s.as_str())
+    }
+
+    fn flag(&self, key: &str) -> bool {
+        self.flags.iter().any(|f| f == key)
+    }
+
+    /// 逗号分隔的列表选项
+    fn list(&self, key: &str) -> Vec<String> {
+        self.option(key)
+            .map(|v| {
+                v.split(',')
+                    .map(|s| s.trim().to_lowercase())
+                    .filter(|s| !s.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn positional(&self, index: usize, name: &str) -> Result<&str, String> {
+        self.positional
+            .get(index)
+            .map(|s| s.as_str())
+            .ok_or_else(|| format!("缺少参数 <{name}>\n\n{USAGE}"))
+    }
+}
+
+/// 执行 CLI（参数不含程序名）
+pub async fn run(raw: Vec<String>) -> Result<(), String> {
+    let args = Args::parse(raw)?;
+    if args.flag("help") || args.positional.is_empty() {
+        println!("{USAGE}");
+        return Ok(());
+    }
+
+    let group = args.positional[0].as_str();
+    let action = args.positional.get(1).map(|s| s.as_str()).unwrap_or("");
+
+    match (group, action) {
+        ("provider", "list") => provider_list(&args).await,
+        ("provider", "apply") => provider_apply(&args).await,
+        ("proxy", "start") => proxy_start(&args).await,
+        ("proxy", "stop") => proxy_stop().await,
+        ("proxy", "status") => proxy_status(&args).await,
+        ("mcp", "list") => mcp_list(&args),
+        ("mcp", "sync") => mcp_sync(&args),
+        ("rule", "list") => rule_list(&args),
+        ("rule", "sync") => rule_sync(&args),
+        ("skill", "list") => skill_list(&args),
+        ("skill", "sync") => skill_sync(&args).await,
+        ("logs", "import") => logs_import(&args),
+        ("usage", "summary") => usage_summary(&args),
+        ("usage", "trend") => usage_trend(&args),
+        ("usage", "providers") => usage_providers(&args),
+        ("usage", "projects") => usage_projects(&args),
+        ("usage", "export") => usage_export(&args),
+        ("usage", "cache") => usage_cache(&args),
+        ("usage", "productivity") => usage_productivity(&args),
+        ("usage", "forecast") => usage_forecast(&args),
+        ("usage", "anomalies") => usage_anomalies(&args),
+        ("usage", "sessions") => usage_sessions(&args),
+        ("usage", "session") => usage_session(&args),
+        ("pricing", "import") => pricing_import(&args),
+        ("pricing", "unmatched") => pricing_unmatched(&args),
+        ("pricing", "matches") => pricing_matches(&args),
+        ("pricing", "bind") => pricing_bind(&args),
+        ("pricing", "unbind") => pricing_unbind(&args),
+        ("pricing", "rate") => pricing_rate(&args),
+        ("pricing", "billing") => pricing_billing(&args),
+        ("pricing", "recalc") => pricing_recalc(&args),
+        _ => Err(format!("未知命令: {}\n\n{USAGE}", args.positional.join(" "))),
+    }
+}
+
+// ==================== 服务商 ====================
+
+async fn provider_list(args: &Args) -> Result<(), String> {
+    let app = args.option("app");
+    let manager = OpenSwitchConfigManager::new()?;
+    let current = manager.read_config()?.current;
+
+    let providers: Vec<_> = commands::get_open_switch_providers()
+        .await?
+        .into_iter()
+        .filter(|p| match app {
+            Some("opencode") => p.apps.opencode,
+            Some("claude") => p.apps.claude,
+            Some("codex") => p.apps.codex,
+            Some("gemini") => p.apps.gemini,
+            _ => true,
+        })
+        .collect();
+
+    if args.json {
+        return print_json(&providers);
+    }
+
+    let is_current = |app: &Option<String>, id: &str| app.as_deref() == Some(id);
+    let rows: Vec<Vec<String>> = providers
+        .iter()
+        .map(|p| {
+            vec![
+                p.id.clone(),
+                p.name.clone(),
+                p.base_url.clone(),
+                app_cell(p.apps.opencode, is_current(&current.opencode, &p.id)),
+                app_cell(p.apps.claude, is_current(&current.claude, &p.id)),
+                app_cell(p.apps.codex, is_current(&current.codex, &p.id)),
+                app_cell(p.apps.gemini, is_current(&current.gemini, &p.id)),
+            ]
+        })
+        .collect();
+    print_table(
+        &["ID", "名称", "Base URL", "opencode", "claude", "codex", "gemini"],
+        &rows,
+    );
+    println!("\n✓ 已启用  * 当前使用");
+    Ok(())
+}
+
+fn app_cell(enabled: bool, current: bool) -> String {
+    match (enabled, current) {
+        (_, true) => "*".to_string(),
+        (enabled, false) => check_mark(enabled),
+    }
+}
+
+async fn provider_apply(args: &Args) -> Result<(), String> {
+    let id = args.positional(2, "id")?.to_string();
+    let app = args
+        .option("app")
+        .ok_or_else(|| "缺少 --app（opencode/claude/codex/gemini）".to_string())?
+        .to_string();
+
+    commands::apply_open_switch_provider(id.clone(), app.clone()).await?;
+
+    if args.json {
+        print_json(&serde_json::json!({ "id": id, "app": app, "applied": true }))
+    } else {
+        println!("已将服务商 {id} 应用到 {app}");
+        Ok(())
+    }
+}
+
+// ==================== 代理 ====================
+
+async fn proxy_start(args: &Args) -> Result<(), String> {
+    let mut config = DaemonConfig::load(args.option("config").map(std::path::Path::new))
+        .map_err(|e| e.to_string())?;
+
+    if let Some(address) = args.option("address") {
+        config.listen_address = Some(address.to_string());
+    }
+    if let Some(port) = args.option("port") {
+        config.listen_port = Some(port.parse().map_err(|e| format!("端口无效: {e}"))?);
+    }
+    if args.option("takeover").is_some() {
+        config.takeover = args.list("takeover");
+    }
+    if args.flag("no-restore") {
+        config.restore_on_exit = false;
+    }
+    config.validate().map_err(|e| e.to_string())?;
+
+    daemon::run(config).await.map_err(|e| e.to_string())
+}
+
+async fn proxy_stop() -> Result<(), String> {
+    let pid_path = DaemonConfig::pid_path().ok_or("无法获取用户目录")?;
+    if !pid_path.exists() {
+        return Err("未找到运行中的代理守护进程".to_string());
+    }
+    let state = DaemonState::read(&pid_path).ok_or("PID 文件无效")?;
+    let pid = state.pid;
+
+    // PID 可能已被其他进程复用：只有守护进程记录的监听地址仍由该 PID 提供服务时才发送信号
+    let listen = match (state.address.clone(), state.port) {
+        (Some(address), Some(port)) => (address, port),
+        _ => daemon_listen(None)?,
+    };
+    if probe_status(&listen).await.map(|s| s.pid) != Some(pid) {
+        let _ = std::fs::remove_file(&pid_path);
+        return Err(format!(
+            "PID 文件中的进程 {pid} 不是正在运行的代理守护进程（{}:{} 无响应或由其他进程监听），已清理过期的 PID 文件",
+            listen.0, listen.1
+        ));
+    }
+
+    #[cfg(unix)]
+    let status = std::process::Command::new("kill")
+        .args(["-TERM", &pid.to_string()])
+        .status();
+    // 守护进程是没有窗口的控制台进程，不带 /F 的 taskkill 无法结束它；强制结束后不会自动恢复被接管的配置
+    #[cfg(windows)]
+    let status = std::process::Command::new("taskkill")
+        .args(["/PID", &pid.to_string(), "/F"])
+        .status();
+
+    match status {
+        Ok(s) if s.success() => {
+            #[cfg(windows)]
+            let _ = std::fs::remove_file(&pid_path);
+            println!("已向代理进程 {pid} 发送停止信号");
+            Ok(())
+        }
+        Ok(s) => Err(format!("停止代理进程 {pid} 失败: {s}")),
+        Err(e) => Err(format!("停止代理进程 {pid} 失败: {e}")),
+    }
+}
+
+/// 守护进程会使用的监听地址（配置文件 / 环境变量覆盖数据库配置）
+fn daemon_listen(config_path: Option<&str>) -> Result<(String, u16), String> {
+    let db_config = open_db()?.get_proxy_config().map_err(|e| e.to_string())?;
+    let config = DaemonConfig::load(config_path.map(Path::new)).map_err(|e| e.to_string())?;
+    Ok(config.listen_on(&db_config))
+}
+
+/// 通过 /status 端点探测代理
+async fn probe_status((address, port): &(String, u16)) -> Option<crate::proxy::ProxyStatus> {
+    let url = format!("http://{address}:{port}/status");
+    let resp = reqwest::Client::new()
+        .get(&url)
+        .timeout(std::time::Duration::from_secs(2))
+        .send()
+        .await
+        .ok()?;
+    resp.json().await.ok()
+}
+
+async fn proxy_status(args: &Args) -> Result<(), String> {
+    let db = open_db()?;
+    let config = db.get_proxy_config().map_err(|e| e.to_string())?;
+    let state = DaemonConfig::pid_path().and_then(|p| DaemonState::read(&p));
+
+    // 优先使用守护进程记录的实际监听地址，其次是守护进程会使用的配置
+    let listen = match state.as_ref().and_then(|s| s.address.clone().zip(s.port)) {
+        Some(listen) => listen,
+        None => daemon_listen(args.option("config"))?,
+    };
+    let live = probe_status(&listen).await;
+    // PID 文件可能已过期，只展示确实在提供服务的守护进程
+    let pid = state
+        .map(|s| s.pid)
+        .filter(|pid| live.as_ref().is_some_and(|status| status.pid == *pid))
+        .map(|pid| pid.to_string());
+
+    if args.json {
+        return print_json(&serde_json::json!({
+            "running": live.as_ref().map(|s| s.running).unwrap_or(false),
+            "daemonPid": pid,
+            "listenAddress": listen.0,
+            "listenPort": listen.1,
+            "config": config,
+            "status": live,
+        }));
+    }
+
+    let mut pairs = vec![
+        (
+            "运行中",
+            if live.as_ref().map(|s| s.running).unwrap_or(false) { "是" } else { "否" }.to_string(),
+        ),
+        ("监听地址", format!("{}:{}", listen.0, listen.1)),
+        ("守护进程 PID", pid.unwrap_or_else(|| "-".to_string())),
+        (
+            "接管",
+            [
+                ("claude", config.takeover_claude),
+                ("codex", config.takeover_codex),
+                ("gemini", config.takeover_gemini),
+            ]
+            .iter()
+            .filter(|(_, on)| *on)
+            .map(|(app, _)| *app)
+            .collect::<Vec<_>>()
+            .join(", "),
+        ),
+    ];
+    if let Some(status) = live {
+        pairs.push(("运行时间", format!("{}s", status.uptime_seconds)));
+    }
+    print_pairs(&pairs);
+    Ok(())
+}
+
+// ==================== MCP / 规则 / 技能 ====================
+
+fn mcp_list(args: &Args) -> Result<(), String> {
+    let manager = ConfigManager::new().map_err(|e| e.to_string())?;
+    let config = manager.mcp().read_config().map_err(|e| e.to_string())?;
+    let mut servers: Vec<_> = config.servers.into_iter().collect();
+    servers.sort_by(|a, b| a.0.cmp(&b.0));
+
+    if args.json {
+        let map: serde_json::Map<String, serde_json::Value> = servers
+            .into_iter()
+            .map(|(name, server)| (name, serde_json::to_value(server).unwrap_or_default()))
+            .collect();
+        return print_json(&map);
+    }
+
+    let rows: Vec<Vec<String>> = servers
+        .iter()
+        .map(|(name, server)| {
+            let target = server
+                .url
+                .clone()
+                .or_else(|| server.command.as_ref().map(|c| c.join(" ")))
+                .unwrap_or_default();
+            vec![
+                name.clone(),
+                server.server_type.to_string(),
+                check_mark(server.enabled),
+                target,
+            ]
+        })
+        .collect();
+    print_table(&["名称", "类型", "启用", "命令 / URL"], &rows);
+    Ok(())
+}
+
+fn mcp_sync(args: &Args) -> Result<(), String> {
+    let targets = match args.list("targets") {
+        list if list.is_empty() => vec![
+            McpSyncTarget::OpenCode,
+            McpSyncTarget::ClaudeCode,
+            McpSyncTarget::Codex,
+            McpSyncTarget::Gemini,
+            McpSyncTarget::Cursor,
+        ],
+        list => list
+            .iter()
+            .map(|t| match t.as_str() {
+                "opencode" => Ok(McpSyncTarget::OpenCode),
+                "claude" | "claudecode" => Ok(McpSyncTarget::ClaudeCode),
+                "codex" => Ok(McpSyncTarget::Codex),
+                "gemini" => Ok(McpSyncTarget::Gemini),
+                "cursor" => Ok(McpSyncTarget::Cursor),
+                other => Err(format!("不支持的 MCP 同步目标: {other}")),
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+    };
+
+    let input = CrossAppMcpSyncInput {
+        server_names: args.positional.iter().skip(2).cloned().collect(),
+        targets,
+    };
+    let manager = ConfigManager::new().map_err(|e| e.to_string())?;
+    let results = commands::sync_mcp_to_apps_with(&manager, &input).map_err(|e| e.to_string())?;
+
+    if args.json {
+        return print_json(&results);
+    }
+    let rows: Vec<Vec<String>> = results
+        .iter()
+        .map(|r| {
+            vec![
+                r.target.clone(),
+                check_mark(r.success),
+                r.synced_count.to_string(),
+                r.message.clone(),
+            ]
+        })
+        .collect();
+    print_table(&["目标", "成功", "数量", "信息"], &rows);
+    Ok(())
+}
+
+fn rule_list(args: &Args) -> Result<(), String> {
+    let rules = commands::get_managed_rules();
+    if args.json {
+        return print_json(&rules);
+    }
+    let rows: Vec<Vec<String>> = rules
+        .iter()
+        .map(|r| {
+            vec![
+                r.name.clone(),
+                check_mark(r.opencode_enabled),
+                check_mark(r.claude_enabled),
+                check_mark(r.codex_enabled),
+                check_mark(r.gemini_enabled),
+                check_mark(r.cursor_enabled),
+                check_mark(r.windsurf_enabled),
+                check_mark(r.kiro_enabled),
+            ]
+        })
+        .collect();
+    print_table(
+        &["名称", "opencode", "claude", "codex", "gemini", "cursor", "windsurf", "kiro"],
+        &rows,
+    );
+    Ok(())
+}
+
+fn rule_sync(args: &Args) -> Result<(), String> {
+    let name = args.positional(2, "name")?;
+    let apps = args.list("apps");
+    if apps.is_empty() {
+        return Err("缺少 --apps".to_string());
+    }
+
+    let rule = commands::get_managed_rules()
+        .into_iter()
+        .find(|r| r.name == name)
+        .ok_or_else(|| format!("规则 {name} 不存在"))?;
+
+    for app in &apps {
+        commands::toggle_rule_app(rule.name.clone(), app.clone(), true, rule.content.clone())?;
+    }
+    report_synced(args, name, &apps)
+}
+
+fn skill_list(args: &Args) -> Result<(), String> {
+    let skills = commands::get_managed_skills().map_err(|e| e.to_string())?;
+    if args.json {
+        return print_json(&skills);
+    }
+    let rows: Vec<Vec<String>> = skills
+        .iter()
+        .map(|s| {
+            vec![
+                s.name.clone(),
+                check_mark(s.opencode_enabled),
+                check_mark(s.claude_enabled),
+                check_mark(s.codex_enabled),
+                check_mark(s.gemini_enabled),
+                check_mark(s.cursor_enabled),
+                check_mark(s.windsurf_enabled),
+                check_mark(s.kiro_enabled),
+            ]
+        })
+        .collect();
+    print_table(
+        &["名称", "opencode", "claude", "codex", "gemini", "cursor", "windsurf", "kiro"],
+        &rows,
+    );
+    Ok(())
+}
+
+async fn skill_sync(args: &Args) -> Result<(), String> {
+    let name = args.positional(2, "name")?;
+    let tools = args.list("tools");
+    if tools.is_empty() {
+        return Err("缺少 --tools".to_string());
+    }
+    for tool in &tools {
+        commands::toggle_skill_tool(name.to_string(), tool.clone(), true)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    report_synced(args, name, &tools)
+}
+
+fn report_synced(args: &Args, name: &str, targets: &[String]) -> Result<(), String> {
+    if args.json {
+        print_json(&serde_json::json!({ "name": name, "synced": targets }))
+    } else {
+        println!("已将 {name} 同步到: {}", targets.join(", "));
+        Ok(())
+    }
+}
+
+// ==================== 日志与统计 ====================
+
+fn logs_import(args: &Args) -> Result<(), String> {
+    let requested = args.list("sources");
+    let supported: Vec<&str> = LOG_SOURCES.iter().map(|source| source.id()).collect();
+    for source in &requested {
+        if !supported.contains(&source.as_str()) {
+            return Err(format!(
+                "不支持的日志来源: {source}（可选: {}）",
+                supported.join(", ")
+            ));
+        }
+    }
+    let sources: Vec<&str> = if requested.is_empty() {
+        supported
+    } else {
+        requested.iter().map(|s| s.as_str()).collect()
+    };
+
+    let db = open_db()?;
+    let imported = commands::import_local_sources(&db, &sources)?;
+
+    if args.json {
+        print_json(&serde_json::json!({ "sources": sources, "imported": imported }))
+    } else {
+        println!("已导入 {imported} 条新记录（来源: {}）", sources.join(", "));
+        Ok(())
+    }
+}
+
+fn usage_summary(args: &Args) -> Result<(), String> {
+    let period = period_arg(args)?;
+    let (start, end) = commands::get_time_range(period);
+    let db = open_db()?;
+    let (currency, rate) = currency_arg(&db, args)?;
+    let mut summary = db
+        .get_usage_summary(start, end, args.option("project"))
+        .map_err(|e| e.to_string())?;
+    summary.convert_currency(rate);
+
+    if args.json {
+        return print_json(&summary);
+    }
+    print_pairs(&[
+        ("时间范围", period.to_string()),
+        ("请求数", format_count(summary.total_requests)),
+        (&format!("总费用 ({currency})"), summary.total_cost.clone()),
+        ("输入 tokens", format_count(summary.total_input_tokens)),
+        ("输出 tokens", format_count(summary.total_output_tokens)),
+        ("缓存写入 tokens", format_count(summary.total_cache_creation_tokens)),
+        ("缓存读取 tokens", format_count(summary.total_cache_read_tokens)),
+        ("其中估算 tokens", format_count(summary.estimated_tokens)),
+        ("成功率", format!("{:.1}%", summary.success_rate)),
+    ]);
+    Ok(())
+}
+
+fn usage_trend(args: &Args) -> Result<(), String> {
+    let period = period_arg(args)?;
+    let (start, end) = commands::get_time_range(period);
+    let db = open_db()?;
+    let (currency, rate) = currency_arg(&db, args)?;
+    let mut trend = db
+        .get_usage_trend(start, end, period, args.option("provider"), args.option("project"))
+        .map_err(|e| e.to_string())?;
+    trend.convert_currency(rate);
+
+    if args.json {
+        return print_json(&trend);
+    }
+    let rows: Vec<Vec<String>> = trend
+        .iter()
+        .map(|t| {
+            vec![
+                t.period.clone(),
+                format_count(t.request_count),
+                format_count(t.input_tokens),
+                format_count(t.output_tokens),
+                format!("{:.4}", t.total_cost),
+                t.top_model.clone().unwrap_or_default(),
+            ]
+        })
+        .collect();
+    let cost_header = format!("费用 ({currency})");
+    print_table(&["时间", "请求", "输入", "输出", &cost_header, "主要模型"], &rows);
+    Ok(())
+}
+
+fn usage_providers(args: &Args) -> Result<(), String> {
+    let period = period_arg(args)?;
+    let (start, end) = commands::get_time_range(period);
+    let db = open_db()?;
+    let (currency, rate) = currency_arg(&db, args)?;
+    let mut stats = db.get_provider_stats(start, end).map_err(|e| e.to_string())?;
+    stats.convert_currency(rate);
+
+    if args.json {
+        return print_json(&stats);
+    }
+    let rows: Vec<Vec<String>> = stats
+        .iter()
+        .map(|s| {
+            vec![
+                s.provider_name.clone(),
+                format_count(s.request_count),
+                format_count(s.total_tokens),
+                s.total_cost.clone(),
+                format!("{:.1}%", s.success_rate),
+            ]
+        })
+        .collect();
+    let cost_header = format!("费用 ({currency})");
+    print_table(&["服务商", "请求", "tokens", &cost_header, "成功率"], &rows);
+    Ok(())
+}
+
+fn usage_projects(args: &Args) -> Result<(), String> {
+    let period = period_arg(args)?;
+    let (start, end) = commands::get_time_range(period);
+    let db = open_db()?;
+    let (currency, rate) = currency_arg(&db, args)?;
+    let mut stats = db.get_project_stats(start, end).map_err(|e| e.to_string())?;
+    stats.convert_currency(rate);
+
+    if args.json {
+        return print_json(&stats);
+    }
+    let rows: Vec<Vec<String>> = stats
+        .iter()
+        .map(|s| {
+            vec![
+                s.project_name.clone(),
+                format_count(s.request_count),
+                format_count(s.total_tokens),
+                s.total_cost.clone(),
+                s.project.clone().unwrap_or_default(),
+            ]
+        })
+        .collect();
+    let cost_header = format!("费用 ({currency})");
+    print_table(&["项目", "请求", "tokens", &cost_header, "路径"], &rows);
+    Ok(())
+}
+
+fn usage_export(args: &Args) -> Result<(), String> {
+    let format = args
+        .option("format")
+        .unwrap_or("csv")
+        .parse::<ReportFormat>()
+        .map_err(|e| e.to_string())?;
+    let options = report_options(args)?;
+    let db = open_db()?;
+
+    match args.option("output") {
+        Some(path) => {
+            let result = report::export(&db, &options, format, Path::new(path)).map_err(|e| e.to_string())?;
+            if args.json {
+                return print_json(&result);
+            }
+            println!("已导出 {} 行到 {}", result.rows, result.path);
+        }
+        None => {
+            let report = report::build(&db, &options).map_err(|e| e.to_string())?;
+            print!("{}", render(&report, format).map_err(|e| e.to_string())?);
+        }
+    }
+    Ok(())
+}
+
+fn usage_cache(args: &Args) -> Result<(), String> {
+    let options = report_options(args)?;
+    let report = report::cache::analyze(&open_db()?, &options).map_err(|e| e.to_string())?;
+    if args.json {
+        return print_json(&report);
+    }
+
+    let currency = &report.currency;
+    let cells = |label: String, row: &CacheStats| {
+        vec![
+            label,
+            format_count(row.request_count),
+            format_count(row.cache_read_tokens),
+            format_count(row.cache_creation_tokens),
+            format!("{:.1}%", row.hit_ratio * 100.0),
+            format!("{:.4}", row.cache_savings),
+            format!("{:.4}", row.write_overhead),
+            format!("{:.4}", row.net_savings),
+        ]
+    };
+    let mut rows: Vec<Vec<String>> = report
+        .rows
+        .iter()
+        .filter(|row| row.period.is_some() || row.group.is_some())
+        .map(|row| {
+            let label = [row.period.as_deref(), Some(row.group.as_deref().unwrap_or("-"))]
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>()
+                .join(" ");
+            cells(label, row)
+        })
+        .collect();
+    rows.push(cells("合计".to_string(), &report.totals));
+    let headers = [
+        "分组".to_string(),
+        "请求".to_string(),
+        "缓存读取".to_string(),
+        "缓存写入".to_string(),
+        "命中率".to_string(),
+        format!("节省 ({currency})"),
+        format!("写入开销 ({currency})"),
+        format!("净节省 ({currency})"),
+    ];
+    print_table(&headers.iter().map(String::as_str).collect::<Vec<_>>(), &rows);
+    Ok(())
+}
+
+fn usage_productivity(args: &Args) -> Result<(), String> {
+    let options = report_options(args)?;
+    let report = report::productivity::analyze(&open_db()?, &options).map_err(|e| e.to_string())?;
+    if args.json {
+        return print_json(&report);
+    }
+
+    let ratio = |value: Option<f64>, digits: usize| value.map_or_else(|| "-".to_string(), |v| format!("{v:.digits$}"));
+    let cells = |label: String, row: &ProductivityRow| {
+        let tools = row
+            .tool_mix
+            .iter()
+            .take(3)
+            .map(|t| format!("{} {:.0}%", t.tool_name, t.share * 100.0))
+            .collect::<Vec<_>>()
+            .join(", ");
+        vec![
+            label,
+            format_count(row.session_count),
+            format!("{:.4}", row.cost),
+            format!("+{} -{}", row.lines_added, row.lines_deleted),
+            ratio(row.cost_per_100_lines, 4),
+            format_count(row.edit_count),
+            ratio(row.tokens_per_edit, 0),
+            ratio(row.thinking_ratio.map(|r| r * 100.0), 1),
+            tools,
+        ]
+    };
+    let mut rows: Vec<Vec<String>> = report
+        .rows
+        .iter()
+        .filter(|row| row.period.is_some() || row.group.is_some())
+        .map(|row| {
+            let label = [row.period.as_deref(), Some(row.group.as_deref().unwrap_or("-"))]
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>()
+                .join(" ");
+            cells(label, row)
+        })
+        .collect();
+    rows.push(cells("合计".to_string(), &report.totals));
+    let currency = &report.currency;
+    let headers = [
+        "分组".to_string(),
+        "会话".to_string(),
+        format!("费用 ({currency})"),
+        "改动行".to_string(),
+        format!("每百行费用 ({currency})"),
+        "编辑".to_string(),
+        "每次编辑 token".to_string(),
+        "思考占比 %".to_string(),
+        "主要工具".to_string(),
+    ];
+    print_table(&headers.iter().map(String::as_str).collect::<Vec<_>>(), &rows);
+    Ok(())
+}
+
+fn usage_forecast(args: &Args) -> Result<(), String> {
+    let forecast = report::forecast::build(&open_db()?, args.option("currency"), chrono::Utc::now())
+        .map_err(|e| e.to_string())?;
+    if args.json {
+        return print_json(&forecast);
+    }
+    let currency = &forecast.currency;
+    print_pairs(&[
+        ("月份", forecast.month.clone()),
+        ("已过天数", format!("{:.1} / {}", forecast.days_elapsed, forecast.days_in_month)),
+        (&format!("本月已用 ({currency})"), format!("{:.2}", forecast.month_to_date)),
+        (&format!("日均 ({currency})"), format!("{:.2}", forecast.daily_average)),
+        (&format!("预计月末 ({currency})"), format!("{:.2}", forecast.projected_total)),
+        (
+            &format!("预测区间 ({currency})"),
+            format!("{:.2} ~ {:.2}", forecast.projected_low, forecast.projected_high),
+        ),
+    ]);
+    Ok(())
+}
+
+fn usage_anomalies(args: &Args) -> Result<(), String> {
+    let mut options = AnomalyOptions::default();
+    if let Some(days) = args.option("days") {
+        options.days = days.parse().map_err(|e| format!("天数无效: {e}"))?;
+    }
+    let anomalies = report::anomaly::detect(&open_db()?, &options, chrono::Utc::now()).map_err(|e| e.to_string())?;
+    if args.json {
+        return print_json(&anomalies);
+    }
+    if anomalies.is_empty() {
+        println!("最近 {} 天没有发现异常", options.days);
+        return Ok(());
+    }
+    for anomaly in &anomalies {
+        println!("{}", anomaly.message);
+    }
+    Ok(())
+}
+
+fn usage_sessions(args: &Args) -> Result<(), String> {
+    let number = |key: &str| -> Result<Option<u32>, String> {
+        args.option(key)
+            .map(|v| v.parse().map_err(|e| format!("--{key} 的取值无效: {e}")))
+            .transpose()
+    };
+    let options = SessionListOptions {
+        start_time: args.option("from").map(|v| parse_date(v, false)).transpose()?,
+        end_time: args.option("to").map(|v| parse_date(v, true)).transpose()?,
+        app: args.option("app").map(str::to_string),
+        project: args.option("project").map(str::to_string),
+        model: args.option("model").map(str::to_string),
+        search: args.option("search").map(str::to_string),
+        offset: number("offset")?,
+        limit: number("limit")?,
+        currency: args.option("currency").map(str::to_string),
+    };
+    let page = report::sessions::list(&open_db()?, &options).map_err(|e| e.to_string())?;
+    if args.json {
+        return print_json(&page);
+    }
+
+    let rows: Vec<Vec<String>> = page
+        .sessions
+        .iter()
+        .map(|s| {
+            vec![
+                s.session_id.clone(),
+                s.app_type.clone(),
+                s.project.clone().unwrap_or_else(|| "-".to_string()),
+                format_time(s.started_at),
+                format_time(s.ended_at),
+                format_count(s.request_count),
+                format_count(s.input_tokens + s.output_tokens + s.cache_read_tokens + s.cache_creation_tokens),
+                format!("{:.4}", s.cost),
+                s.models.iter().map(|m| m.model.as_str()).collect::<Vec<_>>().join(", "),
+                format_count(s.tool_call_count),
+                format!("+{} -{}", s.lines_added, s.lines_deleted),
+            ]
+        })
+        .collect();
+    let cost_header = format!("费用 ({})", page.currency);
+    print_table(
+        &["会话", "应用", "项目", "开始", "结束", "请求", "Token", &cost_header, "模型", "工具调用", "改动行"],
+        &rows,
+    );
+    let shown = page.offset as u64 + page.sessions.len() as u64;
+    println!("\n第 {}-{} 个，共 {} 个会话", (page.offset as u64 + 1).min(shown), shown, page.total);
+    Ok(())
+}
+
+fn usage_session(args: &Args) -> Result<(), String> {
+    let session_id = args.positional(2, "id")?;
+    let timeline = report::sessions::timeline(&open_db()?, session_id, args.option("currency"))
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("会话不存在: {session_id}"))?;
+    if args.json {
+        return print_json(&timeline);
+    }
+
+    let currency = &timeline.currency;
+    let session = &timeline.session;
+    print_pairs(&[
+        ("会话", session.session_id.clone()),
+        ("应用", session.app_type.clone()),
+        ("项目", session.project.clone().unwrap_or_else(|| "-".to_string())),
+        ("时间", format!("{} ~ {}", format_time(session.started_at), format_time(session.ended_at))),
+        ("请求", format_count(session.request_count)),
+        (&format!("费用 ({currency})"), format!("{:.4}", session.cost)),
+        ("改动", format!("{} 个文件，+{} -{}", session.files_changed, session.lines_added, session.lines_deleted)),
+    ]);
+
+    println!();
+    let rows: Vec<Vec<String>> = timeline
+        .requests
+        .iter()
+        .map(|r| {
+            vec![
+                format_time(r.created_at),
+                r.model.clone(),
+                format_count(r.input_tokens),
+                format_count(r.output_tokens),
+                format_count(r.cache_read_tokens),
+                format_count(r.cache_creation_tokens),
+                format!("{:.4}", r.cost),
+            ]
+        })
+        .collect();
+    let cost_header = format!("费用 ({currency})");
+    print_table(&["时间", "模型", "输入", "输出", "缓存读取", "缓存写入", &cost_header], &rows);
+
+    if !timeline.tool_calls.is_empty() {
+        println!();
+        let rows: Vec<Vec<String>> = timeline
+            .tool_calls
+            .iter()
+            .map(|t| vec![t.tool_name.clone(), format_count(t.call_count)])
+            .collect();
+        print_table(&["工具", "调用次数"], &rows);
+    }
+    Ok(())
+}
+
+/// 本地时间，精确到分钟
+fn format_time(ts: i64) -> String {
+    chrono::DateTime::from_timestamp(ts, 0)
+        .map(|dt| dt.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default()
+}
+
+/// 解析报表筛选与分组选项（usage export / usage cache 共用）
+fn report_options(args: &Args) -> Result<ReportOptions, String> {
+    Ok(ReportOptions {
+        start_time: args.option("from").map(|v| parse_date(v, false)).transpose()?,
+        end_time: args.option("to").map(|v| parse_date(v, true)).transpose()?,
+        app: args.option("app").map(str::to_string),
+        provider_id: args.option("provider").map(str::to_string),
+        model: args.option("model").map(str::to_string),
+        source: args.option("source").map(|v| enum_arg("source", v)).transpose()?,
+        project: args.option("project").map(str::to_string),
+        period: args.option("period").map(|v| enum_arg("period", v)).transpose()?,
+        group_by: args.option("by").map(|v| enum_arg("by", v)).transpose()?,
+        currency: args.option("currency").map(str::to_string),
+    })
+}
+
+/// 解析枚举选项（取值与 serde 名称一致）
+fn enum_arg<T: serde::de::DeserializeOwned>(name: &str, value: &str) -> Result<T, String> {
+    serde_json::from_value(serde_json::Value::String(value.to_lowercase()))
+        .map_err(|_| format!("--{name} 的取值无效: {value}"))
+}
+
+// ==================== 定价 ====================
+
+fn pricing_import(args: &Args) -> Result<(), String> {
+    let path = args.positional(2, "file")?;
+    let options = PricingImportOptions {
+        overwrite_manual: args.flag("overwrite-manual"),
+    };
+    let report = pricing::catalog::import_file(&open_db()?, Path::new(path), &options)
+        .map_err(|e| e.to_string())?;
+
+    if args.json {
+        return print_json(&report);
+    }
+    print_pairs(&[
+        ("有效模型", format_count(report.total as u64)),
+        ("新增", format_count(report.inserted as u64)),
+        ("更新", format_count(report.updated as u64)),
+        ("未变化", format_count(report.unchanged as u64)),
+        ("保留手动定价", format_count(report.skipped_manual as u64)),
+        ("无法解析", format_count(report.skipped_invalid as u64)),
+    ]);
+    print_unmatched(&report.unmatched_models);
+    Ok(())
+}
+
+fn pricing_unmatched(args: &Args) -> Result<(), String> {
+    let models = pricing::matching::unmatched_models(&open_db()?).map_err(|e| e.to_string())?;
+    if args.json {
+        return print_json(&models);
+    }
+    print_unmatched(&models);
+    Ok(())
+}
+
+fn pricing_matches(args: &Args) -> Result<(), String> {
+    let matches = pricing::matching::usage_model_matches(&open_db()?).map_err(|e| e.to_string())?;
+    if args.json {
+        return print_json(&matches);
+    }
+    let rows: Vec<Vec<String>> = matches
+        .iter()
+        .map(|m| {
+            let kind = match m.match_kind {
+                Some(MatchKind::Exact) => "精确",
+                Some(MatchKind::Alias) => "别名",
+                Some(MatchKind::Fuzzy) => "模糊",
+                None => "无定价",
+            };
+            vec![
+                m.model.clone(),
+                format_count(m.request_count),
+                format_count(m.total_tokens),
+                kind.to_string(),
+                m.matched_model_id.clone().unwrap_or_default(),
+            ]
+        })
+        .collect();
+    print_table(&["模型", "请求", "tokens", "匹配", "定价模型"], &rows);
+    Ok(())
+}
+
+fn pricing_bind(args: &Args) -> Result<(), String> {
+    let model = args.positional(2, "model")?;
+    let model_id = args.positional(3, "model_id")?;
+    pricing::matching::bind_alias(&open_db()?, model, model_id).map_err(|e| e.to_string())?;
+    println!("已将 {model} 绑定到 {model_id} 的定价（可用 pricing recalc 重算历史费用）");
+    Ok(())
+}
+
+fn pricing_unbind(args: &Args) -> Result<(), String> {
+    let model = args.positional(2, "model")?;
+    pricing::matching::unbind_alias(&open_db()?, model).map_err(|e| e.to_string())?;
+    println!("已解除 {model} 的定价绑定");
+    Ok(())
+}
+
+fn pricing_recalc(args: &Args) -> Result<(), String> {
+    let filter = RecalcFilter {
+        start_time: args.option("from").map(|v| parse_date(v, false)).transpose()?,
+        end_time: args.option("to").map(|v| parse_date(v, true)).transpose()?,
+        provider_id: args.option("provider").map(str::to_string),
+        model: args.option("model").map(str::to_string),
+        source: args.option("source").map(str::to_string),
+        include_unversioned_local: args.flag("include-unversioned-local"),
+    };
+    let db = open_db()?;
+    let report = if args.flag("apply") {
+        pricing::recalc::apply(&db, &filter)
+    } else {
+        pricing::recalc::preview(&db, &filter)
+    }
+    .map_err(|e| e.to_string())?;
+
+    if args.json {
+        return print_json(&report);
+    }
+    let rows: Vec<Vec<String>> = report
+        .by_model
+        .iter()
+        .filter(|m| m.changed > 0)
+        .map(|m| {
+            vec![
+                m.model.clone(),
+                format_count(m.records as u64),
+                format_count(m.changed as u64),
+                m.old_cost.clone(),
+                m.new_cost.clone(),
+            ]
+        })
+        .collect();
+    if !rows.is_empty() {
+        print_table(&["模型", "记录", "变化", "原费用", "新费用"], &rows);
+        println!();
+    }
+    print_pairs(&[
+        ("定价版本", report.pricing_version.to_string()),
+        ("范围内记录", format_count(report.matched as u64)),
+        ("费用变化", format_count(report.changed as u64)),
+        ("日志自带费用（跳过）", format_count(report.skipped_source_cost as u64)),
+        ("旧版本地日志记录（跳过）", format_count(report.skipped_unversioned_local as u64)),
+        ("无定价（保留原值）", format_count(report.unpriced as u64)),
+        ("原总费用", report.old_total.clone()),
+        ("新总费用", report.new_total.clone()),
+    ]);
+    if report.applied {
+        println!("\n已写入 {} 条记录的新费用", report.changed);
+    } else if report.changed > 0 {
+        println!("\n以上为预览，确认后加 --apply 写入");
+    }
+    Ok(())
+}
+
+/// 解析日期参数（YYYY-MM-DD 按 UTC 当天起止，或 Unix 秒）
+fn parse_date(value: &str, end_of_day: bool) -> Result<i64, String> {
+    if let Ok(ts) = value.parse::<i64>() {
+        return Ok(ts);
+    }
+    let date = chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| format!("日期格式无效（应为 YYYY-MM-DD 或 Unix 秒）: {value}"))?;
+    let time = if end_of_day {
+        date.and_hms_opt(23, 59, 59)
+    } else {
+        date.and_hms_opt(0, 0, 0)
+    };
+    Ok(time.map(|t| t.and_utc().timestamp()).unwrap_or_default())
+}
+
+fn print_unmatched(models: &[String]) {
+    if models.is_empty() {
+        println!("\n所有已使用的模型均有定价");
+    } else {
+        println!("\n以下 {} 个模型没有定价（费用按 0 计算，可用 pricing bind 绑定到已有定价）:", models.len());
+        for model in models {
+            println!("  {model}");
+        }
+    }
+}
+
+fn pricing_rate(args: &Args) -> Result<(), String> {
+    let currency = args.positional(2, "currency")?;
+    let rate = args.positional(3, "rate")?;
+    billing::save_exchange_rate(&open_db()?, currency, rate).map_err(|e| e.to_string())?;
+    println!("已设置汇率: 1 USD = {rate} {}", currency.to_ascii_uppercase());
+    Ok(())
+}
+
+fn pricing_billing(args: &Args) -> Result<(), String> {
+    let provider_id = args.positional(2, "provider_id")?;
+    let settings = ProviderBilling {
+        provider_id: provider_id.to_string(),
+        currency: args.option("currency").unwrap_or(billing::BASE_CURRENCY).to_string(),
+        cost_multiplier: args.option("multiplier").unwrap_or("1").to_string(),
+        updated_at: 0,
+    };
+    billing::save_provider_billing(&open_db()?, &settings).map_err(|e| e.to_string())?;
+    println!(
+        "已设置 {provider_id} 按 {} 计费，倍率 {}",
+        settings.currency.to_ascii_uppercase(),
+        settings.cost_multiplier
+    );
+    Ok(())
+}
+
+/// 解析 --currency，返回 (货币代码, 相对 USD 的汇率)
+fn currency_arg(db: &Database, args: &Args) -> Result<(String, f64), String> {
+    let currency = args.option("currency").unwrap_or(billing::BASE_CURRENCY);
+    let rate = billing::report_rate(db, Some(currency)).map_err(|e| e.to_string())?;
+    Ok((currency.to_ascii_uppercase(), rate))
+}
+
+fn period_arg(args: &Args) -> Result<&str, String> {
+    match args.option("period").unwrap_or("7d") {
+        p @ ("24h" | "7d" | "30d" | "all") => Ok(p),
+        other => Err(format!("不支持的时间范围: {other}（可选: 24h, 7d, 30d, all）")),
+    }
+}
+
+fn open_db() -> Result<Database, String> {
+    Database::open().map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Args {
+        Args::parse(args.iter().map(|s| s.to_string()).collect()).unwrap()
+    }
+
+    #[test]
+    fn parses_options_flags_and_positionals() {
+        let args = parse(&["--json", "provider", "apply", "abc", "--app=claude"]);
+        assert!(args.json);
+        assert_eq!(args.positional, vec!["provider", "apply", "abc"]);
+        assert_eq!(args.option("app"), Some("claude"));
+
+        let args = parse(&["proxy", "start", "--takeover", "Claude, codex", "--no-restore"]);
+        assert_eq!(args.list("takeover"), vec!["claude", "codex"]);
+        assert!(args.flag("no-restore"));
+    }
+
+    #[test]
+    fn missing_option_value_is_error() {
+        assert!(Args::parse(vec!["usage".into(), "summary".into(), "--period".into()]).is_err());
+    }
+}
@@ -0,0 +1,76 @@
+//! CLI 输出格式化（表格 / JSON）
+
+use serde::Serialize;
+
+/// 以格式化 JSON 输出
+pub fn print_json<T: Serialize + ?Sized>(value: &T) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(value).map_err(|e| format!("序列化输出失败: {e}"))?;
+    println!("{json}");
+    Ok(())
+}
+
+/// 以对齐表格输出
+pub fn print_table(headers: &[&str], rows: &[Vec<String>]) {
+    let mut widths: Vec<usize> = headers.iter().map(|h| display_width(h)).collect();
+    for row in rows {
+        for (i, cell) in row.iter().enumerate().take(widths.len()) {
+            widths[i] = widths[i].max(display_width(cell));
+        }
+    }
+
+    let header: Vec<String> = headers.iter().map(|h| h.to_string()).collect();
+    println!("{}", format_row(&header, &widths));
+    let separator: Vec<String> = widths.iter().map(|w| "-".repeat(*w)).collect();
+    println!("{}", separator.join("  "));
+    for row in rows {
+        println!("{}", format_row(row, &widths));
+    }
+    if rows.is_empty() {
+        println!("（无数据）");
+    }
+}
+
+/// 以「键: 值」形式输出单条记录
+pub fn print_pairs(pairs: &[(&str, String)]) {
+    let width = pairs.iter().map(|(k, _)| display_width(k)).max().unwrap_or(0);
+    for (key, value) in pairs {
+        println!("{}{}  {}", key, " ".repeat(width - display_width(key)), value);
+    }
+}
+
+fn format_row(cells: &[String], widths: &[usize]) -> String {
+    let mut line = String::new();
+    for (i, width) in widths.iter().enumerate() {
+        let cell = cells.get(i).map(|s| s.as_str()).unwrap_or("");
+        if i > 0 {
+            line.push_str("  ");
+        }
+        line.push_str(cell);
+        if i + 1 < widths.len() {
+            line.push_str(&" ".repeat(width.saturating_sub(display_width(cell))));
+        }
+    }
+    line
+}
+
+/// 终端显示宽度（CJK 等全角字符按 2 列计算）
+fn display_width(s: &str) -> usize {
+    s.chars().map(|c| if c.is_ascii() { 1 } else { 2 }).sum()
+}
+
+/// 千位分隔的整数
+pub fn format_count(n: u64) -> String {
+    let digits = n.to_string();
+    digits
+        .as_bytes()
+        .rchunks(3)
+        .rev()
+        .map(|chunk| String::from_utf8_lossy(chunk).into_owned())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// 布尔值显示为勾选标记
+pub fn check_mark(enabled: bool) -> String {
+    if enabled { "✓" } else { "-" }.to_string()
+}
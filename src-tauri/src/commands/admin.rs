@@ -0,0 +1,103 @@
+//! 管理 API 相关命令
+
+use super::ProxyServiceState;
+use crate::admin::{generate_token, AdminApiConfig, AdminServer};
+use crate::database::Database;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tauri::State;
+use tokio::sync::RwLock;
+
+/// 管理 API 服务器状态
+pub struct AdminApiState(pub Arc<RwLock<Option<AdminServer>>>);
+
+/// 管理 API 状态（返回前端）
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdminApiStatus {
+    pub running: bool,
+    pub config: AdminApiConfig,
+    pub base_url: String,
+}
+
+/// 更新管理 API 配置的输入
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdminApiConfigInput {
+    pub enabled: bool,
+    pub listen_address: String,
+    pub listen_port: u16,
+}
+
+/// 获取管理 API 状态
+#[tauri::command]
+pub async fn get_admin_api_status(
+    admin_state: State<'_, AdminApiState>,
+) -> Result<AdminApiStatus, String> {
+    let config = AdminApiConfig::load().map_err(|e| e.to_string())?;
+    let running = admin_state.0.read().await.is_some();
+    Ok(status_of(config, running))
+}
+
+/// 更新管理 API 配置（按需重启服务器）
+#[tauri::command]
+pub async fn update_admin_api_config(
+    input: AdminApiConfigInput,
+    db: State<'_, Arc<Database>>,
+    proxy_state: State<'_, ProxyServiceState>,
+    admin_state: State<'_, AdminApiState>,
+) -> Result<AdminApiStatus, String> {
+    let mut config = AdminApiConfig::load().map_err(|e| e.to_string())?;
+    config.enabled = input.enabled;
+    config.listen_address = input.listen_address;
+    config.listen_port = input.listen_port;
+    config.save().map_err(|e| e.to_string())?;
+
+    restart_admin_server(&config, db.inner().clone(), &proxy_state, &admin_state).await?;
+    let running = admin_state.0.read().await.is_some();
+    Ok(status_of(config, running))
+}
+
+/// 重新生成访问令牌（旧令牌立即失效）
+#[tauri::command]
+pub async fn regenerate_admin_api_token(
+    db: State<'_, Arc<Database>>,
+    proxy_state: State<'_, ProxyServiceState>,
+    admin_state: State<'_, AdminApiState>,
+) -> Result<AdminApiStatus, String> {
+    let mut config = AdminApiConfig::load().map_err(|e| e.to_string())?;
+    config.token = generate_token();
+    config.save().map_err(|e| e.to_string())?;
+
+    restart_admin_server(&config, db.inner().clone(), &proxy_state, &admin_state).await?;
+    let running = admin_state.0.read().await.is_some();
+    Ok(status_of(config, running))
+}
+
+/// 停止现有服务器，启用时按新配置重新启动
+async fn restart_admin_server(
+    config: &AdminApiConfig,
+    db: Arc<Database>,
+    proxy_state: &ProxyServiceState,
+    admin_state: &AdminApiState,
+) -> Result<(), String> {
+    let mut guard = admin_state.0.write().await;
+    if let Some(server) = guard.take() {
+        server.stop().await;
+    }
+    if config.enabled {
+        let server = AdminServer::new(config.clone(), db, proxy_state.0.clone());
+        server.start().await.map_err(|e| e.to_string())?;
+        *guard = Some(server);
+    }
+    Ok(())
+}
+
+fn status_of(config: AdminApiConfig, running: bool) -> AdminApiStatus {
+    let base_url = format!("http://{}:{}/api/v1", config.listen_address, config.listen_port);
+    AdminApiStatus {
+        running,
+        config,
+        base_url,
+    }
+}
@@ -624,7 +624,7 @@ pub fn export_backup(
     if !opts.include_gemini { backup.gemini_config = None; }
 
     if opts.include_usage_stats {
-        let conn = db.conn.lock().map_err(|e| AppError::Custom(format!("DB lock failed: {}", e)))?;
+        let conn = db.read_conn()?;
         let mut usage_records = Vec::new();
         if let Ok(mut stmt) = conn.prepare(
             "SELECT session_id, created_at, model, app_type, input_tokens, output_tokens, cache_read_tokens, cache_creation_tokens, cost FROM proxy_request_logs ORDER BY created_at"
@@ -724,7 +724,7 @@ pub fn export_backup_filtered(
     }
 
     if !options.usage_sources.is_empty() {
-        let conn = db.conn.lock().map_err(|e| AppError::Custom(format!("DB lock failed: {}", e)))?;
+        let conn = db.read_conn()?;
         let mut usage_records = Vec::new();
         let placeholders: Vec<&str> = options.usage_sources.iter().map(|_| "?").collect();
         let sql = format!(
@@ -0,0 +1,418 @@
+//! Aider 日志解析
+//!
+//! Aider 在每个项目的 git 根目录写入 `.aider.chat.history.md`，每条回复后有一行
+//! `> Tokens: 2.3k sent, 150 received. Cost: $0.0081 message, $0.0123 session.`。
+//! 配置了分析日志（`AIDER_ANALYTICS_LOG` 或 `~/.aider.conf.yml` 中的 `analytics-log`）时，
+//! 使用记录改从分析日志的 `message_send` 事件读取（时间精确到秒），聊天记录只用于会话统计。
+
+use super::*;
+
+const HISTORY_FILE: &str = ".aider.chat.history.md";
+
+pub(super) struct AiderSource;
+
+impl LogSource for AiderSource {
+    fn id(&self) -> &'static str {
+        "aider"
+    }
+
+    fn name(&self) -> &'static str {
+        "Aider"
+    }
+
+    fn location(&self) -> Option<PathBuf> {
+        aider_analytics_logs()
+            .into_iter()
+            .next()
+            .or_else(|| find_aider_histories().into_iter().next())
+    }
+
+    fn discover(&self) -> Vec<PathBuf> {
+        let mut files = aider_analytics_logs();
+        files.extend(find_aider_histories());
+        files
+    }
+
+    fn estimate_entries(&self, files: &[PathBuf]) -> u32 {
+        let analytics = !aider_analytics_logs().is_empty();
+        files
+            .iter()
+            .map(|file| {
+                if is_history_file(file) {
+                    if analytics {
+                        0
+                    } else {
+                        fs::read_to_string(file)
+                            .map(|content| content.matches("\n> Tokens: ").count() as u32)
+                            .unwrap_or(0)
+                    }
+                } else {
+                    estimate_entries_from_file(file, true)
+                }
+            })
+            .sum()
+    }
+
+    fn parse_entries(&self, path: &Path) -> Vec<LocalLogEntry> {
+        if !is_history_file(path) {
+            return parse_aider_analytics(path, &ParseCursor::default())
+                .map(|(entries, _)| entries)
+                .unwrap_or_default();
+        }
+        // 有分析日志时以分析日志为准，避免重复计算
+        if !aider_analytics_logs().is_empty() {
+            return vec![];
+        }
+        parse_aider_history(path)
+    }
+
+    /// 分析日志只追加写入，增量解析；聊天记录变化后完整解析
+    fn parse_appended(&self, path: &Path, cursor: &ParseCursor) -> Option<(Vec<LocalLogEntry>, ParseCursor)> {
+        if is_history_file(path) {
+            return None;
+        }
+        parse_aider_analytics(path, cursor)
+    }
+
+    fn parse_session_stats(&self, path: &Path, _cursor: &ParseCursor) -> Option<(String, SessionStats)> {
+        if !is_history_file(path) {
+            return None;
+        }
+        Some((aider_session_id(path), parse_aider_session_stats(path)))
+    }
+}
+
+fn is_history_file(path: &Path) -> bool {
+    path.file_name().is_some_and(|name| name == HISTORY_FILE)
+}
+
+/// 聊天记录按项目划分会话
+fn aider_session_id(path: &Path) -> String {
+    let project = path.parent().unwrap_or(path).to_string_lossy().replace(['\\', '/', ':'], "_");
+    format!("aider-{project}")
+}
+
+/// 配置的分析日志文件
+fn aider_analytics_logs() -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    if let Ok(path) = std::env::var("AIDER_ANALYTICS_LOG") {
+        paths.push(PathBuf::from(path));
+    }
+    if let Some(home) = dirs::home_dir() {
+        if let Ok(config) = fs::read_to_string(home.join(".aider.conf.yml")) {
+            let configured = config.lines().find_map(|line| {
+                let value = line.trim().strip_prefix("analytics-log:")?.trim();
+                Some(value.trim_matches(['"', '\'']).to_string())
+            });
+            if let Some(path) = configured.filter(|p| !p.is_empty()) {
+                paths.push(match path.strip_prefix("~/") {
+                    Some(rest) => home.join(rest),
+                    None => PathBuf::from(path),
+                });
+            }
+        }
+    }
+    paths.dedup();
+    paths.retain(|path| path.is_file());
+    paths
+}
+
+/// 在用户目录及其下几层目录中查找聊天记录
+fn find_aider_histories() -> Vec<PathBuf> {
+    find_project_files(Path::new(HISTORY_FILE))
+}
+
+/// 解析分析日志中的 `message_send` 事件
+///
+/// 事件没有 ID，按日志文件和行内容生成记录 ID；没有时间的事件无法生成稳定的 ID，跳过
+fn parse_aider_analytics(path: &Path, cursor: &ParseCursor) -> Option<(Vec<LocalLogEntry>, ParseCursor)> {
+    let (lines, offset) = read_jsonl_from(path, cursor.offset)?;
+    let file = file_hash(path);
+    let entries = lines
+        .iter()
+        .filter_map(|line| {
+            let json: serde_json::Value = serde_json::from_str(line).ok()?;
+            if json.get("event").and_then(|v| v.as_str()) != Some("message_send") {
+                return None;
+            }
+            let props = json.get("properties")?;
+            let tokens = |key: &str| props.get(key).and_then(|v| v.as_u64()).unwrap_or(0) as u32;
+            let input_tokens = tokens("prompt_tokens");
+            let output_tokens = tokens("completion_tokens");
+            if input_tokens == 0 && output_tokens == 0 {
+                return None;
+            }
+            let timestamp = json.get("time").and_then(|v| v.as_i64())?;
+            // 事件带有会话或运行 ID 时按其划分会话，否则每个分析日志为一个会话
+            let session_id = match ["session_id", "run_id"]
+                .iter()
+                .find_map(|key| props.get(*key).and_then(|v| v.as_str()))
+            {
+                Some(session_id) => format!("aider-{session_id}"),
+                None => format!("aider-{file}"),
+            };
+            Some(LocalLogEntry {
+                source: "aider".to_string(),
+                timestamp,
+                model: props
+                    .get("main_model")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("unknown")
+                    .to_string(),
+                input_tokens,
+                output_tokens,
+                cache_read_tokens: 0,
+                cache_creation_tokens: 0,
+                cost_usd: props.get("cost").and_then(|v| v.as_f64()),
+                estimated: false,
+                request_id: format!("aider-{file}-{timestamp}-{}", content_hash(line)),
+                session_id,
+                project_name: None,
+                project: None,
+            })
+        })
+        .collect();
+    Some((entries, ParseCursor { offset, context: None }))
+}
+
+/// 解析 `2.3k` / `12,345` / `1.2M` 形式的 token 数
+fn parse_token_count(text: &str) -> Option<u32> {
+    let text = text.trim().replace(',', "");
+    let (number, scale) = match text.chars().last()? {
+        'k' | 'K' => (&text[..text.len() - 1], 1_000.0),
+        'm' | 'M' => (&text[..text.len() - 1], 1_000_000.0),
+        _ => (text.as_str(), 1.0),
+    };
+    number.parse::<f64>().ok().map(|n| (n * scale).round() as u32)
+}
+
+/// `> Tokens:` 行中的 (输入, 输出, 缓存写入, 缓存命中, 本条成本)
+fn parse_tokens_line(line: &str) -> Option<(u32, u32, u32, u32, Option<f64>)> {
+    let rest = line.strip_prefix("> Tokens: ")?;
+    let (tokens, cost) = match rest.split_once(". Cost: ") {
+        Some((tokens, cost)) => (tokens, Some(cost)),
+        None => (rest.trim_end_matches('.'), None),
+    };
+
+    let (mut sent, mut received, mut cache_write, mut cache_hit) = (0, 0, 0, 0);
+    for part in tokens.split(", ") {
+        let Some((count, kind)) = part.split_once(' ') else {
+            continue;
+        };
+        let count = parse_token_count(count).unwrap_or(0);
+        match kind {
+            "sent" => sent = count,
+            "received" => received = count,
+            "cache write" => cache_write = count,
+            "cache hit" => cache_hit = count,
+            _ => {}
+        }
+    }
+    let cost = cost
+        .and_then(|c| c.split_whitespace().next())
+        .and_then(|c| c.trim_start_matches('$').parse::<f64>().ok());
+    Some((sent, received, cache_write, cache_hit, cost))
+}
+
+/// `> Main model: X with ...` / `> Model: X with ...` 中的模型名
+fn parse_model_line(line: &str) -> Option<String> {
+    let rest = line
+        .strip_prefix("> Main model: ")
+        .or_else(|| line.strip_prefix("> Model: "))?;
+    Some(rest.split(" with ").next().unwrap_or(rest).trim().to_string())
+}
+
+/// `# aider chat started at 2024-05-01 10:00:00`（本地时间）
+fn parse_session_start(line: &str) -> Option<i64> {
+    let text = line.strip_prefix("# aider chat started at ")?;
+    let naive = chrono::NaiveDateTime::parse_from_str(text.trim(), "%Y-%m-%d %H:%M:%S").ok()?;
+    naive
+        .and_local_timezone(chrono::Local)
+        .earliest()
+        .map(|dt| dt.timestamp())
+}
+
+/// 解析聊天记录，每个会话中的回复按顺序编号，时间为会话开始时间
+fn parse_aider_history(path: &Path) -> Vec<LocalLogEntry> {
+    let Ok(content) = fs::read_to_string(path) else {
+        return vec![];
+    };
+    let project_dir = path.parent();
+    let project_name = project_dir
+        .and_then(|dir| dir.file_name())
+        .map(|name| name.to_string_lossy().to_string());
+    let project = project_dir.and_then(|dir| project_root(&dir.to_string_lossy()));
+    let id_prefix = project_name.clone().unwrap_or_else(|| "unknown".to_string());
+    let session_id = aider_session_id(path);
+
+    let mut entries = Vec::new();
+    let mut session_start: Option<i64> = None;
+    let mut model = "unknown".to_string();
+    let mut index = 0u32;
+
+    for line in content.lines() {
+        if let Some(start) = parse_session_start(line) {
+            session_start = Some(start);
+            index = 0;
+            continue;
+        }
+        if let Some(m) = parse_model_line(line) {
+            model = m;
+            continue;
+        }
+        let Some((sent, received, cache_write, cache_hit, cost)) = parse_tokens_line(line) else {
+            continue;
+        };
+        let Some(start) = session_start else {
+            continue;
+        };
+        index += 1;
+        entries.push(LocalLogEntry {
+            source: "aider".to_string(),
+            timestamp: start,
+            model: model.clone(),
+            input_tokens: sent.saturating_sub(cache_hit + cache_write),
+            output_tokens: received,
+            cache_read_tokens: cache_hit,
+            cache_creation_tokens: cache_write,
+            cost_usd: cost,
+            estimated: false,
+            request_id: format!("aider-{id_prefix}-{start}-{index}"),
+            session_id: session_id.clone(),
+            project_name: project_name.clone(),
+            project: project.clone(),
+        });
+    }
+    entries
+}
+
+/// 聊天记录的会话统计：用户消息（`#### `）为对话轮数，`> Applied edit to` 为文件修改
+fn parse_aider_session_stats(path: &Path) -> SessionStats {
+    let mut stats = SessionStats::default();
+    let Ok(content) = fs::read_to_string(path) else {
+        return stats;
+    };
+    let mut files_modified: HashSet<&str> = HashSet::new();
+    for line in content.lines() {
+        if line.starts_with("#### ") {
+            stats.conversation_count += 1;
+        } else if let Some(file) = line.strip_prefix("> Applied edit to ") {
+            files_modified.insert(file.trim());
+            *stats.tool_calls.entry("StrReplace".to_string()).or_insert(0) += 1;
+        } else if line.starts_with("> Running ") {
+            *stats.tool_calls.entry("Shell".to_string()).or_insert(0) += 1;
+        } else if line.starts_with("> Commit ") {
+            *stats.tool_calls.entry("Commit".to_string()).or_insert(0) += 1;
+        }
+    }
+    stats.files_changed = files_modified.len() as u32;
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_tokens_line() {
+        assert_eq!(
+            parse_tokens_line("> Tokens: 4.4k sent, 1.2k cache write, 2.3k cache hit, 212 received. Cost: $0.02 message, $0.05 session."),
+            Some((4400, 212, 1200, 2300, Some(0.02)))
+        );
+        assert_eq!(
+            parse_tokens_line("> Tokens: 12,345 sent, 150 received."),
+            Some((12345, 150, 0, 0, None))
+        );
+        assert_eq!(
+            parse_model_line("> Main model: claude-3-5-sonnet-20240620 with diff edit format, infinite output").as_deref(),
+            Some("claude-3-5-sonnet-20240620")
+        );
+    }
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ai-switch-aider-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn parses_message_send_events() {
+        let dir = temp_dir();
+        let path = dir.join("analytics.jsonl");
+        let lines = [
+            r#"{"event":"launched","properties":{"main_model":"gpt-4o"},"user_id":"u1","time":1790848790}"#,
+            // 同一秒内 token 数相同的两次请求
+            r#"{"event":"message_send","properties":{"main_model":"gpt-4o","prompt_tokens":1200,"completion_tokens":80,"cost":0.004,"total_cost":0.004},"user_id":"u1","time":1790848800}"#,
+            r#"{"event":"message_send","properties":{"main_model":"gpt-4o","prompt_tokens":1200,"completion_tokens":80,"cost":0.004,"total_cost":0.008},"user_id":"u1","time":1790848800}"#,
+            r#"{"event":"message_send","properties":{"main_model":"gpt-4o","prompt_tokens":0,"completion_tokens":0},"time":1790848801}"#,
+            r#"{"event":"message_send","properties":{"main_model":"gpt-4o","prompt_tokens":300,"completion_tokens":20}}"#,
+            r#"{"event":"message_send","properties":{"main_model":"gpt-4o","prompt_tokens":500,"completion_tokens":50,"run_id":"r1"},"time":1790848802}"#,
+        ];
+        fs::write(&path, lines.join("\n") + "\n").unwrap();
+
+        let (entries, _) = parse_aider_analytics(&path, &ParseCursor::default()).unwrap();
+        let (again, _) = parse_aider_analytics(&path, &ParseCursor::default()).unwrap();
+        fs::remove_dir_all(&dir).ok();
+
+        let summary: Vec<_> = entries
+            .iter()
+            .map(|e| (e.timestamp, e.model.as_str(), e.input_tokens, e.output_tokens, e.cost_usd))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (1_790_848_800, "gpt-4o", 1200, 80, Some(0.004)),
+                (1_790_848_800, "gpt-4o", 1200, 80, Some(0.004)),
+                (1_790_848_802, "gpt-4o", 500, 50, None),
+            ]
+        );
+        assert_ne!(entries[0].request_id, entries[1].request_id);
+        assert_eq!(entries[0].session_id, format!("aider-{}", file_hash(&path)));
+        assert_eq!(entries[2].session_id, "aider-r1");
+
+        let ids = |entries: &[LocalLogEntry]| entries.iter().map(|e| e.request_id.clone()).collect::<Vec<_>>();
+        assert_eq!(ids(&entries), ids(&again));
+    }
+
+    #[test]
+    fn parses_chat_history() {
+        let dir = temp_dir();
+        let path = dir.join(HISTORY_FILE);
+        let history = "\
+# aider chat started at 2026-10-01 10:00:00
+
+> Main model: gpt-4o with diff edit format
+
+#### add a test
+
+> Tokens: 2.3k sent, 150 received. Cost: $0.0081 message, $0.0081 session.
+> Applied edit to tests/a.rs
+> Commit 1a2b3c4 add a test
+
+#### run it
+
+> Running cargo test
+> Tokens: 3k sent, 1k cache hit, 40 received. Cost: $0.0050 message, $0.0131 session.
+";
+        fs::write(&path, history).unwrap();
+
+        let entries = parse_aider_history(&path);
+        let stats = parse_aider_session_stats(&path);
+        fs::remove_dir_all(&dir).ok();
+
+        let summary: Vec<_> = entries
+            .iter()
+            .map(|e| (e.model.as_str(), e.input_tokens, e.output_tokens, e.cache_read_tokens, e.cost_usd))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![("gpt-4o", 2300, 150, 0, Some(0.0081)), ("gpt-4o", 2000, 40, 1000, Some(0.005))]
+        );
+        assert!(entries.iter().all(|e| e.session_id == aider_session_id(&path)));
+        assert_ne!(entries[0].request_id, entries[1].request_id);
+
+        assert_eq!(stats.conversation_count, 2);
+        assert_eq!(stats.files_changed, 1);
+        assert_eq!(stats.tool_calls.values().sum::<u32>(), 3);
+    }
+}
@@ -0,0 +1,338 @@
+//! Cline / Roo Code 日志解析
+//!
+//! 两者都是 VS Code 扩展，每个任务一个目录：`<User>/globalStorage/<扩展 ID>/tasks/<任务 ID>/`。
+//! - `ui_messages.json`：界面消息，`api_req_started` 消息的 text 中记录每次请求的 token 和成本
+//! - `task_metadata.json`（Cline）：`model_usage` 记录任务中使用过的模型
+//! - `api_conversation_history.json`：发送给模型的消息，环境信息中包含工作目录
+
+use super::*;
+
+/// 安装了 Cline / Roo Code 的编辑器（VS Code 及其分支）
+const CLINE_EDITORS: &[&str] = &["Code", "Code - Insiders", "VSCodium", "Cursor", "Windsurf", "Kiro"];
+
+/// Cline 系扩展
+pub(super) struct ClineSource {
+    pub id: &'static str,
+    pub name: &'static str,
+    /// 扩展 ID（globalStorage 下的目录名）
+    pub extension_id: &'static str,
+}
+
+impl ClineSource {
+    /// 各编辑器中该扩展的任务目录
+    fn task_roots(&self) -> Vec<PathBuf> {
+        vscode_user_dirs(CLINE_EDITORS)
+            .into_iter()
+            .map(|user_dir| user_dir.join("globalStorage").join(self.extension_id).join("tasks"))
+            .filter(|dir| dir.exists())
+            .collect()
+    }
+}
+
+impl LogSource for ClineSource {
+    fn id(&self) -> &'static str {
+        self.id
+    }
+
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn location(&self) -> Option<PathBuf> {
+        self.task_roots().into_iter().next()
+    }
+
+    /// 每个任务的 ui_messages.json
+    fn discover(&self) -> Vec<PathBuf> {
+        self.task_roots()
+            .iter()
+            .filter_map(|root| fs::read_dir(root).ok())
+            .flat_map(|tasks| tasks.flatten())
+            .map(|task| task.path().join("ui_messages.json"))
+            .filter(|path| path.exists())
+            .collect()
+    }
+
+    /// 每个任务按一条预估
+    fn estimate_entries(&self, files: &[PathBuf]) -> u32 {
+        files.len() as u32
+    }
+
+    fn parse_entries(&self, path: &Path) -> Vec<LocalLogEntry> {
+        parse_cline_task(path, self.id)
+    }
+
+    fn parse_session_stats(&self, path: &Path, _cursor: &ParseCursor) -> Option<(String, SessionStats)> {
+        let task_id = cline_task_id(path)?;
+        Some((format!("{}-{task_id}", self.id), parse_cline_session_stats(path)))
+    }
+
+    /// 请求进行中时 token 会随流式输出更新，重新导入时刷新
+    fn refresh_existing(&self) -> bool {
+        true
+    }
+}
+
+/// 任务 ID（任务目录名）
+fn cline_task_id(path: &Path) -> Option<String> {
+    path.parent()
+        .and_then(|dir| dir.file_name())
+        .and_then(|name| name.to_str())
+        .map(|name| name.to_string())
+}
+
+fn read_json_array(path: &Path) -> Vec<serde_json::Value> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        .and_then(|json| json.as_array().cloned())
+        .unwrap_or_default()
+}
+
+/// 消息 text 字段中嵌套的 JSON
+fn message_payload(message: &serde_json::Value) -> Option<serde_json::Value> {
+    message
+        .get("text")
+        .and_then(|t| t.as_str())
+        .and_then(|t| serde_json::from_str(t).ok())
+}
+
+fn message_kind<'a>(message: &'a serde_json::Value, field: &str) -> Option<&'a str> {
+    message.get(field).and_then(|v| v.as_str())
+}
+
+/// 解析 Cline 系任务的每次 API 请求
+fn parse_cline_task(path: &Path, source: &str) -> Vec<LocalLogEntry> {
+    let Some(task_id) = cline_task_id(path) else {
+        return vec![];
+    };
+    let messages = read_json_array(path);
+    if messages.is_empty() {
+        return vec![];
+    }
+
+    let task_dir = path.parent().unwrap_or(path);
+    let models = load_cline_models(task_dir);
+    let history = read_json_array(&task_dir.join("api_conversation_history.json"));
+    let fallback_model = history
+        .iter()
+        .rev()
+        .find_map(|m| m.get("modelInfo").and_then(|i| i.get("modelId")).and_then(|v| v.as_str()))
+        .unwrap_or("unknown")
+        .to_string();
+    let project_dir = history.iter().find_map(cline_working_directory);
+
+    let mut entries = Vec::new();
+    for message in &messages {
+        if message_kind(message, "say") != Some("api_req_started") {
+            continue;
+        }
+        let Some(ts) = message.get("ts").and_then(|v| v.as_i64()) else {
+            continue;
+        };
+        let Some(request) = message_payload(message) else {
+            continue;
+        };
+        let tokens = |key: &str| request.get(key).and_then(|v| v.as_u64()).unwrap_or(0) as u32;
+        let input_tokens = tokens("tokensIn");
+        let output_tokens = tokens("tokensOut");
+        if input_tokens == 0 && output_tokens == 0 {
+            continue;
+        }
+
+        // 请求时正在使用的模型：最后一个不晚于请求时间的 model_usage
+        let model = models
+            .iter()
+            .take_while(|(used_at, _)| *used_at <= ts)
+            .last()
+            .or(models.first())
+            .map_or_else(|| fallback_model.clone(), |(_, model)| model.clone());
+
+        entries.push(LocalLogEntry {
+            source: source.to_string(),
+            timestamp: ts / 1000,
+            model,
+            input_tokens,
+            output_tokens,
+            cache_read_tokens: tokens("cacheReads"),
+            cache_creation_tokens: tokens("cacheWrites"),
+            cost_usd: request.get("cost").and_then(|v| v.as_f64()),
+            estimated: false,
+            request_id: format!("{source}-{task_id}-{ts}"),
+            session_id: format!("{source}-{task_id}"),
+            project_name: project_dir
+                .as_deref()
+                .and_then(|dir| Path::new(dir).file_name())
+                .map(|name| name.to_string_lossy().to_string()),
+            project: project_dir.as_deref().and_then(project_root),
+        });
+    }
+    entries
+}
+
+/// task_metadata.json 中的模型使用记录，按时间排序
+fn load_cline_models(task_dir: &Path) -> Vec<(i64, String)> {
+    let Some(metadata) = fs::read_to_string(task_dir.join("task_metadata.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+    else {
+        return vec![];
+    };
+    let mut models: Vec<(i64, String)> = metadata
+        .get("model_usage")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|usage| {
+            let ts = usage.get("ts").and_then(|v| v.as_i64())?;
+            let model = usage.get("model_id").and_then(|v| v.as_str())?;
+            Some((ts, model.to_string()))
+        })
+        .collect();
+    models.sort_by_key(|(ts, _)| *ts);
+    models
+}
+
+/// 从环境信息中提取工作目录（Cline: `# Current Working Directory (...)`，Roo: `# Current Workspace Directory (...)`）
+fn cline_working_directory(message: &serde_json::Value) -> Option<String> {
+    let texts: Vec<&str> = match message.get("content") {
+        Some(serde_json::Value::String(text)) => vec![text.as_str()],
+        Some(serde_json::Value::Array(blocks)) => blocks
+            .iter()
+            .filter_map(|b| b.get("text").and_then(|t| t.as_str()))
+            .collect(),
+        _ => vec![],
+    };
+    texts.into_iter().find_map(|text| {
+        ["# Current Working Directory (", "# Current Workspace Directory ("]
+            .iter()
+            .find_map(|marker| {
+                let start = text.find(marker)? + marker.len();
+                let end = text[start..].find(')')?;
+                Some(text[start..start + end].to_string())
+            })
+    })
+}
+
+/// Cline 工具名映射为统一的工具名
+fn cline_tool_name(tool: &str) -> &str {
+    match tool {
+        "readFile" => "Read",
+        "newFileCreated" => "Write",
+        "editedExistingFile" | "appliedDiff" | "insertContent" | "searchAndReplace" => "StrReplace",
+        "listFilesTopLevel" | "listFilesRecursive" | "listCodeDefinitionNames" => "Glob",
+        "searchFiles" | "codebaseSearch" => "Grep",
+        "webFetch" => "WebFetch",
+        "newTask" => "Task",
+        other => other,
+    }
+}
+
+/// 统计 SEARCH/REPLACE 块的删除和新增行数，不是该格式时按统一 diff 统计
+fn count_diff_lines(diff: &str) -> (u32, u32) {
+    if !diff.contains("<<<<<<< SEARCH") {
+        return extract_line_changes(diff);
+    }
+    let mut added = 0u32;
+    let mut deleted = 0u32;
+    let mut in_search = false;
+    let mut in_replace = false;
+    for line in diff.lines() {
+        match line.trim_end() {
+            l if l.starts_with("<<<<<<< SEARCH") => in_search = true,
+            "=======" if in_search => {
+                in_search = false;
+                in_replace = true;
+            }
+            l if l.starts_with(">>>>>>> REPLACE") => in_replace = false,
+            // Roo 的 SEARCH 块中带有 `:start_line:` 等标记
+            l if l.starts_with(':') || l == "-------" => {}
+            _ if in_search => deleted += 1,
+            _ if in_replace => added += 1,
+            _ => {}
+        }
+    }
+    (added, deleted)
+}
+
+/// 解析 Cline 系任务的会话统计
+fn parse_cline_session_stats(path: &Path) -> SessionStats {
+    let mut stats = SessionStats::default();
+    let messages = read_json_array(path);
+    let mut files_modified: HashSet<String> = HashSet::new();
+
+    for (idx, message) in messages.iter().enumerate() {
+        let say = message_kind(message, "say");
+        let ask = message_kind(message, "ask");
+
+        match (say, ask) {
+            (Some("task") | Some("user_feedback"), _) => stats.conversation_count += 1,
+            (_, Some("command")) | (Some("command"), _) => {
+                *stats.tool_calls.entry("Shell".to_string()).or_insert(0) += 1;
+            }
+            (Some("tool"), _) | (_, Some("tool")) => {
+                let Some(payload) = message_payload(message) else {
+                    continue;
+                };
+                let Some(tool) = payload.get("tool").and_then(|t| t.as_str()) else {
+                    continue;
+                };
+                let name = normalize_tool_name(cline_tool_name(tool));
+                if name == "Write" || name == "StrReplace" {
+                    if let Some(file) = payload.get("path").and_then(|p| p.as_str()) {
+                        files_modified.insert(file.to_string());
+                    }
+                    let (added, deleted) = match payload.get("diff").and_then(|d| d.as_str()) {
+                        Some(diff) => count_diff_lines(diff),
+                        None => payload
+                            .get("content")
+                            .and_then(|c| c.as_str())
+                            .map_or((0, 0), |c| (c.lines().count() as u32, 0)),
+                    };
+                    stats.lines_added += added;
+                    stats.lines_deleted += deleted;
+                }
+                *stats.tool_calls.entry(name).or_insert(0) += 1;
+            }
+            (Some("api_req_started"), _) => {
+                // 响应时间：请求开始到下一条消息
+                let started = message.get("ts").and_then(|v| v.as_i64()).unwrap_or(0);
+                let next = messages
+                    .get(idx + 1)
+                    .and_then(|m| m.get("ts"))
+                    .and_then(|v| v.as_i64())
+                    .unwrap_or(0);
+                if started > 0 && next > started && next - started < 3_600_000 {
+                    stats.response_time_ms += (next - started) as u64;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    stats.files_changed = files_modified.len() as u32;
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_search_replace_blocks() {
+        let diff = "<<<<<<< SEARCH\n:start_line:3\n-------\nold one\nold two\n=======\nnew one\n>>>>>>> REPLACE";
+        assert_eq!(count_diff_lines(diff), (1, 2));
+    }
+
+    #[test]
+    fn extracts_working_directory_from_environment_details() {
+        let message = serde_json::json!({
+            "role": "user",
+            "content": [
+                { "type": "text", "text": "<task>fix</task>" },
+                { "type": "text", "text": "<environment_details>\n# Current Working Directory (/home/me/app) Files\nsrc/" }
+            ]
+        });
+        assert_eq!(cline_working_directory(&message).as_deref(), Some("/home/me/app"));
+    }
+}
@@ -0,0 +1,287 @@
+//! Continue 日志解析
+//!
+//! Continue 的数据目录为 `~/.continue`（可用 `CONTINUE_GLOBAL_DIR` 覆盖）：
+//! - `dev_data/**/tokensGenerated.jsonl`：每次模型调用的输入、输出 token，只追加写入
+//! - `sessions/<会话 ID>.json`：会话消息和工具调用，用于会话统计（`sessions.json` 是索引）
+
+use super::*;
+
+const TOKENS_FILE: &str = "tokensGenerated.jsonl";
+
+pub(super) struct ContinueSource;
+
+impl LogSource for ContinueSource {
+    fn id(&self) -> &'static str {
+        "continue"
+    }
+
+    fn name(&self) -> &'static str {
+        "Continue"
+    }
+
+    fn location(&self) -> Option<PathBuf> {
+        get_continue_dir()
+    }
+
+    fn discover(&self) -> Vec<PathBuf> {
+        let Some(dir) = get_continue_dir() else {
+            return vec![];
+        };
+        let mut files = Vec::new();
+        find_token_logs(&dir.join("dev_data"), &mut files);
+        if let Ok(sessions) = fs::read_dir(dir.join("sessions")) {
+            files.extend(sessions.flatten().map(|entry| entry.path()).filter(|path| {
+                path.extension().is_some_and(|ext| ext == "json")
+                    && path.file_name().is_some_and(|name| name != "sessions.json")
+            }));
+        }
+        files
+    }
+
+    fn watch_dir(&self) -> Option<PathBuf> {
+        get_continue_dir()
+    }
+
+    fn estimate_entries(&self, files: &[PathBuf]) -> u32 {
+        files
+            .iter()
+            .filter(|file| is_token_log(file))
+            .map(|file| estimate_entries_from_file(file, true))
+            .sum()
+    }
+
+    /// 会话文件不含 token，只用于会话统计
+    fn parse_entries(&self, path: &Path) -> Vec<LocalLogEntry> {
+        self.parse_appended(path, &ParseCursor::default())
+            .map(|(entries, _)| entries)
+            .unwrap_or_default()
+    }
+
+    fn parse_appended(&self, path: &Path, cursor: &ParseCursor) -> Option<(Vec<LocalLogEntry>, ParseCursor)> {
+        if !is_token_log(path) {
+            return Some((vec![], ParseCursor::default()));
+        }
+        parse_continue_tokens(path, cursor)
+    }
+
+    fn parse_session_stats(&self, path: &Path, _cursor: &ParseCursor) -> Option<(String, SessionStats)> {
+        if is_token_log(path) {
+            return None;
+        }
+        let session_id = path.file_stem()?.to_string_lossy();
+        Some((format!("continue-{session_id}"), parse_continue_session_stats(path)))
+    }
+}
+
+/// 获取 Continue 数据目录
+fn get_continue_dir() -> Option<PathBuf> {
+    let dir = match std::env::var("CONTINUE_GLOBAL_DIR") {
+        Ok(dir) => PathBuf::from(dir),
+        Err(_) => dirs::home_dir()?.join(".continue"),
+    };
+    dir.exists().then_some(dir)
+}
+
+fn is_token_log(path: &Path) -> bool {
+    path.file_name().is_some_and(|name| name == TOKENS_FILE)
+}
+
+/// dev_data 下各 schema 版本目录中的 token 日志
+fn find_token_logs(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            find_token_logs(&path, files);
+        } else if is_token_log(&path) {
+            files.push(path);
+        }
+    }
+}
+
+/// 解析 `tokensGenerated` 事件
+///
+/// 事件没有 ID，按日志文件和行内容生成记录 ID；没有时间的事件无法生成稳定的 ID，跳过
+fn parse_continue_tokens(path: &Path, cursor: &ParseCursor) -> Option<(Vec<LocalLogEntry>, ParseCursor)> {
+    let (lines, offset) = read_jsonl_from(path, cursor.offset)?;
+    let file = file_hash(path);
+    let entries = lines
+        .iter()
+        .filter_map(|line| {
+            let json: serde_json::Value = serde_json::from_str(line).ok()?;
+            let tokens = |key: &str| json.get(key).and_then(|v| v.as_u64()).unwrap_or(0) as u32;
+            let input_tokens = tokens("promptTokens");
+            let output_tokens = tokens("generatedTokens");
+            if input_tokens == 0 && output_tokens == 0 {
+                return None;
+            }
+            let timestamp_ms = extract_timestamp_ms(&json)?;
+            // 事件带有会话 ID 时与会话文件关联，否则每个日志文件为一个会话
+            let session_id = match json.get("sessionId").and_then(|v| v.as_str()) {
+                Some(session_id) => format!("continue-{session_id}"),
+                None => format!("continue-{file}"),
+            };
+            Some(LocalLogEntry {
+                source: "continue".to_string(),
+                timestamp: timestamp_ms / 1000,
+                model: json
+                    .get("model")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("unknown")
+                    .to_string(),
+                input_tokens,
+                output_tokens,
+                cache_read_tokens: 0,
+                cache_creation_tokens: 0,
+                cost_usd: None,
+                estimated: false,
+                request_id: format!("continue-{file}-{timestamp_ms}-{}", content_hash(line)),
+                session_id,
+                project_name: None,
+                project: None,
+            })
+        })
+        .collect();
+    Some((entries, ParseCursor { offset, context: None }))
+}
+
+/// 会话统计：用户消息为对话轮数，助手消息的 toolCalls 为工具调用
+fn parse_continue_session_stats(path: &Path) -> SessionStats {
+    let mut stats = SessionStats::default();
+    let Some(session) = fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+    else {
+        return stats;
+    };
+
+    let mut files_modified: HashSet<String> = HashSet::new();
+    let history = session.get("history").and_then(|h| h.as_array());
+    for item in history.into_iter().flatten() {
+        let Some(message) = item.get("message") else {
+            continue;
+        };
+        match message.get("role").and_then(|r| r.as_str()) {
+            Some("user") => stats.conversation_count += 1,
+            Some("assistant") => {
+                let tool_calls = message.get("toolCalls").and_then(|t| t.as_array());
+                for call in tool_calls.into_iter().flatten() {
+                    let Some(function) = call.get("function") else {
+                        continue;
+                    };
+                    let Some(raw_name) = function.get("name").and_then(|n| n.as_str()) else {
+                        continue;
+                    };
+                    let name = continue_tool_name(raw_name);
+                    // arguments 是 JSON 字符串
+                    let args: serde_json::Value = function
+                        .get("arguments")
+                        .and_then(|a| a.as_str())
+                        .and_then(|a| serde_json::from_str(a).ok())
+                        .unwrap_or(serde_json::Value::Null);
+                    if is_file_edit_tool(&name) {
+                        if let Some(file) = args.get("filepath").and_then(|f| f.as_str()) {
+                            files_modified.insert(file.to_string());
+                        }
+                        let (added, deleted) = extract_code_changes_from_input(&args, &name);
+                        stats.lines_added += added;
+                        stats.lines_deleted += deleted;
+                    }
+                    *stats.tool_calls.entry(normalize_tool_name(&name)).or_insert(0) += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    stats.files_changed = files_modified.len() as u32;
+    stats
+}
+
+/// Continue 内置工具名映射（`builtin_read_file` -> `read_file`）
+fn continue_tool_name(name: &str) -> String {
+    match name.strip_prefix("builtin_").unwrap_or(name) {
+        "create_new_file" => "create_file".to_string(),
+        "edit_existing_file" | "single_find_and_replace" => "edit_file".to_string(),
+        "run_terminal_command" => "shell".to_string(),
+        "grep_search" | "file_glob_search" => "grep".to_string(),
+        "search_web" => "web_search".to_string(),
+        "fetch_url_content" => "web_fetch".to_string(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ai-switch-continue-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn parses_token_events() {
+        let dir = temp_dir();
+        let path = dir.join(TOKENS_FILE);
+        let lines = [
+            r#"{"model":"gpt-4o","promptTokens":1200,"generatedTokens":80,"timestamp":"2026-10-01T10:00:00Z"}"#,
+            r#"{"model":"gpt-4o","promptTokens":0,"generatedTokens":0,"timestamp":"2026-10-01T10:00:01Z"}"#,
+            r#"{"model":"gpt-4o","promptTokens":300,"generatedTokens":20}"#,
+            r#"{"model":"gpt-4o","promptTokens":1200,"generatedTokens":80,"timestamp":"2026-10-01T10:00:00Z","sessionId":"s1"}"#,
+        ];
+        fs::write(&path, lines.join("\n") + "\n").unwrap();
+
+        let (entries, cursor) = parse_continue_tokens(&path, &ParseCursor::default()).unwrap();
+        let (again, _) = parse_continue_tokens(&path, &ParseCursor::default()).unwrap();
+        fs::remove_dir_all(&dir).ok();
+
+        // 没有 token 和没有时间的事件跳过；相同时间和 token 数的事件不会合并
+        let summary: Vec<_> = entries
+            .iter()
+            .map(|e| (e.timestamp, e.model.as_str(), e.input_tokens, e.output_tokens))
+            .collect();
+        assert_eq!(summary, vec![(1_790_848_800, "gpt-4o", 1200, 80), (1_790_848_800, "gpt-4o", 1200, 80)]);
+        assert_ne!(entries[0].request_id, entries[1].request_id);
+        assert_eq!(entries[0].session_id, format!("continue-{}", file_hash(&path)));
+        assert_eq!(entries[1].session_id, "continue-s1");
+        assert!(cursor.offset > 0);
+
+        let ids = |entries: &[LocalLogEntry]| entries.iter().map(|e| e.request_id.clone()).collect::<Vec<_>>();
+        assert_eq!(ids(&entries), ids(&again));
+    }
+
+    #[test]
+    fn session_stats_count_turns_tools_and_files() {
+        let dir = temp_dir();
+        let path = dir.join("s1.json");
+        let session = serde_json::json!({
+            "history": [
+                { "message": { "role": "user", "content": "add a test" } },
+                {
+                    "message": {
+                        "role": "assistant",
+                        "toolCalls": [
+                            { "function": { "name": "builtin_read_file", "arguments": "{\"filepath\":\"src/lib.rs\"}" } },
+                            { "function": { "name": "builtin_create_new_file", "arguments": "{\"filepath\":\"tests/a.rs\",\"contents\":\"fn a() {}\\n\"}" } },
+                            { "function": { "name": "builtin_edit_existing_file", "arguments": "{\"filepath\":\"src/lib.rs\",\"changes\":\"x\"}" } }
+                        ]
+                    }
+                },
+                { "message": { "role": "user", "content": "thanks" } },
+                { "message": { "role": "assistant", "content": "done" } }
+            ]
+        });
+        fs::write(&path, session.to_string()).unwrap();
+
+        let stats = parse_continue_session_stats(&path);
+        fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(stats.conversation_count, 2);
+        assert_eq!(stats.tool_calls.values().sum::<u32>(), 3);
+        assert_eq!(stats.files_changed, 2);
+    }
+}
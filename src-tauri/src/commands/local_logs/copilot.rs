@@ -0,0 +1,352 @@
+//! GitHub Copilot CLI 日志解析
+//!
+//! Copilot CLI 的会话保存在 `~/.copilot` 下：
+//! - `session-state/<会话 ID>/events.jsonl` 或 `session-state/<会话 ID>.jsonl`：事件流，只追加写入
+//! - `history-session-state/session_*.json`：早期版本的整份会话（`chatMessages` / `timeline`）
+//!
+//! 格式没有公开文档且随版本变化，解析时在事件和消息中查找带 token 数的 `usage` 对象
+//! （`inputTokens` / `input_tokens` / `prompt_tokens` 等写法均支持）。
+
+use super::*;
+
+pub(super) struct CopilotSource;
+
+impl LogSource for CopilotSource {
+    fn id(&self) -> &'static str {
+        "copilot"
+    }
+
+    fn name(&self) -> &'static str {
+        "Copilot CLI"
+    }
+
+    fn location(&self) -> Option<PathBuf> {
+        get_copilot_dir()
+    }
+
+    fn discover(&self) -> Vec<PathBuf> {
+        let Some(dir) = get_copilot_dir() else {
+            return vec![];
+        };
+        let mut files = Vec::new();
+        for sub in ["session-state", "history-session-state"] {
+            find_session_files(&dir.join(sub), 2, &mut files);
+        }
+        files
+    }
+
+    fn watch_dir(&self) -> Option<PathBuf> {
+        get_copilot_dir()
+    }
+
+    fn estimate_entries(&self, files: &[PathBuf]) -> u32 {
+        files
+            .iter()
+            .map(|file| estimate_entries_from_file(file, is_jsonl(file)))
+            .sum()
+    }
+
+    fn parse_entries(&self, path: &Path) -> Vec<LocalLogEntry> {
+        if is_jsonl(path) {
+            return parse_copilot_events(path, &ParseCursor::default())
+                .map(|(entries, _)| entries)
+                .unwrap_or_default();
+        }
+        let session_id = copilot_session_id(path);
+        read_json(path)
+            .map(|json| {
+                let mut entries = Vec::new();
+                collect_usage_entries(&json, &session_id, UsageContext::default(), &mut entries);
+                entries
+            })
+            .unwrap_or_default()
+    }
+
+    /// 事件流增量解析，整份会话文件变化后完整解析
+    fn parse_appended(&self, path: &Path, cursor: &ParseCursor) -> Option<(Vec<LocalLogEntry>, ParseCursor)> {
+        if !is_jsonl(path) {
+            return None;
+        }
+        parse_copilot_events(path, cursor)
+    }
+
+    /// 事件流的统计在增量解析时累计在解析位置中，不重新读取整个文件
+    fn parse_session_stats(&self, path: &Path, cursor: &ParseCursor) -> Option<(String, SessionStats)> {
+        let stats = match stats_from_cursor(cursor) {
+            Some(stats) => stats,
+            None if is_jsonl(path) => {
+                let mut ctx = StatsContext::default();
+                ctx.collect_lines(fs::read_to_string(path).ok()?.lines(), collect_event_stats);
+                ctx.stats()
+            }
+            None => {
+                let mut stats = SessionStats::default();
+                collect_stats(&read_json(path)?, &mut stats);
+                stats
+            }
+        };
+        Some((format!("copilot-{}", copilot_session_id(path)), stats))
+    }
+}
+
+/// 获取 Copilot CLI 数据目录
+fn get_copilot_dir() -> Option<PathBuf> {
+    let dir = dirs::home_dir()?.join(".copilot");
+    dir.exists().then_some(dir)
+}
+
+fn is_jsonl(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "jsonl")
+}
+
+fn read_json(path: &Path) -> Option<serde_json::Value> {
+    serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+fn find_session_files(dir: &Path, depth: usize, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            if depth > 0 {
+                find_session_files(&path, depth - 1, files);
+            }
+        } else if path.extension().is_some_and(|ext| ext == "jsonl" || ext == "json") {
+            files.push(path);
+        }
+    }
+}
+
+/// 会话 ID：`<会话 ID>/events.jsonl` 取目录名，其余取文件名
+fn copilot_session_id(path: &Path) -> String {
+    let name = if path.file_stem().is_some_and(|stem| stem == "events") {
+        path.parent().and_then(|dir| dir.file_name())
+    } else {
+        path.file_stem()
+    };
+    name.map_or_else(|| "unknown".to_string(), |n| n.to_string_lossy().to_string())
+}
+
+/// 从指定位置解析事件流，会话统计累计在解析位置的上下文中
+///
+/// 之前的位置没有统计上下文时（旧版本的导入状态）从头解析，已导入的记录会被跳过
+fn parse_copilot_events(path: &Path, cursor: &ParseCursor) -> Option<(Vec<LocalLogEntry>, ParseCursor)> {
+    let resumed = cursor
+        .context
+        .clone()
+        .and_then(|c| serde_json::from_value::<StatsContext>(c).ok());
+    let start = if resumed.is_some() { cursor.offset } else { 0 };
+    let mut stats = resumed.unwrap_or_default();
+    let (lines, offset) = read_jsonl_from(path, start)?;
+    stats.collect_lines(lines.iter().map(String::as_str), collect_event_stats);
+
+    let session_id = copilot_session_id(path);
+    let mut entries = Vec::new();
+    for line in &lines {
+        if let Ok(json) = serde_json::from_str::<serde_json::Value>(line) {
+            collect_usage_entries(&json, &session_id, UsageContext::default(), &mut entries);
+        }
+    }
+    let context = serde_json::to_value(&stats).ok();
+    Some((entries, ParseCursor { offset, context }))
+}
+
+/// 读取 token 数（兼容驼峰、下划线和 OpenAI 写法）
+fn usage_tokens(usage: &serde_json::Value, keys: &[&str]) -> u32 {
+    keys.iter()
+        .find_map(|key| usage.get(*key).and_then(|v| v.as_u64()))
+        .unwrap_or(0) as u32
+}
+
+/// 上级对象中的模型、时间和事件 ID，用量在事件的 `data` 中时从事件继承
+#[derive(Clone, Copy, Default)]
+struct UsageContext<'a> {
+    model: Option<&'a str>,
+    timestamp_ms: Option<i64>,
+    id: Option<&'a str>,
+}
+
+/// 递归查找带 `usage` 的对象
+fn collect_usage_entries<'a>(
+    json: &'a serde_json::Value,
+    session_id: &str,
+    inherited: UsageContext<'a>,
+    entries: &mut Vec<LocalLogEntry>,
+) {
+    match json {
+        serde_json::Value::Array(items) => {
+            for item in items {
+                collect_usage_entries(item, session_id, inherited, entries);
+            }
+        }
+        serde_json::Value::Object(map) => {
+            let context = UsageContext {
+                model: map.get("model").and_then(|m| m.as_str()).or(inherited.model),
+                timestamp_ms: extract_timestamp_ms(json).or(inherited.timestamp_ms),
+                id: map.get("id").and_then(|id| id.as_str()).or(inherited.id),
+            };
+
+            // 对象本身即为用量（如 `assistant.usage` 事件的 data）或包含 usage 字段
+            let usage = map
+                .get("usage")
+                .filter(|u| u.is_object())
+                .or_else(|| map.contains_key("inputTokens").then_some(json));
+            if let Some(usage) = usage {
+                let input_tokens = usage_tokens(usage, &["inputTokens", "input_tokens", "prompt_tokens", "promptTokens"]);
+                let output_tokens =
+                    usage_tokens(usage, &["outputTokens", "output_tokens", "completion_tokens", "completionTokens"]);
+                if input_tokens > 0 || output_tokens > 0 {
+                    // 没有时间的用量无法确定所属时段，跳过
+                    let Some(timestamp_ms) = context.timestamp_ms else {
+                        return;
+                    };
+                    // 没有事件 ID 时按对象内容生成稳定的 ID
+                    let id = match context.id {
+                        Some(id) => format!("copilot-{session_id}-{id}"),
+                        None => format!("copilot-{session_id}-{}", content_hash(&json.to_string())),
+                    };
+                    entries.push(LocalLogEntry {
+                        source: "copilot".to_string(),
+                        timestamp: timestamp_ms / 1000,
+                        model: context.model.unwrap_or("unknown").to_string(),
+                        input_tokens,
+                        output_tokens,
+                        cache_read_tokens: usage_tokens(usage, &["cacheReadTokens", "cache_read_input_tokens", "cached_tokens"]),
+                        cache_creation_tokens: usage_tokens(usage, &["cacheWriteTokens", "cache_creation_input_tokens"]),
+                        cost_usd: None,
+                        estimated: false,
+                        request_id: id,
+                        session_id: format!("copilot-{session_id}"),
+                        project_name: None,
+                        project: None,
+                    });
+                    return;
+                }
+            }
+
+            for (key, value) in map {
+                if key != "usage" {
+                    collect_usage_entries(value, session_id, context, entries);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// 累计一行事件的会话统计
+fn collect_event_stats(json: &serde_json::Value, ctx: &mut StatsContext) {
+    collect_stats(json, &mut ctx.stats);
+}
+
+/// 会话统计：用户消息为对话轮数，`tool_calls` 和 `toolName` 为工具调用
+fn collect_stats(json: &serde_json::Value, stats: &mut SessionStats) {
+    match json {
+        serde_json::Value::Array(items) => {
+            for item in items {
+                collect_stats(item, stats);
+            }
+        }
+        serde_json::Value::Object(map) => {
+            let kind = map.get("type").and_then(|t| t.as_str());
+            if map.get("role").and_then(|r| r.as_str()) == Some("user") || kind == Some("user.message") {
+                stats.conversation_count += 1;
+            }
+            if kind.is_some_and(|k| k.starts_with("tool.execution_start")) || kind == Some("tool_call") {
+                if let Some(name) = map
+                    .get("toolName")
+                    .or_else(|| map.get("data").and_then(|d| d.get("toolName")))
+                    .and_then(|n| n.as_str())
+                {
+                    *stats.tool_calls.entry(normalize_tool_name(name)).or_insert(0) += 1;
+                }
+            }
+            let tool_calls = map.get("tool_calls").and_then(|t| t.as_array());
+            for call in tool_calls.into_iter().flatten() {
+                if let Some(name) = call.get("function").and_then(|f| f.get("name")).and_then(|n| n.as_str()) {
+                    *stats.tool_calls.entry(normalize_tool_name(name)).or_insert(0) += 1;
+                }
+            }
+            for (key, value) in map {
+                if key != "tool_calls" {
+                    collect_stats(value, stats);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_usage_in_events_and_messages() {
+        let event = serde_json::json!({
+            "type": "assistant.usage",
+            "id": "evt-1",
+            "timestamp": "2026-10-01T10:00:00Z",
+            "data": { "model": "claude-sonnet-4.5", "inputTokens": 1200, "outputTokens": 80, "cacheReadTokens": 900 }
+        });
+        let legacy = serde_json::json!({
+            "chatMessages": [{
+                "role": "assistant",
+                "model": "gpt-4.1",
+                "timestamp": 1_790_000_000_000i64,
+                "usage": { "prompt_tokens": 300, "completion_tokens": 20 }
+            }]
+        });
+        let no_timestamp = serde_json::json!({
+            "chatMessages": [{ "role": "assistant", "usage": { "prompt_tokens": 300, "completion_tokens": 20 } }]
+        });
+        let mut entries = Vec::new();
+        collect_usage_entries(&event, "s1", UsageContext::default(), &mut entries);
+        collect_usage_entries(&legacy, "s2", UsageContext::default(), &mut entries);
+        collect_usage_entries(&no_timestamp, "s3", UsageContext::default(), &mut entries);
+        let legacy_id = format!("copilot-s2-{}", content_hash(&legacy["chatMessages"][0].to_string()));
+
+        let summary: Vec<_> = entries
+            .iter()
+            .map(|e| (e.request_id.as_str(), e.model.as_str(), e.input_tokens, e.output_tokens, e.cache_read_tokens))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("copilot-s1-evt-1", "claude-sonnet-4.5", 1200, 80, 900),
+                (legacy_id.as_str(), "gpt-4.1", 300, 20, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn accumulates_event_stats_across_appends() {
+        use std::io::Write;
+
+        let user = "{\"type\":\"user.message\",\"data\":{\"content\":\"hi\"}}\n";
+        let tool = "{\"type\":\"tool.execution_start\",\"data\":{\"toolName\":\"bash\"}}\n";
+        let dir = std::env::temp_dir().join(format!("ai-switch-copilot-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("events.jsonl");
+        fs::write(&path, format!("{user}{tool}")).unwrap();
+        let (_, cursor) = parse_copilot_events(&path, &ParseCursor::default()).unwrap();
+
+        fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(format!("{user}{tool}{tool}").as_bytes())
+            .unwrap();
+        let (_, cursor) = parse_copilot_events(&path, &cursor).unwrap();
+
+        let (_, stats) = CopilotSource.parse_session_stats(&path, &cursor).unwrap();
+        let (_, full) = CopilotSource.parse_session_stats(&path, &ParseCursor::default()).unwrap();
+        assert_eq!(stats.conversation_count, 2);
+        assert_eq!(stats.tool_calls.values().sum::<u32>(), 3);
+        assert_eq!(serde_json::to_value(&stats).unwrap(), serde_json::to_value(&full).unwrap());
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
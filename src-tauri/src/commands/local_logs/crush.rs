@@ -0,0 +1,243 @@
+//! Crush 日志解析
+//!
+//! Crush 把会话保存在项目目录下的 SQLite 数据库 `.crush/crush.db` 中：
+//! - `sessions`：每个会话累计的 prompt_tokens / completion_tokens / cost
+//! - `messages`：消息，`model` 为回复所用模型，`parts` 为 JSON 数组（包含 `tool_call`）
+//!
+//! 用量按会话汇总，每个会话导入一条记录和一份会话统计，会话继续后重新导入时刷新。
+
+use super::*;
+use rusqlite::{Connection, OpenFlags};
+
+const CRUSH_DB: &str = ".crush/crush.db";
+
+pub(super) struct CrushSource;
+
+impl LogSource for CrushSource {
+    fn id(&self) -> &'static str {
+        "crush"
+    }
+
+    fn name(&self) -> &'static str {
+        "Crush"
+    }
+
+    fn location(&self) -> Option<PathBuf> {
+        self.discover().into_iter().next()
+    }
+
+    /// 各项目的 `.crush/crush.db`
+    fn discover(&self) -> Vec<PathBuf> {
+        find_project_files(Path::new(CRUSH_DB))
+    }
+
+    /// 统计会话数
+    fn estimate_entries(&self, files: &[PathBuf]) -> u32 {
+        files
+            .iter()
+            .filter_map(|file| open_crush_db(file))
+            .filter_map(|conn| {
+                conn.query_row("SELECT COUNT(*) FROM sessions", [], |row| row.get::<_, u32>(0))
+                    .ok()
+            })
+            .sum()
+    }
+
+    fn parse_entries(&self, path: &Path) -> Vec<LocalLogEntry> {
+        parse_crush_db(path)
+    }
+
+    /// 一个数据库包含多个会话，统计按会话划分
+    fn parse_all_session_stats(&self, path: &Path, _cursor: &ParseCursor) -> Vec<(String, SessionStats)> {
+        parse_crush_session_stats(path)
+    }
+
+    /// 会话用量会随会话继续增加
+    fn refresh_existing(&self) -> bool {
+        true
+    }
+}
+
+fn open_crush_db(path: &Path) -> Option<Connection> {
+    Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX).ok()
+}
+
+/// Crush 的时间戳早期为秒，之后改为毫秒
+fn crush_timestamp(value: i64) -> i64 {
+    if value > 1_000_000_000_000 {
+        value / 1000
+    } else {
+        value
+    }
+}
+
+/// 每个会话一条记录
+fn parse_crush_db(path: &Path) -> Vec<LocalLogEntry> {
+    let Some(conn) = open_crush_db(path) else {
+        return vec![];
+    };
+    // 项目目录为 .crush 的上级目录
+    let project_dir = path.parent().and_then(|dir| dir.parent());
+    let project_name = project_dir
+        .and_then(|dir| dir.file_name())
+        .map(|name| name.to_string_lossy().to_string());
+    let project = project_dir.and_then(|dir| project_root(&dir.to_string_lossy()));
+
+    // 会话最后一条助手消息所用的模型
+    let Ok(mut stmt) = conn.prepare(
+        "SELECT s.id, s.prompt_tokens, s.completion_tokens, s.cost, s.created_at,
+                (SELECT m.model FROM messages m
+                 WHERE m.session_id = s.id AND m.role = 'assistant' AND m.model IS NOT NULL
+                 ORDER BY m.created_at DESC LIMIT 1)
+         FROM sessions s
+         WHERE s.prompt_tokens > 0 OR s.completion_tokens > 0",
+    ) else {
+        return vec![];
+    };
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, i64>(1)?,
+            row.get::<_, i64>(2)?,
+            row.get::<_, f64>(3)?,
+            row.get::<_, i64>(4)?,
+            row.get::<_, Option<String>>(5)?,
+        ))
+    });
+    let Ok(rows) = rows else {
+        return vec![];
+    };
+
+    rows.flatten()
+        .map(|(id, prompt_tokens, completion_tokens, cost, created_at, model)| LocalLogEntry {
+            source: "crush".to_string(),
+            timestamp: crush_timestamp(created_at),
+            model: model.unwrap_or_else(|| "unknown".to_string()),
+            input_tokens: prompt_tokens.max(0) as u32,
+            output_tokens: completion_tokens.max(0) as u32,
+            cache_read_tokens: 0,
+            cache_creation_tokens: 0,
+            cost_usd: (cost > 0.0).then_some(cost),
+            estimated: false,
+            request_id: format!("crush-{id}"),
+            session_id: format!("crush-{id}"),
+            project_name: project_name.clone(),
+            project: project.clone(),
+        })
+        .collect()
+}
+
+/// 各会话的统计（会话 ID 与记录一致，为 `crush-{sessions.id}`）：
+/// 用户消息为对话轮数，`tool_call` part 为工具调用
+fn parse_crush_session_stats(path: &Path) -> Vec<(String, SessionStats)> {
+    let Some(conn) = open_crush_db(path) else {
+        return vec![];
+    };
+    let Ok(mut stmt) = conn.prepare("SELECT session_id, role, parts, created_at, finished_at FROM messages") else {
+        return vec![];
+    };
+    let Ok(rows) = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, i64>(3)?,
+            row.get::<_, Option<i64>>(4)?,
+        ))
+    }) else {
+        return vec![];
+    };
+
+    // 会话 ID -> (统计, 修改过的文件)
+    let mut sessions: HashMap<String, (SessionStats, HashSet<String>)> = HashMap::new();
+    for (session_id, role, parts, created_at, finished_at) in rows.flatten() {
+        let (stats, files_modified) = sessions.entry(session_id).or_default();
+        if role == "user" {
+            stats.conversation_count += 1;
+            continue;
+        }
+        if role != "assistant" {
+            continue;
+        }
+        if let Some(finished_at) = finished_at {
+            let elapsed = crush_timestamp(finished_at) - crush_timestamp(created_at);
+            if (0..3600).contains(&elapsed) {
+                stats.response_time_ms += elapsed as u64 * 1000;
+            }
+        }
+
+        let parts: Vec<serde_json::Value> = serde_json::from_str(&parts).unwrap_or_default();
+        for part in &parts {
+            if part.get("type").and_then(|t| t.as_str()) != Some("tool_call") {
+                continue;
+            }
+            let Some(data) = part.get("data") else {
+                continue;
+            };
+            let Some(name) = data.get("name").and_then(|n| n.as_str()) else {
+                continue;
+            };
+            // input 是 JSON 字符串
+            let input: serde_json::Value = data
+                .get("input")
+                .and_then(|i| i.as_str())
+                .and_then(|i| serde_json::from_str(i).ok())
+                .unwrap_or(serde_json::Value::Null);
+            if is_file_edit_tool(name) || name == "multiedit" {
+                if let Some(file) = input.get("file_path").and_then(|f| f.as_str()) {
+                    files_modified.insert(file.to_string());
+                }
+                let (added, deleted) = extract_code_changes_from_input(&input, name);
+                stats.lines_added += added;
+                stats.lines_deleted += deleted;
+            }
+            *stats.tool_calls.entry(normalize_tool_name(name)).or_insert(0) += 1;
+        }
+    }
+
+    sessions
+        .into_iter()
+        .map(|(id, (mut stats, files_modified))| {
+            stats.files_changed = files_modified.len() as u32;
+            (format!("crush-{id}"), stats)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn session_stats_share_entry_session_ids() {
+        let dir = std::env::temp_dir().join(format!("ai-switch-crush-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(dir.join(".crush")).unwrap();
+        let path = dir.join(CRUSH_DB);
+        Connection::open(&path)
+            .unwrap()
+            .execute_batch(
+                r#"CREATE TABLE sessions (id TEXT, prompt_tokens INTEGER, completion_tokens INTEGER, cost REAL, created_at INTEGER);
+                   CREATE TABLE messages (session_id TEXT, role TEXT, parts TEXT, model TEXT, created_at INTEGER, finished_at INTEGER);
+                   INSERT INTO sessions VALUES ('a', 100, 10, 0.1, 1700000000), ('b', 200, 20, 0.2, 1700000100);
+                   INSERT INTO messages VALUES
+                       ('a', 'user', '[]', NULL, 1700000000, NULL),
+                       ('a', 'assistant', '[{"type":"tool_call","data":{"name":"view","input":"{}"}}]', 'gpt-5', 1700000001, 1700000002),
+                       ('b', 'user', '[]', NULL, 1700000100, NULL),
+                       ('b', 'user', '[]', NULL, 1700000200, NULL);"#,
+            )
+            .unwrap();
+
+        let mut entries: Vec<String> = parse_crush_db(&path).into_iter().map(|e| e.session_id).collect();
+        let mut stats = parse_crush_session_stats(&path);
+        entries.sort();
+        stats.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let ids: Vec<String> = stats.iter().map(|(id, _)| id.clone()).collect();
+        assert_eq!(ids, entries);
+        assert_eq!(ids, vec!["crush-a", "crush-b"]);
+        assert_eq!((stats[0].1.conversation_count, stats[0].1.tool_calls.values().sum::<u32>()), (1, 1));
+        assert_eq!(stats[1].1.conversation_count, 2);
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
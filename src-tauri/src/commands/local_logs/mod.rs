@@ -1,87 +1,68 @@
 //! 本地日志解析和导入模块
 //!
-//! 支持从 Claude Code、Codex CLI、Gemini CLI 和 Opencode 的本地日志文件中解析使用统计数据
-
-use crate::database::Database;
+//! 支持从 Claude Code、Codex CLI、Gemini CLI、Qwen Code、Opencode、Warp、Aider、Continue、Cline / Roo Code、
+//! Crush、Copilot CLI 以及 Cursor 等 VSCode 系工具的本地日志中解析使用统计数据。
+//! 每个工具实现一个 [`LogSource`]，在 [`LOG_SOURCES`] 中注册后即可被扫描、导入和自动导入。
+//! 提供日志目录的来源还会被 [`run_local_log_watcher`] 监听，文件变化后几秒内导入。
+
+mod aider;
+mod cline;
+mod continue_dev;
+mod copilot;
+mod crush;
+mod sources;
+mod state;
+mod tokenizer;
+mod watcher;
+
+pub use sources::{log_source, LogSource, LOG_SOURCES};
+pub use state::ParseCursor;
+pub use watcher::{run_local_log_watcher, LocalLogsImported, SourceImported};
+
+use crate::database::{lock_conn, Database};
 use crate::error::AppError;
+use crate::pricing::billing::BillingResolver;
+use crate::pricing::{self, PriceResolver, TokenCounts, SOURCE_COST_VERSION};
+use crate::project::project_root;
+use rayon::prelude::*;
+use state::{file_fingerprint, load_import_state, read_jsonl_from, save_import_state, FileFingerprint, ImportState};
+use rusqlite::{OptionalExtension, TransactionBehavior};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
 use std::sync::{Arc, Mutex, OnceLock};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use tauri::{Emitter, State};
-use tiktoken_rs::{cl100k_base, get_bpe_from_model, CoreBPE};
 
 // ============================================================================
 // 数据结构
 // ============================================================================
 
+/// 单个日志来源的扫描结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogSourceScan {
+    /// 来源 ID（导入时使用）
+    pub id: String,
+    /// 显示名称
+    pub name: String,
+    /// 日志文件 / 数据库文件数
+    pub files: u32,
+    /// 日志条目数（预估）
+    pub entries: u32,
+    /// 日志目录或数据库路径
+    pub path: Option<String>,
+}
+
 /// 扫描结果
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ScanResult {
-    /// Claude Code 日志文件数
-    pub claude_files: u32,
-    /// Claude Code 日志条目数（预估）
-    pub claude_entries: u32,
-    /// Claude Code 日志目录
-    pub claude_path: Option<String>,
-    /// Codex CLI 日志文件数
-    pub codex_files: u32,
-    /// Codex CLI 日志条目数（预估）
-    pub codex_entries: u32,
-    /// Codex CLI 日志目录
-    pub codex_path: Option<String>,
-    /// Gemini CLI 日志文件数
-    pub gemini_files: u32,
-    /// Gemini CLI 日志条目数（预估）
-    pub gemini_entries: u32,
-    /// Gemini CLI 日志目录
-    pub gemini_path: Option<String>,
-    /// Opencode 日志文件数
-    pub opencode_files: u32,
-    /// Opencode 日志条目数（预估）
-    pub opencode_entries: u32,
-    /// Opencode 日志目录
-    pub opencode_path: Option<String>,
-    /// Cursor 数据库文件数
-    pub cursor_files: u32,
-    /// Cursor 会话条目数（预估）
-    pub cursor_entries: u32,
-    /// Cursor 数据库路径
-    pub cursor_path: Option<String>,
-    /// Windsurf 数据库文件数
-    pub windsurf_files: u32,
-    /// Windsurf 会话条目数（预估）
-    pub windsurf_entries: u32,
-    /// Windsurf 数据库路径
-    pub windsurf_path: Option<String>,
-    /// Kiro 数据库文件数
-    pub kiro_files: u32,
-    /// Kiro 会话条目数（预估）
-    pub kiro_entries: u32,
-    /// Kiro 数据库路径
-    pub kiro_path: Option<String>,
-    /// Antigravity 数据库文件数
-    pub antigravity_files: u32,
-    /// Antigravity 会话条目数（预估）
-    pub antigravity_entries: u32,
-    /// Antigravity 数据库路径
-    pub antigravity_path: Option<String>,
-    /// Warp 数据库文件数
-    pub warp_files: u32,
-    /// Warp 会话条目数（预估）
-    pub warp_entries: u32,
-    /// Warp 数据库路径
-    pub warp_path: Option<String>,
-    /// Augment 数据库文件数
-    pub augment_files: u32,
-    /// Augment 会话条目数（预估）
-    pub augment_entries: u32,
-    /// Augment 数据库路径
-    pub augment_path: Option<String>,
+    /// 各来源的扫描结果（按注册顺序）
+    pub sources: Vec<LogSourceScan>,
     /// 数据库中已有的本地导入记录数
     pub existing_records: u32,
 }
@@ -106,8 +87,8 @@ pub struct LocalLogImportResult {
 struct LocalLogProgress {
     pub phase: String,
     pub source: String,
-    pub current: u32,
-    pub total: u32,
+    pub current: u64,
+    pub total: u64,
     pub message: String,
 }
 
@@ -115,8 +96,8 @@ fn emit_local_log_progress(
     window: &tauri::Window,
     phase: &str,
     source: &str,
-    current: u32,
-    total: u32,
+    current: u64,
+    total: u64,
     message: &str,
 ) {
     let _ = window.emit(
@@ -150,14 +131,21 @@ pub struct LocalLogEntry {
     pub cache_creation_tokens: u32,
     /// 成本（如果日志中有）
     pub cost_usd: Option<f64>,
-    /// 会话 ID（用于去重）
+    /// token 数为估算值（日志中没有用量数据，按消息文本估算或按比例拆分）
+    pub estimated: bool,
+    /// 记录 ID：来源内稳定且逐条唯一（消息 ID 或内容哈希），用于去重和重新导入
+    pub request_id: String,
+    /// 所属会话 ID（与会话统计的 session_id 一致）
     pub session_id: String,
     /// 项目名称
     pub project_name: Option<String>,
+    /// 项目路径（工作目录所在的 git 仓库根目录）
+    pub project: Option<String>,
 }
 
 /// 会话统计信息
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SessionStats {
     /// 对话轮数
     pub conversation_count: u32,
@@ -175,6 +163,39 @@ pub struct SessionStats {
     pub thinking_time_ms: u64,
 }
 
+/// 逐行累计的会话统计
+///
+/// 增量解析的来源把它保存在解析位置的上下文中，文件追加内容后只解析新增的行
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct StatsContext {
+    stats: SessionStats,
+    /// 修改过的文件（去重后即 files_changed）
+    files: HashSet<String>,
+    /// 最近一条用户消息的时间（毫秒）
+    last_user_ms: Option<i64>,
+    /// 用户消息之后还没有收到助手回复
+    awaiting_response: bool,
+}
+
+impl StatsContext {
+    /// 逐行累计 JSONL 文件的统计
+    fn collect_lines<'a>(&mut self, lines: impl IntoIterator<Item = &'a str>, collect: fn(&serde_json::Value, &mut Self)) {
+        for line in lines {
+            if let Ok(json) = serde_json::from_str::<serde_json::Value>(line) {
+                collect(&json, self);
+            }
+        }
+    }
+
+    fn stats(&self) -> SessionStats {
+        SessionStats {
+            files_changed: self.files.len() as u32,
+            ..self.stats.clone()
+        }
+    }
+}
+
 /// 工具调用信息
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -233,7 +254,7 @@ fn get_claude_log_dir() -> Option<PathBuf> {
 const FAST_SCAN_MAX_BYTES: u64 = 256 * 1024;
 const APPROX_BYTES_PER_LINE: u64 = 400;
 
-fn estimate_entries_from_file(path: &PathBuf, is_jsonl: bool) -> u32 {
+fn estimate_entries_from_file(path: &Path, is_jsonl: bool) -> u32 {
     let Ok(meta) = fs::metadata(path) else {
         return 0;
     };
@@ -251,14 +272,13 @@ fn estimate_entries_from_file(path: &PathBuf, is_jsonl: bool) -> u32 {
     1
 }
 
-/// 扫描 Claude Code 日志文件
-fn scan_claude_logs() -> (Vec<PathBuf>, u32) {
+/// 查找 Claude Code 日志文件
+fn find_claude_log_files() -> Vec<PathBuf> {
     let Some(log_dir) = get_claude_log_dir() else {
-        return (vec![], 0);
+        return vec![];
     };
 
     let mut files = Vec::new();
-    let mut entry_count = 0u32;
 
     // 遍历 projects 目录下的所有子目录
     if let Ok(entries) = fs::read_dir(&log_dir) {
@@ -270,7 +290,6 @@ fn scan_claude_logs() -> (Vec<PathBuf>, u32) {
                     for sub_entry in sub_entries.flatten() {
                         let file_path = sub_entry.path();
                         if file_path.extension().map_or(false, |ext| ext == "jsonl") {
-                            entry_count += estimate_entries_from_file(&file_path, true);
                             files.push(file_path);
                         }
                     }
@@ -279,16 +298,29 @@ fn scan_claude_logs() -> (Vec<PathBuf>, u32) {
         }
     }
 
-    (files, entry_count)
+    files
 }
 
 /// 解析 Claude Code 日志文件
-fn parse_claude_log_file(path: &PathBuf) -> Vec<LocalLogEntry> {
-    let mut entries = Vec::new();
+fn parse_claude_log_file(path: &Path) -> Vec<LocalLogEntry> {
+    parse_claude_log_from(path, &ParseCursor::default())
+        .map(|(entries, _)| entries)
+        .unwrap_or_default()
+}
 
-    let Ok(content) = fs::read_to_string(path) else {
-        return entries;
-    };
+/// 从指定位置解析 Claude Code 日志文件
+///
+/// 记录每行独立；会话统计跨行累计，保存在解析位置的上下文中。
+/// 之前的位置没有统计上下文时（旧版本的导入状态）从头解析，已导入的记录会被跳过
+fn parse_claude_log_from(path: &Path, cursor: &ParseCursor) -> Option<(Vec<LocalLogEntry>, ParseCursor)> {
+    let resumed = cursor
+        .context
+        .clone()
+        .and_then(|c| serde_json::from_value::<StatsContext>(c).ok());
+    let start = if resumed.is_some() { cursor.offset } else { 0 };
+    let mut stats = resumed.unwrap_or_default();
+    let (lines, offset) = read_jsonl_from(path, start)?;
+    stats.collect_lines(lines.iter().map(String::as_str), collect_claude_stats);
 
     // 从文件路径提取项目名称
     let project_name = path
@@ -304,13 +336,20 @@ fn parse_claude_log_file(path: &PathBuf) -> Vec<LocalLogEntry> {
         .map(|s| s.to_string())
         .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
 
-    for line in content.lines() {
-        if let Some(entry) = parse_claude_log_line(line, &session_id, &project_name) {
-            entries.push(entry);
-        }
-    }
+    let entries = lines
+        .iter()
+        .filter_map(|line| parse_claude_log_line(line, &session_id, &project_name))
+        .collect();
 
-    entries
+    let context = serde_json::to_value(&stats).ok();
+    Some((entries, ParseCursor { offset, context }))
+}
+
+/// 增量解析后解析位置中累计的会话统计（上下文即 [`StatsContext`] 的来源，如 Claude Code、Copilot CLI）
+fn stats_from_cursor(cursor: &ParseCursor) -> Option<SessionStats> {
+    serde_json::from_value::<StatsContext>(cursor.context.clone()?)
+        .ok()
+        .map(|ctx| ctx.stats())
 }
 
 /// 解析 Claude Code 日志行
@@ -401,8 +440,18 @@ fn parse_claude_log_line(
         .or_else(|| json.get("cost_usd"))
         .and_then(|v| v.as_f64());
 
-    // 生成唯一的条目 ID
-    let entry_session_id = format!("{}-{}", session_id, timestamp);
+    // 同一条回复的多个内容块各占一行且 usage 相同，按消息 ID + 请求 ID 去重；
+    // 恢复的会话会把历史消息复制到新文件，消息 ID 不随文件变化
+    let message_id = json.get("message").and_then(|m| m.get("id")).and_then(|v| v.as_str());
+    let request_id = json.get("requestId").and_then(|v| v.as_str());
+    let request_id = match (message_id, request_id) {
+        (Some(message_id), Some(request_id)) => format!("claude-{message_id}-{request_id}"),
+        (Some(message_id), None) => format!("claude-{message_id}"),
+        _ => match json.get("uuid").and_then(|v| v.as_str()) {
+            Some(uuid) => format!("claude-{session_id}-{uuid}"),
+            None => format!("claude-{session_id}-{}", content_hash(line)),
+        },
+    };
 
     Some(LocalLogEntry {
         source: "claude".to_string(),
@@ -413,8 +462,11 @@ fn parse_claude_log_line(
         cache_read_tokens,
         cache_creation_tokens,
         cost_usd,
-        session_id: entry_session_id,
+        estimated: false,
+        request_id,
+        session_id: session_id.to_string(),
         project_name: project_name.clone(),
+        project: json.get("cwd").and_then(|v| v.as_str()).and_then(project_root),
     })
 }
 
@@ -442,17 +494,16 @@ fn get_codex_log_dir() -> Option<PathBuf> {
     }
 }
 
-/// 扫描 Codex CLI 日志文件
-fn scan_codex_logs() -> (Vec<PathBuf>, u32) {
+/// 查找 Codex CLI 日志文件
+fn find_codex_log_files() -> Vec<PathBuf> {
     let Some(log_dir) = get_codex_log_dir() else {
-        return (vec![], 0);
+        return vec![];
     };
 
     let mut files = Vec::new();
-    let mut entry_count = 0u32;
 
     // 递归扫描函数
-    fn scan_dir_recursive(dir: &PathBuf, files: &mut Vec<PathBuf>, entry_count: &mut u32) {
+    fn scan_dir_recursive(dir: &Path, files: &mut Vec<PathBuf>) {
         if !dir.exists() {
             return;
         }
@@ -462,10 +513,9 @@ fn scan_codex_logs() -> (Vec<PathBuf>, u32) {
                 let path = entry.path();
                 if path.is_dir() {
                     // 递归扫描子目录 (sessions/YYYY/MM/DD/)
-                    scan_dir_recursive(&path, files, entry_count);
+                    scan_dir_recursive(&path, files);
                 } else if path.extension().map_or(false, |ext| ext == "jsonl") {
                     // 找到 .jsonl 文件
-                    *entry_count += estimate_entries_from_file(&path, true);
                     files.push(path);
                 }
             }
@@ -473,24 +523,63 @@ fn scan_codex_logs() -> (Vec<PathBuf>, u32) {
     }
 
     // 扫描根目录和 sessions 子目录
-    scan_dir_recursive(&log_dir, &mut files, &mut entry_count);
+    scan_dir_recursive(&log_dir, &mut files);
     
     // 也扫描 sessions 目录（如果和根目录不同）
     let sessions_dir = log_dir.join("sessions");
     if sessions_dir.exists() && sessions_dir != log_dir {
-        scan_dir_recursive(&sessions_dir, &mut files, &mut entry_count);
+        scan_dir_recursive(&sessions_dir, &mut files);
     }
 
-    (files, entry_count)
+    files
 }
 
 /// 解析 Codex CLI 日志文件
-fn parse_codex_log_file(path: &PathBuf) -> Vec<LocalLogEntry> {
-    let mut entries = Vec::new();
+fn parse_codex_log_file(path: &Path) -> Vec<LocalLogEntry> {
+    parse_codex_log_from(path, &ParseCursor::default())
+        .map(|(entries, _)| entries)
+        .unwrap_or_default()
+}
 
-    let Ok(content) = fs::read_to_string(path) else {
-        return entries;
-    };
+/// Codex 跨行的解析上下文（增量导入时保存在导入状态中）
+#[derive(Debug, Serialize, Deserialize)]
+struct CodexContext {
+    /// Codex 使用累计 token，需要追踪上一次的值来计算 delta
+    last_input: u32,
+    last_output: u32,
+    last_cached: u32,
+    model: String,
+    project: Option<String>,
+    /// 会话统计（旧版本的导入状态中没有）
+    #[serde(default)]
+    stats: Option<StatsContext>,
+}
+
+impl Default for CodexContext {
+    fn default() -> Self {
+        Self {
+            last_input: 0,
+            last_output: 0,
+            last_cached: 0,
+            model: "gpt-5".to_string(),
+            project: None,
+            stats: Some(StatsContext::default()),
+        }
+    }
+}
+
+/// 从指定位置解析 Codex CLI 日志文件
+///
+/// 之前的位置没有会话统计上下文时（旧版本的导入状态）从头解析，已导入的记录会被跳过
+fn parse_codex_log_from(path: &Path, cursor: &ParseCursor) -> Option<(Vec<LocalLogEntry>, ParseCursor)> {
+    let resumed = cursor
+        .context
+        .clone()
+        .and_then(|c| serde_json::from_value::<CodexContext>(c).ok())
+        .filter(|ctx| ctx.stats.is_some());
+    let start = if resumed.is_some() { cursor.offset } else { 0 };
+    let mut ctx = resumed.unwrap_or_default();
+    let (lines, offset) = read_jsonl_from(path, start)?;
 
     // 从文件名提取会话 ID
     let session_id = path
@@ -499,33 +588,43 @@ fn parse_codex_log_file(path: &PathBuf) -> Vec<LocalLogEntry> {
         .map(|s| s.to_string())
         .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
 
-    // Codex 使用累计 token，需要追踪上一次的值来计算 delta
-    let mut last_input_tokens = 0u32;
-    let mut last_output_tokens = 0u32;
-    let mut last_cached_tokens = 0u32;
-    let mut current_model = "gpt-5".to_string();
+    let mut entries = Vec::new();
+    if let Some(stats) = ctx.stats.as_mut() {
+        stats.collect_lines(lines.iter().map(String::as_str), collect_codex_stats);
+    }
 
-    for line in content.lines() {
-        if let Some(result) =
-            parse_codex_log_line(line, &session_id, last_input_tokens, last_output_tokens, last_cached_tokens)
-        {
-            last_input_tokens = result.new_input;
-            last_output_tokens = result.new_output;
-            last_cached_tokens = result.new_cached;
+    for line in &lines {
+        if let Some(result) = parse_codex_log_line(line, &session_id, ctx.last_input, ctx.last_output, ctx.last_cached) {
+            ctx.last_input = result.new_input;
+            ctx.last_output = result.new_output;
+            ctx.last_cached = result.new_cached;
             if let Some(m) = result.model {
-                current_model = m;
+                ctx.model = m;
+            }
+            if let Some(cwd) = result.cwd {
+                ctx.project = project_root(&cwd);
             }
 
             if let Some(mut entry) = result.entry {
                 if entry.model == "unknown" {
-                    entry.model = current_model.clone();
+                    entry.model = ctx.model.clone();
                 }
+                entry.project = ctx.project.clone();
                 entries.push(entry);
             }
         }
     }
 
-    entries
+    let context = serde_json::to_value(&ctx).ok();
+    Some((entries, ParseCursor { offset, context }))
+}
+
+/// 增量解析后 Codex 解析位置中累计的会话统计
+fn codex_stats_from_cursor(cursor: &ParseCursor) -> Option<SessionStats> {
+    serde_json::from_value::<CodexContext>(cursor.context.clone()?)
+        .ok()?
+        .stats
+        .map(|stats| stats.stats())
 }
 
 /// Codex 解析结果
@@ -535,6 +634,8 @@ struct CodexParseResult {
     new_output: u32,
     new_cached: u32,
     model: Option<String>,
+    /// 会话工作目录（session_meta / turn_context 中的 cwd）
+    cwd: Option<String>,
 }
 
 /// 解析 Codex CLI 日志行
@@ -548,13 +649,18 @@ fn parse_codex_log_line(
 ) -> Option<CodexParseResult> {
     let json: serde_json::Value = serde_json::from_str(line).ok()?;
 
-    // 处理模型上下文
-    if json.get("type").and_then(|v| v.as_str()) == Some("turn_context") {
-        let model = json
-            .get("payload")
+    // 处理会话元数据和模型上下文
+    let line_type = json.get("type").and_then(|v| v.as_str());
+    if matches!(line_type, Some("session_meta" | "turn_context")) {
+        let payload = json.get("payload");
+        let model = payload
             .and_then(|p| p.get("model"))
             .and_then(|v| v.as_str())
             .map(|s| s.to_string());
+        let cwd = payload
+            .and_then(|p| p.get("cwd"))
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string());
 
         return Some(CodexParseResult {
             entry: None,
@@ -562,6 +668,7 @@ fn parse_codex_log_line(
             new_output: last_output,
             new_cached: last_cached,
             model,
+            cwd,
         });
     }
 
@@ -669,8 +776,7 @@ fn parse_codex_log_line(
         })
         .unwrap_or_else(|| chrono::Utc::now().timestamp());
 
-    let entry_session_id = format!("{}-{}", session_id, timestamp);
-
+    // token_count 事件没有 ID，同一秒内可能有多次请求，按行内容区分
     let entry = LocalLogEntry {
         source: "codex".to_string(),
         timestamp,
@@ -680,8 +786,11 @@ fn parse_codex_log_line(
         cache_read_tokens: cached_delta,
         cache_creation_tokens: 0,
         cost_usd: None,
-        session_id: entry_session_id,
+        estimated: false,
+        request_id: format!("codex-{session_id}-{}", content_hash(line)),
+        session_id: session_id.to_string(),
         project_name: None,
+        project: None,
     };
 
     Some(CodexParseResult {
@@ -690,6 +799,7 @@ fn parse_codex_log_line(
         new_output: total_output,
         new_cached: total_cached,
         model: None,
+        cwd: None,
     })
 }
 
@@ -708,17 +818,23 @@ fn get_gemini_log_dir() -> Option<PathBuf> {
     }
 }
 
-/// 扫描 Gemini CLI 日志文件
-fn scan_gemini_logs() -> (Vec<PathBuf>, u32) {
-    let Some(log_dir) = get_gemini_log_dir() else {
-        return (vec![], 0);
-    };
+/// 获取 Qwen Code 日志目录（Gemini CLI 分支，目录结构相同）
+fn get_qwen_log_dir() -> Option<PathBuf> {
+    let qwen_dir = dirs::home_dir()?.join(".qwen").join("tmp");
+    qwen_dir.exists().then_some(qwen_dir)
+}
+
+/// 查找 Gemini CLI 日志文件
+fn find_gemini_log_files() -> Vec<PathBuf> {
+    get_gemini_log_dir().map_or_else(Vec::new, |log_dir| find_gemini_style_log_files(&log_dir))
+}
 
+/// 查找 Gemini CLI 格式的会话文件（Gemini CLI 及其分支 Qwen Code）
+fn find_gemini_style_log_files(log_dir: &Path) -> Vec<PathBuf> {
     let mut files = Vec::new();
-    let mut entry_count = 0u32;
 
     // 递归扫描 tmp/<project_hash>/chats/ 目录
-    fn scan_gemini_recursive(dir: &PathBuf, files: &mut Vec<PathBuf>, entry_count: &mut u32) {
+    fn scan_gemini_recursive(dir: &Path, files: &mut Vec<PathBuf>) {
         if !dir.exists() {
             return;
         }
@@ -728,7 +844,7 @@ fn scan_gemini_logs() -> (Vec<PathBuf>, u32) {
                 let path = entry.path();
                 if path.is_dir() {
                     // 递归扫描子目录
-                    scan_gemini_recursive(&path, files, entry_count);
+                    scan_gemini_recursive(&path, files);
                 } else {
                     // Gemini 日志可能是 .json 或 .jsonl
                     let ext = path.extension().and_then(|e| e.to_str());
@@ -742,8 +858,6 @@ fn scan_gemini_logs() -> (Vec<PathBuf>, u32) {
                             .map_or(false, |n| n.starts_with("session-"));
                         
                         if is_chat_file || is_session_file {
-                            let is_jsonl = ext == Some("jsonl");
-                            *entry_count += estimate_entries_from_file(&path, is_jsonl);
                             files.push(path);
                         }
                     }
@@ -752,13 +866,13 @@ fn scan_gemini_logs() -> (Vec<PathBuf>, u32) {
         }
     }
 
-    scan_gemini_recursive(&log_dir, &mut files, &mut entry_count);
+    scan_gemini_recursive(log_dir, &mut files);
 
-    (files, entry_count)
+    files
 }
 
 /// 解析 Gemini CLI 日志文件
-fn parse_gemini_log_file(path: &PathBuf) -> Vec<LocalLogEntry> {
+fn parse_gemini_log_file(path: &Path) -> Vec<LocalLogEntry> {
     let mut entries = Vec::new();
 
     let Ok(content) = fs::read_to_string(path) else {
@@ -814,6 +928,10 @@ fn parse_gemini_entries_from_value(json: &serde_json::Value, session_id: &str) -
         return messages
             .iter()
             .filter_map(|m| parse_gemini_message(m, base_session_id))
+            .map(|mut entry| {
+                entry.session_id = session_id.to_string();
+                entry
+            })
             .collect();
     }
 
@@ -908,11 +1026,10 @@ fn parse_gemini_message(message: &serde_json::Value, session_id: &str) -> Option
         })
         .unwrap_or_else(|| chrono::Utc::now().timestamp());
 
-    let msg_id = message
-        .get("id")
-        .and_then(|v| v.as_str())
-        .unwrap_or("msg");
-    let entry_session_id = format!("{}-{}-{}", session_id, timestamp, msg_id);
+    let request_id = match message.get("id").and_then(|v| v.as_str()) {
+        Some(msg_id) => format!("{session_id}-{timestamp}-{msg_id}"),
+        None => format!("{session_id}-{}", content_hash(&message.to_string())),
+    };
 
     Some(LocalLogEntry {
         source: "gemini".to_string(),
@@ -923,8 +1040,11 @@ fn parse_gemini_message(message: &serde_json::Value, session_id: &str) -> Option
         cache_read_tokens,
         cache_creation_tokens,
         cost_usd: None,
-        session_id: entry_session_id,
+        estimated: false,
+        request_id,
+        session_id: session_id.to_string(),
         project_name: None,
+        project: None,
     })
 }
 
@@ -1007,8 +1127,6 @@ fn parse_gemini_usage_from_json(json: &serde_json::Value, session_id: &str) -> O
         })
         .unwrap_or_else(|| chrono::Utc::now().timestamp());
 
-    let entry_session_id = format!("{}-{}", session_id, timestamp);
-
     Some(LocalLogEntry {
         source: "gemini".to_string(),
         timestamp,
@@ -1018,8 +1136,11 @@ fn parse_gemini_usage_from_json(json: &serde_json::Value, session_id: &str) -> O
         cache_read_tokens: cached_tokens,
         cache_creation_tokens: 0,
         cost_usd: None,
-        session_id: entry_session_id,
+        estimated: false,
+        request_id: format!("{session_id}-{}", content_hash(&json.to_string())),
+        session_id: session_id.to_string(),
         project_name: None,
+        project: None,
     })
 }
 
@@ -1044,14 +1165,14 @@ fn get_opencode_log_dir() -> Option<PathBuf> {
     }
 }
 
-/// 扫描 Opencode 日志文件
-fn scan_opencode_logs() -> (Vec<PathBuf>, u32) {
+/// 查找 Opencode 消息文件
+fn find_opencode_log_files() -> Vec<PathBuf> {
     let Some(storage_dir) = get_opencode_log_dir() else {
-        return (vec![], 0);
+        return vec![];
     };
 
     let mut files = Vec::new();
-    let mut entry_count = 0u32;
+    let mut latest_messages = HashMap::new();
 
     // 扫描 message/{sessionID}/{messageID}.json 文件
     // opencode 的消息文件名是 messageID.json，不是 msg_messageID.json
@@ -1061,13 +1182,15 @@ fn scan_opencode_logs() -> (Vec<PathBuf>, u32) {
             for session in sessions.flatten() {
                 let session_path = session.path();
                 if session_path.is_dir() {
+                    if let Some(latest) = latest_json_file(&session_path) {
+                        latest_messages.insert(session_path.clone(), latest);
+                    }
                     if let Ok(messages) = fs::read_dir(&session_path) {
                         for msg in messages.flatten() {
                             let msg_path = msg.path();
                             if msg_path.extension().and_then(|e| e.to_str()) == Some("json") {
                                 // 所有 .json 文件都是消息文件
                                 files.push(msg_path);
-                                entry_count += 1;
                             }
                         }
                     }
@@ -1076,11 +1199,19 @@ fn scan_opencode_logs() -> (Vec<PathBuf>, u32) {
         }
     }
 
-    (files, entry_count)
+    if let Ok(mut cache) = OPENCODE_LATEST_MESSAGES.get_or_init(Default::default).lock() {
+        *cache = latest_messages;
+    }
+    files
+}
+
+/// Opencode 消息的会话 ID：所在的会话目录名（message/{sessionID}/{messageID}.json）
+fn opencode_session_id(path: &Path) -> Option<String> {
+    path.parent()?.file_name()?.to_str().map(|s| s.to_string())
 }
 
 /// 解析 Opencode 日志文件
-fn parse_opencode_log_file(path: &PathBuf) -> Vec<LocalLogEntry> {
+fn parse_opencode_log_file(path: &Path) -> Vec<LocalLogEntry> {
     let mut entries = Vec::new();
 
     let Ok(content) = fs::read_to_string(path) else {
@@ -1092,12 +1223,7 @@ fn parse_opencode_log_file(path: &PathBuf) -> Vec<LocalLogEntry> {
     };
 
     // 从路径提取会话 ID
-    let session_id = path
-        .parent()
-        .and_then(|p| p.file_name())
-        .and_then(|n| n.to_str())
-        .map(|s| s.to_string())
-        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    let session_id = opencode_session_id(path).unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
 
     // 尝试解析 Opencode 消息格式
     if let Some(entry) = parse_opencode_message(&json, &session_id) {
@@ -1214,11 +1340,10 @@ fn parse_opencode_message(json: &serde_json::Value, session_id: &str) -> Option<
         .or_else(|| json.get("costUSD"))
         .and_then(|v| v.as_f64());
 
-    let msg_id = json
-        .get("id")
-        .and_then(|v| v.as_str())
-        .unwrap_or("msg");
-    let entry_session_id = format!("{}-{}-{}", session_id, timestamp, msg_id);
+    let request_id = match json.get("id").and_then(|v| v.as_str()) {
+        Some(msg_id) => format!("{session_id}-{timestamp}-{msg_id}"),
+        None => format!("{session_id}-{}", content_hash(&json.to_string())),
+    };
 
     Some(LocalLogEntry {
         source: "opencode".to_string(),
@@ -1229,8 +1354,11 @@ fn parse_opencode_message(json: &serde_json::Value, session_id: &str) -> Option<
         cache_read_tokens,
         cache_creation_tokens,
         cost_usd,
-        session_id: entry_session_id,
+        estimated: false,
+        request_id,
+        session_id: session_id.to_string(),
         project_name: None,
+        project: None,
     })
 }
 
@@ -1380,33 +1508,24 @@ fn get_cursor_db_path() -> Option<PathBuf> {
     paths.into_iter().next()
 }
 
-/// 扫描 Cursor 数据库
-fn scan_cursor_logs() -> (Vec<PathBuf>, u32) {
-    let db_paths = get_cursor_db_paths();
-    if db_paths.is_empty() {
-        return (vec![], 0);
-    }
-
-    let mut entry_count = 0u32;
+/// 预估 VSCode 系数据库（Cursor / Windsurf 等）中的会话数
+fn estimate_vscode_db_entries(db_paths: &[PathBuf]) -> u32 {
     // 如果数据库文件过多，扫描计数会很慢，优先只统计 globalStorage
     if db_paths.len() > 8 {
-        if let Some(global) = db_paths
+        db_paths
             .iter()
             .find(|p| p.to_string_lossy().contains("globalStorage"))
-        {
-            entry_count = count_cursor_sessions(global).unwrap_or(0);
-        }
+            .and_then(|global| count_cursor_sessions(global))
+            .unwrap_or(0)
     } else {
-        for path in &db_paths {
-            entry_count = entry_count.saturating_add(count_cursor_sessions(path).unwrap_or(0));
-        }
+        db_paths
+            .iter()
+            .fold(0u32, |count, path| count.saturating_add(count_cursor_sessions(path).unwrap_or(0)))
     }
-
-    (db_paths, entry_count)
 }
 
 /// 统计 Cursor 数据库中的会话数量
-fn count_cursor_sessions(db_path: &PathBuf) -> Option<u32> {
+fn count_cursor_sessions(db_path: &Path) -> Option<u32> {
     use rusqlite::{Connection, OpenFlags};
     
     // 以只读模式打开 Cursor 数据库
@@ -1451,7 +1570,7 @@ fn count_cursor_sessions(db_path: &PathBuf) -> Option<u32> {
 }
 
 /// 获取 workspaceStorage 的 workspace ID
-fn cursor_workspace_id(path: &PathBuf) -> Option<String> {
+fn cursor_workspace_id(path: &Path) -> Option<String> {
     let parent = path.parent()?;
     let parent_name = parent.file_name()?.to_string_lossy().to_string();
     let grand = parent.parent()?;
@@ -1496,7 +1615,7 @@ fn parse_json_bytes(bytes: &[u8]) -> Option<serde_json::Value> {
 }
 
 /// 解析 Cursor 数据库文件
-fn parse_cursor_db(path: &PathBuf) -> Vec<LocalLogEntry> {
+fn parse_cursor_db(path: &Path) -> Vec<LocalLogEntry> {
     use rusqlite::{Connection, OpenFlags};
     
     let mut entries = Vec::new();
@@ -1610,13 +1729,6 @@ fn parse_cursor_chat_data(json: &serde_json::Value, workspace_id: Option<&str>)
                     .or_else(|| tab.get("model"))
                     .or_else(|| tab.get("modelName"))
                     .and_then(|v| v.as_str());
-
-                // 估算 token
-                let (input_tokens, output_tokens) = estimate_tokens_from_messages(bubbles, model_hint);
-                
-                if input_tokens == 0 && output_tokens == 0 {
-                    continue;
-                }
                 
                 // 获取 tab ID
                 let tab_id = tab.get("tabId")
@@ -1628,26 +1740,13 @@ fn parse_cursor_chat_data(json: &serde_json::Value, workspace_id: Option<&str>)
                     .and_then(|v| v.as_str())
                     .unwrap_or("Untitled");
 
-                let timestamp = extract_cursor_timestamp(tab);
-                let session_id = if let Some(id) = workspace_id {
-                    format!("cursor-chat-{}-{}", id, tab_id)
-                } else {
-                    format!("cursor-chat-{}", tab_id)
+                let conversation = CursorConversation {
+                    id: cursor_session_id("chat", workspace_id, tab_id),
+                    model: model_hint.unwrap_or("cursor-chat"),
+                    title,
+                    timestamp: extract_cursor_timestamp(tab),
                 };
-                
-                let model_name = model_hint.unwrap_or("cursor-chat");
-                entries.push(LocalLogEntry {
-                    source: "cursor".to_string(),
-                    timestamp,
-                    model: model_name.to_string(),
-                    input_tokens,
-                    output_tokens,
-                    cache_read_tokens: 0,
-                    cache_creation_tokens: 0,
-                    cost_usd: None,
-                    session_id,
-                    project_name: Some(title.to_string()),
-                });
+                entries.extend(conversation.turn_entries(bubbles, model_hint, estimate_tokens_from_messages));
             }
         }
     }
@@ -1663,8 +1762,8 @@ fn parse_cursor_workspace_composer(json: &serde_json::Value, workspace_id: Optio
     if let Some(all_composers) = json.get("allComposers").and_then(|a| a.as_array()) {
         for composer in all_composers {
             // 检查 conversation 数组
-            if let Some(conversation) = composer.get("conversation").and_then(|c| c.as_array()) {
-                if conversation.is_empty() {
+            if let Some(messages) = composer.get("conversation").and_then(|c| c.as_array()) {
+                if messages.is_empty() {
                     continue;
                 }
                 
@@ -1673,43 +1772,23 @@ fn parse_cursor_workspace_composer(json: &serde_json::Value, workspace_id: Optio
                     .and_then(|m| m.get("modelName"))
                     .and_then(|v| v.as_str())
                     .unwrap_or("cursor-composer");
-
-                // 估算 token
-                let (input_tokens, output_tokens) = estimate_tokens_from_cursor_conversation(conversation, Some(model));
-                
-                if input_tokens == 0 && output_tokens == 0 {
-                    continue;
-                }
                 
                 // 获取 composer ID
                 let composer_id = composer.get("composerId")
                     .and_then(|v| v.as_str())
                     .unwrap_or("unknown");
-                
-                // 获取名称
-                let name = composer.get("name")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("Untitled");
 
-                let timestamp = extract_cursor_timestamp(composer);
-                let session_id = if let Some(id) = workspace_id {
-                    format!("cursor-workspace-{}-{}", id, composer_id)
-                } else {
-                    format!("cursor-workspace-{}", composer_id)
+                let conversation = CursorConversation {
+                    id: cursor_session_id("workspace", workspace_id, composer_id),
+                    model,
+                    title: composer.get("name").and_then(|v| v.as_str()).unwrap_or("Untitled"),
+                    timestamp: extract_cursor_timestamp(composer),
                 };
-                
-                entries.push(LocalLogEntry {
-                    source: "cursor".to_string(),
-                    timestamp,
-                    model: model.to_string(),
-                    input_tokens,
-                    output_tokens,
-                    cache_read_tokens: 0,
-                    cache_creation_tokens: 0,
-                    cost_usd: None,
-                    session_id,
-                    project_name: Some(name.to_string()),
-                });
+                entries.extend(conversation.turn_entries(
+                    messages,
+                    Some(model),
+                    estimate_tokens_from_cursor_conversation,
+                ));
             }
         }
     }
@@ -1717,43 +1796,177 @@ fn parse_cursor_workspace_composer(json: &serde_json::Value, workspace_id: Optio
     entries
 }
 
-/// 解析 Cursor Global Composer 数据 (composerData:{uuid})
-type BubbleTokenMap = HashMap<String, (u32, u32)>;
+/// Cursor 对话的会话 ID：`cursor-{类型}-{工作区 ID}-{对话 ID}`（全局存储没有工作区 ID）
+fn cursor_session_id(kind: &str, workspace_id: Option<&str>, conversation_id: &str) -> String {
+    match workspace_id {
+        Some(workspace_id) => format!("cursor-{kind}-{workspace_id}-{conversation_id}"),
+        None => format!("cursor-{kind}-{conversation_id}"),
+    }
+}
+
+/// aiService 旧格式按工作区汇总为一个会话
+fn cursor_aiservice_session_id(workspace_id: Option<&str>) -> String {
+    match workspace_id {
+        Some(workspace_id) => format!("cursor-aiservice-{workspace_id}"),
+        None => "cursor-aiservice".to_string(),
+    }
+}
+
+/// 复用 Cursor 解析的 VSCode 系工具的 ID：替换 `cursor-` 前缀，避免与 Cursor 冲突
+fn vscode_app_id(cursor_id: &str, source_name: &str) -> String {
+    cursor_id.replacen("cursor-", &format!("{source_name}-"), 1)
+}
 
-static TOKENIZER_CACHE: OnceLock<Mutex<HashMap<String, Arc<CoreBPE>>>> = OnceLock::new();
+/// Cursor 的一个对话（Chat tab 或 Composer），按轮次拆分为使用记录
+struct CursorConversation<'a> {
+    /// 对话 ID，作为记录的会话 ID
+    id: String,
+    model: &'a str,
+    title: &'a str,
+    /// 对话时间，消息没有时间时使用
+    timestamp: i64,
+}
 
-fn tokenizer_for_model(model: &str) -> Arc<CoreBPE> {
-    let cleaned = clean_model_id(model).to_lowercase();
-    let cache = TOKENIZER_CACHE.get_or_init(|| Mutex::new(HashMap::new()));
+impl CursorConversation<'_> {
+    /// 每轮（连续的用户消息及其后的助手回复）一条记录，ID 为该轮首条消息的 bubbleId
+    fn turn_entries(
+        &self,
+        messages: &[serde_json::Value],
+        model_hint: Option<&str>,
+        estimate: fn(&[serde_json::Value], Option<&str>) -> (u32, u32),
+    ) -> Vec<LocalLogEntry> {
+        split_cursor_turns(messages)
+            .into_iter()
+            .filter_map(|(turn_id, turn)| {
+                let (input_tokens, output_tokens) = estimate(turn, model_hint);
+                if input_tokens == 0 && output_tokens == 0 {
+                    return None;
+                }
+                let timestamp = turn.iter().find_map(cursor_message_timestamp).unwrap_or(self.timestamp);
+                Some(self.entry(&turn_id, timestamp, input_tokens, output_tokens))
+            })
+            .collect()
+    }
 
-    if let Ok(guard) = cache.lock() {
-        if let Some(tok) = guard.get(&cleaned) {
-            return tok.clone();
+    /// bubbleId 单独存储的对话：按对话中的顺序分轮汇总各消息的 token
+    fn bubble_entries(&self, bubbles: &[&CursorBubble]) -> Vec<LocalLogEntry> {
+        let mut entries = Vec::new();
+        let mut turn: Option<(&str, Option<i64>, u32, u32)> = None;
+        let mut last_is_user = false;
+        for bubble in bubbles {
+            if bubble.is_user && !last_is_user {
+                if let Some((id, ts, input, output)) = turn.take() {
+                    entries.push(self.entry(id, ts.unwrap_or(self.timestamp), input, output));
+                }
+            }
+            last_is_user = bubble.is_user;
+            let (_, ts, input, output) = turn.get_or_insert((&bubble.id, bubble.timestamp, 0, 0));
+            if ts.is_none() {
+                *ts = bubble.timestamp;
+            }
+            if bubble.is_user {
+                *input = input.saturating_add(bubble.tokens);
+            } else {
+                *output = output.saturating_add(bubble.tokens);
+            }
+        }
+        if let Some((id, ts, input, output)) = turn {
+            entries.push(self.entry(id, ts.unwrap_or(self.timestamp), input, output));
         }
+        entries.retain(|e| e.input_tokens > 0 || e.output_tokens > 0);
+        entries
     }
 
-    let bpe = get_bpe_from_model(&cleaned).or_else(|_| cl100k_base()).unwrap_or_else(|_| {
-        // 极端情况下 fallback 到 cl100k_base
-        cl100k_base().expect("cl100k_base tokenizer init failed")
-    });
-    let arc = Arc::new(bpe);
+    fn entry(&self, turn_id: &str, timestamp: i64, input_tokens: u32, output_tokens: u32) -> LocalLogEntry {
+        LocalLogEntry {
+            source: "cursor".to_string(),
+            timestamp,
+            model: self.model.to_string(),
+            input_tokens,
+            output_tokens,
+            cache_read_tokens: 0,
+            cache_creation_tokens: 0,
+            cost_usd: None,
+            estimated: true,
+            request_id: format!("{}-{}", self.id, turn_id),
+            session_id: self.id.clone(),
+            project_name: Some(self.title.to_string()),
+            project: None,
+        }
+    }
+}
 
-    if let Ok(mut guard) = cache.lock() {
-        guard.insert(cleaned, arc.clone());
+/// Cursor 消息角色：`Some(true)` 为用户，`Some(false)` 为助手
+fn cursor_message_is_user(message: &serde_json::Value) -> Option<bool> {
+    let role = message.get("type").or_else(|| message.get("role"))?;
+    let role = role.as_i64().map(|n| n.to_string()).or_else(|| role.as_str().map(|s| s.to_string()))?;
+    match role.as_str() {
+        "user" | "human" | "1" => Some(true),
+        "assistant" | "ai" | "bot" | "2" => Some(false),
+        _ => None,
     }
+}
 
-    arc
+/// 消息 ID（bubbleId）
+fn cursor_message_id(message: &serde_json::Value) -> Option<&str> {
+    message
+        .get("bubbleId")
+        .or_else(|| message.get("id"))
+        .or_else(|| message.get("messageId"))
+        .and_then(|v| v.as_str())
 }
 
-fn count_tokens_for_text(text: &str, model: Option<&str>) -> usize {
-    if text.is_empty() {
-        return 0;
+/// 消息自身的时间（秒）
+fn cursor_message_timestamp(message: &serde_json::Value) -> Option<i64> {
+    let value = message.get("timestamp").or_else(|| message.get("createdAt"))?;
+    match value.as_i64() {
+        Some(ts) if ts > 1_000_000_000_000 => Some(ts / 1000),
+        Some(ts) => Some(ts),
+        None => chrono::DateTime::parse_from_rfc3339(value.as_str()?)
+            .ok()
+            .map(|dt| dt.timestamp()),
+    }
+}
+
+/// 将对话按轮次拆分，返回 (轮次 ID, 该轮消息)；没有 bubbleId 时以首条消息的序号为 ID
+fn split_cursor_turns(messages: &[serde_json::Value]) -> Vec<(String, &[serde_json::Value])> {
+    let mut turns = Vec::new();
+    let mut start = 0;
+    let mut last_is_user = false;
+    for (idx, message) in messages.iter().enumerate() {
+        let Some(is_user) = cursor_message_is_user(message) else {
+            continue;
+        };
+        if is_user && !last_is_user && idx > start {
+            turns.push(start..idx);
+            start = idx;
+        }
+        last_is_user = is_user;
+    }
+    if start < messages.len() {
+        turns.push(start..messages.len());
     }
-    let model_name = model.unwrap_or("cl100k_base");
-    let tokenizer = tokenizer_for_model(model_name);
-    tokenizer.encode_with_special_tokens(text).len()
+    turns
+        .into_iter()
+        .map(|range| {
+            let id = cursor_message_id(&messages[range.start]).map_or_else(|| range.start.to_string(), |id| id.to_string());
+            (id, &messages[range])
+        })
+        .collect()
+}
+
+/// bubbleId 单独存储的一条消息
+struct CursorBubble {
+    id: String,
+    is_user: bool,
+    tokens: u32,
+    timestamp: Option<i64>,
 }
 
+/// composer ID -> 该对话的消息
+type BubbleTokenMap = HashMap<String, Vec<CursorBubble>>;
+
+/// 解析 Cursor Global Composer 数据 (composerData:{uuid})
 fn parse_cursor_composer_data(
     conn: &rusqlite::Connection,
     json: &serde_json::Value,
@@ -1761,8 +1974,6 @@ fn parse_cursor_composer_data(
     workspace_id: Option<&str>,
     bubble_token_map: Option<&BubbleTokenMap>,
 ) -> Vec<LocalLogEntry> {
-    let mut entries = Vec::new();
-    
     // 从 key 提取 composer ID
     let composer_id = json.get("composerId")
         .and_then(|v| v.as_str())
@@ -1776,72 +1987,51 @@ fn parse_cursor_composer_data(
         .or_else(|| json.get("model").and_then(|v| v.as_str()))
         .unwrap_or("cursor-composer");
 
-    // 检查 conversation 数组 (inline storage)
-    let conversation = json.get("conversation").and_then(|c| c.as_array());
-    
-    // 估算 token（优先 inline conversation，其次 bubbleId）
-    let (input_tokens, output_tokens) = if let Some(msgs) = conversation {
-        if msgs.is_empty() {
-            (0, 0)
-        } else {
-            estimate_tokens_from_cursor_conversation(msgs, Some(model))
-        }
-    } else {
-        (0, 0)
+    let conversation = CursorConversation {
+        id: cursor_session_id("global", workspace_id, composer_id),
+        model,
+        title: json.get("name").and_then(|v| v.as_str()).unwrap_or("Untitled"),
+        timestamp: extract_cursor_timestamp(json),
     };
 
-    let (input_tokens, output_tokens) = if input_tokens == 0 && output_tokens == 0 {
-        if let Some(map) = bubble_token_map {
-            if let Some((in_tokens, out_tokens)) = map.get(composer_id) {
-                (*in_tokens, *out_tokens)
-            } else {
-                (0, 0)
-            }
-        } else {
-            let bubbles = load_cursor_bubbles(conn, composer_id);
-            if bubbles.is_empty() {
-                (0, 0)
-            } else {
-                estimate_tokens_from_messages(&bubbles, Some(model))
-            }
-        }
-    } else {
-        (input_tokens, output_tokens)
-    };
-    
-    if input_tokens == 0 && output_tokens == 0 {
+    // 优先 inline conversation，其次 bubbleId
+    let messages = json.get("conversation").and_then(|c| c.as_array());
+    let entries = messages
+        .map(|msgs| conversation.turn_entries(msgs, Some(model), estimate_tokens_from_cursor_conversation))
+        .unwrap_or_default();
+    if !entries.is_empty() {
         return entries;
     }
-    
-    // 获取名称
-    let name = json.get("name")
-        .and_then(|v| v.as_str())
-        .unwrap_or("Untitled");
-    
-    
-    // 获取时间戳
-    let timestamp = extract_cursor_timestamp(json);
-    
-    let session_id = if let Some(id) = workspace_id {
-        format!("cursor-global-{}-{}", id, composer_id)
-    } else {
-        format!("cursor-global-{}", composer_id)
+
+    let loaded;
+    let mut bubbles: Vec<&CursorBubble> = match bubble_token_map {
+        Some(map) => map.get(composer_id).map(|b| b.iter().collect()).unwrap_or_default(),
+        None => {
+            loaded = load_cursor_bubbles(conn, composer_id, Some(model));
+            loaded.iter().collect()
+        }
     };
-    
-    entries.push(LocalLogEntry {
-        source: "cursor".to_string(),
-        timestamp,
-        model: model.to_string(),
-        input_tokens,
-        output_tokens,
-        cache_read_tokens: 0,
-        cache_creation_tokens: 0,
-        cost_usd: None,
-        session_id,
-        project_name: Some(name.to_string()),
+    order_cursor_bubbles(&mut bubbles, json);
+    conversation.bubble_entries(&bubbles)
+}
+
+/// 按 composer 中记录的消息顺序（fullConversationHeadersOnly）排列，没有时按时间
+fn order_cursor_bubbles(bubbles: &mut [&CursorBubble], composer: &serde_json::Value) {
+    let order: HashMap<&str, usize> = composer
+        .get("fullConversationHeadersOnly")
+        .and_then(|h| h.as_array())
+        .into_iter()
+        .flatten()
+        .enumerate()
+        .filter_map(|(idx, header)| Some((header.get("bubbleId")?.as_str()?, idx)))
+        .collect();
+    bubbles.sort_by(|a, b| {
+        let pos = |bubble: &CursorBubble| order.get(bubble.id.as_str()).copied().unwrap_or(usize::MAX);
+        pos(a)
+            .cmp(&pos(b))
+            .then(a.timestamp.cmp(&b.timestamp))
+            .then(a.id.cmp(&b.id))
     });
-    
-    entries
 }
 
 /// 解析 Cursor aiService 旧格式（pre-v0.43）
@@ -1870,23 +2060,19 @@ fn parse_cursor_aiservice(
             .and_then(|v| v.as_str())
             .unwrap_or("");
 
-        let input_tokens = (prompt_text.len() / 4).max(if !prompt_text.is_empty() { 1 } else { 0 }) as u32;
-        let output_tokens = (gen_text.len() / 4).max(if !gen_text.is_empty() { 1 } else { 0 }) as u32;
-
-        if input_tokens == 0 && output_tokens == 0 {
-            continue;
-        }
-
         let model = gens_arr.get(idx)
             .and_then(|g| g.get("model").or_else(|| g.get("modelId")).or_else(|| g.get("modelName")))
             .and_then(|v| v.as_str())
             .unwrap_or("cursor-aiservice");
 
-        let session_id = if let Some(id) = workspace_id {
-            format!("cursor-aiservice-{}-{}", id, idx)
-        } else {
-            format!("cursor-aiservice-{}", idx)
-        };
+        let input_tokens = tokenizer::estimate_tokens(prompt_text, Some(model));
+        let output_tokens = tokenizer::estimate_tokens(gen_text, Some(model));
+
+        if input_tokens == 0 && output_tokens == 0 {
+            continue;
+        }
+
+        let session_id = cursor_aiservice_session_id(workspace_id);
 
         entries.push(LocalLogEntry {
             source: "cursor".to_string(),
@@ -1897,31 +2083,37 @@ fn parse_cursor_aiservice(
             cache_read_tokens: 0,
             cache_creation_tokens: 0,
             cost_usd: None,
+            estimated: true,
+            request_id: format!("{session_id}-{idx}"),
             session_id,
             project_name: None,
+            project: None,
         });
     }
 
     entries
 }
 
-/// 加载 Cursor bubbleId 存储的消息
-fn load_cursor_bubbles(conn: &rusqlite::Connection, composer_id: &str) -> Vec<serde_json::Value> {
+/// 加载 Cursor bubbleId 存储的某个对话的消息
+fn load_cursor_bubbles(conn: &rusqlite::Connection, composer_id: &str, model_hint: Option<&str>) -> Vec<CursorBubble> {
     let mut bubbles = Vec::new();
     let key_pattern = format!("bubbleId:{}:%", composer_id);
 
-    if let Ok(mut stmt) = conn.prepare("SELECT value FROM cursorDiskKV WHERE key LIKE ?1") {
+    if let Ok(mut stmt) = conn.prepare("SELECT key, value FROM cursorDiskKV WHERE key LIKE ?1") {
         if let Ok(rows) = stmt.query_map([key_pattern], |row| {
+            let key: String = row.get(0)?;
             let value = row
-                .get::<_, Vec<u8>>(0)
+                .get::<_, Vec<u8>>(1)
                 .ok()
-                .or_else(|| row.get::<_, String>(0).ok().map(|s| s.into_bytes()));
-            Ok(value)
+                .or_else(|| row.get::<_, String>(1).ok().map(|s| s.into_bytes()));
+            Ok((key, value))
         }) {
-            for row_result in rows.flatten() {
-                let Some(value_bytes) = row_result else { continue };
-                if let Some(json) = parse_json_bytes(&value_bytes) {
-                    bubbles.push(json);
+            for (key, value_opt) in rows.flatten() {
+                let Some(value_bytes) = value_opt else { continue };
+                let Some(json) = parse_json_bytes(&value_bytes) else { continue };
+                let bubble_id = key.rsplit(':').next().unwrap_or(&key);
+                if let Some(bubble) = cursor_bubble(bubble_id, &json, model_hint) {
+                    bubbles.push(bubble);
                 }
             }
         }
@@ -1930,8 +2122,7 @@ fn load_cursor_bubbles(conn: &rusqlite::Connection, composer_id: &str) -> Vec<se
     bubbles
 }
 
-/// 加载 bubbleId 的 token 统计（按 composer 聚合）
-/// 包含 codeBlocks, selections, diffHistories, toolResults 的重度准确统计
+/// 加载所有 bubbleId 存储的消息（按 composer 分组）
 fn load_cursor_bubble_token_map(conn: &rusqlite::Connection) -> BubbleTokenMap {
     let mut map: BubbleTokenMap = HashMap::new();
 
@@ -1948,141 +2139,83 @@ fn load_cursor_bubble_token_map(conn: &rusqlite::Connection) -> BubbleTokenMap {
                 let (key, value_opt) = row_result;
                 let Some(value_bytes) = value_opt else { continue };
 
+                // bubbleId:{composer}:{bubble}
                 let mut parts = key.split(':');
                 let _prefix = parts.next();
-                let composer_id = match parts.next() {
-                    Some(id) => id.to_string(),
-                    None => continue,
+                let (Some(composer_id), Some(bubble_id)) = (parts.next(), parts.next()) else {
+                    continue;
                 };
 
                 let Some(json) = parse_json_bytes(&value_bytes) else { continue };
+                if let Some(bubble) = cursor_bubble(bubble_id, &json, None) {
+                    map.entry(composer_id.to_string()).or_default().push(bubble);
+                }
+            }
+        }
+    }
 
-                let role = json.get("type")
-                    .or_else(|| json.get("role"))
-                    .and_then(|r| {
-                        if let Some(s) = r.as_str() {
-                            Some(s.to_string())
-                        } else if let Some(n) = r.as_i64() {
-                            Some(n.to_string())
-                        } else {
-                            None
-                        }
-                    })
-                    .unwrap_or_default();
+    map
+}
 
-                let model = json.get("modelId")
-                    .or_else(|| json.get("model"))
-                    .or_else(|| json.get("modelName"))
-                    .and_then(|v| v.as_str());
+/// 统计单条 bubble 的 token
+/// 包含 codeBlocks, selections, diffHistories, toolResults 的重度准确统计
+fn cursor_bubble(bubble_id: &str, json: &serde_json::Value, model_hint: Option<&str>) -> Option<CursorBubble> {
+    let is_user = cursor_message_is_user(json)?;
+    let model = json.get("modelId")
+        .or_else(|| json.get("model"))
+        .or_else(|| json.get("modelName"))
+        .and_then(|v| v.as_str())
+        .or(model_hint);
 
-                let is_user = role == "user" || role == "human" || role == "1";
-                let is_assistant = role == "assistant" || role == "ai" || role == "bot" || role == "2";
+    let mut text = String::new();
+    let mut append = |part: String| {
+        if !part.is_empty() {
+            if !text.is_empty() { text.push('\n'); }
+            text.push_str(&part);
+        }
+    };
 
-                if is_user {
-                    // User 输入
-                    let mut input_text = String::new();
-                    
-                    // 主内容
-                    if let Some(content) = json.get("rawText")
-                        .or_else(|| json.get("text"))
-                        .or_else(|| json.get("content"))
-                        .and_then(|c| c.as_str()) {
-                        input_text.push_str(content);
-                    }
-                    
-                    // === 重度准确：selections ===
-                    if let Some(sels) = json.get("selections") {
-                        let sel_text = extract_selections_text(sels);
-                        if !sel_text.is_empty() {
-                            if !input_text.is_empty() { input_text.push('\n'); }
-                            input_text.push_str(&sel_text);
-                        }
-                    }
-                    // context.selections
-                    if let Some(ctx) = json.get("context") {
-                        if let Some(sels) = ctx.get("selections") {
-                            let sel_text = extract_selections_text(sels);
-                            if !sel_text.is_empty() {
-                                if !input_text.is_empty() { input_text.push('\n'); }
-                                input_text.push_str(&sel_text);
-                            }
-                        }
-                    }
-                    
-                    let tokens = count_tokens_for_text(&input_text, model) as u32;
-                    if tokens > 0 {
-                        let entry = map.entry(composer_id).or_insert((0, 0));
-                        entry.0 = entry.0.saturating_add(tokens);
-                    }
-                    
-                } else if is_assistant {
-                    // Assistant 输出
-                    let mut output_text = String::new();
-                    
-                    // 主内容
-                    if let Some(content) = json.get("rawText")
-                        .or_else(|| json.get("text"))
-                        .or_else(|| json.get("content"))
-                        .and_then(|c| c.as_str()) {
-                        output_text.push_str(content);
-                    }
-                    
-                    // === 重度准确：codeBlocks ===
-                    if let Some(blocks) = json.get("codeBlocks") {
-                        let blocks_text = extract_code_blocks_text(blocks);
-                        if !blocks_text.is_empty() {
-                            if !output_text.is_empty() { output_text.push('\n'); }
-                            output_text.push_str(&blocks_text);
-                        }
-                    }
-                    
-                    // === 重度准确：suggestedCodeBlocks ===
-                    if let Some(blocks) = json.get("suggestedCodeBlocks") {
-                        let blocks_text = extract_code_blocks_text(blocks);
-                        if !blocks_text.is_empty() {
-                            if !output_text.is_empty() { output_text.push('\n'); }
-                            output_text.push_str(&blocks_text);
-                        }
-                    }
-                    
-                    // === 重度准确：suggestedDiffs ===
-                    if let Some(diffs) = json.get("suggestedDiffs") {
-                        let diffs_text = extract_diff_histories_text(diffs);
-                        if !diffs_text.is_empty() {
-                            if !output_text.is_empty() { output_text.push('\n'); }
-                            output_text.push_str(&diffs_text);
-                        }
-                    }
-                    
-                    // === 重度准确：diffHistories ===
-                    if let Some(diffs) = json.get("diffHistories") {
-                        let diffs_text = extract_diff_histories_text(diffs);
-                        if !diffs_text.is_empty() {
-                            if !output_text.is_empty() { output_text.push('\n'); }
-                            output_text.push_str(&diffs_text);
-                        }
-                    }
-                    
-                    // === 重度准确：toolResults ===
-                    if let Some(results) = json.get("toolResults") {
-                        let results_text = extract_tool_results_text(results);
-                        if !results_text.is_empty() {
-                            if !output_text.is_empty() { output_text.push('\n'); }
-                            output_text.push_str(&results_text);
-                        }
-                    }
-                    
-                    let tokens = count_tokens_for_text(&output_text, model) as u32;
-                    if tokens > 0 {
-                        let entry = map.entry(composer_id).or_insert((0, 0));
-                        entry.1 = entry.1.saturating_add(tokens);
-                    }
-                }
+    // 主内容
+    if let Some(content) = json.get("rawText")
+        .or_else(|| json.get("text"))
+        .or_else(|| json.get("content"))
+        .and_then(|c| c.as_str()) {
+        append(content.to_string());
+    }
+
+    if is_user {
+        // === 重度准确：selections / context.selections ===
+        if let Some(sels) = json.get("selections") {
+            append(extract_selections_text(sels));
+        }
+        if let Some(sels) = json.get("context").and_then(|ctx| ctx.get("selections")) {
+            append(extract_selections_text(sels));
+        }
+    } else {
+        // === 重度准确：codeBlocks / suggestedCodeBlocks ===
+        for key in ["codeBlocks", "suggestedCodeBlocks"] {
+            if let Some(blocks) = json.get(key) {
+                append(extract_code_blocks_text(blocks));
+            }
+        }
+        // === 重度准确：suggestedDiffs / diffHistories ===
+        for key in ["suggestedDiffs", "diffHistories"] {
+            if let Some(diffs) = json.get(key) {
+                append(extract_diff_histories_text(diffs));
             }
         }
+        // === 重度准确：toolResults ===
+        if let Some(results) = json.get("toolResults") {
+            append(extract_tool_results_text(results));
+        }
     }
 
-    map
+    Some(CursorBubble {
+        id: bubble_id.to_string(),
+        is_user,
+        tokens: tokenizer::estimate_tokens(&text, model),
+        timestamp: cursor_message_timestamp(json),
+    })
 }
 
 /// 从 selections / context.selections 提取代码上下文文本 (用于输入 token)
@@ -2281,8 +2414,8 @@ fn estimate_tokens_from_cursor_conversation(messages: &[serde_json::Value], mode
     }
     
     let model = model_hint.filter(|m| !m.is_empty());
-    let input_tokens = count_tokens_for_text(&input_text, model) as u32;
-    let output_tokens = count_tokens_for_text(&output_text, model) as u32;
+    let input_tokens = tokenizer::estimate_tokens(&input_text, model);
+    let output_tokens = tokenizer::estimate_tokens(&output_text, model);
     let input_tokens = if input_tokens == 0 && input_count > 0 { 1 } else { input_tokens };
     let output_tokens = if output_tokens == 0 && output_count > 0 { 1 } else { output_tokens };
     (input_tokens, output_tokens)
@@ -2406,8 +2539,8 @@ fn estimate_tokens_from_messages(messages: &[serde_json::Value], model_hint: Opt
     }
     
     let model = model_hint.filter(|m| !m.is_empty());
-    let input_tokens = count_tokens_for_text(&input_text, model) as u32;
-    let output_tokens = count_tokens_for_text(&output_text, model) as u32;
+    let input_tokens = tokenizer::estimate_tokens(&input_text, model);
+    let output_tokens = tokenizer::estimate_tokens(&output_text, model);
 
     // 若内容为空，但存在消息数量，至少返回 1 token
     let input_tokens = if input_tokens == 0 && input_count > 0 { 1 } else { input_tokens };
@@ -2448,11 +2581,47 @@ fn extract_cursor_timestamp(json: &serde_json::Value) -> i64 {
 // VSCode 系工具通用扫描（Windsurf / Kiro / Antigravity / Augment）
 // ============================================================================
 
-/// 获取 VSCode 系 app 的 state.vscdb 路径列表
-fn get_vscode_app_db_paths(app_names: &[&str]) -> Vec<PathBuf> {
-    let mut paths: Vec<PathBuf> = Vec::new();
-    let mut seen: HashSet<String> = HashSet::new();
+/// 查找项目文件时跳过的目录
+const SKIPPED_PROJECT_DIRS: &[&str] = &["node_modules", "target", "Library", "AppData", "vendor", "venv"];
+
+/// 从用户目录向下查找项目文件的最大深度（如 `~/code/org/project`）
+const PROJECT_SEARCH_DEPTH: usize = 3;
+
+/// 在用户目录及其下几层目录（跳过隐藏目录）中查找写在项目目录里的日志文件（如 Aider 聊天记录、Crush 数据库）
+fn find_project_files(relative: &Path) -> Vec<PathBuf> {
+    fn scan(dir: &Path, relative: &Path, depth: usize, files: &mut Vec<PathBuf>) {
+        let candidate = dir.join(relative);
+        if candidate.is_file() {
+            files.push(candidate);
+        }
+        if depth == 0 {
+            return;
+        }
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if name.starts_with('.') || SKIPPED_PROJECT_DIRS.contains(&name.as_ref()) {
+                continue;
+            }
+            if entry.file_type().is_ok_and(|t| t.is_dir()) {
+                scan(&entry.path(), relative, depth - 1, files);
+            }
+        }
+    }
+
+    let Some(home) = dirs::home_dir() else {
+        return vec![];
+    };
+    let mut files = Vec::new();
+    scan(&home, relative, PROJECT_SEARCH_DEPTH, &mut files);
+    files
+}
 
+/// 获取已安装的 VSCode 系 app 的用户数据目录（`<配置目录>/<app>/User`）
+fn vscode_user_dirs(app_names: &[&str]) -> Vec<PathBuf> {
     let mut base_dirs: Vec<PathBuf> = Vec::new();
 
     #[cfg(windows)]
@@ -2479,26 +2648,36 @@ fn get_vscode_app_db_paths(app_names: &[&str]) -> Vec<PathBuf> {
         }
     }
 
-    for base in &base_dirs {
-        for name in app_names {
-            let user_dir = base.join(name).join("User");
-            if !user_dir.exists() { continue; }
-            // globalStorage/state.vscdb
-            let global_db = user_dir.join("globalStorage").join("state.vscdb");
-            if global_db.exists() {
-                let key = global_db.to_string_lossy().to_string();
-                if seen.insert(key) { paths.push(global_db); }
-            }
-            // workspaceStorage/*/state.vscdb
-            let workspace_dir = user_dir.join("workspaceStorage");
-            if workspace_dir.exists() {
-                if let Ok(entries) = fs::read_dir(&workspace_dir) {
-                    for entry in entries.flatten() {
-                        let db_path = entry.path().join("state.vscdb");
-                        if db_path.exists() {
-                            let key = db_path.to_string_lossy().to_string();
-                            if seen.insert(key) { paths.push(db_path); }
-                        }
+    // Windows 上 APPDATA 与 AppData/Roaming 通常相同，去重
+    let mut seen: HashSet<PathBuf> = HashSet::new();
+    base_dirs
+        .iter()
+        .flat_map(|base| app_names.iter().map(move |name| base.join(name).join("User")))
+        .filter(|user_dir| user_dir.exists() && seen.insert(user_dir.clone()))
+        .collect()
+}
+
+/// 获取 VSCode 系 app 的 state.vscdb 路径列表
+fn get_vscode_app_db_paths(app_names: &[&str]) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = Vec::new();
+    let mut seen: HashSet<String> = HashSet::new();
+
+    for user_dir in vscode_user_dirs(app_names) {
+        // globalStorage/state.vscdb
+        let global_db = user_dir.join("globalStorage").join("state.vscdb");
+        if global_db.exists() {
+            let key = global_db.to_string_lossy().to_string();
+            if seen.insert(key) { paths.push(global_db); }
+        }
+        // workspaceStorage/*/state.vscdb
+        let workspace_dir = user_dir.join("workspaceStorage");
+        if workspace_dir.exists() {
+            if let Ok(entries) = fs::read_dir(&workspace_dir) {
+                for entry in entries.flatten() {
+                    let db_path = entry.path().join("state.vscdb");
+                    if db_path.exists() {
+                        let key = db_path.to_string_lossy().to_string();
+                        if seen.insert(key) { paths.push(db_path); }
                     }
                 }
             }
@@ -2507,81 +2686,20 @@ fn get_vscode_app_db_paths(app_names: &[&str]) -> Vec<PathBuf> {
     paths
 }
 
-/// 扫描 VSCode 系 app 的数据库文件
-fn scan_vscode_app_logs(app_names: &[&str]) -> (Vec<PathBuf>, u32) {
-    let db_paths = get_vscode_app_db_paths(app_names);
-    if db_paths.is_empty() {
-        return (vec![], 0);
-    }
-    let mut entry_count = 0u32;
-    // 只统计 globalStorage 中的会话数
-    if db_paths.len() > 8 {
-        if let Some(global) = db_paths.iter().find(|p| p.to_string_lossy().contains("globalStorage")) {
-            entry_count = count_cursor_sessions(global).unwrap_or(0);
-        }
-    } else {
-        for path in &db_paths {
-            entry_count = entry_count.saturating_add(count_cursor_sessions(path).unwrap_or(0));
-        }
-    }
-    (db_paths, entry_count)
-}
-
 /// 解析 VSCode 系 app 的数据库，将 source 替换为指定工具名
-fn parse_vscode_app_db(path: &PathBuf, source_name: &str) -> Vec<LocalLogEntry> {
+fn parse_vscode_app_db(path: &Path, source_name: &str) -> Vec<LocalLogEntry> {
     // 复用 Cursor 的解析逻辑，然后替换 source
     let entries = parse_cursor_db(path);
     entries.into_iter().map(|mut e| {
         // 替换 source 标识
         e.source = source_name.to_string();
-        // 替换 session_id 前缀避免与 Cursor 冲突
-        e.session_id = e.session_id.replace("cursor-", &format!("{}-", source_name));
+        // 替换 ID 前缀避免与 Cursor 冲突
+        e.request_id = vscode_app_id(&e.request_id, source_name);
+        e.session_id = vscode_app_id(&e.session_id, source_name);
         e
     }).collect()
 }
 
-// ---- Windsurf ----
-fn scan_windsurf_logs() -> (Vec<PathBuf>, u32) {
-    scan_vscode_app_logs(&["Windsurf", "WindSurf"])
-}
-
-fn get_windsurf_log_path() -> Option<PathBuf> {
-    let paths = get_vscode_app_db_paths(&["Windsurf", "WindSurf"]);
-    paths.into_iter().find(|p| p.to_string_lossy().contains("globalStorage"))
-}
-
-// ---- Kiro ----
-fn scan_kiro_logs() -> (Vec<PathBuf>, u32) {
-    scan_vscode_app_logs(&["Kiro"])
-}
-
-fn get_kiro_log_path() -> Option<PathBuf> {
-    let paths = get_vscode_app_db_paths(&["Kiro"]);
-    paths.into_iter().find(|p| p.to_string_lossy().contains("globalStorage"))
-}
-
-// ---- Antigravity ----
-fn scan_antigravity_logs() -> (Vec<PathBuf>, u32) {
-    scan_vscode_app_logs(&["Antigravity"])
-}
-
-fn get_antigravity_log_path() -> Option<PathBuf> {
-    let paths = get_vscode_app_db_paths(&["Antigravity"]);
-    paths.into_iter().find(|p| p.to_string_lossy().contains("globalStorage"))
-}
-
-// ---- Augment（VS Code 扩展，复用 VS Code 数据库）----
-fn scan_augment_logs() -> (Vec<PathBuf>, u32) {
-    // Augment 是 VS Code 扩展，数据存储在 VS Code 的 state.vscdb 中
-    // 扫描 VS Code / Cursor 的 globalStorage 以查找 Augment 相关数据
-    scan_vscode_app_logs(&["Code", "Code - Insiders"])
-}
-
-fn get_augment_log_path() -> Option<PathBuf> {
-    let paths = get_vscode_app_db_paths(&["Code", "Code - Insiders"]);
-    paths.into_iter().find(|p| p.to_string_lossy().contains("globalStorage"))
-}
-
 // ============================================================================
 // Warp 日志解析
 // ============================================================================
@@ -2617,18 +2735,8 @@ fn get_warp_db_path() -> Option<PathBuf> {
     None
 }
 
-/// 扫描 Warp 数据库
-fn scan_warp_logs() -> (Vec<PathBuf>, u32) {
-    let Some(db_path) = get_warp_db_path() else {
-        return (vec![], 0);
-    };
-    // 统计对话数
-    let entry_count = count_warp_conversations(&db_path).unwrap_or(0);
-    (vec![db_path], entry_count)
-}
-
 /// 统计 Warp 数据库中的对话数量
-fn count_warp_conversations(db_path: &PathBuf) -> Option<u32> {
+fn count_warp_conversations(db_path: &Path) -> Option<u32> {
     use rusqlite::{Connection, OpenFlags};
     let conn = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX).ok()?;
     // 检查表是否存在
@@ -2644,7 +2752,7 @@ fn count_warp_conversations(db_path: &PathBuf) -> Option<u32> {
 }
 
 /// 解析 Warp 数据库，提取用量记录
-fn parse_warp_db(db_path: &PathBuf) -> Vec<LocalLogEntry> {
+fn parse_warp_db(db_path: &Path) -> Vec<LocalLogEntry> {
     use rusqlite::{Connection, OpenFlags};
     let mut entries = Vec::new();
 
@@ -2694,7 +2802,7 @@ fn parse_warp_db(db_path: &PathBuf) -> Vec<LocalLogEntry> {
                 let input_tokens = (all_tokens * 60 / 100) as u32;
                 let output_tokens = (all_tokens - input_tokens as u64) as u32;
 
-                let session_id = format!("warp-{}-{}", conv_id, model_id);
+                let request_id = format!("warp-{}-{}", conv_id, model_id);
 
                 entries.push(LocalLogEntry {
                     source: "warp".to_string(),
@@ -2705,8 +2813,11 @@ fn parse_warp_db(db_path: &PathBuf) -> Vec<LocalLogEntry> {
                     cache_read_tokens: 0,
                     cache_creation_tokens: 0,
                     cost_usd: None,
-                    session_id,
+                    estimated: true,
+                    request_id,
+                    session_id: format!("warp-{}", conv_id),
                     project_name: None,
+                    project: None,
                 });
             }
         } else {
@@ -2723,8 +2834,11 @@ fn parse_warp_db(db_path: &PathBuf) -> Vec<LocalLogEntry> {
                     cache_read_tokens: 0,
                     cache_creation_tokens: 0,
                     cost_usd: Some(credits),
+                    estimated: false,
+                    request_id: session_id.clone(),
                     session_id,
                     project_name: None,
+                    project: None,
                 });
             }
         }
@@ -2737,144 +2851,146 @@ fn parse_warp_db(db_path: &PathBuf) -> Vec<LocalLogEntry> {
 // 数据库操作
 // ============================================================================
 
-/// 获取服务商特定的模型定价
-fn get_provider_model_pricing(conn: &rusqlite::Connection, provider_id: &str, model_id: &str) -> Option<(Decimal, Decimal, Decimal, Decimal)> {
-    let cleaned = clean_model_id(model_id);
+/// 旧记录（v9 之前导入，没有会话 ID）的内容：(时间, 模型, 输入, 输出, 缓存读取, 缓存创建)
+type LegacyKey = (i64, String, u32, u32, u32, u32);
+
+fn legacy_key(entry: &LocalLogEntry) -> LegacyKey {
+    (
+        entry.timestamp,
+        entry.model.clone(),
+        entry.input_tokens,
+        entry.output_tokens,
+        entry.cache_read_tokens,
+        entry.cache_creation_tokens,
+    )
+}
 
-    // 先查询服务商特定定价
-    let result = conn.query_row(
-        "SELECT input_cost_per_million, output_cost_per_million,
-                cache_read_cost_per_million, cache_creation_cost_per_million
-         FROM provider_model_pricing WHERE provider_id = ?1 AND model_id = ?2",
-        [provider_id, &cleaned],
-        |row| {
-            Ok((
-                row.get::<_, String>(0)?,
-                row.get::<_, String>(1)?,
-                row.get::<_, String>(2)?,
-                row.get::<_, String>(3)?,
-            ))
-        },
-    );
+/// 某个来源已导入的记录
+///
+/// 只加载旧记录（没有会话 ID，按 idx_proxy_logs_session 索引查找，升级后即不再加载），
+/// 新记录按 request_id 主键逐条查询，追加少量内容时的开销与导入历史的大小无关
+#[derive(Default)]
+struct ExistingRecords {
+    /// 旧记录 request_id -> 内容
+    legacy: HashMap<String, LegacyKey>,
+    /// 旧记录内容 -> request_id（内容相同的可能有多条）
+    legacy_by_content: HashMap<LegacyKey, Vec<String>>,
+    /// 本次导入中已改名或删除的旧记录 request_id
+    released: HashSet<String>,
+}
 
-    match result {
-        Ok((input, output, cache_read, cache_creation)) => Some((
-            Decimal::from_str(&input).unwrap_or(Decimal::ZERO),
-            Decimal::from_str(&output).unwrap_or(Decimal::ZERO),
-            Decimal::from_str(&cache_read).unwrap_or(Decimal::ZERO),
-            Decimal::from_str(&cache_creation).unwrap_or(Decimal::ZERO),
-        )),
-        // 如果没有服务商特定定价，回退到默认定价
-        Err(_) => get_model_pricing_default(conn, &cleaned),
-    }
-}
-
-/// 获取默认模型定价
-fn get_model_pricing_default(conn: &rusqlite::Connection, cleaned_model_id: &str) -> Option<(Decimal, Decimal, Decimal, Decimal)> {
-    let result = conn.query_row(
-        "SELECT input_cost_per_million, output_cost_per_million,
-                cache_read_cost_per_million, cache_creation_cost_per_million
-         FROM model_pricing WHERE model_id = ?1",
-        [cleaned_model_id],
-        |row| {
-            Ok((
-                row.get::<_, String>(0)?,
-                row.get::<_, String>(1)?,
-                row.get::<_, String>(2)?,
-                row.get::<_, String>(3)?,
-            ))
-        },
-    );
+impl ExistingRecords {
+    fn load(db: &Database, source: &str) -> Result<Self, String> {
+        let conn = db.read_conn().map_err(|e| e.to_string())?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT request_id, created_at, model,
+                        input_tokens, output_tokens, cache_read_tokens, cache_creation_tokens
+                 FROM proxy_request_logs WHERE session_id IS NULL AND app_type = ?1",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([format!("{source}_local")], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    (row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?, row.get(6)?),
+                ))
+            })
+            .map_err(|e| e.to_string())?;
 
-    match result {
-        Ok((input, output, cache_read, cache_creation)) => Some((
-            Decimal::from_str(&input).unwrap_or(Decimal::ZERO),
-            Decimal::from_str(&output).unwrap_or(Decimal::ZERO),
-            Decimal::from_str(&cache_read).unwrap_or(Decimal::ZERO),
-            Decimal::from_str(&cache_creation).unwrap_or(Decimal::ZERO),
-        )),
-        Err(_) => None,
+        let mut existing = Self::default();
+        for (request_id, key) in rows.flatten() {
+            existing.legacy_by_content.entry(key.clone()).or_default().push(request_id.clone());
+            existing.legacy.insert(request_id, key);
+        }
+        Ok(existing)
     }
-}
-
-/// 清洗模型 ID
-fn clean_model_id(model_id: &str) -> String {
-    let without_prefix = model_id.rsplit_once('/').map_or(model_id, |(_, r)| r);
-    let without_suffix = without_prefix.split(':').next().unwrap_or(without_prefix);
-    without_suffix.trim().replace('@', "-")
-}
 
-/// 计算成本
-fn calculate_cost(entry: &LocalLogEntry, pricing: Option<(Decimal, Decimal, Decimal, Decimal)>) -> Decimal {
-    let Some((input_price, output_price, cache_read_price, cache_creation_price)) = pricing else {
-        return Decimal::ZERO;
-    };
+    /// 查询这些记录中已导入的，返回 request_id -> 是否还没有项目（用于补充项目）
+    fn imported(db: &Database, entries: &[LocalLogEntry]) -> Result<HashMap<String, bool>, String> {
+        let conn = db.read_conn().map_err(|e| e.to_string())?;
+        let mut stmt = conn
+            .prepare_cached("SELECT project IS NULL FROM proxy_request_logs WHERE request_id = ?1")
+            .map_err(|e| e.to_string())?;
+        let mut imported = HashMap::new();
+        for entry in entries {
+            let no_project = stmt
+                .query_row([&entry.request_id], |row| row.get::<_, bool>(0))
+                .optional()
+                .map_err(|e| e.to_string())?;
+            if let Some(no_project) = no_project {
+                imported.insert(entry.request_id.clone(), no_project);
+            }
+        }
+        Ok(imported)
+    }
 
-    let million = Decimal::from(1_000_000u64);
-    
-    let billable_input = (entry.input_tokens as u64).saturating_sub(entry.cache_read_tokens as u64);
-    
-    let input_cost = Decimal::from(billable_input) * input_price / million;
-    let output_cost = Decimal::from(entry.output_tokens as u64) * output_price / million;
-    let cache_read_cost = Decimal::from(entry.cache_read_tokens as u64) * cache_read_price / million;
-    let cache_creation_cost = Decimal::from(entry.cache_creation_tokens as u64) * cache_creation_price / million;
+    /// 取出与新记录对应的旧记录：ID 相同、内容相同，或旧记录是该会话的汇总记录
+    /// （Cursor 等曾按对话汇总，旧 request_id 即现在的会话 ID）。
+    /// 返回 (旧 request_id, 是否为需要删除的汇总记录)
+    fn take_legacy(&mut self, entry: &LocalLogEntry) -> Option<(String, bool)> {
+        if self.legacy.is_empty() {
+            return None;
+        }
+        if let Some(key) = self.legacy.remove(&entry.request_id) {
+            self.forget_content(&key, &entry.request_id);
+            return Some((entry.request_id.clone(), false));
+        }
+        if let Some(key) = self.legacy.remove(&entry.session_id) {
+            self.forget_content(&key, &entry.session_id);
+            self.released.insert(entry.session_id.clone());
+            return Some((entry.session_id.clone(), true));
+        }
+        let ids = self.legacy_by_content.get_mut(&legacy_key(entry))?;
+        let request_id = ids.pop()?;
+        self.legacy.remove(&request_id);
+        self.released.insert(request_id.clone());
+        Some((request_id, false))
+    }
 
-    input_cost + output_cost + cache_read_cost + cache_creation_cost
+    fn forget_content(&mut self, key: &LegacyKey, request_id: &str) {
+        if let Some(ids) = self.legacy_by_content.get_mut(key) {
+            ids.retain(|id| id != request_id);
+        }
+    }
 }
 
-/// 检查记录是否已存在
-fn record_exists(conn: &rusqlite::Connection, session_id: &str) -> bool {
-    conn.query_row(
-        "SELECT 1 FROM proxy_request_logs WHERE request_id = ?1",
-        [session_id],
-        |_| Ok(()),
-    )
-    .is_ok()
+/// 本地导入记录的服务商名称
+fn local_provider_name(source: &str) -> String {
+    log_source(source).map_or_else(|| "Local Import".to_string(), |source| format!("{} (Local)", source.name()))
 }
 
-/// 加载已存在的 request_id（按前缀过滤）
-fn load_existing_request_ids_by_prefix(conn: &rusqlite::Connection, prefix: &str) -> HashSet<String> {
-    let mut set = HashSet::new();
-    let like_pattern = format!("{prefix}%");
-    if let Ok(mut stmt) = conn.prepare("SELECT request_id FROM proxy_request_logs WHERE request_id LIKE ?1") {
-        if let Ok(rows) = stmt.query_map([like_pattern], |row| row.get::<_, String>(0)) {
-            for row_result in rows.flatten() {
-                set.insert(row_result);
-            }
-        }
-    }
-    set
+/// 记录的计费结果
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct EntryCost {
+    /// 实际花费（美元，已按服务商计费系数换算）
+    total: Decimal,
+    /// 计价版本（日志自带成本时为 SOURCE_COST_VERSION，没有定价时为空）
+    pricing_version: Option<i64>,
+    /// 服务商倍率（写入 cost_multiplier）
+    multiplier: Decimal,
 }
 
-fn load_existing_request_ids_by_app_type(conn: &rusqlite::Connection, app_type: &str) -> HashSet<String> {
-    let mut set = HashSet::new();
-    if let Ok(mut stmt) = conn.prepare("SELECT request_id FROM proxy_request_logs WHERE app_type = ?1") {
-        if let Ok(rows) = stmt.query_map([app_type], |row| row.get::<_, String>(0)) {
-            for row_result in rows.flatten() {
-                set.insert(row_result);
-            }
+impl EntryCost {
+    /// 没有定价的记录
+    fn unpriced() -> Self {
+        Self {
+            total: Decimal::ZERO,
+            pricing_version: None,
+            multiplier: Decimal::ONE,
         }
     }
-    set
 }
 
 /// 插入日志条目到数据库
-fn insert_log_entry(conn: &rusqlite::Connection, entry: &LocalLogEntry, cost: Decimal) -> Result<(), AppError> {
+fn insert_log_entry(
+    conn: &rusqlite::Connection,
+    entry: &LocalLogEntry,
+    cost: EntryCost,
+) -> Result<(), AppError> {
     let app_type = format!("{}_local", entry.source);
     let provider_id = format!("{}_local", entry.source);
-    let provider_name = match entry.source.as_str() {
-        "claude" => "Claude Code (Local)",
-        "codex" => "Codex CLI (Local)",
-        "gemini" => "Gemini CLI (Local)",
-        "opencode" => "Opencode (Local)",
-        "cursor" => "Cursor (Local)",
-        "windsurf" => "Windsurf (Local)",
-        "kiro" => "Kiro (Local)",
-        "antigravity" => "Antigravity (Local)",
-        "warp" => "Warp (Local)",
-        "augment" => "Augment (Local)",
-        _ => "Local Import",
-    };
+    let provider_name = local_provider_name(&entry.source);
 
     let zero = Decimal::ZERO;
     
@@ -2883,10 +2999,11 @@ fn insert_log_entry(conn: &rusqlite::Connection, entry: &LocalLogEntry, cost: De
             request_id, provider_id, provider_name, app_type, model,
             input_tokens, output_tokens, cache_read_tokens, cache_creation_tokens,
             input_cost_usd, output_cost_usd, cache_read_cost_usd, cache_creation_cost_usd, total_cost_usd,
-            latency_ms, status_code, is_streaming, created_at
-        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)",
+            latency_ms, status_code, is_streaming, cost_multiplier, pricing_version, project, created_at,
+            session_id, tokens_estimated
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23)",
         rusqlite::params![
-            entry.session_id,
+            entry.request_id,
             provider_id,
             provider_name,
             app_type,
@@ -2899,11 +3016,16 @@ fn insert_log_entry(conn: &rusqlite::Connection, entry: &LocalLogEntry, cost: De
             zero.to_string(),
             zero.to_string(),
             zero.to_string(),
-            cost.to_string(),
+            cost.total.to_string(),
             0i64, // latency_ms
             200i64, // status_code
             0, // is_streaming
+            cost.multiplier.to_string(),
+            cost.pricing_version,
+            entry.project,
             entry.timestamp,
+            session_id_param(entry),
+            entry.estimated,
         ],
     )
     .map_err(|e| AppError::Database(format!("插入日志条目失败: {e}")))?;
@@ -2911,23 +3033,20 @@ fn insert_log_entry(conn: &rusqlite::Connection, entry: &LocalLogEntry, cost: De
     Ok(())
 }
 
-/// 更新已存在的日志条目（用于重新导入 Cursor）
-fn update_log_entry(conn: &rusqlite::Connection, entry: &LocalLogEntry, cost: Decimal) -> Result<(), AppError> {
+/// 会话 ID 为空（来源不记录会话）时存为 NULL
+fn session_id_param(entry: &LocalLogEntry) -> Option<&str> {
+    Some(entry.session_id.as_str()).filter(|id| !id.is_empty())
+}
+
+/// 更新已存在的日志条目（用于会继续更新的记录）
+fn update_log_entry(
+    conn: &rusqlite::Connection,
+    entry: &LocalLogEntry,
+    cost: EntryCost,
+) -> Result<(), AppError> {
     let app_type = format!("{}_local", entry.source);
     let provider_id = format!("{}_local", entry.source);
-    let provider_name = match entry.source.as_str() {
-        "claude" => "Claude Code (Local)",
-        "codex" => "Codex CLI (Local)",
-        "gemini" => "Gemini CLI (Local)",
-        "opencode" => "Opencode (Local)",
-        "cursor" => "Cursor (Local)",
-        "windsurf" => "Windsurf (Local)",
-        "kiro" => "Kiro (Local)",
-        "antigravity" => "Antigravity (Local)",
-        "warp" => "Warp (Local)",
-        "augment" => "Augment (Local)",
-        _ => "Local Import",
-    };
+    let provider_name = local_provider_name(&entry.source);
 
     conn.execute(
         "UPDATE proxy_request_logs SET
@@ -2940,8 +3059,13 @@ fn update_log_entry(conn: &rusqlite::Connection, entry: &LocalLogEntry, cost: De
             cache_read_tokens = ?7,
             cache_creation_tokens = ?8,
             total_cost_usd = ?9,
-            created_at = ?10
-         WHERE request_id = ?11",
+            pricing_version = ?10,
+            created_at = ?11,
+            project = COALESCE(?13, project),
+            session_id = ?14,
+            tokens_estimated = ?15,
+            cost_multiplier = ?16
+         WHERE request_id = ?12",
         rusqlite::params![
             provider_id,
             provider_name,
@@ -2951,9 +3075,14 @@ fn update_log_entry(conn: &rusqlite::Connection, entry: &LocalLogEntry, cost: De
             entry.output_tokens,
             entry.cache_read_tokens,
             entry.cache_creation_tokens,
-            cost.to_string(),
+            cost.total.to_string(),
+            cost.pricing_version,
             entry.timestamp,
-            entry.session_id,
+            entry.request_id,
+            entry.project,
+            session_id_param(entry),
+            entry.estimated,
+            cost.multiplier.to_string(),
         ],
     )
     .map_err(|e| AppError::Database(format!("更新日志条目失败: {e}")))?;
@@ -3602,86 +3731,35 @@ pub async fn scan_local_logs(
     window: tauri::Window,
     db: State<'_, Arc<Database>>,
 ) -> Result<ScanResult, String> {
-    let conn = db.conn.lock().map_err(|e| format!("获取数据库锁失败: {e}"))?;
-
-    let total_steps = 10;
-    let mut step = 0u32;
-
-    step += 1;
-    emit_local_log_progress(&window, "scan", "claude", step, total_steps, "扫描 Claude Code");
-    let (claude_files, claude_entries) = scan_claude_logs();
-
-    step += 1;
-    emit_local_log_progress(&window, "scan", "codex", step, total_steps, "扫描 Codex CLI");
-    let (codex_files, codex_entries) = scan_codex_logs();
+    let conn = db.read_conn().map_err(|e| e.to_string())?;
 
-    step += 1;
-    emit_local_log_progress(&window, "scan", "gemini", step, total_steps, "扫描 Gemini CLI");
-    let (gemini_files, gemini_entries) = scan_gemini_logs();
-
-    step += 1;
-    emit_local_log_progress(&window, "scan", "opencode", step, total_steps, "扫描 Opencode");
-    let (opencode_files, opencode_entries) = scan_opencode_logs();
-
-    step += 1;
-    emit_local_log_progress(&window, "scan", "cursor", step, total_steps, "扫描 Cursor");
-    let (cursor_files, cursor_entries) = scan_cursor_logs();
-
-    step += 1;
-    emit_local_log_progress(&window, "scan", "windsurf", step, total_steps, "扫描 Windsurf");
-    let (windsurf_files, windsurf_entries) = scan_windsurf_logs();
-
-    step += 1;
-    emit_local_log_progress(&window, "scan", "kiro", step, total_steps, "扫描 Kiro");
-    let (kiro_files, kiro_entries) = scan_kiro_logs();
-
-    step += 1;
-    emit_local_log_progress(&window, "scan", "antigravity", step, total_steps, "扫描 Antigravity");
-    let (antigravity_files, antigravity_entries) = scan_antigravity_logs();
-
-    step += 1;
-    emit_local_log_progress(&window, "scan", "warp", step, total_steps, "扫描 Warp");
-    let (warp_files, warp_entries) = scan_warp_logs();
-
-    step += 1;
-    emit_local_log_progress(&window, "scan", "augment", step, total_steps, "扫描 Augment");
-    let (augment_files, augment_entries) = scan_augment_logs();
+    let total_steps = LOG_SOURCES.len() as u64;
+    let mut sources = Vec::with_capacity(LOG_SOURCES.len());
+    for (idx, source) in LOG_SOURCES.iter().enumerate() {
+        emit_local_log_progress(
+            &window,
+            "scan",
+            source.id(),
+            idx as u64 + 1,
+            total_steps,
+            &format!("扫描 {}", source.name()),
+        );
+        let files = source.discover();
+        sources.push(LogSourceScan {
+            id: source.id().to_string(),
+            name: source.name().to_string(),
+            files: files.len() as u32,
+            entries: source.estimate_entries(&files),
+            path: source.location().map(|p| p.to_string_lossy().to_string()),
+        });
+    }
 
     let existing_records = get_existing_local_records(&conn);
 
     emit_local_log_progress(&window, "scan", "done", total_steps, total_steps, "扫描完成");
 
     Ok(ScanResult {
-        claude_files: claude_files.len() as u32,
-        claude_entries,
-        claude_path: get_claude_log_dir().map(|p| p.to_string_lossy().to_string()),
-        codex_files: codex_files.len() as u32,
-        codex_entries,
-        codex_path: get_codex_log_dir().map(|p| p.to_string_lossy().to_string()),
-        gemini_files: gemini_files.len() as u32,
-        gemini_entries,
-        gemini_path: get_gemini_log_dir().map(|p| p.to_string_lossy().to_string()),
-        opencode_files: opencode_files.len() as u32,
-        opencode_entries,
-        opencode_path: get_opencode_log_dir().map(|p| p.to_string_lossy().to_string()),
-        cursor_files: cursor_files.len() as u32,
-        cursor_entries,
-        cursor_path: get_cursor_db_path().map(|p| p.to_string_lossy().to_string()),
-        windsurf_files: windsurf_files.len() as u32,
-        windsurf_entries,
-        windsurf_path: get_windsurf_log_path().map(|p| p.to_string_lossy().to_string()),
-        kiro_files: kiro_files.len() as u32,
-        kiro_entries,
-        kiro_path: get_kiro_log_path().map(|p| p.to_string_lossy().to_string()),
-        antigravity_files: antigravity_files.len() as u32,
-        antigravity_entries,
-        antigravity_path: get_antigravity_log_path().map(|p| p.to_string_lossy().to_string()),
-        warp_files: warp_files.len() as u32,
-        warp_entries,
-        warp_path: get_warp_db_path().map(|p| p.to_string_lossy().to_string()),
-        augment_files: augment_files.len() as u32,
-        augment_entries,
-        augment_path: get_augment_log_path().map(|p| p.to_string_lossy().to_string()),
+        sources,
         existing_records,
     })
 }
@@ -3693,658 +3771,553 @@ pub async fn import_local_logs(
     sources: Vec<String>,
     db: State<'_, Arc<Database>>,
 ) -> Result<LocalLogImportResult, String> {
+    let selected: Vec<&'static dyn LogSource> = sources.iter().filter_map(|id| log_source(id)).collect();
+    let db = Arc::clone(&db);
+    // 解析和等待 IMPORT_LOCK 都是阻塞操作，不占用异步运行时的工作线程
+    tokio::task::spawn_blocking(move || -> Result<LocalLogImportResult, String> {
+        let result = import_from_sources(&db, &selected, |progress| {
+            let source = progress.source.map_or("all", |source| source.id());
+            let name = progress.source.map_or("", |source| source.name());
+            emit_local_log_progress(
+                &window,
+                "import",
+                source,
+                progress.bytes_done,
+                progress.bytes_total,
+                &format!("导入 {name} ({}/{})", progress.files_done, progress.files_total),
+            );
+        })?;
+        emit_local_log_progress(&window, "import", "done", 1, 1, "导入完成");
+        Ok(result)
+    })
+    .await
+    .map_err(|e| format!("导入本地日志失败: {e}"))?
+}
+
+/// 清除本地导入的日志
+#[tauri::command]
+pub async fn clear_local_logs(db: State<'_, Arc<Database>>) -> Result<u32, String> {
     let conn = db.conn.lock().map_err(|e| format!("获取数据库锁失败: {e}"))?;
-    
-    let mut imported = 0u32;
+
+    let deleted = conn
+        .execute("DELETE FROM proxy_request_logs WHERE app_type LIKE '%_local'", [])
+        .map_err(|e| format!("清除本地日志失败: {e}"))?;
+    // 清除导入位置，之后重新导入时完整解析
+    conn.execute("DELETE FROM local_import_state", [])
+        .map_err(|e| format!("清除本地导入状态失败: {e}"))?;
+
+    Ok(deleted as u32)
+}
+
+/// 自动导入本地日志（静默模式，用于后台自动导入）
+/// 返回新导入的记录数
+#[tauri::command]
+pub async fn auto_import_local_logs(db: State<'_, Arc<Database>>) -> Result<u32, String> {
+    let db = Arc::clone(&db);
+    tokio::task::spawn_blocking(move || import_from_sources(&db, LOG_SOURCES, |_| {}).map(|result| result.imported))
+        .await
+        .map_err(|e| format!("导入本地日志失败: {e}"))?
+}
+
+/// 静默导入指定来源的本地日志（不依赖窗口，供自动导入和 CLI 复用），未知来源忽略
+/// 返回新导入的记录数
+pub fn import_local_sources(db: &Database, sources: &[&str]) -> Result<u32, String> {
+    let selected: Vec<&'static dyn LogSource> = sources.iter().filter_map(|id| log_source(id)).collect();
+    import_from_sources(db, &selected, |_| {}).map(|result| result.imported)
+}
+
+/// 导入进度（开始时，以及每解析完 5 个文件和最后一个文件后回调）
+struct ImportProgress {
+    /// 最近解析完的文件所属来源（开始时为 None）
+    source: Option<&'static dyn LogSource>,
+    /// 已解析的字节数（增量解析的文件只计追加部分）
+    bytes_done: u64,
+    bytes_total: u64,
+    files_done: u32,
+    files_total: u32,
+}
+
+/// 需要解析的文件（大小或修改时间与上次导入不同）
+struct ParseTask {
+    source_idx: usize,
+    path: PathBuf,
+    fingerprint: Option<FileFingerprint>,
+    cursor: Option<ParseCursor>,
+    /// 需要解析的字节数
+    bytes: u64,
+}
+
+/// 一个文件的解析结果
+struct ParsedFile {
+    source_idx: usize,
+    path: PathBuf,
+    fingerprint: Option<FileFingerprint>,
+    entries: Vec<LocalLogEntry>,
+    cursor: ParseCursor,
+    stats: Vec<(String, SessionStats)>,
+    bytes: u64,
+}
+
+impl ParseTask {
+    /// 支持增量解析的来源从上次的位置继续，其余完整解析
+    fn parse(self, source: &dyn LogSource) -> ParsedFile {
+        let (entries, cursor) = source
+            .parse_appended(&self.path, &self.cursor.unwrap_or_default())
+            .unwrap_or_else(|| (source.parse_entries(&self.path), ParseCursor::default()));
+        let stats = source.parse_all_session_stats(&self.path, &cursor);
+        ParsedFile {
+            source_idx: self.source_idx,
+            path: self.path,
+            fingerprint: self.fingerprint,
+            entries,
+            cursor,
+            stats,
+            bytes: self.bytes,
+        }
+    }
+}
+
+/// 同一时间只进行一次导入
+static IMPORT_LOCK: Mutex<()> = Mutex::new(());
+
+/// 导入各来源的使用记录和会话统计
+///
+/// 已导入的记录按 request_id 跳过（之前没有项目的补充项目），`refresh_existing` 的来源则覆盖更新。
+/// 大小和修改时间与上次导入相同的文件直接跳过，支持增量解析的来源只解析追加的内容。
+/// 查找和解析文件在 rayon 线程池中并行进行，写入在调用线程上按批串行提交。
+fn import_from_sources(
+    db: &Database,
+    sources: &[&'static dyn LogSource],
+    mut progress: impl FnMut(&ImportProgress),
+) -> Result<LocalLogImportResult, String> {
+    // 手动导入、自动导入和文件监听可能同时触发，依次进行以免重复解析同一文件
+    let _running = IMPORT_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let discovered: Vec<Vec<PathBuf>> = sources.par_iter().map(|source| source.discover()).collect();
+
+    // 对比上次导入的状态，只解析有变化的文件
+    let mut tasks = Vec::new();
+    let mut stale_paths = Vec::new();
+    {
+        let conn = db.read_conn().map_err(|e| e.to_string())?;
+        for (source_idx, files) in discovered.into_iter().enumerate() {
+            let mut import_states = load_import_state(&conn, sources[source_idx].id());
+            for path in files {
+                let fingerprint = file_fingerprint(&path);
+                let previous = import_states.remove(path.to_string_lossy().as_ref());
+                if let (Some(fp), Some(prev)) = (&fingerprint, &previous) {
+                    if prev.fingerprint == *fp {
+                        continue;
+                    }
+                }
+                // 文件变小说明被重写，从头解析
+                let size = fingerprint.map_or(0, |fp| fp.size);
+                let cursor = previous
+                    .map(|prev| prev.cursor)
+                    .filter(|cursor| fingerprint.is_some() && size >= cursor.offset);
+                tasks.push(ParseTask {
+                    source_idx,
+                    path,
+                    fingerprint,
+                    bytes: size - cursor.as_ref().map_or(0, |cursor| cursor.offset),
+                    cursor,
+                });
+            }
+            // 已删除的文件不再保留导入位置
+            stale_paths.extend(import_states.into_keys());
+        }
+    }
+
+    let mut state = ImportProgress {
+        source: None,
+        bytes_done: 0,
+        bytes_total: tasks.iter().map(|task| task.bytes).sum(),
+        files_done: 0,
+        files_total: tasks.len() as u32,
+    };
+    progress(&state);
+
+    // 解析在锁外进行，写入按批提交，导入期间不阻塞代理记录和统计查询
+    let mut batch = ImportBatch::new(db);
     let mut skipped = 0u32;
-    let mut failed = 0u32;
     let mut total = 0u32;
-    
+
     // 用于去重的集合
     let mut seen_ids: HashSet<String> = HashSet::new();
-    let total_sources = sources.len() as u32;
-    let mut source_index = 0u32;
-    
-    // 导入 Claude Code 日志
-    if sources.contains(&"claude".to_string()) {
-        source_index += 1;
-        let (files, _) = scan_claude_logs();
-        let total_files = files.len() as u32;
-        emit_local_log_progress(
-            &window,
-            "import",
-            "claude",
-            0,
-            total_files,
-            &format!("导入 Claude Code ({}/{})", source_index, total_sources),
-        );
-        let mut existing_ids = load_existing_request_ids_by_app_type(&conn, "claude_local");
-        let _ = conn.execute_batch("BEGIN IMMEDIATE");
-        for (idx, file) in files.iter().enumerate() {
-            let entries = parse_claude_log_file(file);
-            let file_index = idx as u32 + 1;
-            for entry in entries {
-                total += 1;
-                
-                // 检查是否已处理过
-                if seen_ids.contains(&entry.session_id) {
-                    skipped += 1;
-                    continue;
-                }
-                seen_ids.insert(entry.session_id.clone());
-                
-                // 检查数据库中是否已存在
-                if existing_ids.contains(&entry.session_id) {
-                    skipped += 1;
-                    continue;
+    // 各来源的旧记录，只在该来源有新记录时才加载
+    let mut existing: Vec<Option<ExistingRecords>> = sources.iter().map(|_| None).collect();
+
+    std::thread::scope(|scope| -> Result<(), String> {
+        let (tx, rx) = std::sync::mpsc::channel::<ParsedFile>();
+        // 在独立线程中等待线程池，调用线程边接收解析结果边写入
+        scope.spawn(move || {
+            tasks.into_par_iter().for_each_with(tx, |tx, task| {
+                let source = sources[task.source_idx];
+                let _ = tx.send(task.parse(source));
+            });
+        });
+
+        for parsed in rx {
+            let source = sources[parsed.source_idx];
+            batch.begin_file(parsed.path.to_string_lossy().to_string());
+
+            if !parsed.entries.is_empty() {
+                let existing = match existing[parsed.source_idx] {
+                    Some(ref mut loaded) => loaded,
+                    None => existing[parsed.source_idx].insert(ExistingRecords::load(db, source.id())?),
+                };
+                let imported = ExistingRecords::imported(db, &parsed.entries)?;
+
+                for entry in parsed.entries {
+                    total += 1;
+
+                    // 检查是否已处理过
+                    if !seen_ids.insert(entry.request_id.clone()) {
+                        skipped += 1;
+                        continue;
+                    }
+
+                    // 旧记录就地升级为新 ID；按对话汇总的旧记录删除后按新记录导入
+                    if let Some((legacy_id, aggregate)) = existing.take_legacy(&entry) {
+                        if aggregate {
+                            batch.delete(legacy_id);
+                        } else {
+                            skipped += 1;
+                            batch.upgrade(legacy_id, entry);
+                            continue;
+                        }
+                    }
+
+                    // 检查数据库中是否已存在（本次导入中被旧记录改名或删除而释放的 ID 不算）
+                    let no_project = imported
+                        .get(&entry.request_id)
+                        .filter(|_| !existing.released.contains(&entry.request_id));
+                    if let Some(&no_project) = no_project {
+                        if source.refresh_existing() {
+                            batch.update(entry);
+                            continue;
+                        }
+                        skipped += 1;
+                        if no_project {
+                            batch.backfill_project(entry);
+                        }
+                        continue;
+                    }
+
+                    batch.insert(entry);
                 }
-                existing_ids.insert(entry.session_id.clone());
-                
-                // 计算成本（优先使用服务商特定定价）
-                let provider_id = format!("{}_local", entry.source);
-                let pricing = get_provider_model_pricing(&conn, &provider_id, &entry.model);
-                let cost = entry.cost_usd
-                    .map(|c| Decimal::from_str(&c.to_string()).unwrap_or(Decimal::ZERO))
-                    .unwrap_or_else(|| calculate_cost(&entry, pricing));
-                
-                // 插入数据库
-                match insert_log_entry(&conn, &entry, cost) {
-                    Ok(_) => imported += 1,
-                    Err(_) => failed += 1,
+            }
+
+            // 保存会话统计信息（只有有数据时才保存）
+            for (session_id, stats) in parsed.stats {
+                if stats.conversation_count > 0 || stats.tool_calls.values().sum::<u32>() > 0 {
+                    batch.session_stats(session_id, source.id(), stats);
                 }
             }
 
-            if total_files > 0 && (file_index == total_files || file_index % 5 == 0) {
-                emit_local_log_progress(
-                    &window,
-                    "import",
-                    "claude",
-                    file_index,
-                    total_files,
-                    &format!("导入 Claude Code ({}/{})", source_index, total_sources),
-                );
+            // 导入位置与本文件的记录在同一批次中提交
+            batch.end_file(
+                source.id(),
+                parsed.fingerprint.map(|fingerprint| ImportState {
+                    fingerprint,
+                    cursor: parsed.cursor,
+                }),
+            );
+
+            state.source = Some(source);
+            state.bytes_done += parsed.bytes;
+            state.files_done += 1;
+            if state.files_done == state.files_total || state.files_done.is_multiple_of(5) {
+                progress(&state);
             }
-            
-            // 解析并保存会话统计信息
-            let stats = parse_claude_session_stats(file);
-            let session_id = file
-                .file_stem()
-                .and_then(|n| n.to_str())
-                .map(|s| s.to_string())
-                .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
-            let provider_id = "claude_local".to_string();
-            let _ = save_session_stats(&conn, &session_id, "claude", Some(&provider_id), &stats);
         }
-        let _ = conn.execute_batch("COMMIT");
+        Ok(())
+    })?;
+
+    for path in stale_paths {
+        batch.clear_import_state(path);
     }
-    
-    // 导入 Codex CLI 日志
-    if sources.contains(&"codex".to_string()) {
-        source_index += 1;
-        let (files, _) = scan_codex_logs();
-        let total_files = files.len() as u32;
-        emit_local_log_progress(
-            &window,
-            "import",
-            "codex",
-            0,
-            total_files,
-            &format!("导入 Codex CLI ({}/{})", source_index, total_sources),
-        );
-        let mut existing_ids = load_existing_request_ids_by_app_type(&conn, "codex_local");
-        let _ = conn.execute_batch("BEGIN IMMEDIATE");
-        for (idx, file) in files.iter().enumerate() {
-            let entries = parse_codex_log_file(file);
-            for entry in entries {
-                total += 1;
-                
-                if seen_ids.contains(&entry.session_id) {
-                    skipped += 1;
-                    continue;
-                }
-                seen_ids.insert(entry.session_id.clone());
-                
-                if existing_ids.contains(&entry.session_id) {
-                    skipped += 1;
-                    continue;
-                }
-                existing_ids.insert(entry.session_id.clone());
-                
-                // 计算成本（优先使用服务商特定定价）
-                let provider_id = format!("{}_local", entry.source);
-                let pricing = get_provider_model_pricing(&conn, &provider_id, &entry.model);
-                let cost = calculate_cost(&entry, pricing);
-                
-                match insert_log_entry(&conn, &entry, cost) {
-                    Ok(_) => imported += 1,
-                    Err(_) => failed += 1,
-                }
-            }
 
-            let file_index = idx as u32 + 1;
-            if total_files > 0 && (file_index == total_files || file_index % 5 == 0) {
-                emit_local_log_progress(
-                    &window,
-                    "import",
-                    "codex",
-                    file_index,
-                    total_files,
-                    &format!("导入 Codex CLI ({}/{})", source_index, total_sources),
-                );
-            }
-            
-            // 解析并保存会话统计信息
-            let stats = parse_codex_session_stats(file);
-            if stats.conversation_count > 0 || stats.tool_calls.values().sum::<u32>() > 0 {
-                let session_id = file
-                    .file_stem()
-                    .and_then(|n| n.to_str())
-                    .map(|s| s.to_string())
-                    .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
-                let provider_id = "codex_local".to_string();
-                let _ = save_session_stats(&conn, &session_id, "codex", Some(&provider_id), &stats);
-            }
+    let (imported, failed) = batch.finish();
+    Ok(LocalLogImportResult {
+        imported,
+        skipped,
+        failed,
+        total,
+    })
+}
+
+/// 每批提交的最大写入条数
+const IMPORT_BATCH_SIZE: usize = 500;
+
+/// 待写入的导入数据
+enum PendingWrite {
+    Insert(LocalLogEntry),
+    /// 覆盖已存在的记录（用于会继续更新的记录，如进行中的请求）
+    Update(LocalLogEntry),
+    /// 旧记录改为新的 request_id 并补充会话 ID
+    Upgrade {
+        legacy_id: String,
+        entry: LocalLogEntry,
+    },
+    /// 删除已被逐条记录取代的旧汇总记录
+    Delete(String),
+    SessionStats {
+        session_id: String,
+        source: String,
+        stats: SessionStats,
+    },
+    /// 为已导入但没有项目的记录补充项目
+    Project {
+        request_id: String,
+        project: String,
+    },
+    /// 文件的增量导入位置
+    ImportState {
+        path: String,
+        source: String,
+        state: ImportState,
+    },
+    /// 删除已不存在的文件的导入位置
+    ClearImportState(String),
+}
+
+/// 批量导入写入器
+///
+/// 解析结果先缓存在内存中，每满 `IMPORT_BATCH_SIZE` 条才获取写连接，
+/// 在一个 IMMEDIATE 事务内写入后立即释放，避免长时间占用写锁。
+/// 一个文件的数据可能跨多个批次，只要其中有写入失败（含整批回滚），
+/// 就不保存该文件的导入位置，下次导入时重新解析
+struct ImportBatch<'a> {
+    db: &'a Database,
+    /// 待写入的数据及其来源文件
+    pending: Vec<(Option<Arc<str>>, PendingWrite)>,
+    /// 正在写入的文件
+    file: Option<Arc<str>>,
+    /// 有数据写入失败的文件
+    failed_files: HashSet<Arc<str>>,
+    imported: u32,
+    failed: u32,
+}
+
+impl<'a> ImportBatch<'a> {
+    fn new(db: &'a Database) -> Self {
+        Self {
+            db,
+            pending: Vec::with_capacity(IMPORT_BATCH_SIZE),
+            file: None,
+            failed_files: HashSet::new(),
+            imported: 0,
+            failed: 0,
         }
-        let _ = conn.execute_batch("COMMIT");
     }
-    
-    // 导入 Gemini CLI 日志
-    if sources.contains(&"gemini".to_string()) {
-        source_index += 1;
-        let (files, _) = scan_gemini_logs();
-        let total_files = files.len() as u32;
-        emit_local_log_progress(
-            &window,
-            "import",
-            "gemini",
-            0,
-            total_files,
-            &format!("导入 Gemini CLI ({}/{})", source_index, total_sources),
-        );
-        let mut existing_ids = load_existing_request_ids_by_app_type(&conn, "gemini_local");
-        let _ = conn.execute_batch("BEGIN IMMEDIATE");
-        for (idx, file) in files.iter().enumerate() {
-            let entries = parse_gemini_log_file(file);
-            for entry in entries {
-                total += 1;
-                
-                if seen_ids.contains(&entry.session_id) {
-                    skipped += 1;
-                    continue;
-                }
-                seen_ids.insert(entry.session_id.clone());
-                
-                if existing_ids.contains(&entry.session_id) {
-                    skipped += 1;
-                    continue;
-                }
-                existing_ids.insert(entry.session_id.clone());
-                
-                // 计算成本（优先使用服务商特定定价）
-                let provider_id = format!("{}_local", entry.source);
-                let pricing = get_provider_model_pricing(&conn, &provider_id, &entry.model);
-                let cost = calculate_cost(&entry, pricing);
-                
-                match insert_log_entry(&conn, &entry, cost) {
-                    Ok(_) => imported += 1,
-                    Err(_) => failed += 1,
-                }
-            }
 
-            let file_index = idx as u32 + 1;
-            if total_files > 0 && (file_index == total_files || file_index % 5 == 0) {
-                emit_local_log_progress(
-                    &window,
-                    "import",
-                    "gemini",
-                    file_index,
-                    total_files,
-                    &format!("导入 Gemini CLI ({}/{})", source_index, total_sources),
-                );
-            }
-            
-            // 解析并保存会话统计信息
-            let stats = parse_gemini_session_stats(file);
-            if stats.conversation_count > 0 || stats.tool_calls.values().sum::<u32>() > 0 {
-                let session_id = file
-                    .file_stem()
-                    .and_then(|n| n.to_str())
-                    .map(|s| s.to_string())
-                    .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
-                let provider_id = "gemini_local".to_string();
-                let _ = save_session_stats(&conn, &session_id, "gemini", Some(&provider_id), &stats);
-            }
-        }
-        let _ = conn.execute_batch("COMMIT");
+    /// 之后写入的数据属于该文件
+    fn begin_file(&mut self, path: String) {
+        self.file = Some(Arc::from(path));
     }
-    
-    // 导入 Opencode 日志
-    if sources.contains(&"opencode".to_string()) {
-        source_index += 1;
-        let (files, _) = scan_opencode_logs();
-        let total_files = files.len() as u32;
-        emit_local_log_progress(
-            &window,
-            "import",
-            "opencode",
-            0,
-            total_files,
-            &format!("导入 Opencode ({}/{})", source_index, total_sources),
-        );
-        let mut existing_ids = load_existing_request_ids_by_app_type(&conn, "opencode_local");
-        let _ = conn.execute_batch("BEGIN IMMEDIATE");
-        for (idx, file) in files.iter().enumerate() {
-            let entries = parse_opencode_log_file(file);
-            for entry in entries {
-                total += 1;
-                
-                if seen_ids.contains(&entry.session_id) {
-                    skipped += 1;
-                    continue;
-                }
-                seen_ids.insert(entry.session_id.clone());
-                
-                if existing_ids.contains(&entry.session_id) {
-                    skipped += 1;
-                    continue;
-                }
-                existing_ids.insert(entry.session_id.clone());
-                
-                // 计算成本（优先使用服务商特定定价）
-                let provider_id = format!("{}_local", entry.source);
-                let pricing = get_provider_model_pricing(&conn, &provider_id, &entry.model);
-                let cost = calculate_cost(&entry, pricing);
-                
-                match insert_log_entry(&conn, &entry, cost) {
-                    Ok(_) => imported += 1,
-                    Err(_) => failed += 1,
-                }
-            }
 
-            let file_index = idx as u32 + 1;
-            if total_files > 0 && (file_index == total_files || file_index % 5 == 0) {
-                emit_local_log_progress(
-                    &window,
-                    "import",
-                    "opencode",
-                    file_index,
-                    total_files,
-                    &format!("导入 Opencode ({}/{})", source_index, total_sources),
-                );
-            }
-            
-            // 解析并保存会话统计信息
-            let stats = parse_opencode_session_stats(file);
-            if stats.conversation_count > 0 || stats.tool_calls.values().sum::<u32>() > 0 {
-                let session_id = file
-                    .file_stem()
-                    .and_then(|n| n.to_str())
-                    .map(|s| s.to_string())
-                    .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
-                let provider_id = "opencode_local".to_string();
-                let _ = save_session_stats(&conn, &session_id, "opencode", Some(&provider_id), &stats);
-            }
+    /// 文件的数据已全部加入，记录其导入位置（与本文件的数据在同一批次中提交）
+    fn end_file(&mut self, source: &str, state: Option<ImportState>) {
+        if let (Some(path), Some(state)) = (self.file.clone(), state) {
+            self.push(PendingWrite::ImportState {
+                path: path.to_string(),
+                source: source.to_string(),
+                state,
+            });
         }
-        let _ = conn.execute_batch("COMMIT");
+        self.file = None;
     }
-    
-    // 导入 Cursor 日志
-    if sources.contains(&"cursor".to_string()) {
-        source_index += 1;
-        let mut existing_cursor_ids = load_existing_request_ids_by_prefix(&conn, "cursor-");
-        let (files, _) = scan_cursor_logs();
-        let total_files = files.len() as u32;
-        emit_local_log_progress(
-            &window,
-            "import",
-            "cursor",
-            0,
-            total_files,
-            &format!("导入 Cursor ({}/{})", source_index, total_sources),
-        );
-        let _ = conn.execute_batch("BEGIN IMMEDIATE");
-        for (idx, file) in files.iter().enumerate() {
-            let entries = parse_cursor_db(file);
-            for entry in entries {
-                total += 1;
-                
-                if seen_ids.contains(&entry.session_id) {
-                    skipped += 1;
-                    continue;
-                }
-                seen_ids.insert(entry.session_id.clone());
-                
-                if existing_cursor_ids.contains(&entry.session_id) {
-                    // 已存在则更新（用于重新导入刷新统计）
-                    let provider_id = format!("{}_local", entry.source);
-                    let pricing = get_provider_model_pricing(&conn, &provider_id, &entry.model);
-                    let cost = calculate_cost(&entry, pricing);
-                    match update_log_entry(&conn, &entry, cost) {
-                        Ok(_) => imported += 1,
-                        Err(_) => failed += 1,
-                    }
-                    continue;
-                }
-                existing_cursor_ids.insert(entry.session_id.clone());
-                
-                // 计算成本（优先使用服务商特定定价）
-                let provider_id = format!("{}_local", entry.source);
-                let pricing = get_provider_model_pricing(&conn, &provider_id, &entry.model);
-                let cost = calculate_cost(&entry, pricing);
-                
-                match insert_log_entry(&conn, &entry, cost) {
-                    Ok(_) => imported += 1,
-                    Err(_) => failed += 1,
-                }
-            }
 
-            let file_index = idx as u32 + 1;
-            if total_files > 0 && (file_index == total_files || file_index % 5 == 0) {
-                emit_local_log_progress(
-                    &window,
-                    "import",
-                    "cursor",
-                    file_index,
-                    total_files,
-                    &format!("导入 Cursor ({}/{})", source_index, total_sources),
-                );
-            }
-            
-            // 解析并保存会话统计信息
-            let stats = parse_cursor_session_stats(file);
-            if stats.conversation_count > 0 || stats.tool_calls.values().sum::<u32>() > 0 {
-                let session_id = file
-                    .to_string_lossy()
-                    .replace(['\\', '/', ':'], "_");
-                let provider_id = "cursor_local".to_string();
-                let _ = save_session_stats(&conn, &session_id, "cursor", Some(&provider_id), &stats);
-            }
-        }
-        let _ = conn.execute_batch("COMMIT");
+    fn insert(&mut self, entry: LocalLogEntry) {
+        self.push(PendingWrite::Insert(entry));
     }
-    
-    // 导入 Windsurf 日志
-    if sources.contains(&"windsurf".to_string()) {
-        source_index += 1;
-        let (files, _) = scan_windsurf_logs();
-        let total_files = files.len() as u32;
-        emit_local_log_progress(&window, "import", "windsurf", 0, total_files, &format!("导入 Windsurf ({}/{})", source_index, total_sources));
-        let mut existing_ids = load_existing_request_ids_by_app_type(&conn, "windsurf_local");
-        let _ = conn.execute_batch("BEGIN IMMEDIATE");
-        for (idx, file) in files.iter().enumerate() {
-            let entries = parse_vscode_app_db(file, "windsurf");
-            for entry in entries {
-                total += 1;
-                if seen_ids.contains(&entry.session_id) { skipped += 1; continue; }
-                seen_ids.insert(entry.session_id.clone());
-                if existing_ids.contains(&entry.session_id) { skipped += 1; continue; }
-                existing_ids.insert(entry.session_id.clone());
-                let provider_id = format!("{}_local", entry.source);
-                let pricing = get_provider_model_pricing(&conn, &provider_id, &entry.model);
-                let cost = calculate_cost(&entry, pricing);
-                match insert_log_entry(&conn, &entry, cost) {
-                    Ok(_) => imported += 1,
-                    Err(_) => failed += 1,
-                }
-            }
-            let file_index = idx as u32 + 1;
-            if total_files > 0 && (file_index == total_files || file_index % 5 == 0) {
-                emit_local_log_progress(&window, "import", "windsurf", file_index, total_files, &format!("导入 Windsurf ({}/{})", source_index, total_sources));
-            }
-        }
-        let _ = conn.execute_batch("COMMIT");
-    }
-
-    // 导入 Kiro 日志
-    if sources.contains(&"kiro".to_string()) {
-        source_index += 1;
-        let (files, _) = scan_kiro_logs();
-        let total_files = files.len() as u32;
-        emit_local_log_progress(&window, "import", "kiro", 0, total_files, &format!("导入 Kiro ({}/{})", source_index, total_sources));
-        let mut existing_ids = load_existing_request_ids_by_app_type(&conn, "kiro_local");
-        let _ = conn.execute_batch("BEGIN IMMEDIATE");
-        for (idx, file) in files.iter().enumerate() {
-            let entries = parse_vscode_app_db(file, "kiro");
-            for entry in entries {
-                total += 1;
-                if seen_ids.contains(&entry.session_id) { skipped += 1; continue; }
-                seen_ids.insert(entry.session_id.clone());
-                if existing_ids.contains(&entry.session_id) { skipped += 1; continue; }
-                existing_ids.insert(entry.session_id.clone());
-                let provider_id = format!("{}_local", entry.source);
-                let pricing = get_provider_model_pricing(&conn, &provider_id, &entry.model);
-                let cost = calculate_cost(&entry, pricing);
-                match insert_log_entry(&conn, &entry, cost) {
-                    Ok(_) => imported += 1,
-                    Err(_) => failed += 1,
-                }
-            }
-            let file_index = idx as u32 + 1;
-            if total_files > 0 && (file_index == total_files || file_index % 5 == 0) {
-                emit_local_log_progress(&window, "import", "kiro", file_index, total_files, &format!("导入 Kiro ({}/{})", source_index, total_sources));
-            }
+
+    fn update(&mut self, entry: LocalLogEntry) {
+        self.push(PendingWrite::Update(entry));
+    }
+
+    fn upgrade(&mut self, legacy_id: String, entry: LocalLogEntry) {
+        self.push(PendingWrite::Upgrade { legacy_id, entry });
+    }
+
+    fn delete(&mut self, request_id: String) {
+        self.push(PendingWrite::Delete(request_id));
+    }
+
+    fn session_stats(&mut self, session_id: String, source: &str, stats: SessionStats) {
+        self.push(PendingWrite::SessionStats {
+            session_id,
+            source: source.to_string(),
+            stats,
+        });
+    }
+
+    /// 已导入的记录：之前没有项目时补充
+    fn backfill_project(&mut self, entry: LocalLogEntry) {
+        if let Some(project) = entry.project {
+            self.push(PendingWrite::Project {
+                request_id: entry.request_id,
+                project,
+            });
         }
-        let _ = conn.execute_batch("COMMIT");
-    }
-
-    // 导入 Antigravity 日志
-    if sources.contains(&"antigravity".to_string()) {
-        source_index += 1;
-        let (files, _) = scan_antigravity_logs();
-        let total_files = files.len() as u32;
-        emit_local_log_progress(&window, "import", "antigravity", 0, total_files, &format!("导入 Antigravity ({}/{})", source_index, total_sources));
-        let mut existing_ids = load_existing_request_ids_by_app_type(&conn, "antigravity_local");
-        let _ = conn.execute_batch("BEGIN IMMEDIATE");
-        for (idx, file) in files.iter().enumerate() {
-            let entries = parse_vscode_app_db(file, "antigravity");
-            for entry in entries {
-                total += 1;
-                if seen_ids.contains(&entry.session_id) { skipped += 1; continue; }
-                seen_ids.insert(entry.session_id.clone());
-                if existing_ids.contains(&entry.session_id) { skipped += 1; continue; }
-                existing_ids.insert(entry.session_id.clone());
-                let provider_id = format!("{}_local", entry.source);
-                let pricing = get_provider_model_pricing(&conn, &provider_id, &entry.model);
-                let cost = calculate_cost(&entry, pricing);
-                match insert_log_entry(&conn, &entry, cost) {
-                    Ok(_) => imported += 1,
-                    Err(_) => failed += 1,
-                }
-            }
-            let file_index = idx as u32 + 1;
-            if total_files > 0 && (file_index == total_files || file_index % 5 == 0) {
-                emit_local_log_progress(&window, "import", "antigravity", file_index, total_files, &format!("导入 Antigravity ({}/{})", source_index, total_sources));
-            }
+    }
+
+    fn clear_import_state(&mut self, path: String) {
+        self.push(PendingWrite::ClearImportState(path));
+    }
+
+    fn push(&mut self, write: PendingWrite) {
+        self.pending.push((self.file.clone(), write));
+        if self.pending.len() >= IMPORT_BATCH_SIZE {
+            self.flush();
         }
-        let _ = conn.execute_batch("COMMIT");
-    }
-
-    // 导入 Warp 日志
-    if sources.contains(&"warp".to_string()) {
-        source_index += 1;
-        let (files, _) = scan_warp_logs();
-        let total_files = files.len() as u32;
-        emit_local_log_progress(&window, "import", "warp", 0, total_files, &format!("导入 Warp ({}/{})", source_index, total_sources));
-        let mut existing_ids = load_existing_request_ids_by_app_type(&conn, "warp_local");
-        let _ = conn.execute_batch("BEGIN IMMEDIATE");
-        for file in &files {
-            let entries = parse_warp_db(file);
-            for entry in entries {
-                total += 1;
-                if seen_ids.contains(&entry.session_id) { skipped += 1; continue; }
-                seen_ids.insert(entry.session_id.clone());
-                if existing_ids.contains(&entry.session_id) { skipped += 1; continue; }
-                existing_ids.insert(entry.session_id.clone());
-                let provider_id = format!("{}_local", entry.source);
-                let pricing = get_provider_model_pricing(&conn, &provider_id, &entry.model);
-                let cost = entry.cost_usd
-                    .map(|c| Decimal::from_str(&c.to_string()).unwrap_or(Decimal::ZERO))
-                    .unwrap_or_else(|| calculate_cost(&entry, pricing));
-                match insert_log_entry(&conn, &entry, cost) {
-                    Ok(_) => imported += 1,
-                    Err(_) => failed += 1,
-                }
-            }
+    }
+
+    /// 提交当前批次（失败时整批回滚并计入失败数）
+    fn flush(&mut self) {
+        if self.pending.is_empty() {
+            return;
         }
-        let _ = conn.execute_batch("COMMIT");
-        emit_local_log_progress(&window, "import", "warp", total_files, total_files, &format!("导入 Warp ({}/{})", source_index, total_sources));
-    }
-
-    // 导入 Augment 日志
-    if sources.contains(&"augment".to_string()) {
-        source_index += 1;
-        let (files, _) = scan_augment_logs();
-        let total_files = files.len() as u32;
-        emit_local_log_progress(&window, "import", "augment", 0, total_files, &format!("导入 Augment ({}/{})", source_index, total_sources));
-        let mut existing_ids = load_existing_request_ids_by_app_type(&conn, "augment_local");
-        let _ = conn.execute_batch("BEGIN IMMEDIATE");
-        for (idx, file) in files.iter().enumerate() {
-            let entries = parse_vscode_app_db(file, "augment");
-            for entry in entries {
-                total += 1;
-                if seen_ids.contains(&entry.session_id) { skipped += 1; continue; }
-                seen_ids.insert(entry.session_id.clone());
-                if existing_ids.contains(&entry.session_id) { skipped += 1; continue; }
-                existing_ids.insert(entry.session_id.clone());
-                let provider_id = format!("{}_local", entry.source);
-                let pricing = get_provider_model_pricing(&conn, &provider_id, &entry.model);
-                let cost = calculate_cost(&entry, pricing);
-                match insert_log_entry(&conn, &entry, cost) {
-                    Ok(_) => imported += 1,
-                    Err(_) => failed += 1,
-                }
+        let writes = std::mem::take(&mut self.pending);
+        let entry_count = writes
+            .iter()
+            .filter(|(_, w)| matches!(w, PendingWrite::Insert(_) | PendingWrite::Update(_)))
+            .count() as u32;
+        let files: Vec<Arc<str>> = writes.iter().filter_map(|(file, _)| file.clone()).collect();
+
+        match self.write_batch(writes) {
+            Ok((imported, failed)) => {
+                self.imported += imported;
+                self.failed += failed;
             }
-            let file_index = idx as u32 + 1;
-            if total_files > 0 && (file_index == total_files || file_index % 5 == 0) {
-                emit_local_log_progress(&window, "import", "augment", file_index, total_files, &format!("导入 Augment ({}/{})", source_index, total_sources));
+            Err(e) => {
+                eprintln!("批量导入本地日志失败: {e}");
+                self.failed += entry_count;
+                // 整批回滚，本批次涉及的文件之后的导入位置也不能保存
+                self.failed_files.extend(files);
             }
         }
-        let _ = conn.execute_batch("COMMIT");
     }
 
-    emit_local_log_progress(&window, "import", "done", total_sources, total_sources, "导入完成");
-
-    Ok(LocalLogImportResult {
-        imported,
-        skipped,
-        failed,
-        total,
-    })
-}
-
-/// 清除本地导入的日志
-#[tauri::command]
-pub async fn clear_local_logs(db: State<'_, Arc<Database>>) -> Result<u32, String> {
-    let conn = db.conn.lock().map_err(|e| format!("获取数据库锁失败: {e}"))?;
-    
-    let deleted = conn
-        .execute("DELETE FROM proxy_request_logs WHERE app_type LIKE '%_local'", [])
-        .map_err(|e| format!("清除本地日志失败: {e}"))?;
-    
-    Ok(deleted as u32)
-}
-
-/// 自动导入本地日志（静默模式，用于后台自动导入）
-/// 返回新导入的记录数
-#[tauri::command]
-pub async fn auto_import_local_logs(db: State<'_, Arc<Database>>) -> Result<u32, String> {
-    let conn = db.conn.lock().map_err(|e| format!("获取数据库锁失败: {e}"))?;
-    
-    let mut imported = 0u32;
-    
-    // 用于去重的集合
-    let mut seen_ids: HashSet<String> = HashSet::new();
-    
-    // 自动导入所有来源的日志
-    let sources = vec!["claude", "codex", "gemini", "opencode", "cursor", "windsurf", "kiro", "antigravity", "warp", "augment"];
-    
-    for source in sources {
-        let (files, _) = match source {
-            "claude" => scan_claude_logs(),
-            "codex" => scan_codex_logs(),
-            "gemini" => scan_gemini_logs(),
-            "opencode" => scan_opencode_logs(),
-            "cursor" => scan_cursor_logs(),
-            "windsurf" => scan_windsurf_logs(),
-            "kiro" => scan_kiro_logs(),
-            "antigravity" => scan_antigravity_logs(),
-            "warp" => scan_warp_logs(),
-            "augment" => scan_augment_logs(),
-            _ => continue,
-        };
-        
-        for file in &files {
-            let entries: Vec<LocalLogEntry> = match source {
-                "claude" => parse_claude_log_file(file),
-                "codex" => parse_codex_log_file(file),
-                "gemini" => parse_gemini_log_file(file),
-                "opencode" => parse_opencode_log_file(file),
-                "cursor" => parse_cursor_db(file),
-                "windsurf" => parse_vscode_app_db(file, "windsurf"),
-                "kiro" => parse_vscode_app_db(file, "kiro"),
-                "antigravity" => parse_vscode_app_db(file, "antigravity"),
-                "warp" => parse_warp_db(file),
-                "augment" => parse_vscode_app_db(file, "augment"),
-                _ => continue,
+    fn write_batch(&mut self, writes: Vec<(Option<Arc<str>>, PendingWrite)>) -> Result<(u32, u32), AppError> {
+        let db = self.db;
+        let mut conn = lock_conn!(db.conn);
+        let tx = conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_err(|e| AppError::Database(format!("开启事务失败: {e}")))?;
+
+        // 同一批次内相同服务商和模型的定价、规则与计费设置只查询一次
+        let mut resolver = PriceResolver::new(&tx);
+        let mut billing = BillingResolver::new(&tx);
+        let pricing_version = pricing::current_version(&tx)?;
+        // 日志自带成本时记为 SOURCE_COST_VERSION，重算时保留原值；
+        // 否则与代理日志和历史重算一样，按服务商计费货币和倍率换算为实际花费
+        let mut cost_of = |entry: &LocalLogEntry| -> EntryCost {
+            if let Some(cost) = entry.cost_usd {
+                return EntryCost {
+                    total: Decimal::from_str(&cost.to_string()).unwrap_or(Decimal::ZERO),
+                    pricing_version: Some(SOURCE_COST_VERSION),
+                    multiplier: Decimal::ONE,
+                };
+            }
+            let provider_id = format!("{}_local", entry.source);
+            let tokens = TokenCounts {
+                input: entry.input_tokens as u64,
+                output: entry.output_tokens as u64,
+                cache_read: entry.cache_read_tokens as u64,
+                cache_creation: entry.cache_creation_tokens as u64,
             };
-            
-            for entry in entries {
-                // 检查是否已处理过
-                if seen_ids.contains(&entry.session_id) {
+            let Some(rates) = resolver
+                .resolve(Some(&provider_id), &entry.model, &tokens, entry.timestamp)
+                .ok()
+                .flatten()
+            else {
+                return EntryCost::unpriced();
+            };
+            let factor = billing.factor(&provider_id).unwrap_or_default();
+            EntryCost {
+                total: rates.cost(&tokens).scaled(factor.usd_factor).total_cost,
+                pricing_version: Some(pricing_version),
+                multiplier: factor.multiplier,
+            }
+        };
+
+        let mut imported = 0u32;
+        let mut failed = 0u32;
+        for (file, write) in writes {
+            let is_entry = matches!(write, PendingWrite::Insert(_) | PendingWrite::Update(_));
+            let result = match write {
+                PendingWrite::Insert(entry) => insert_log_entry(&tx, &entry, cost_of(&entry)),
+                PendingWrite::Update(entry) => update_log_entry(&tx, &entry, cost_of(&entry)),
+                PendingWrite::SessionStats { session_id, source, stats } => {
+                    let provider_id = format!("{source}_local");
+                    save_session_stats(&tx, &session_id, &source, Some(&provider_id), &stats)
+                }
+                PendingWrite::Upgrade { legacy_id, entry } => tx
+                    .execute(
+                        "UPDATE proxy_request_logs SET request_id = ?1, session_id = ?2, project = COALESCE(project, ?3),
+                                pricing_version = COALESCE(?4, pricing_version)
+                         WHERE request_id = ?5",
+                        rusqlite::params![
+                            entry.request_id,
+                            session_id_param(&entry),
+                            entry.project,
+                            // 版本记录之前导入的日志自带成本没有标记，升级时补上，重算时保留原值
+                            entry.cost_usd.map(|_| SOURCE_COST_VERSION),
+                            legacy_id
+                        ],
+                    )
+                    .map(|_| ())
+                    .map_err(|e| AppError::Database(format!("升级日志条目失败: {e}"))),
+                PendingWrite::Delete(request_id) => tx
+                    .execute("DELETE FROM proxy_request_logs WHERE request_id = ?1", [request_id])
+                    .map(|_| ())
+                    .map_err(|e| AppError::Database(format!("删除日志条目失败: {e}"))),
+                PendingWrite::Project { request_id, project } => tx
+                    .execute(
+                        "UPDATE proxy_request_logs SET project = ?1 WHERE request_id = ?2 AND project IS NULL",
+                        rusqlite::params![project, request_id],
+                    )
+                    .map(|_| ())
+                    .map_err(|e| AppError::Database(format!("补充日志项目失败: {e}"))),
+                PendingWrite::ImportState { path, source, state } => {
+                    // 本文件有数据写入失败时不保存导入位置，下次重新解析
+                    if !self.failed_files.contains(path.as_str()) {
+                        save_import_state(&tx, &path, &source, &state)
+                            .map_err(|e| AppError::Database(format!("保存导入状态失败: {e}")))?;
+                    }
                     continue;
                 }
-                seen_ids.insert(entry.session_id.clone());
-                
-                // 检查数据库中是否已存在
-                if record_exists(&conn, &entry.session_id) {
+                PendingWrite::ClearImportState(path) => {
+                    tx.execute("DELETE FROM local_import_state WHERE path = ?1", [path])
+                        .map_err(|e| AppError::Database(format!("清除导入状态失败: {e}")))?;
                     continue;
                 }
-                
-                // 计算成本（优先使用服务商特定定价）
-                let provider_id = format!("{}_local", entry.source);
-                let pricing = get_provider_model_pricing(&conn, &provider_id, &entry.model);
-                let cost = entry.cost_usd
-                    .map(|c| Decimal::from_str(&c.to_string()).unwrap_or(Decimal::ZERO))
-                    .unwrap_or_else(|| calculate_cost(&entry, pricing));
-                
-                // 插入数据库
-                if insert_log_entry(&conn, &entry, cost).is_ok() {
-                    imported += 1;
+            };
+            if result.is_err() {
+                if let Some(file) = file {
+                    self.failed_files.insert(file);
                 }
             }
-        }
-        
-        // 解析并保存会话统计信息
-        for file in &files {
-            let stats = match source {
-                "claude" => parse_claude_session_stats(file),
-                "codex" => parse_codex_session_stats(file),
-                "gemini" => parse_gemini_session_stats(file),
-                "opencode" => parse_opencode_session_stats(file),
-                "cursor" | "windsurf" | "kiro" | "antigravity" | "augment" => parse_cursor_session_stats(file),
-                // Warp 暂无会话统计解析
-                _ => continue,
-            };
-            
-            // 只有有数据时才保存
-            if stats.conversation_count > 0 || stats.tool_calls.values().sum::<u32>() > 0 {
-                let session_id = if matches!(source, "cursor" | "windsurf" | "kiro" | "antigravity" | "augment") {
-                    // VSCode 系工具使用时间戳作为会话 ID
-                    format!("{}-{}", source, chrono::Utc::now().timestamp())
-                } else {
-                    file
-                        .file_stem()
-                        .and_then(|n| n.to_str())
-                        .map(|s| s.to_string())
-                        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string())
-                };
-                let provider_id = format!("{}_local", source);
-                let _ = save_session_stats(&conn, &session_id, source, Some(&provider_id), &stats);
+            if is_entry {
+                match result {
+                    Ok(_) => imported += 1,
+                    Err(_) => failed += 1,
+                }
             }
         }
+
+        tx.commit()
+            .map_err(|e| AppError::Database(format!("提交事务失败: {e}")))?;
+        Ok((imported, failed))
+    }
+
+    /// 提交剩余数据，返回 (导入数, 失败数)
+    fn finish(mut self) -> (u32, u32) {
+        self.flush();
+        (self.imported, self.failed)
     }
-    
-    Ok(imported)
 }
 
 // ============================================================================
@@ -4352,118 +4325,110 @@ pub async fn auto_import_local_logs(db: State<'_, Arc<Database>>) -> Result<u32,
 // ============================================================================
 
 /// 解析 Claude Code 日志文件的会话统计信息
-fn parse_claude_session_stats(path: &PathBuf) -> SessionStats {
-    let mut stats = SessionStats::default();
-    
-    let Ok(content) = fs::read_to_string(path) else {
-        return stats;
-    };
-    
-    let mut last_user_timestamp_ms: Option<i64> = None;
-    let mut first_assistant_after_user = true;
-    let mut files_modified: HashSet<String> = HashSet::new();
+fn parse_claude_session_stats(path: &Path) -> SessionStats {
+    let mut ctx = StatsContext::default();
+    if let Ok(content) = fs::read_to_string(path) {
+        ctx.collect_lines(content.lines(), collect_claude_stats);
+    }
+    ctx.stats()
+}
+
+/// 累计一行 Claude Code 日志的会话统计
+fn collect_claude_stats(json: &serde_json::Value, ctx: &mut StatsContext) {
+    let msg_type = json.get("type").and_then(|v| v.as_str());
     
-    for line in content.lines() {
-        if let Ok(json) = serde_json::from_str::<serde_json::Value>(line) {
-            let msg_type = json.get("type").and_then(|v| v.as_str());
-            
-            match msg_type {
-                Some("user") => {
-                    // 用户消息
-                    // 检查是否是工具结果（嵌套在 user 消息中）
-                    if let Some(message) = json.get("message") {
-                        if let Some(content_arr) = message.get("content").and_then(|c| c.as_array()) {
-                            let has_tool_result = content_arr.iter().any(|item| {
-                                item.get("type").and_then(|t| t.as_str()) == Some("tool_result")
-                            });
-                            
-                            if !has_tool_result {
-                                // 普通用户消息，计算对话轮数
-                                stats.conversation_count += 1;
-                                last_user_timestamp_ms = extract_timestamp_ms(&json);
-                                first_assistant_after_user = true;
-                            }
-                        } else if message.get("role").and_then(|r| r.as_str()) == Some("user") {
-                            // 旧格式用户消息
-                            stats.conversation_count += 1;
-                            last_user_timestamp_ms = extract_timestamp_ms(&json);
-                            first_assistant_after_user = true;
-                        }
+    match msg_type {
+        Some("user") => {
+            // 用户消息
+            // 检查是否是工具结果（嵌套在 user 消息中）
+            if let Some(message) = json.get("message") {
+                if let Some(content_arr) = message.get("content").and_then(|c| c.as_array()) {
+                    let has_tool_result = content_arr.iter().any(|item| {
+                        item.get("type").and_then(|t| t.as_str()) == Some("tool_result")
+                    });
+                    
+                    if !has_tool_result {
+                        // 普通用户消息，计算对话轮数
+                        ctx.stats.conversation_count += 1;
+                        ctx.last_user_ms = extract_timestamp_ms(json);
+                        ctx.awaiting_response = true;
                     }
+                } else if message.get("role").and_then(|r| r.as_str()) == Some("user") {
+                    // 旧格式用户消息
+                    ctx.stats.conversation_count += 1;
+                    ctx.last_user_ms = extract_timestamp_ms(json);
+                    ctx.awaiting_response = true;
                 }
-                Some("assistant") => {
-                    // 助手消息，解析 content 数组
-                    // 只计算用户消息后第一个助手消息的响应时间
-                    if first_assistant_after_user {
-                        if let (Some(user_ts_ms), Some(assistant_ts_ms)) = 
-                            (last_user_timestamp_ms, extract_timestamp_ms(&json)) 
-                        {
-                            let response_ms = (assistant_ts_ms - user_ts_ms).abs() as u64;
-                            // 响应时间应该在合理范围内（小于5分钟）
-                            if response_ms < 300000 {
-                                stats.response_time_ms += response_ms;
-                            }
-                        }
-                        first_assistant_after_user = false;
+            }
+        }
+        Some("assistant") => {
+            // 助手消息，解析 content 数组
+            // 只计算用户消息后第一个助手消息的响应时间
+            if ctx.awaiting_response {
+                if let (Some(user_ts_ms), Some(assistant_ts_ms)) = 
+                    (ctx.last_user_ms, extract_timestamp_ms(json)) 
+                {
+                    let response_ms = (assistant_ts_ms - user_ts_ms).abs() as u64;
+                    // 响应时间应该在合理范围内（小于5分钟）
+                    if response_ms < 300000 {
+                        ctx.stats.response_time_ms += response_ms;
                     }
-                    
-                    if let Some(message) = json.get("message") {
-                        if let Some(content_arr) = message.get("content").and_then(|c| c.as_array()) {
-                            for block in content_arr {
-                                let block_type = block.get("type").and_then(|t| t.as_str());
-                                
-                                match block_type {
-                                    Some("thinking") => {
-                                        // 思考内容
-                                        if let Some(thinking_text) = block.get("thinking").and_then(|t| t.as_str()) {
-                                            // 估算思考时间：每1000字符约2秒
-                                            let thinking_ms = (thinking_text.len() as u64).saturating_mul(2);
-                                            stats.thinking_time_ms += thinking_ms;
+                }
+                ctx.awaiting_response = false;
+            }
+            
+            if let Some(message) = json.get("message") {
+                if let Some(content_arr) = message.get("content").and_then(|c| c.as_array()) {
+                    for block in content_arr {
+                        let block_type = block.get("type").and_then(|t| t.as_str());
+                        
+                        match block_type {
+                            Some("thinking") => {
+                                // 思考内容
+                                if let Some(thinking_text) = block.get("thinking").and_then(|t| t.as_str()) {
+                                    // 估算思考时间：每1000字符约2秒
+                                    let thinking_ms = (thinking_text.len() as u64).saturating_mul(2);
+                                    ctx.stats.thinking_time_ms += thinking_ms;
+                                }
+                            }
+                            Some("tool_use") => {
+                                // 工具调用（在 assistant 消息的 content 中）
+                                if let Some(tool_name) = block.get("name").and_then(|n| n.as_str()) {
+                                    let normalized_name = normalize_tool_name(tool_name);
+                                    *ctx.stats.tool_calls.entry(normalized_name).or_insert(0) += 1;
+                                    
+                                    // 检查是否是文件编辑工具
+                                    if is_file_edit_tool(tool_name) {
+                                        if let Some(file_path) = extract_file_path_from_tool(block) {
+                                            ctx.files.insert(file_path);
                                         }
-                                    }
-                                    Some("tool_use") => {
-                                        // 工具调用（在 assistant 消息的 content 中）
-                                        if let Some(tool_name) = block.get("name").and_then(|n| n.as_str()) {
-                                            let normalized_name = normalize_tool_name(tool_name);
-                                            *stats.tool_calls.entry(normalized_name).or_insert(0) += 1;
-                                            
-                                            // 检查是否是文件编辑工具
-                                            if is_file_edit_tool(tool_name) {
-                                                if let Some(file_path) = extract_file_path_from_tool(block) {
-                                                    files_modified.insert(file_path);
-                                                }
-                                                
-                                                // 从工具参数中提取代码变更
-                                                if let Some(input) = block.get("input") {
-                                                    let (added, deleted) = extract_code_changes_from_input(input, tool_name);
-                                                    stats.lines_added += added;
-                                                    stats.lines_deleted += deleted;
-                                                }
-                                            }
+                                        
+                                        // 从工具参数中提取代码变更
+                                        if let Some(input) = block.get("input") {
+                                            let (added, deleted) = extract_code_changes_from_input(input, tool_name);
+                                            ctx.stats.lines_added += added;
+                                            ctx.stats.lines_deleted += deleted;
                                         }
                                     }
-                                    _ => {}
                                 }
                             }
+                            _ => {}
                         }
                     }
                 }
-                _ => {}
-            }
-            
-            // 从工具结果中提取代码变更
-            if let Some(tool_result) = json.get("toolUseResult") {
-                if let Some(stdout) = tool_result.get("stdout").and_then(|s| s.as_str()) {
-                    let (added, deleted) = extract_line_changes(stdout);
-                    stats.lines_added += added;
-                    stats.lines_deleted += deleted;
-                }
             }
         }
+        _ => {}
     }
     
-    stats.files_changed = files_modified.len() as u32;
-    stats
+    // 从工具结果中提取代码变更
+    if let Some(tool_result) = json.get("toolUseResult") {
+        if let Some(stdout) = tool_result.get("stdout").and_then(|s| s.as_str()) {
+            let (added, deleted) = extract_line_changes(stdout);
+            ctx.stats.lines_added += added;
+            ctx.stats.lines_deleted += deleted;
+        }
+    }
 }
 
 /// 提取时间戳（毫秒）
@@ -4488,100 +4453,104 @@ fn extract_timestamp_ms(json: &serde_json::Value) -> Option<i64> {
         })
 }
 
+/// 内容哈希（FNV-1a），用于没有消息 ID 的记录生成稳定的 ID
+fn content_hash(content: &str) -> String {
+    let hash = content.bytes().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("{hash:016x}")
+}
+
+/// 日志文件路径的哈希，记录没有会话 ID 时用于区分同一来源的多个日志文件
+fn file_hash(path: &Path) -> String {
+    content_hash(&path.to_string_lossy())
+}
+
 /// 解析 Codex CLI 日志文件的会话统计信息
-fn parse_codex_session_stats(path: &PathBuf) -> SessionStats {
-    let mut stats = SessionStats::default();
-    
-    let Ok(content) = fs::read_to_string(path) else {
-        return stats;
-    };
-    
-    let mut files_modified: HashSet<String> = HashSet::new();
-    let mut _last_user_timestamp: Option<i64> = None;
+fn parse_codex_session_stats(path: &Path) -> SessionStats {
+    let mut ctx = StatsContext::default();
+    if let Ok(content) = fs::read_to_string(path) {
+        ctx.collect_lines(content.lines(), collect_codex_stats);
+    }
+    ctx.stats()
+}
+
+/// 累计一行 Codex CLI 日志的会话统计
+fn collect_codex_stats(json: &serde_json::Value, ctx: &mut StatsContext) {
+    let event_type = json.get("type").and_then(|v| v.as_str());
     
-    for line in content.lines() {
-        if let Ok(json) = serde_json::from_str::<serde_json::Value>(line) {
-            let event_type = json.get("type").and_then(|v| v.as_str());
-            
-            match event_type {
-                Some("event_msg") => {
-                    // 用户消息事件
-                    if let Some(payload) = json.get("payload") {
-                        if payload.get("type").and_then(|t| t.as_str()) == Some("user_message") {
-                            stats.conversation_count += 1;
-                            _last_user_timestamp = extract_timestamp(&json);
-                        }
-                    }
+    match event_type {
+        Some("event_msg") => {
+            // 用户消息事件
+            if let Some(payload) = json.get("payload") {
+                if payload.get("type").and_then(|t| t.as_str()) == Some("user_message") {
+                    ctx.stats.conversation_count += 1;
                 }
-                Some("response_item") => {
-                    // 响应项
-                    if let Some(payload) = json.get("payload") {
-                        let payload_type = payload.get("type").and_then(|t| t.as_str());
-                        
-                        match payload_type {
-                            Some("message") => {
-                                // 检查 role
-                                let role = payload.get("role").and_then(|r| r.as_str());
-                                if role == Some("user") {
-                                    // 检查是否是工具结果
-                                    let is_tool_result = payload.get("content")
-                                        .and_then(|c| c.as_array())
-                                        .map(|arr| arr.iter().any(|item| {
-                                            item.get("type").and_then(|t| t.as_str()) == Some("tool_result")
-                                        }))
-                                        .unwrap_or(false);
-                                    
-                                    if !is_tool_result {
-                                        stats.conversation_count += 1;
-                                        _last_user_timestamp = extract_timestamp(&json);
-                                    }
-                                }
+            }
+        }
+        Some("response_item") => {
+            // 响应项
+            if let Some(payload) = json.get("payload") {
+                let payload_type = payload.get("type").and_then(|t| t.as_str());
+                
+                match payload_type {
+                    Some("message") => {
+                        // 检查 role
+                        let role = payload.get("role").and_then(|r| r.as_str());
+                        if role == Some("user") {
+                            // 检查是否是工具结果
+                            let is_tool_result = payload.get("content")
+                                .and_then(|c| c.as_array())
+                                .map(|arr| arr.iter().any(|item| {
+                                    item.get("type").and_then(|t| t.as_str()) == Some("tool_result")
+                                }))
+                                .unwrap_or(false);
+                            
+                            if !is_tool_result {
+                                ctx.stats.conversation_count += 1;
                             }
-                            Some("function_call") => {
-                                // 工具调用
-                                if let Some(tool_name) = payload.get("name").and_then(|n| n.as_str()) {
-                                    let normalized_name = normalize_tool_name(tool_name);
-                                    *stats.tool_calls.entry(normalized_name).or_insert(0) += 1;
-                                    
-                                    if is_file_edit_tool(tool_name) {
-                                        // 尝试从 arguments 中提取文件路径
-                                        if let Some(args_str) = payload.get("arguments").and_then(|a| a.as_str()) {
-                                            if let Ok(args) = serde_json::from_str::<serde_json::Value>(args_str) {
-                                                if let Some(file_path) = extract_file_path_from_tool(&args) {
-                                                    files_modified.insert(file_path);
-                                                }
-                                            }
+                        }
+                    }
+                    Some("function_call") => {
+                        // 工具调用
+                        if let Some(tool_name) = payload.get("name").and_then(|n| n.as_str()) {
+                            let normalized_name = normalize_tool_name(tool_name);
+                            *ctx.stats.tool_calls.entry(normalized_name).or_insert(0) += 1;
+                            
+                            if is_file_edit_tool(tool_name) {
+                                // 尝试从 arguments 中提取文件路径
+                                if let Some(args_str) = payload.get("arguments").and_then(|a| a.as_str()) {
+                                    if let Ok(args) = serde_json::from_str::<serde_json::Value>(args_str) {
+                                        if let Some(file_path) = extract_file_path_from_tool(&args) {
+                                            ctx.files.insert(file_path);
                                         }
                                     }
                                 }
                             }
-                            Some("reasoning") => {
-                                // 思考内容
-                                if let Some(summary) = payload.get("summary").and_then(|s| s.as_array()) {
-                                    for item in summary {
-                                        if let Some(text) = item.get("text").and_then(|t| t.as_str()) {
-                                            // 估算思考时间
-                                            let thinking_ms = (text.len() as u64).saturating_mul(2);
-                                            stats.thinking_time_ms += thinking_ms;
-                                        }
-                                    }
+                        }
+                    }
+                    Some("reasoning") => {
+                        // 思考内容
+                        if let Some(summary) = payload.get("summary").and_then(|s| s.as_array()) {
+                            for item in summary {
+                                if let Some(text) = item.get("text").and_then(|t| t.as_str()) {
+                                    // 估算思考时间
+                                    let thinking_ms = (text.len() as u64).saturating_mul(2);
+                                    ctx.stats.thinking_time_ms += thinking_ms;
                                 }
                             }
-                            _ => {}
                         }
                     }
+                    _ => {}
                 }
-                _ => {}
             }
         }
+        _ => {}
     }
-    
-    stats.files_changed = files_modified.len() as u32;
-    stats
 }
 
 /// 解析 Gemini CLI 日志文件的会话统计信息
-fn parse_gemini_session_stats(path: &PathBuf) -> SessionStats {
+fn parse_gemini_session_stats(path: &Path) -> SessionStats {
     let mut stats = SessionStats::default();
     
     let Ok(content) = fs::read_to_string(path) else {
@@ -4663,25 +4632,87 @@ fn parse_gemini_session_stats(path: &PathBuf) -> SessionStats {
     stats
 }
 
-/// 解析 Opencode 日志文件的会话统计信息
+/// 各 Opencode 会话目录中最近修改的消息文件名
+///
+/// 查找日志文件时整体更新，解析时不必为每个消息文件重新扫描会话目录
+static OPENCODE_LATEST_MESSAGES: OnceLock<Mutex<HashMap<PathBuf, OsString>>> = OnceLock::new();
+
+/// 目录中最近修改的 JSON 文件名（修改时间相同时取文件名较大的）
+fn latest_json_file(dir: &Path) -> Option<OsString> {
+    fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .filter(|entry| entry.path().extension().and_then(|e| e.to_str()) == Some("json"))
+        .map(|entry| {
+            let modified = entry
+                .metadata()
+                .and_then(|m| m.modified())
+                .unwrap_or(std::time::UNIX_EPOCH);
+            (modified, entry.file_name())
+        })
+        .max()
+        .map(|(_, name)| name)
+}
+
+/// 会话目录中最近修改的消息文件名，未经查找日志文件缓存时扫描一次目录
+fn opencode_latest_message(session_dir: &Path) -> Option<OsString> {
+    let cache = OPENCODE_LATEST_MESSAGES.get_or_init(Default::default);
+    if let Some(name) = cache.lock().ok().and_then(|c| c.get(session_dir).cloned()) {
+        return Some(name);
+    }
+    let name = latest_json_file(session_dir)?;
+    if let Ok(mut cache) = cache.lock() {
+        cache.insert(session_dir.to_path_buf(), name.clone());
+    }
+    Some(name)
+}
+
+/// 解析 Opencode 会话的统计信息
 /// Opencode 使用分离的 JSON 文件存储：message/{sessionID}/{messageID}.json 和 part/{messageID}/{partID}.json
-fn parse_opencode_session_stats(path: &PathBuf) -> SessionStats {
+///
+/// 统计汇总整个会话目录，只在解析会话中最近修改的消息文件时计算，每个会话只汇总一次
+fn parse_opencode_session_stats(path: &Path) -> Option<(String, SessionStats)> {
+    let session_id = opencode_session_id(path)?;
+    let session_dir = path.parent()?;
+    if path.file_name() != Some(opencode_latest_message(session_dir)?.as_os_str()) {
+        return None;
+    }
+
+    // part 文件路径: storage/part/{messageID}/{partID}.json
+    let storage_dir = session_dir.parent()?.parent()?;
     let mut stats = SessionStats::default();
-    
+    let mut files_modified: HashSet<String> = HashSet::new();
+    for entry in fs::read_dir(session_dir).ok()?.flatten() {
+        let message = entry.path();
+        if message.extension().and_then(|e| e.to_str()) == Some("json") {
+            collect_opencode_message_stats(&message, storage_dir, &mut stats, &mut files_modified);
+        }
+    }
+    stats.files_changed = files_modified.len() as u32;
+    Some((session_id, stats))
+}
+
+/// 累计一个 Opencode 消息文件及其 part 的统计
+fn collect_opencode_message_stats(
+    path: &Path,
+    storage_dir: &Path,
+    stats: &mut SessionStats,
+    files_modified: &mut HashSet<String>,
+) {
     let Ok(content) = fs::read_to_string(path) else {
-        return stats;
+        return;
     };
     
     // opencode 消息是单个 JSON 文件，不是 JSONL
     let Ok(json) = serde_json::from_str::<serde_json::Value>(&content) else {
-        return stats;
+        return;
     };
     
     let role = json.get("role").and_then(|r| r.as_str());
     
     // 用户消息
     if role == Some("user") {
-        stats.conversation_count = 1;
+        stats.conversation_count += 1;
     }
     
     // 助手消息
@@ -4695,7 +4726,7 @@ fn parse_opencode_session_stats(path: &PathBuf) -> SessionStats {
                 let response_ms = (completed - created) as u64;
                 // 响应时间应该合理（小于1小时）
                 if response_ms < 3600000 {
-                    stats.response_time_ms = response_ms;
+                    stats.response_time_ms += response_ms;
                 }
             }
         }
@@ -4704,211 +4735,268 @@ fn parse_opencode_session_stats(path: &PathBuf) -> SessionStats {
         if let Some(tokens) = json.get("tokens") {
             let reasoning = tokens.get("reasoning").and_then(|v| v.as_u64()).unwrap_or(0);
             // 估算思考时间：每1000 reasoning tokens 约 2 秒
-            stats.thinking_time_ms = (reasoning as u64).saturating_mul(2);
+            stats.thinking_time_ms += reasoning.saturating_mul(2);
         }
     }
     
     // 从 part 目录读取工具调用
-    // part 文件路径: storage/part/{messageID}/{partID}.json
-    let message_id = json.get("id").and_then(|v| v.as_str());
-    if let Some(msg_id) = message_id {
-        if let Some(storage_dir) = path.parent().and_then(|p| p.parent()) {
-            let part_dir = storage_dir.join("part").join(msg_id);
-            if part_dir.exists() {
-                let mut files_modified: HashSet<String> = HashSet::new();
+    let Some(msg_id) = json.get("id").and_then(|v| v.as_str()) else {
+        return;
+    };
+    let Ok(parts) = fs::read_dir(storage_dir.join("part").join(msg_id)) else {
+        return;
+    };
+    for part_entry in parts.flatten() {
+        let part_path = part_entry.path();
+        if part_path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+        let Some(part_json) = fs::read_to_string(&part_path)
+            .ok()
+            .and_then(|c| serde_json::from_str::<serde_json::Value>(&c).ok())
+        else {
+            continue;
+        };
+        let part_type = part_json.get("type").and_then(|t| t.as_str());
+        
+        // 工具调用 part
+        if part_type == Some("tool") {
+            if let Some(tool_name) = part_json.get("tool").and_then(|t| t.as_str()) {
+                let normalized_name = normalize_tool_name(tool_name);
+                *stats.tool_calls.entry(normalized_name).or_insert(0) += 1;
                 
-                if let Ok(parts) = fs::read_dir(&part_dir) {
-                    for part_entry in parts.flatten() {
-                        let part_path = part_entry.path();
-                        if part_path.extension().and_then(|e| e.to_str()) == Some("json") {
-                            if let Ok(part_content) = fs::read_to_string(&part_path) {
-                                if let Ok(part_json) = serde_json::from_str::<serde_json::Value>(&part_content) {
-                                    let part_type = part_json.get("type").and_then(|t| t.as_str());
-                                    
-                                    // 工具调用 part
-                                    if part_type == Some("tool") {
-                                        if let Some(tool_name) = part_json.get("tool").and_then(|t| t.as_str()) {
-                                            let normalized_name = normalize_tool_name(tool_name);
-                                            *stats.tool_calls.entry(normalized_name).or_insert(0) += 1;
-                                            
-                                            // 提取文件变更
-                                            if is_file_edit_tool(tool_name) {
-                                                if let Some(state) = part_json.get("state") {
-                                                    if let Some(input) = state.get("input") {
-                                                        if let Some(file_path) = input.get("path").and_then(|p| p.as_str()) {
-                                                            files_modified.insert(file_path.to_string());
-                                                        }
-                                                        
-                                                        // 提取代码变更
-                                                        let (added, deleted) = extract_code_changes_from_input(input, tool_name);
-                                                        stats.lines_added += added;
-                                                        stats.lines_deleted += deleted;
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    }
-                                    
-                                    // 推理 part
-                                    if part_type == Some("reasoning") {
-                                        if let Some(text) = part_json.get("text").and_then(|t| t.as_str()) {
-                                            // 估算思考时间：每1000字符约2秒
-                                            let thinking_ms = (text.len() as u64).saturating_mul(2);
-                                            stats.thinking_time_ms += thinking_ms;
-                                        }
-                                    }
-                                }
-                            }
+                // 提取文件变更
+                if is_file_edit_tool(tool_name) {
+                    if let Some(input) = part_json.get("state").and_then(|s| s.get("input")) {
+                        if let Some(file_path) = input.get("path").and_then(|p| p.as_str()) {
+                            files_modified.insert(file_path.to_string());
                         }
+                        
+                        // 提取代码变更
+                        let (added, deleted) = extract_code_changes_from_input(input, tool_name);
+                        stats.lines_added += added;
+                        stats.lines_deleted += deleted;
                     }
                 }
-                
-                stats.files_changed = files_modified.len() as u32;
+            }
+        }
+        
+        // 推理 part
+        if part_type == Some("reasoning") {
+            if let Some(text) = part_json.get("text").and_then(|t| t.as_str()) {
+                // 估算思考时间：每1000字符约2秒
+                stats.thinking_time_ms += (text.len() as u64).saturating_mul(2);
+            }
+        }
+    }
+}
+
+/// 会话统计中的一条 Cursor 消息：(是否为用户消息, 工具调用 (工具名, 参数))
+type CursorStatsMessage = (bool, Vec<(String, Option<serde_json::Value>)>);
+
+/// 解析 Cursor 数据库中各对话的会话统计
+///
+/// 与 `parse_cursor_db` 遍历相同的对话，会话 ID 与记录的 `session_id` 一致
+fn parse_cursor_session_stats(path: &Path) -> Vec<(String, SessionStats)> {
+    use rusqlite::{Connection, OpenFlags};
+
+    // 以只读模式打开数据库
+    let Ok(conn) = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY) else {
+        return vec![];
+    };
+    let workspace_id = cursor_workspace_id(path);
+    let workspace_id = workspace_id.as_deref();
+    let mut sessions = Vec::new();
+
+    // 1. Chat 模式
+    if let Some(json) = query_itemtable_value(&conn, "workbench.panel.aichat.view.aichat.chatdata")
+        .and_then(|b| parse_json_bytes(&b))
+    {
+        for tab in json.get("tabs").and_then(|t| t.as_array()).into_iter().flatten() {
+            let Some(bubbles) = tab.get("bubbles").and_then(|b| b.as_array()) else {
+                continue;
+            };
+            let tab_id = tab.get("tabId").and_then(|v| v.as_str()).unwrap_or("unknown");
+            sessions.push((
+                cursor_session_id("chat", workspace_id, tab_id),
+                cursor_conversation_stats(bubbles.iter().filter_map(cursor_stats_message)),
+            ));
+        }
+    }
+
+    // 2. Workspace Composer
+    if let Some(json) = query_itemtable_value(&conn, "composer.composerData").and_then(|b| parse_json_bytes(&b)) {
+        for composer in json.get("allComposers").and_then(|a| a.as_array()).into_iter().flatten() {
+            let Some(messages) = composer.get("conversation").and_then(|c| c.as_array()) else {
+                continue;
+            };
+            let composer_id = composer.get("composerId").and_then(|v| v.as_str()).unwrap_or("unknown");
+            sessions.push((
+                cursor_session_id("workspace", workspace_id, composer_id),
+                cursor_conversation_stats(messages.iter().filter_map(cursor_stats_message)),
+            ));
+        }
+    }
+
+    // 3. aiService 旧格式：每条提问为一轮
+    let prompts = query_itemtable_value(&conn, "aiService.prompts")
+        .and_then(|b| parse_json_bytes(&b))
+        .and_then(|v| v.as_array().map(Vec::len))
+        .unwrap_or(0);
+    if prompts > 0 {
+        let stats = SessionStats {
+            conversation_count: prompts as u32,
+            ..Default::default()
+        };
+        sessions.push((cursor_aiservice_session_id(workspace_id), stats));
+    }
+
+    // 4. Global Composer (cursorDiskKV)，消息内联或按 bubbleId 单独存储
+    let mut composers: Vec<(String, serde_json::Value)> = Vec::new();
+    if let Ok(mut stmt) = conn.prepare("SELECT key, value FROM cursorDiskKV WHERE key LIKE 'composerData:%'") {
+        if let Ok(rows) = stmt.query_map([], |row| {
+            let key: String = row.get(0)?;
+            let value = row
+                .get::<_, Vec<u8>>(1)
+                .ok()
+                .or_else(|| row.get::<_, String>(1).ok().map(|s| s.into_bytes()));
+            Ok((key, value))
+        }) {
+            for (key, value) in rows.flatten() {
+                if let Some(json) = value.and_then(|bytes| parse_json_bytes(&bytes)) {
+                    composers.push((key, json));
+                }
             }
         }
     }
-    
-    stats
+    let mut bubbles: Option<HashMap<String, Vec<(String, CursorStatsMessage)>>> = None;
+    for (key, json) in &composers {
+        let composer_id = json
+            .get("composerId")
+            .and_then(|v| v.as_str())
+            .unwrap_or_else(|| key.strip_prefix("composerData:").unwrap_or(key));
+        let session_id = cursor_session_id("global", workspace_id, composer_id);
+        let inline = json.get("conversation").and_then(|c| c.as_array()).filter(|c| !c.is_empty());
+        let stats = match inline {
+            Some(messages) => cursor_conversation_stats(messages.iter().filter_map(cursor_stats_message)),
+            None => {
+                let bubbles = bubbles.get_or_insert_with(|| load_cursor_stats_bubbles(&conn));
+                let mut messages: Vec<&(String, CursorStatsMessage)> =
+                    bubbles.get(composer_id).map(|b| b.iter().collect()).unwrap_or_default();
+                // 按 composer 中记录的消息顺序
+                let order: HashMap<&str, usize> = json
+                    .get("fullConversationHeadersOnly")
+                    .and_then(|h| h.as_array())
+                    .into_iter()
+                    .flatten()
+                    .enumerate()
+                    .filter_map(|(idx, header)| Some((header.get("bubbleId")?.as_str()?, idx)))
+                    .collect();
+                messages.sort_by_key(|(id, _)| (order.get(id.as_str()).copied().unwrap_or(usize::MAX), id.clone()));
+                cursor_conversation_stats(messages.into_iter().map(|(_, message)| message.clone()))
+            }
+        };
+        sessions.push((session_id, stats));
+    }
+
+    sessions
 }
 
-/// 解析 Cursor 数据库的会话统计信息
-fn parse_cursor_session_stats(path: &PathBuf) -> SessionStats {
-    use rusqlite::{Connection, OpenFlags};
-    
-    let mut stats = SessionStats::default();
-    let mut files_modified: HashSet<String> = HashSet::new();
-    
-    // 以只读模式打开数据库
-    let Ok(conn) = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY) else {
-        return stats;
-    };
-    
-    // 查询包含 AI 对话数据的记录
-    let mut stmt = match conn.prepare(
-        "SELECT key, value FROM ItemTable WHERE key LIKE '%composerData%' OR key LIKE '%aiService%' OR key LIKE '%chat%'"
-    ) {
-        Ok(stmt) => stmt,
-        Err(_) => return stats,
+/// 加载所有 bubbleId 存储的消息中与会话统计相关的内容（按 composer 分组）
+fn load_cursor_stats_bubbles(conn: &rusqlite::Connection) -> HashMap<String, Vec<(String, CursorStatsMessage)>> {
+    let mut map: HashMap<String, Vec<(String, CursorStatsMessage)>> = HashMap::new();
+    let Ok(mut stmt) = conn.prepare("SELECT key, value FROM cursorDiskKV WHERE key LIKE 'bubbleId:%'") else {
+        return map;
     };
-    
-    let rows = match stmt.query_map([], |row| {
-        Ok((
-            row.get::<_, String>(0)?,
-            row.get::<_, Vec<u8>>(1)?,
-        ))
-    }) {
-        Ok(rows) => rows,
-        Err(_) => return stats,
+    let Ok(rows) = stmt.query_map([], |row| {
+        let key: String = row.get(0)?;
+        let value = row
+            .get::<_, Vec<u8>>(1)
+            .ok()
+            .or_else(|| row.get::<_, String>(1).ok().map(|s| s.into_bytes()));
+        Ok((key, value))
+    }) else {
+        return map;
     };
-    
-    for row_result in rows {
-        if let Ok((_key, value_bytes)) = row_result {
-            // 尝试解析 JSON 数据
-            if let Ok(value_str) = String::from_utf8(value_bytes) {
-                if let Ok(json) = serde_json::from_str::<serde_json::Value>(&value_str) {
-                    // 解析对话数据
-                    parse_cursor_stats_from_json(&json, &mut stats, &mut files_modified);
-                }
-            }
-        }
+    for (key, value) in rows.flatten() {
+        // bubbleId:{composer}:{bubble}
+        let mut parts = key.split(':').skip(1);
+        let (Some(composer_id), Some(bubble_id)) = (parts.next(), parts.next()) else {
+            continue;
+        };
+        let Some(message) = value
+            .and_then(|bytes| parse_json_bytes(&bytes))
+            .and_then(|json| cursor_stats_message(&json))
+        else {
+            continue;
+        };
+        map.entry(composer_id.to_string())
+            .or_default()
+            .push((bubble_id.to_string(), message));
     }
-    
-    stats.files_changed = files_modified.len() as u32;
-    stats
+    map
 }
 
-/// 从 Cursor JSON 数据中提取统计信息
-fn parse_cursor_stats_from_json(
-    json: &serde_json::Value,
-    stats: &mut SessionStats,
-    files_modified: &mut HashSet<String>,
-) {
-    // 检查是否有消息数组
-    if let Some(messages) = json.get("messages").and_then(|m| m.as_array()) {
-        for msg in messages {
-            let role = msg.get("role").and_then(|r| r.as_str());
-            
-            // 用户消息计数
-            if role == Some("user") {
-                stats.conversation_count += 1;
-            }
-            
-            // 检查助手消息中的工具调用
-            if role == Some("assistant") {
-                // 检查工具调用
-                if let Some(tool_calls) = msg.get("tool_calls").and_then(|t| t.as_array()) {
-                    for tc in tool_calls {
-                        if let Some(function) = tc.get("function") {
-                            if let Some(tool_name) = function.get("name").and_then(|n| n.as_str()) {
-                                let normalized = normalize_tool_name(tool_name);
-                                *stats.tool_calls.entry(normalized).or_insert(0) += 1;
-                                
-                                // 检查文件编辑
-                                if is_file_edit_tool(tool_name) {
-                                    if let Some(args) = function.get("arguments") {
-                                        if let Some(args_str) = args.as_str() {
-                                            if let Ok(args_json) = serde_json::from_str::<serde_json::Value>(args_str) {
-                                                if let Some(file_path) = args_json.get("path").and_then(|p| p.as_str()) {
-                                                    files_modified.insert(file_path.to_string());
-                                                }
-                                                // 提取代码变更
-                                                let (added, deleted) = extract_code_changes_from_input(&args_json, tool_name);
-                                                stats.lines_added += added;
-                                                stats.lines_deleted += deleted;
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }
-    }
-    
-    // 检查对话/会话数组
-    if let Some(conversations) = json.get("conversations").and_then(|c| c.as_array()) {
-        for conv in conversations {
-            if let Some(msgs) = conv.get("messages").and_then(|m| m.as_array()) {
-                for msg in msgs {
-                    if msg.get("role").and_then(|r| r.as_str()) == Some("user") {
-                        stats.conversation_count += 1;
-                    }
-                }
-            }
+/// 提取 Cursor 消息的角色和工具调用（OpenAI 格式的 `tool_calls` 或 Composer 的 `toolFormerData`）
+fn cursor_stats_message(message: &serde_json::Value) -> Option<CursorStatsMessage> {
+    let is_user = cursor_message_is_user(message)?;
+    let mut tools = Vec::new();
+    for call in message.get("tool_calls").and_then(|t| t.as_array()).into_iter().flatten() {
+        let Some(function) = call.get("function") else {
+            continue;
+        };
+        if let Some(name) = function.get("name").and_then(|n| n.as_str()) {
+            let args = function
+                .get("arguments")
+                .and_then(|a| a.as_str())
+                .and_then(|a| serde_json::from_str(a).ok());
+            tools.push((name.to_string(), args));
         }
     }
-    
-    // 检查工具使用数组
-    if let Some(tools) = json.get("tools").and_then(|t| t.as_array()) {
-        for tool in tools {
-            if let Some(name) = tool.get("name").and_then(|n| n.as_str()) {
-                let normalized = normalize_tool_name(name);
-                *stats.tool_calls.entry(normalized).or_insert(0) += 1;
-            }
+    if let Some(tool) = message.get("toolFormerData") {
+        if let Some(name) = tool.get("name").and_then(|n| n.as_str()) {
+            let args = tool
+                .get("rawArgs")
+                .and_then(|a| a.as_str())
+                .and_then(|a| serde_json::from_str(a).ok())
+                .or_else(|| tool.get("params").cloned());
+            tools.push((name.to_string(), args));
         }
     }
+    Some((is_user, tools))
 }
 
-/// 提取时间戳
-fn extract_timestamp(json: &serde_json::Value) -> Option<i64> {
-    json.get("timestamp")
-        .and_then(|v| {
-            if let Some(ts) = v.as_i64() {
-                if ts > 1_000_000_000_000 {
-                    Some(ts / 1000)
-                } else {
-                    Some(ts)
-                }
-            } else if let Some(s) = v.as_str() {
-                chrono::DateTime::parse_from_rfc3339(s)
-                    .ok()
-                    .map(|dt| dt.timestamp())
-            } else {
-                None
+/// 一个 Cursor 对话的会话统计：连续的用户消息为一轮（与记录的拆分方式一致）
+fn cursor_conversation_stats(messages: impl IntoIterator<Item = CursorStatsMessage>) -> SessionStats {
+    let mut stats = SessionStats::default();
+    let mut files_modified: HashSet<String> = HashSet::new();
+    let mut last_is_user = false;
+    for (is_user, tools) in messages {
+        if is_user && !last_is_user {
+            stats.conversation_count += 1;
+        }
+        last_is_user = is_user;
+
+        for (name, args) in tools {
+            *stats.tool_calls.entry(normalize_tool_name(&name)).or_insert(0) += 1;
+            // 检查文件编辑
+            let Some(args) = args.filter(|_| is_file_edit_tool(&name)) else {
+                continue;
+            };
+            if let Some(file_path) = ["path", "file_path", "filePath", "targetFile"]
+                .iter()
+                .find_map(|key| args.get(*key).and_then(|p| p.as_str()))
+            {
+                files_modified.insert(file_path.to_string());
             }
-        })
+            // 提取代码变更
+            let (added, deleted) = extract_code_changes_from_input(&args, &name);
+            stats.lines_added += added;
+            stats.lines_deleted += deleted;
+        }
+    }
+    stats.files_changed = files_modified.len() as u32;
+    stats
 }
 
 /// 标准化工具名称
@@ -5105,12 +5193,13 @@ fn save_session_stats(
     )
     .map_err(|e| AppError::Database(format!("保存会话统计失败: {e}")))?;
     
-    // 插入工具调用明细
+    // 工具调用明细是会话的累计值，替换之前保存的明细
+    conn.execute("DELETE FROM tool_calls WHERE session_id = ?1", [session_id])
+        .map_err(|e| AppError::Database(format!("清除工具调用记录失败: {e}")))?;
     for (tool_name, count) in &stats.tool_calls {
         conn.execute(
             "INSERT INTO tool_calls (session_id, tool_name, call_count, created_at)
-             VALUES (?1, ?2, ?3, ?4)
-             ON CONFLICT DO NOTHING",
+             VALUES (?1, ?2, ?3, ?4)",
             rusqlite::params![session_id, tool_name, count, now],
         )
         .map_err(|e| AppError::Database(format!("保存工具调用记录失败: {e}")))?;
@@ -5118,3 +5207,662 @@ fn save_session_stats(
     
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: &str) -> LocalLogEntry {
+        LocalLogEntry {
+            source: "claude".to_string(),
+            timestamp: 1_700_000_000,
+            model: "claude-sonnet-4".to_string(),
+            input_tokens: 100,
+            output_tokens: 50,
+            cache_read_tokens: 0,
+            cache_creation_tokens: 0,
+            cost_usd: None,
+            estimated: false,
+            request_id: id.to_string(),
+            session_id: "session".to_string(),
+            project_name: None,
+            project: None,
+        }
+    }
+
+    #[test]
+    fn import_batch_commits_across_batches_and_counts_failures() {
+        let db = Database::memory().unwrap();
+        let mut batch = ImportBatch::new(&db);
+        for i in 0..IMPORT_BATCH_SIZE + 10 {
+            batch.insert(entry(&format!("claude-{i}")));
+        }
+        // 主键冲突计为失败，不影响同批次其他记录
+        batch.insert(entry("claude-0"));
+        let (imported, failed) = batch.finish();
+
+        assert_eq!(imported as usize, IMPORT_BATCH_SIZE + 10);
+        assert_eq!(failed, 1);
+        let conn = db.read_conn().unwrap();
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM proxy_request_logs WHERE app_type = 'claude_local'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count as usize, IMPORT_BATCH_SIZE + 10);
+    }
+
+    #[test]
+    fn import_state_is_skipped_for_files_with_failed_records() {
+        let db = Database::memory().unwrap();
+        db.conn
+            .lock()
+            .unwrap()
+            .execute_batch(
+                "CREATE TEMP TRIGGER reject_bad BEFORE INSERT ON proxy_request_logs
+                 WHEN NEW.request_id = 'claude-bad' BEGIN SELECT RAISE(ABORT, 'rejected'); END;",
+            )
+            .unwrap();
+        let state = || ImportState {
+            fingerprint: FileFingerprint { size: 10, modified_at: 1 },
+            cursor: ParseCursor { offset: 10, context: None },
+        };
+
+        let mut batch = ImportBatch::new(&db);
+        batch.begin_file("bad.jsonl".to_string());
+        batch.insert(entry("claude-ok"));
+        batch.insert(entry("claude-bad"));
+        batch.end_file("claude", Some(state()));
+        batch.begin_file("good.jsonl".to_string());
+        batch.insert(entry("claude-good"));
+        batch.end_file("claude", Some(state()));
+        assert_eq!(batch.finish(), (2, 1));
+
+        let saved: Vec<String> = load_import_state(&db.read_conn().unwrap(), "claude").into_keys().collect();
+        assert_eq!(saved, vec!["good.jsonl".to_string()]);
+    }
+
+    #[test]
+    fn import_batch_applies_provider_billing() {
+        use crate::pricing::billing::{save_provider_billing, ProviderBilling};
+        use crate::pricing::recalc::{self, RecalcFilter};
+
+        let db = Database::memory().unwrap();
+        save_provider_billing(
+            &db,
+            &ProviderBilling {
+                provider_id: "claude_local".to_string(),
+                currency: "USD".to_string(),
+                cost_multiplier: "0.5".to_string(),
+                updated_at: 0,
+            },
+        )
+        .unwrap();
+        let mut batch = ImportBatch::new(&db);
+        batch.insert(entry("claude-1"));
+        assert_eq!(batch.finish(), (1, 0));
+
+        let (cost, multiplier): (String, String) = db
+            .read_conn()
+            .unwrap()
+            .query_row(
+                "SELECT total_cost_usd, cost_multiplier FROM proxy_request_logs WHERE request_id = 'claude-1'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert!(cost.parse::<f64>().unwrap() > 0.0);
+        assert_eq!(multiplier, "0.5");
+        // 与历史重算的结果一致
+        let report = recalc::preview(&db, &RecalcFilter::default()).unwrap();
+        assert_eq!((report.matched, report.changed, report.unpriced), (1, 0, 0));
+    }
+
+    #[test]
+    fn upgrade_marks_legacy_source_cost() {
+        use crate::pricing::recalc::{self, RecalcFilter};
+
+        let db = Database::memory().unwrap();
+        {
+            let conn = db.conn.lock().unwrap();
+            // v4 之前导入的记录：成本来自日志，但没有计价版本
+            let mut legacy = entry("claude-old");
+            legacy.cost_usd = Some(2.0);
+            insert_log_entry(&conn, &legacy, EntryCost::unpriced()).unwrap();
+            conn.execute("UPDATE proxy_request_logs SET total_cost_usd = '2', pricing_version = NULL", [])
+                .unwrap();
+        }
+
+        let mut upgraded = entry("claude-new");
+        upgraded.cost_usd = Some(2.0);
+        let mut batch = ImportBatch::new(&db);
+        batch.upgrade("claude-old".to_string(), upgraded);
+        batch.finish();
+
+        let version: Option<i64> = db
+            .read_conn()
+            .unwrap()
+            .query_row(
+                "SELECT pricing_version FROM proxy_request_logs WHERE request_id = 'claude-new'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(version, Some(SOURCE_COST_VERSION));
+        let report = recalc::preview(&db, &RecalcFilter::default()).unwrap();
+        assert_eq!((report.changed, report.skipped_source_cost), (0, 1));
+    }
+
+    #[test]
+    fn resaving_session_stats_replaces_tool_calls() {
+        let db = Database::memory().unwrap();
+        let conn = db.conn.lock().unwrap();
+        let mut stats = SessionStats::default();
+        stats.tool_calls.insert("read".to_string(), 1);
+        save_session_stats(&conn, "s1", "claude", None, &stats).unwrap();
+        stats.tool_calls.insert("read".to_string(), 3);
+        stats.tool_calls.insert("edit".to_string(), 1);
+        save_session_stats(&conn, "s1", "claude", None, &stats).unwrap();
+
+        let mut stmt = conn
+            .prepare("SELECT tool_name, call_count FROM tool_calls WHERE session_id = 's1' ORDER BY tool_name")
+            .unwrap();
+        let rows: Vec<(String, i64)> = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .flatten()
+            .collect();
+        assert_eq!(rows, vec![("edit".to_string(), 1), ("read".to_string(), 3)]);
+    }
+
+    /// 两个文件，第二个文件重复包含第一个文件的记录
+    struct FakeSource;
+
+    impl LogSource for FakeSource {
+        fn id(&self) -> &'static str {
+            "claude"
+        }
+
+        fn name(&self) -> &'static str {
+            "Fake"
+        }
+
+        fn location(&self) -> Option<PathBuf> {
+            None
+        }
+
+        fn discover(&self) -> Vec<PathBuf> {
+            vec![PathBuf::from("a.jsonl"), PathBuf::from("b.jsonl")]
+        }
+
+        fn estimate_entries(&self, files: &[PathBuf]) -> u32 {
+            files.len() as u32
+        }
+
+        fn parse_entries(&self, path: &Path) -> Vec<LocalLogEntry> {
+            match path.to_str() {
+                Some("a.jsonl") => vec![entry("claude-a1"), entry("claude-a2")],
+                _ => vec![entry("claude-a2"), entry("claude-b1")],
+            }
+        }
+    }
+
+    static FAKE: FakeSource = FakeSource;
+
+    #[test]
+    fn imports_registered_sources_generically() {
+        let ids: HashSet<&str> = LOG_SOURCES.iter().map(|source| source.id()).collect();
+        assert_eq!(ids.len(), LOG_SOURCES.len());
+        assert_eq!(log_source("codex").map(|source| source.name()), Some("Codex CLI"));
+        assert!(log_source("unknown").is_none());
+
+        let db = Database::memory().unwrap();
+        let mut progress = Vec::new();
+        let result = import_from_sources(&db, &[&FAKE], |p| progress.push(p.files_done)).unwrap();
+        assert_eq!((result.imported, result.skipped, result.total), (3, 1, 4));
+        assert_eq!(progress, vec![0, 2]);
+
+        // 再次导入全部跳过
+        let result = import_from_sources(&db, &[&FAKE], |_| {}).unwrap();
+        assert_eq!((result.imported, result.skipped), (0, 4));
+    }
+
+    /// 与 FakeSource 相同的记录，但带有项目
+    struct ProjectSource;
+
+    impl LogSource for ProjectSource {
+        fn id(&self) -> &'static str {
+            "claude"
+        }
+
+        fn name(&self) -> &'static str {
+            "Project"
+        }
+
+        fn location(&self) -> Option<PathBuf> {
+            None
+        }
+
+        fn discover(&self) -> Vec<PathBuf> {
+            vec![PathBuf::from("a.jsonl")]
+        }
+
+        fn estimate_entries(&self, files: &[PathBuf]) -> u32 {
+            files.len() as u32
+        }
+
+        fn parse_entries(&self, _path: &Path) -> Vec<LocalLogEntry> {
+            ["claude-a1", "claude-c1"]
+                .into_iter()
+                .map(|id| LocalLogEntry {
+                    project: Some("/work/app".to_string()),
+                    ..entry(id)
+                })
+                .collect()
+        }
+    }
+
+    #[test]
+    fn reimport_skips_existing_ids_and_backfills_project() {
+        let db = Database::memory().unwrap();
+        import_from_sources(&db, &[&FAKE], |_| {}).unwrap();
+
+        let result = import_from_sources(&db, &[&ProjectSource], |_| {}).unwrap();
+        assert_eq!((result.imported, result.skipped), (1, 1));
+
+        let conn = db.read_conn().unwrap();
+        let mut stmt = conn
+            .prepare("SELECT request_id FROM proxy_request_logs WHERE project = '/work/app' ORDER BY request_id")
+            .unwrap();
+        let ids: Vec<String> = stmt.query_map([], |row| row.get(0)).unwrap().flatten().collect();
+        assert_eq!(ids, vec!["claude-a1".to_string(), "claude-c1".to_string()]);
+    }
+
+    /// 读取临时目录中的 Claude 格式日志
+    struct DirSource(PathBuf);
+
+    impl LogSource for DirSource {
+        fn id(&self) -> &'static str {
+            "claude"
+        }
+
+        fn name(&self) -> &'static str {
+            "Dir"
+        }
+
+        fn location(&self) -> Option<PathBuf> {
+            Some(self.0.clone())
+        }
+
+        fn discover(&self) -> Vec<PathBuf> {
+            vec![self.0.join("session.jsonl")]
+        }
+
+        fn estimate_entries(&self, files: &[PathBuf]) -> u32 {
+            files.len() as u32
+        }
+
+        fn parse_entries(&self, path: &Path) -> Vec<LocalLogEntry> {
+            parse_claude_log_file(path)
+        }
+
+        fn parse_appended(&self, path: &Path, cursor: &ParseCursor) -> Option<(Vec<LocalLogEntry>, ParseCursor)> {
+            parse_claude_log_from(path, cursor)
+        }
+    }
+
+    fn claude_line(timestamp: &str) -> String {
+        format!(
+            "{{\"type\":\"assistant\",\"timestamp\":\"{timestamp}\",\"message\":{{\"model\":\"claude-sonnet-4\",\"usage\":{{\"input_tokens\":10,\"output_tokens\":5}}}}}}\n"
+        )
+    }
+
+    #[test]
+    fn reimport_parses_only_appended_lines() {
+        use std::io::Write;
+
+        let dir = std::env::temp_dir().join(format!("ai-switch-local-logs-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("session.jsonl");
+        fs::write(&path, claude_line("2025-01-01T00:00:00Z") + &claude_line("2025-01-01T00:01:00Z")).unwrap();
+        let source: &'static DirSource = Box::leak(Box::new(DirSource(dir.clone())));
+
+        let db = Database::memory().unwrap();
+        let result = import_from_sources(&db, &[source], |_| {}).unwrap();
+        assert_eq!((result.imported, result.total), (2, 2));
+
+        // 文件未变化时不解析
+        let result = import_from_sources(&db, &[source], |_| {}).unwrap();
+        assert_eq!((result.imported, result.total), (0, 0));
+
+        fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(claude_line("2025-01-01T00:02:00Z").as_bytes())
+            .unwrap();
+        let result = import_from_sources(&db, &[source], |_| {}).unwrap();
+        assert_eq!((result.imported, result.skipped, result.total), (1, 0, 1));
+
+        let offset: i64 = db
+            .read_conn()
+            .unwrap()
+            .query_row("SELECT offset FROM local_import_state WHERE source = 'claude'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(offset as u64, fs::metadata(&path).unwrap().len());
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn appended_session_stats_match_full_parse() {
+        use std::io::Write;
+
+        let user = "{\"type\":\"user\",\"timestamp\":\"2025-01-01T00:00:00Z\",\"message\":{\"role\":\"user\",\"content\":[{\"type\":\"text\",\"text\":\"edit\"}]}}\n";
+        let edit = "{\"type\":\"assistant\",\"timestamp\":\"2025-01-01T00:00:02Z\",\"message\":{\"content\":[{\"type\":\"tool_use\",\"name\":\"Write\",\"input\":{\"file_path\":\"a.rs\",\"content\":\"x\\ny\"}}]}}\n";
+
+        let dir = std::env::temp_dir().join(format!("ai-switch-local-logs-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("session.jsonl");
+        fs::write(&path, user).unwrap();
+        let (_, cursor) = parse_claude_log_from(&path, &ParseCursor::default()).unwrap();
+
+        // 助手回复在下一次解析中到达，响应时间和修改的文件跨两次解析累计
+        fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(format!("{edit}{user}{edit}").as_bytes())
+            .unwrap();
+        let (_, cursor) = parse_claude_log_from(&path, &cursor).unwrap();
+
+        let appended = stats_from_cursor(&cursor).unwrap();
+        let full = parse_claude_session_stats(&path);
+        assert_eq!(appended.conversation_count, 2);
+        assert_eq!(appended.files_changed, 1);
+        assert_eq!(appended.response_time_ms, 4000);
+        assert_eq!(
+            serde_json::to_value(&appended).unwrap(),
+            serde_json::to_value(&full).unwrap()
+        );
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn upgrades_legacy_records_in_place() {
+        let db = Database::memory().unwrap();
+        {
+            let conn = db.conn.lock().unwrap();
+            // v9 之前的记录：按秒合并的 ID，没有会话 ID
+            insert_log_entry(&conn, &entry("claude-old"), EntryCost::unpriced()).unwrap();
+            conn.execute("UPDATE proxy_request_logs SET session_id = NULL", []).unwrap();
+        }
+
+        // a.jsonl 中的 claude-a1 与旧记录内容相同
+        let result = import_from_sources(&db, &[&FAKE], |_| {}).unwrap();
+        assert_eq!((result.imported, result.skipped), (2, 2));
+
+        let conn = db.read_conn().unwrap();
+        let mut stmt = conn
+            .prepare("SELECT request_id, session_id FROM proxy_request_logs ORDER BY request_id")
+            .unwrap();
+        let rows: Vec<(String, Option<String>)> = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .flatten()
+            .collect();
+        let session = Some("session".to_string());
+        assert_eq!(
+            rows,
+            vec![
+                ("claude-a1".to_string(), session.clone()),
+                ("claude-a2".to_string(), session.clone()),
+                ("claude-b1".to_string(), session),
+            ]
+        );
+    }
+
+    #[test]
+    fn splits_cursor_conversation_into_turns() {
+        let messages = vec![
+            serde_json::json!({ "type": 1, "bubbleId": "u1", "text": "fix the bug" }),
+            serde_json::json!({ "type": 2, "bubbleId": "a1", "text": "done" }),
+            serde_json::json!({ "type": 2, "bubbleId": "a2", "text": "also updated tests" }),
+            serde_json::json!({ "type": 1, "text": "thanks" }),
+            serde_json::json!({ "type": 2, "text": "you're welcome" }),
+        ];
+        let turns: Vec<(String, usize)> = split_cursor_turns(&messages)
+            .into_iter()
+            .map(|(id, turn)| (id, turn.len()))
+            .collect();
+        assert_eq!(turns, vec![("u1".to_string(), 3), ("3".to_string(), 2)]);
+    }
+
+    /// 用已注册来源的解析读取指定文件
+    struct FilesSource {
+        inner: &'static dyn LogSource,
+        files: Vec<PathBuf>,
+    }
+
+    impl LogSource for FilesSource {
+        fn id(&self) -> &'static str {
+            self.inner.id()
+        }
+
+        fn name(&self) -> &'static str {
+            self.inner.name()
+        }
+
+        fn location(&self) -> Option<PathBuf> {
+            None
+        }
+
+        fn discover(&self) -> Vec<PathBuf> {
+            self.files.clone()
+        }
+
+        fn estimate_entries(&self, files: &[PathBuf]) -> u32 {
+            files.len() as u32
+        }
+
+        fn parse_entries(&self, path: &Path) -> Vec<LocalLogEntry> {
+            self.inner.parse_entries(path)
+        }
+
+        fn parse_appended(&self, path: &Path, cursor: &ParseCursor) -> Option<(Vec<LocalLogEntry>, ParseCursor)> {
+            self.inner.parse_appended(path, cursor)
+        }
+
+        fn parse_all_session_stats(&self, path: &Path, cursor: &ParseCursor) -> Vec<(String, SessionStats)> {
+            self.inner.parse_all_session_stats(path, cursor)
+        }
+
+        fn refresh_existing(&self) -> bool {
+            self.inner.refresh_existing()
+        }
+    }
+
+    fn files_source(source: &str, files: Vec<PathBuf>) -> &'static FilesSource {
+        Box::leak(Box::new(FilesSource {
+            inner: log_source(source).unwrap(),
+            files,
+        }))
+    }
+
+    /// 导入指定来源的文件，返回各记录关联到的 (会话 ID, 对话轮数, 工具调用数)
+    fn import_and_join_stats(source: &str, files: Vec<PathBuf>) -> Vec<(String, u32, u32)> {
+        let source = files_source(source, files);
+        let db = Database::memory().unwrap();
+        import_from_sources(&db, &[source], |_| {}).unwrap();
+
+        let conn = db.read_conn().unwrap();
+        let mut stmt = conn
+            .prepare(
+                "SELECT DISTINCT l.session_id, s.conversation_count, s.tool_call_count
+                 FROM proxy_request_logs l JOIN session_stats s ON s.session_id = l.session_id
+                 ORDER BY l.session_id",
+            )
+            .unwrap();
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .flatten()
+            .collect();
+        rows
+    }
+
+    #[test]
+    fn opencode_session_stats_join_entries() {
+        let storage = std::env::temp_dir().join(format!("ai-switch-opencode-{}", uuid::Uuid::new_v4()));
+        let session_dir = storage.join("message").join("ses_1");
+        let part_dir = storage.join("part").join("msg_2");
+        fs::create_dir_all(&session_dir).unwrap();
+        fs::create_dir_all(&part_dir).unwrap();
+        fs::write(session_dir.join("msg_1.json"), r#"{"id":"msg_1","role":"user"}"#).unwrap();
+        let assistant = r#"{"id":"msg_2","role":"assistant","modelID":"claude-sonnet-4","time":{"created":1700000000000},"tokens":{"input":10,"output":5}}"#;
+        fs::write(session_dir.join("msg_2.json"), assistant).unwrap();
+        fs::write(part_dir.join("prt_1.json"), r#"{"type":"tool","tool":"read"}"#).unwrap();
+
+        let files = vec![session_dir.join("msg_1.json"), session_dir.join("msg_2.json")];
+        let rows = import_and_join_stats("opencode", files);
+        fs::remove_dir_all(&storage).ok();
+
+        assert_eq!(rows, vec![("ses_1".to_string(), 1, 1)]);
+    }
+
+    #[test]
+    fn opencode_session_stats_only_on_latest_message() {
+        let storage = std::env::temp_dir().join(format!("ai-switch-opencode-{}", uuid::Uuid::new_v4()));
+        let session_dir = storage.join("message").join("ses_1");
+        fs::create_dir_all(&session_dir).unwrap();
+        fs::write(session_dir.join("msg_1.json"), r#"{"id":"msg_1","role":"user"}"#).unwrap();
+        fs::write(session_dir.join("msg_2.json"), r#"{"id":"msg_2","role":"user"}"#).unwrap();
+
+        let first = parse_opencode_session_stats(&session_dir.join("msg_1.json"));
+        let latest = parse_opencode_session_stats(&session_dir.join("msg_2.json"));
+        fs::remove_dir_all(&storage).ok();
+
+        assert!(first.is_none());
+        let (session_id, stats) = latest.unwrap();
+        assert_eq!((session_id.as_str(), stats.conversation_count), ("ses_1", 2));
+    }
+
+    /// 在临时工作区目录中创建只有一个 Workspace Composer 对话的 VSCode 系数据库，返回 (临时目录, 数据库路径)
+    fn vscode_workspace_db() -> (PathBuf, PathBuf) {
+        let root = std::env::temp_dir().join(format!("ai-switch-vscode-{}", uuid::Uuid::new_v4()));
+        let workspace = root.join("workspaceStorage").join("ws1");
+        fs::create_dir_all(&workspace).unwrap();
+        let path = workspace.join("state.vscdb");
+        let composers = serde_json::json!({
+            "allComposers": [{
+                "composerId": "c1",
+                "conversation": [
+                    { "type": 1, "text": "rename the function" },
+                    {
+                        "type": 2,
+                        "text": "renamed",
+                        "tool_calls": [{ "function": { "name": "edit_file", "arguments": "{\"path\":\"a.rs\"}" } }]
+                    }
+                ]
+            }]
+        });
+        {
+            let conn = rusqlite::Connection::open(&path).unwrap();
+            conn.execute("CREATE TABLE ItemTable (key TEXT PRIMARY KEY, value BLOB)", []).unwrap();
+            conn.execute(
+                "INSERT INTO ItemTable (key, value) VALUES ('composer.composerData', ?1)",
+                [composers.to_string()],
+            )
+            .unwrap();
+        }
+        (root, path)
+    }
+
+    #[test]
+    fn vscode_app_session_stats_join_entries() {
+        let (root, path) = vscode_workspace_db();
+        let rows = import_and_join_stats("windsurf", vec![path]);
+        fs::remove_dir_all(&root).ok();
+
+        assert_eq!(rows, vec![("windsurf-workspace-ws1-c1".to_string(), 1, 1)]);
+    }
+
+    #[test]
+    fn v10_reimport_refreshes_estimated_vscode_app_tokens() {
+        let (root, path) = vscode_workspace_db();
+        let source = files_source("windsurf", vec![path]);
+        let db = Database::memory().unwrap();
+        import_from_sources(&db, &[source], |_| {}).unwrap();
+
+        let tokens = |db: &Database| -> (u32, u32, bool) {
+            db.read_conn()
+                .unwrap()
+                .query_row(
+                    "SELECT input_tokens, output_tokens, tokens_estimated FROM proxy_request_logs WHERE app_type = 'windsurf_local'",
+                    [],
+                    |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+                )
+                .unwrap()
+        };
+        let estimated = tokens(&db);
+        assert!(estimated.2 && (estimated.0, estimated.1) != (1, 1));
+
+        // 模拟旧版本的估算结果，并像 v10 迁移一样清除导入状态
+        {
+            let conn = db.conn.lock().unwrap();
+            conn.execute_batch(
+                "UPDATE proxy_request_logs SET input_tokens = 1, output_tokens = 1 WHERE app_type = 'windsurf_local';
+                 DELETE FROM local_import_state WHERE source = 'windsurf';",
+            )
+            .unwrap();
+        }
+        assert_eq!(tokens(&db), (1, 1, true));
+
+        let result = import_from_sources(&db, &[source], |_| {}).unwrap();
+        fs::remove_dir_all(&root).ok();
+
+        assert_eq!(result.imported, 1);
+        assert_eq!(tokens(&db), estimated);
+    }
+
+    #[test]
+    fn event_sources_keep_request_ids_across_parses() {
+        let dir = std::env::temp_dir().join(format!("ai-switch-request-ids-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let files = [
+            (
+                "aider",
+                "analytics.jsonl",
+                r#"{"event":"message_send","properties":{"main_model":"gpt-4o","prompt_tokens":10,"completion_tokens":5,"total_cost":0.1},"time":1790848800}
+{"event":"message_send","properties":{"main_model":"gpt-4o","prompt_tokens":10,"completion_tokens":5,"total_cost":0.2},"time":1790848800}
+"#,
+            ),
+            (
+                "continue",
+                "tokensGenerated.jsonl",
+                r#"{"model":"gpt-4o","promptTokens":10,"generatedTokens":5,"timestamp":"2026-10-01T10:00:00Z"}
+{"model":"gpt-4o","promptTokens":10,"generatedTokens":5,"timestamp":"2026-10-01T10:00:00.5Z"}
+"#,
+            ),
+            (
+                "copilot",
+                "events.jsonl",
+                r#"{"type":"assistant.usage","timestamp":"2026-10-01T10:00:00Z","data":{"model":"gpt-4.1","inputTokens":10,"outputTokens":5}}
+{"type":"assistant.usage","id":"evt-2","timestamp":"2026-10-01T10:00:00Z","data":{"model":"gpt-4.1","inputTokens":10,"outputTokens":5}}
+"#,
+            ),
+        ];
+        for (source, name, content) in files {
+            let path = dir.join(source).join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, content).unwrap();
+
+            let source = log_source(source).unwrap();
+            let ids = || -> Vec<String> { source.parse_entries(&path).into_iter().map(|e| e.request_id).collect() };
+            let first = ids();
+            assert_eq!(first.len(), 2, "{}", source.id());
+            assert_ne!(first[0], first[1], "{}", source.id());
+            assert_eq!(first, ids(), "{}", source.id());
+        }
+        fs::remove_dir_all(&dir).ok();
+    }
+}
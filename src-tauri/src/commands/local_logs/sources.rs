@@ -0,0 +1,465 @@
+//! 本地日志来源
+//!
+//! 每个工具实现一个 [`LogSource`]：查找日志文件、预估条目数、解析使用记录和会话统计。
+//! 扫描、导入和自动导入只遍历 [`LOG_SOURCES`]，新增工具时实现该 trait 并在注册表中加入即可。
+
+use super::aider::AiderSource;
+use super::cline::ClineSource;
+use super::continue_dev::ContinueSource;
+use super::copilot::CopilotSource;
+use super::crush::CrushSource;
+use super::*;
+
+/// 本地日志来源
+pub trait LogSource: Sync {
+    /// 来源 ID，导入记录的 app_type / provider_id 为 `{id}_local`
+    fn id(&self) -> &'static str;
+
+    /// 显示名称
+    fn name(&self) -> &'static str;
+
+    /// 日志目录或主数据库路径（仅用于展示），未安装时返回 None
+    fn location(&self) -> Option<PathBuf>;
+
+    /// 查找全部日志文件（或数据库文件）
+    fn discover(&self) -> Vec<PathBuf>;
+
+    /// 需要监听变化的日志目录（递归监听），返回 None 表示不监听，只在手动或自动导入时导入
+    fn watch_dir(&self) -> Option<PathBuf> {
+        None
+    }
+
+    /// 预估日志条目数（扫描时展示，不要求精确）
+    fn estimate_entries(&self, files: &[PathBuf]) -> u32;
+
+    /// 解析一个日志文件中的使用记录
+    fn parse_entries(&self, path: &Path) -> Vec<LocalLogEntry>;
+
+    /// 从上次导入的位置继续解析追加的内容，返回 (新记录, 新位置)
+    ///
+    /// 只追加写入的 JSONL 来源实现此方法；返回 None 表示不支持增量解析，文件变化后完整解析
+    fn parse_appended(&self, _path: &Path, _cursor: &ParseCursor) -> Option<(Vec<LocalLogEntry>, ParseCursor)> {
+        None
+    }
+
+    /// 解析一个日志文件的会话统计，返回 (会话 ID, 统计)；不支持时返回 None
+    ///
+    /// `cursor` 是本次解析后的位置，增量解析的来源可以从中取出累计的统计，避免重新读取整个文件
+    fn parse_session_stats(&self, _path: &Path, _cursor: &ParseCursor) -> Option<(String, SessionStats)> {
+        None
+    }
+
+    /// 解析一个日志文件中全部会话的统计
+    ///
+    /// 默认为 [`parse_session_stats`](Self::parse_session_stats) 的单个会话；
+    /// 一个文件包含多个会话的来源（如数据库）覆盖此方法，会话 ID 与记录的 `session_id` 一致
+    fn parse_all_session_stats(&self, path: &Path, cursor: &ParseCursor) -> Vec<(String, SessionStats)> {
+        self.parse_session_stats(path, cursor).into_iter().collect()
+    }
+
+    /// 重新导入时是否覆盖已存在的记录（记录内容会随会话继续而变化的来源）
+    fn refresh_existing(&self) -> bool {
+        false
+    }
+}
+
+/// 已注册的日志来源（扫描和导入按此顺序进行）
+pub static LOG_SOURCES: &[&dyn LogSource] = &[
+    &ClaudeSource,
+    &CodexSource,
+    &GeminiSource,
+    &QwenSource,
+    &OpencodeSource,
+    &CursorSource,
+    &VsCodeAppSource {
+        id: "windsurf",
+        name: "Windsurf",
+        app_dirs: &["Windsurf", "WindSurf"],
+    },
+    &VsCodeAppSource {
+        id: "kiro",
+        name: "Kiro",
+        app_dirs: &["Kiro"],
+    },
+    &VsCodeAppSource {
+        id: "antigravity",
+        name: "Antigravity",
+        app_dirs: &["Antigravity"],
+    },
+    &WarpSource,
+    // Augment 是 VS Code 扩展，数据存储在 VS Code 的 state.vscdb 中
+    &VsCodeAppSource {
+        id: "augment",
+        name: "Augment",
+        app_dirs: &["Code", "Code - Insiders"],
+    },
+    &AiderSource,
+    &ClineSource {
+        id: "cline",
+        name: "Cline",
+        extension_id: "saoudrizwan.claude-dev",
+    },
+    &ClineSource {
+        id: "roo",
+        name: "Roo Code",
+        extension_id: "rooveterinaryinc.roo-cline",
+    },
+    &ContinueSource,
+    &CrushSource,
+    &CopilotSource,
+];
+
+/// 按 ID 查找日志来源
+pub fn log_source(id: &str) -> Option<&'static dyn LogSource> {
+    LOG_SOURCES.iter().copied().find(|source| source.id() == id)
+}
+
+/// 以文件名作为会话 ID
+fn file_session_id(path: &Path) -> String {
+    path.file_stem()
+        .and_then(|n| n.to_str())
+        .map(|s| s.to_string())
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string())
+}
+
+/// 按 JSONL 行数预估条目数
+fn estimate_jsonl_entries(files: &[PathBuf]) -> u32 {
+    files
+        .iter()
+        .map(|file| estimate_entries_from_file(file, true))
+        .sum()
+}
+
+// ---- Claude Code ----
+struct ClaudeSource;
+
+impl LogSource for ClaudeSource {
+    fn id(&self) -> &'static str {
+        "claude"
+    }
+
+    fn name(&self) -> &'static str {
+        "Claude Code"
+    }
+
+    fn location(&self) -> Option<PathBuf> {
+        get_claude_log_dir()
+    }
+
+    fn discover(&self) -> Vec<PathBuf> {
+        find_claude_log_files()
+    }
+
+    fn watch_dir(&self) -> Option<PathBuf> {
+        get_claude_log_dir()
+    }
+
+    fn estimate_entries(&self, files: &[PathBuf]) -> u32 {
+        estimate_jsonl_entries(files)
+    }
+
+    fn parse_entries(&self, path: &Path) -> Vec<LocalLogEntry> {
+        parse_claude_log_file(path)
+    }
+
+    fn parse_appended(&self, path: &Path, cursor: &ParseCursor) -> Option<(Vec<LocalLogEntry>, ParseCursor)> {
+        parse_claude_log_from(path, cursor)
+    }
+
+    fn parse_session_stats(&self, path: &Path, cursor: &ParseCursor) -> Option<(String, SessionStats)> {
+        let stats = stats_from_cursor(cursor).unwrap_or_else(|| parse_claude_session_stats(path));
+        Some((file_session_id(path), stats))
+    }
+}
+
+// ---- Codex CLI ----
+struct CodexSource;
+
+impl LogSource for CodexSource {
+    fn id(&self) -> &'static str {
+        "codex"
+    }
+
+    fn name(&self) -> &'static str {
+        "Codex CLI"
+    }
+
+    fn location(&self) -> Option<PathBuf> {
+        get_codex_log_dir()
+    }
+
+    fn discover(&self) -> Vec<PathBuf> {
+        find_codex_log_files()
+    }
+
+    /// 只监听会话目录，避免配置文件的变化触发导入
+    fn watch_dir(&self) -> Option<PathBuf> {
+        get_codex_log_dir()
+            .map(|dir| dir.join("sessions"))
+            .filter(|dir| dir.exists())
+    }
+
+    fn estimate_entries(&self, files: &[PathBuf]) -> u32 {
+        estimate_jsonl_entries(files)
+    }
+
+    fn parse_entries(&self, path: &Path) -> Vec<LocalLogEntry> {
+        parse_codex_log_file(path)
+    }
+
+    fn parse_appended(&self, path: &Path, cursor: &ParseCursor) -> Option<(Vec<LocalLogEntry>, ParseCursor)> {
+        parse_codex_log_from(path, cursor)
+    }
+
+    fn parse_session_stats(&self, path: &Path, cursor: &ParseCursor) -> Option<(String, SessionStats)> {
+        let stats = codex_stats_from_cursor(cursor).unwrap_or_else(|| parse_codex_session_stats(path));
+        Some((file_session_id(path), stats))
+    }
+}
+
+// ---- Gemini CLI ----
+struct GeminiSource;
+
+impl LogSource for GeminiSource {
+    fn id(&self) -> &'static str {
+        "gemini"
+    }
+
+    fn name(&self) -> &'static str {
+        "Gemini CLI"
+    }
+
+    fn location(&self) -> Option<PathBuf> {
+        get_gemini_log_dir()
+    }
+
+    fn discover(&self) -> Vec<PathBuf> {
+        find_gemini_log_files()
+    }
+
+    fn watch_dir(&self) -> Option<PathBuf> {
+        get_gemini_log_dir()
+    }
+
+    fn estimate_entries(&self, files: &[PathBuf]) -> u32 {
+        files
+            .iter()
+            .map(|file| estimate_entries_from_file(file, file.extension().is_some_and(|ext| ext == "jsonl")))
+            .sum()
+    }
+
+    fn parse_entries(&self, path: &Path) -> Vec<LocalLogEntry> {
+        parse_gemini_log_file(path)
+    }
+
+    fn parse_session_stats(&self, path: &Path, _cursor: &ParseCursor) -> Option<(String, SessionStats)> {
+        Some((file_session_id(path), parse_gemini_session_stats(path)))
+    }
+}
+
+// ---- Qwen Code（Gemini CLI 分支，复用 Gemini 的解析） ----
+struct QwenSource;
+
+impl LogSource for QwenSource {
+    fn id(&self) -> &'static str {
+        "qwen"
+    }
+
+    fn name(&self) -> &'static str {
+        "Qwen Code"
+    }
+
+    fn location(&self) -> Option<PathBuf> {
+        get_qwen_log_dir()
+    }
+
+    fn discover(&self) -> Vec<PathBuf> {
+        get_qwen_log_dir().map_or_else(Vec::new, |dir| find_gemini_style_log_files(&dir))
+    }
+
+    fn watch_dir(&self) -> Option<PathBuf> {
+        get_qwen_log_dir()
+    }
+
+    fn estimate_entries(&self, files: &[PathBuf]) -> u32 {
+        GeminiSource.estimate_entries(files)
+    }
+
+    /// 记录 ID 加上来源前缀，避免与 Gemini 的会话文件重名
+    fn parse_entries(&self, path: &Path) -> Vec<LocalLogEntry> {
+        parse_gemini_log_file(path)
+            .into_iter()
+            .map(|mut entry| {
+                entry.source = "qwen".to_string();
+                entry.request_id = format!("qwen-{}", entry.request_id);
+                entry.session_id = format!("qwen-{}", entry.session_id);
+                entry
+            })
+            .collect()
+    }
+
+    fn parse_session_stats(&self, path: &Path, _cursor: &ParseCursor) -> Option<(String, SessionStats)> {
+        Some((format!("qwen-{}", file_session_id(path)), parse_gemini_session_stats(path)))
+    }
+}
+
+// ---- Opencode ----
+struct OpencodeSource;
+
+impl LogSource for OpencodeSource {
+    fn id(&self) -> &'static str {
+        "opencode"
+    }
+
+    fn name(&self) -> &'static str {
+        "Opencode"
+    }
+
+    fn location(&self) -> Option<PathBuf> {
+        get_opencode_log_dir()
+    }
+
+    fn discover(&self) -> Vec<PathBuf> {
+        find_opencode_log_files()
+    }
+
+    fn watch_dir(&self) -> Option<PathBuf> {
+        get_opencode_log_dir()
+            .map(|dir| dir.join("message"))
+            .filter(|dir| dir.exists())
+    }
+
+    /// 每个消息文件一条记录
+    fn estimate_entries(&self, files: &[PathBuf]) -> u32 {
+        files.len() as u32
+    }
+
+    fn parse_entries(&self, path: &Path) -> Vec<LocalLogEntry> {
+        parse_opencode_log_file(path)
+    }
+
+    /// 会话统计汇总整个会话目录，以会话目录名为会话 ID
+    fn parse_session_stats(&self, path: &Path, _cursor: &ParseCursor) -> Option<(String, SessionStats)> {
+        parse_opencode_session_stats(path)
+    }
+}
+
+// ---- Cursor ----
+struct CursorSource;
+
+impl LogSource for CursorSource {
+    fn id(&self) -> &'static str {
+        "cursor"
+    }
+
+    fn name(&self) -> &'static str {
+        "Cursor"
+    }
+
+    fn location(&self) -> Option<PathBuf> {
+        get_cursor_db_path()
+    }
+
+    fn discover(&self) -> Vec<PathBuf> {
+        get_cursor_db_paths()
+    }
+
+    fn estimate_entries(&self, files: &[PathBuf]) -> u32 {
+        estimate_vscode_db_entries(files)
+    }
+
+    fn parse_entries(&self, path: &Path) -> Vec<LocalLogEntry> {
+        parse_cursor_db(path)
+    }
+
+    /// 一个数据库包含多个对话，按对话汇总
+    fn parse_all_session_stats(&self, path: &Path, _cursor: &ParseCursor) -> Vec<(String, SessionStats)> {
+        parse_cursor_session_stats(path)
+    }
+
+    /// Cursor 按会话汇总用量，会话继续后用量会增加，重新导入时刷新
+    fn refresh_existing(&self) -> bool {
+        true
+    }
+}
+
+// ---- 其他 VSCode 系工具（Windsurf / Kiro / Antigravity / Augment），复用 Cursor 的解析 ----
+struct VsCodeAppSource {
+    id: &'static str,
+    name: &'static str,
+    /// 用户数据目录名（如 `Windsurf`，对应 `<配置目录>/Windsurf/User`）
+    app_dirs: &'static [&'static str],
+}
+
+impl LogSource for VsCodeAppSource {
+    fn id(&self) -> &'static str {
+        self.id
+    }
+
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn location(&self) -> Option<PathBuf> {
+        get_vscode_app_db_paths(self.app_dirs)
+            .into_iter()
+            .find(|p| p.to_string_lossy().contains("globalStorage"))
+    }
+
+    fn discover(&self) -> Vec<PathBuf> {
+        get_vscode_app_db_paths(self.app_dirs)
+    }
+
+    fn estimate_entries(&self, files: &[PathBuf]) -> u32 {
+        estimate_vscode_db_entries(files)
+    }
+
+    fn parse_entries(&self, path: &Path) -> Vec<LocalLogEntry> {
+        parse_vscode_app_db(path, self.id)
+    }
+
+    fn parse_all_session_stats(&self, path: &Path, _cursor: &ParseCursor) -> Vec<(String, SessionStats)> {
+        parse_cursor_session_stats(path)
+            .into_iter()
+            .map(|(id, stats)| (vscode_app_id(&id, self.id), stats))
+            .collect()
+    }
+
+    /// 与 Cursor 相同，按会话汇总用量，重新导入时刷新
+    fn refresh_existing(&self) -> bool {
+        true
+    }
+}
+
+// ---- Warp ----
+struct WarpSource;
+
+impl LogSource for WarpSource {
+    fn id(&self) -> &'static str {
+        "warp"
+    }
+
+    fn name(&self) -> &'static str {
+        "Warp"
+    }
+
+    fn location(&self) -> Option<PathBuf> {
+        get_warp_db_path()
+    }
+
+    fn discover(&self) -> Vec<PathBuf> {
+        get_warp_db_path().into_iter().collect()
+    }
+
+    /// 统计对话数
+    fn estimate_entries(&self, files: &[PathBuf]) -> u32 {
+        files
+            .iter()
+            .map(|file| count_warp_conversations(file).unwrap_or(0))
+            .sum()
+    }
+
+    fn parse_entries(&self, path: &Path) -> Vec<LocalLogEntry> {
+        parse_warp_db(path)
+    }
+}
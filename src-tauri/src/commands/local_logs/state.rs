@@ -0,0 +1,169 @@
+//! 增量导入状态
+//!
+//! 每个已导入的日志文件记录大小、修改时间和已解析到的字节偏移（`local_import_state` 表）。
+//! 再次导入时未变化的文件直接跳过；支持增量解析的 JSONL 来源只解析追加的行。
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+/// 增量解析位置
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParseCursor {
+    /// 已解析到的字节偏移（总在完整行之后）
+    pub offset: u64,
+    /// 来源自定义的解析上下文（如 Codex 的累计 token）
+    pub context: Option<serde_json::Value>,
+}
+
+/// 文件指纹：大小和修改时间（毫秒）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileFingerprint {
+    pub size: u64,
+    pub modified_at: i64,
+}
+
+/// 文件上次导入时的状态
+#[derive(Debug, Clone)]
+pub struct ImportState {
+    pub fingerprint: FileFingerprint,
+    pub cursor: ParseCursor,
+}
+
+/// 读取文件指纹；SQLite 数据库同时计入 `-wal` 文件，未 checkpoint 的写入也能被发现
+pub fn file_fingerprint(path: &Path) -> Option<FileFingerprint> {
+    let meta = fs::metadata(path).ok()?;
+    let mut size = meta.len();
+    let mut modified_at = modified_ms(&meta);
+
+    let mut wal = path.as_os_str().to_owned();
+    wal.push("-wal");
+    if let Ok(wal_meta) = fs::metadata(Path::new(&wal)) {
+        size += wal_meta.len();
+        modified_at = modified_at.max(modified_ms(&wal_meta));
+    }
+
+    Some(FileFingerprint { size, modified_at })
+}
+
+fn modified_ms(meta: &fs::Metadata) -> i64 {
+    meta.modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_millis() as i64)
+}
+
+/// 加载某个来源全部文件的导入状态（按路径）
+pub fn load_import_state(conn: &rusqlite::Connection, source: &str) -> HashMap<String, ImportState> {
+    let mut states = HashMap::new();
+    let Ok(mut stmt) = conn.prepare(
+        "SELECT path, size, modified_at, offset, context FROM local_import_state WHERE source = ?1",
+    ) else {
+        return states;
+    };
+    let rows = stmt.query_map([source], |row| {
+        let context: Option<String> = row.get(4)?;
+        Ok((
+            row.get::<_, String>(0)?,
+            ImportState {
+                fingerprint: FileFingerprint {
+                    size: row.get::<_, i64>(1)? as u64,
+                    modified_at: row.get(2)?,
+                },
+                cursor: ParseCursor {
+                    offset: row.get::<_, i64>(3)? as u64,
+                    context: context.and_then(|c| serde_json::from_str(&c).ok()),
+                },
+            },
+        ))
+    });
+    if let Ok(rows) = rows {
+        states.extend(rows.flatten());
+    }
+    states
+}
+
+/// 保存一个文件的导入状态
+pub fn save_import_state(
+    conn: &rusqlite::Connection,
+    path: &str,
+    source: &str,
+    state: &ImportState,
+) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT INTO local_import_state (path, source, size, modified_at, offset, context, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+         ON CONFLICT(path) DO UPDATE SET
+            source = excluded.source,
+            size = excluded.size,
+            modified_at = excluded.modified_at,
+            offset = excluded.offset,
+            context = excluded.context,
+            updated_at = excluded.updated_at",
+        rusqlite::params![
+            path,
+            source,
+            state.fingerprint.size as i64,
+            state.fingerprint.modified_at,
+            state.cursor.offset as i64,
+            state.cursor.context.as_ref().map(|c| c.to_string()),
+            chrono::Utc::now().timestamp(),
+        ],
+    )?;
+    Ok(())
+}
+
+/// 从指定偏移读取 JSONL 的完整行，返回 (行, 新偏移)
+///
+/// 末尾没有换行的行可能仍在写入，只有能完整解析为 JSON 时才读取，否则留到下次
+pub fn read_jsonl_from(path: &Path, offset: u64) -> Option<(Vec<String>, u64)> {
+    let mut file = fs::File::open(path).ok()?;
+    file.seek(SeekFrom::Start(offset)).ok()?;
+    let mut buf = Vec::new();
+    file.read_to_end(&mut buf).ok()?;
+
+    let mut lines = Vec::new();
+    let mut consumed = 0usize;
+    for chunk in buf.split_inclusive(|&b| b == b'\n') {
+        let complete = chunk.ends_with(b"\n");
+        let line = String::from_utf8_lossy(chunk);
+        let line = line.trim_end_matches(['\n', '\r']);
+        if !complete && serde_json::from_str::<serde_json::Value>(line).is_err() {
+            break;
+        }
+        consumed += chunk.len();
+        if !line.trim().is_empty() {
+            lines.push(line.to_string());
+        }
+    }
+
+    Some((lines, offset + consumed as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn reads_only_complete_lines_from_offset() {
+        let dir = std::env::temp_dir().join(format!("ai-switch-import-state-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("session.jsonl");
+        fs::write(&path, "{\"a\":1}\n{\"b\":2}\n{\"c\":").unwrap();
+
+        let (lines, offset) = read_jsonl_from(&path, 0).unwrap();
+        assert_eq!(lines, vec!["{\"a\":1}", "{\"b\":2}"]);
+        assert_eq!(offset, 16);
+
+        // 补全末尾的行后从上次偏移继续
+        fs::OpenOptions::new().append(true).open(&path).unwrap().write_all(b"3}").unwrap();
+        let (lines, offset) = read_jsonl_from(&path, offset).unwrap();
+        assert_eq!(lines, vec!["{\"c\":3}"]);
+        assert_eq!(offset, fs::metadata(&path).unwrap().len());
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,115 @@
+//! 没有用量数据时的 token 估算
+//!
+//! Cursor 等 VSCode 系工具只保存消息文本，token 数需要按模型家族估算：
+//! - OpenAI：GPT-4o / GPT-4.1 / GPT-5 / o 系列使用 o200k_base，GPT-4 / GPT-3.5 使用 cl100k_base，与官方计数一致
+//! - Claude、Gemini：没有可离线使用的官方分词器，按 o200k_base 计数乘以校准系数近似
+//! - 其他模型（DeepSeek、Qwen、Cursor 自有模型等）：按 cl100k_base 计数
+//!
+//! 估算得到的记录标记为 `tokens_estimated`，统计中与日志实测的 token 分开展示。
+
+use crate::pricing::clean_model_id;
+use tiktoken_rs::{cl100k_base_singleton, o200k_base_singleton, CoreBPE};
+
+/// 分词方式
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Encoding {
+    Cl100k,
+    O200k,
+}
+
+/// 模型家族
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ModelFamily {
+    /// GPT-4o 及之后的 OpenAI 模型
+    OpenAiO200k,
+    /// GPT-4 / GPT-3.5 及未知模型
+    Cl100k,
+    Claude,
+    Gemini,
+}
+
+impl ModelFamily {
+    fn of(model: &str) -> Self {
+        let model = clean_model_id(model).to_lowercase();
+        if model.contains("claude") {
+            Self::Claude
+        } else if model.contains("gemini") || model.contains("gemma") {
+            Self::Gemini
+        } else if ["gpt-4o", "gpt-4.1", "gpt-4.5", "gpt-5", "chatgpt-4o", "gpt-oss", "codex"]
+            .iter()
+            .any(|prefix| model.starts_with(prefix))
+            || is_o_series(&model)
+        {
+            Self::OpenAiO200k
+        } else {
+            Self::Cl100k
+        }
+    }
+
+    /// 计数使用的分词方式和校准系数
+    ///
+    /// Claude 分词器对同样的英文和代码文本比 o200k_base 多出约 15%–25% 的 token，取 1.2；
+    /// Gemini 的 SentencePiece 词表与 o200k_base 接近，代码略多，取 1.05。
+    fn calibration(self) -> (Encoding, f64) {
+        match self {
+            Self::OpenAiO200k => (Encoding::O200k, 1.0),
+            Self::Cl100k => (Encoding::Cl100k, 1.0),
+            Self::Claude => (Encoding::O200k, 1.2),
+            Self::Gemini => (Encoding::O200k, 1.05),
+        }
+    }
+}
+
+/// o1 / o3 / o4-mini 等推理模型
+fn is_o_series(model: &str) -> bool {
+    let mut chars = model.chars();
+    chars.next() == Some('o') && chars.next().is_some_and(|c| c.is_ascii_digit())
+}
+
+fn bpe(encoding: Encoding) -> &'static CoreBPE {
+    match encoding {
+        Encoding::Cl100k => cl100k_base_singleton(),
+        Encoding::O200k => o200k_base_singleton(),
+    }
+}
+
+/// 估算文本在指定模型下的 token 数（未知模型按 cl100k_base 计数）
+pub(super) fn estimate_tokens(text: &str, model: Option<&str>) -> u32 {
+    if text.is_empty() {
+        return 0;
+    }
+    let family = model.map_or(ModelFamily::Cl100k, ModelFamily::of);
+    let (encoding, ratio) = family.calibration();
+    let count = bpe(encoding).encode_with_special_tokens(text).len() as f64;
+    (count * ratio).round().max(1.0) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_tokenizer_by_model_family() {
+        let families: Vec<_> = ["gpt-4o-mini", "openai/o3", "gpt-5-codex", "gpt-4", "claude-4-sonnet", "gemini-2.5-pro", "auto"]
+            .iter()
+            .map(|m| ModelFamily::of(m))
+            .collect();
+        assert_eq!(
+            families,
+            vec![
+                ModelFamily::OpenAiO200k,
+                ModelFamily::OpenAiO200k,
+                ModelFamily::OpenAiO200k,
+                ModelFamily::Cl100k,
+                ModelFamily::Claude,
+                ModelFamily::Gemini,
+                ModelFamily::Cl100k,
+            ]
+        );
+
+        let text = "fn main() { println!(\"hello, world\"); }";
+        let o200k = estimate_tokens(text, Some("gpt-4o"));
+        assert_eq!(estimate_tokens(text, Some("claude-sonnet-4")), (o200k as f64 * 1.2).round() as u32);
+        assert_eq!(estimate_tokens("", Some("gpt-4o")), 0);
+    }
+}
@@ -0,0 +1,129 @@
+//! 本地日志文件监听
+//!
+//! 递归监听各来源的 [`LogSource::watch_dir`]，合并短时间内的连续写入后增量导入有变化的来源，
+//! 并通过 `local-logs-imported` 事件发送新导入的记录数，不经过代理的用量也能实时刷新到统计页面。
+
+use super::*;
+use notify::{RecursiveMode, Watcher};
+use std::time::Duration;
+use tauri::AppHandle;
+
+/// 收到第一个变化后等待的时间，期间的变化合并为一次导入
+const WATCH_DEBOUNCE: Duration = Duration::from_secs(2);
+
+/// 单个来源新导入的记录数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceImported {
+    pub source: String,
+    pub imported: u32,
+}
+
+/// `local-logs-imported` 事件内容（只在有新记录时发送）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalLogsImported {
+    /// 新导入的记录总数
+    pub imported: u32,
+    /// 各来源新导入的记录数（不含没有新记录的来源）
+    pub sources: Vec<SourceImported>,
+}
+
+/// 后台监听本地日志目录，变化后导入新记录
+///
+/// 只监听启动时已存在的目录；监听失败时静默退出，仍可手动或在打开统计页面时自动导入。
+pub async fn run_local_log_watcher(app: AppHandle, db: Arc<Database>) {
+    let watched: Vec<(&'static dyn LogSource, PathBuf)> = LOG_SOURCES
+        .iter()
+        .filter_map(|&source| source.watch_dir().map(|dir| (source, dir)))
+        .collect();
+    if watched.is_empty() {
+        return;
+    }
+
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<PathBuf>();
+    let mut watcher = match notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if let Ok(event) = event {
+            if !event.kind.is_access() {
+                for path in event.paths {
+                    let _ = tx.send(path);
+                }
+            }
+        }
+    }) {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            eprintln!("启动本地日志监听失败: {e}");
+            return;
+        }
+    };
+    for (source, dir) in &watched {
+        if let Err(e) = watcher.watch(dir, RecursiveMode::Recursive) {
+            eprintln!("监听 {} 日志目录失败: {e}", source.name());
+        }
+    }
+
+    while let Some(path) = rx.recv().await {
+        let mut changed: HashSet<&'static str> = HashSet::new();
+        mark_changed(&watched, &path, &mut changed);
+
+        let deadline = tokio::time::sleep(WATCH_DEBOUNCE);
+        tokio::pin!(deadline);
+        loop {
+            tokio::select! {
+                _ = &mut deadline => break,
+                Some(path) = rx.recv() => mark_changed(&watched, &path, &mut changed),
+            }
+        }
+
+        let sources: Vec<&'static dyn LogSource> = watched
+            .iter()
+            .map(|&(source, _)| source)
+            .filter(|source| changed.contains(source.id()))
+            .collect();
+        let import_db = db.clone();
+        let delta = match tokio::task::spawn_blocking(move || import_changed(&import_db, &sources)).await {
+            Ok(delta) => delta,
+            Err(e) => {
+                eprintln!("导入本地日志失败: {e}");
+                continue;
+            }
+        };
+        if delta.imported > 0 {
+            let _ = app.emit("local-logs-imported", &delta);
+        }
+    }
+}
+
+/// 按监听目录找到变化文件所属的来源
+fn mark_changed(
+    watched: &[(&'static dyn LogSource, PathBuf)],
+    path: &Path,
+    changed: &mut HashSet<&'static str>,
+) {
+    if let Some((source, _)) = watched.iter().find(|(_, dir)| path.starts_with(dir)) {
+        changed.insert(source.id());
+    }
+}
+
+/// 逐个来源导入，记录各来源新导入的记录数
+fn import_changed(db: &Database, sources: &[&'static dyn LogSource]) -> LocalLogsImported {
+    let mut delta = LocalLogsImported {
+        imported: 0,
+        sources: Vec::new(),
+    };
+    for &source in sources {
+        match import_from_sources(db, &[source], |_| {}) {
+            Ok(result) if result.imported > 0 => {
+                delta.imported += result.imported;
+                delta.sources.push(SourceImported {
+                    source: source.id().to_string(),
+                    imported: result.imported,
+                });
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!("导入 {} 日志失败: {e}", source.name()),
+        }
+    }
+    delta
+}
@@ -116,6 +116,11 @@ pub fn get_mcp_servers(
     config_manager: State<'_, Mutex<ConfigManager>>,
 ) -> Result<Vec<McpServerItem>, AppError> {
     let manager = config_manager.lock().map_err(|e| AppError::Custom(e.to_string()))?;
+    list_mcp_servers(&manager)
+}
+
+/// 获取 MCP 服务器列表（不依赖 Tauri 状态，供管理 API 复用）
+pub fn list_mcp_servers(manager: &ConfigManager) -> Result<Vec<McpServerItem>, AppError> {
     let mcp_config = manager.mcp().read_config()?;
     
     // 获取 MCP 配置目录路径
@@ -505,14 +510,21 @@ pub struct CrossAppMcpSyncResult {
 pub fn sync_mcp_to_apps(
     input: CrossAppMcpSyncInput,
     config_manager: State<'_, Mutex<ConfigManager>>,
+) -> Result<Vec<CrossAppMcpSyncResult>, AppError> {
+    let manager = config_manager.lock().map_err(|e| AppError::Custom(e.to_string()))?;
+    sync_mcp_to_apps_with(&manager, &input)
+}
+
+/// 同步 MCP 服务器到多个应用（不依赖 Tauri 状态，供 CLI 复用）
+pub fn sync_mcp_to_apps_with(
+    manager: &ConfigManager,
+    input: &CrossAppMcpSyncInput,
 ) -> Result<Vec<CrossAppMcpSyncResult>, AppError> {
     use crate::config::claude_code_manager::{ClaudeCodeConfigManager, ClaudeMcpServer};
     use crate::config::codex_manager::{CodexConfigManager, CodexMcpServer};
     use crate::config::gemini_manager::{GeminiConfigManager, GeminiMcpServer};
     use crate::config::cursor_manager::{CursorConfigManager, CursorMcpServer};
     
-    let manager = config_manager.lock().map_err(|e| AppError::Custom(e.to_string()))?;
-    
     // 获取要同步的服务器
     let mcp_config = manager.mcp().read_config()?;
     let servers_to_sync: Vec<(&String, &McpServer)> = if input.server_names.is_empty() {
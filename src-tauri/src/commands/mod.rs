@@ -17,6 +17,7 @@ pub mod prompts;
 pub mod speedtest;
 pub mod usage;
 pub mod proxy;
+pub mod admin;
 pub mod open_switch;
 pub mod local_logs;
 pub mod chat_migration;
@@ -44,6 +45,7 @@ pub use prompts::*;
 pub use speedtest::*;
 pub use usage::*;
 pub use proxy::*;
+pub use admin::*;
 pub use open_switch::*;
 pub use local_logs::*;
 pub use chat_migration::*;
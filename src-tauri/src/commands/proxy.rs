@@ -1,7 +1,8 @@
 //! 代理服务器相关命令
 
-use crate::database::schema::{ModelTrendData, ProviderStats, UsageSummary, UsageTrend};
+use crate::database::schema::{ModelTrendData, ProjectStats, ProviderStats, UsageSummary, UsageTrend};
 use crate::database::Database;
+use crate::pricing::billing::{self, ConvertCurrency};
 use crate::proxy::{ProxyServerInfo, ProxyService, ProxyStatus, ProxyTakeoverStatus};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
@@ -153,15 +154,25 @@ pub async fn update_proxy_config(
 }
 
 // ==================== 统计查询命令 ====================
+//
+// currency 为空时以 USD 返回费用，否则按 exchange_rates 中的汇率换算；
+// project 为项目路径，为空时不按项目筛选
 
 /// 获取使用量摘要
 #[tauri::command]
 pub async fn get_proxy_usage_summary(
     period: String,
+    currency: Option<String>,
+    project: Option<String>,
     db: State<'_, Arc<Database>>,
 ) -> Result<UsageSummary, String> {
     let (start_ts, end_ts) = get_time_range(&period);
-    db.get_usage_summary(start_ts, end_ts).map_err(|e| e.to_string())
+    let rate = billing::report_rate(&db, currency.as_deref()).map_err(|e| e.to_string())?;
+    let mut summary = db
+        .get_usage_summary(start_ts, end_ts, project.as_deref())
+        .map_err(|e| e.to_string())?;
+    summary.convert_currency(rate);
+    Ok(summary)
 }
 
 /// 获取使用趋势
@@ -169,11 +180,17 @@ pub async fn get_proxy_usage_summary(
 pub async fn get_proxy_usage_trend(
     period: String,
     provider_id: Option<String>,
+    currency: Option<String>,
+    project: Option<String>,
     db: State<'_, Arc<Database>>,
 ) -> Result<Vec<UsageTrend>, String> {
     let (start_ts, end_ts) = get_time_range(&period);
-    db.get_usage_trend(start_ts, end_ts, &period, provider_id.as_deref())
-        .map_err(|e| e.to_string())
+    let rate = billing::report_rate(&db, currency.as_deref()).map_err(|e| e.to_string())?;
+    let mut trend = db
+        .get_usage_trend(start_ts, end_ts, &period, provider_id.as_deref(), project.as_deref())
+        .map_err(|e| e.to_string())?;
+    trend.convert_currency(rate);
+    Ok(trend)
 }
 
 /// 获取按模型分组的使用趋势（用于堆叠柱形图）
@@ -181,21 +198,45 @@ pub async fn get_proxy_usage_trend(
 pub async fn get_proxy_usage_trend_by_model(
     period: String,
     provider_id: Option<String>,
+    currency: Option<String>,
+    project: Option<String>,
     db: State<'_, Arc<Database>>,
 ) -> Result<Vec<ModelTrendData>, String> {
     let (start_ts, end_ts) = get_time_range(&period);
-    db.get_usage_trend_by_model(start_ts, end_ts, &period, provider_id.as_deref())
-        .map_err(|e| e.to_string())
+    let rate = billing::report_rate(&db, currency.as_deref()).map_err(|e| e.to_string())?;
+    let mut trend = db
+        .get_usage_trend_by_model(start_ts, end_ts, &period, provider_id.as_deref(), project.as_deref())
+        .map_err(|e| e.to_string())?;
+    trend.convert_currency(rate);
+    Ok(trend)
 }
 
 /// 获取各服务商统计
 #[tauri::command]
 pub async fn get_provider_stats(
     period: String,
+    currency: Option<String>,
     db: State<'_, Arc<Database>>,
 ) -> Result<Vec<ProviderStats>, String> {
     let (start_ts, end_ts) = get_time_range(&period);
-    db.get_provider_stats(start_ts, end_ts).map_err(|e| e.to_string())
+    let rate = billing::report_rate(&db, currency.as_deref()).map_err(|e| e.to_string())?;
+    let mut stats = db.get_provider_stats(start_ts, end_ts).map_err(|e| e.to_string())?;
+    stats.convert_currency(rate);
+    Ok(stats)
+}
+
+/// 获取各项目统计
+#[tauri::command]
+pub async fn get_project_stats(
+    period: String,
+    currency: Option<String>,
+    db: State<'_, Arc<Database>>,
+) -> Result<Vec<ProjectStats>, String> {
+    let (start_ts, end_ts) = get_time_range(&period);
+    let rate = billing::report_rate(&db, currency.as_deref()).map_err(|e| e.to_string())?;
+    let mut stats = db.get_project_stats(start_ts, end_ts).map_err(|e| e.to_string())?;
+    stats.convert_currency(rate);
+    Ok(stats)
 }
 
 /// 清空使用统计
@@ -209,7 +250,7 @@ pub async fn clear_proxy_usage_stats(
 // ==================== 辅助函数 ====================
 
 /// 计算时间范围
-fn get_time_range(period: &str) -> (Option<i64>, Option<i64>) {
+pub(crate) fn get_time_range(period: &str) -> (Option<i64>, Option<i64>) {
     use std::time::{SystemTime, UNIX_EPOCH};
     
     let now = SystemTime::now()
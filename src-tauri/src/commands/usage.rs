@@ -1,28 +1,27 @@
 // 使用统计相关的 Tauri commands
+//
+// 所有统计均来自数据库 proxy_request_logs；旧版 usage_stats.json 在打开数据库时一次性导入
 
 use serde::{Deserialize, Serialize};
+use std::collections::hash_map::Entry;
 use std::collections::HashMap;
-use std::fs;
-use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 use chrono::{DateTime, Utc, Timelike, Datelike};
-use tauri::State;
+use tauri::{AppHandle, Emitter, State};
+use crate::database::usage_records::UsageRecord;
 use crate::database::Database;
-
-/// 单条使用记录
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct UsageRecord {
-    pub id: String,
-    pub timestamp: i64,  // Unix timestamp in milliseconds
-    pub provider_name: String,
-    pub model: String,
-    pub input_tokens: u64,
-    pub output_tokens: u64,
-    pub cache_creation_tokens: u64,
-    pub cache_read_tokens: u64,
-    pub cost: f64,  // in USD
-    pub request_type: String,  // "chat", "completion", etc.
-}
+use crate::pricing::billing;
+use crate::report::anomaly::{AnomalyOptions, UsageAnomaly};
+use crate::report::cache::CacheReport;
+use crate::report::forecast::CostForecast;
+use crate::report::productivity::ProductivityReport;
+use crate::report::sessions::{SessionListOptions, SessionPage, SessionTimeline};
+use crate::report::{self, ReportExportResult, ReportFormat, ReportOptions};
+use crate::pricing::{
+    self, ExchangeRate, ModelAlias, ProviderBilling, PricingImportOptions, PricingImportReport, PricingRule, RecalcFilter, RecalcReport,
+    UsageModelMatch,
+};
 
 /// 使用统计摘要
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -33,6 +32,10 @@ pub struct UsageSummary {
     pub total_output_tokens: u64,
     pub total_cache_creation_tokens: u64,
     pub total_cache_read_tokens: u64,
+    /// 来自用量数据的 token
+    pub measured_tokens: u64,
+    /// 按文本估算的 token
+    pub estimated_tokens: u64,
 }
 
 /// 按时间段的使用统计
@@ -45,108 +48,67 @@ pub struct UsageTrend {
     pub tokens: u64,
 }
 
-/// 使用统计数据存储
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
-pub struct UsageData {
-    pub records: Vec<UsageRecord>,
-}
-
-impl UsageData {
-    /// 获取存储路径
-    fn get_storage_path() -> Result<PathBuf, String> {
-        let home = dirs::home_dir().ok_or("无法获取用户目录")?;
-        let config_dir = home.join(".config").join("opencode");
-        fs::create_dir_all(&config_dir).map_err(|e| e.to_string())?;
-        Ok(config_dir.join("usage_stats.json"))
-    }
-
-    /// 从文件加载
-    pub fn load() -> Result<Self, String> {
-        let path = Self::get_storage_path()?;
-        if !path.exists() {
-            return Ok(Self::default());
-        }
-        let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
-        serde_json::from_str(&content).map_err(|e| e.to_string())
-    }
-
-    /// 保存到文件
-    pub fn save(&self) -> Result<(), String> {
-        let path = Self::get_storage_path()?;
-        let content = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
-        fs::write(&path, content).map_err(|e| e.to_string())
-    }
-
-    /// 添加记录
-    pub fn add_record(&mut self, record: UsageRecord) {
-        self.records.push(record);
-    }
-
-    /// 获取时间范围内的记录
-    pub fn get_records_in_range(&self, start: i64, end: i64) -> Vec<&UsageRecord> {
-        self.records
-            .iter()
-            .filter(|r| r.timestamp >= start && r.timestamp <= end)
-            .collect()
-    }
-
-    /// 计算统计摘要
-    pub fn calculate_summary(&self, start: i64, end: i64) -> UsageSummary {
-        let records = self.get_records_in_range(start, end);
-        
-        UsageSummary {
-            total_requests: records.len() as u64,
-            total_cost: records.iter().map(|r| r.cost).sum(),
-            total_input_tokens: records.iter().map(|r| r.input_tokens).sum(),
-            total_output_tokens: records.iter().map(|r| r.output_tokens).sum(),
-            total_cache_creation_tokens: records.iter().map(|r| r.cache_creation_tokens).sum(),
-            total_cache_read_tokens: records.iter().map(|r| r.cache_read_tokens).sum(),
-        }
+/// 时间范围起点（Unix 秒），未知范围按 24 小时处理
+fn period_start(period: &str, now: i64) -> i64 {
+    match period {
+        "7d" => now - 7 * 24 * 60 * 60,
+        "30d" => now - 30 * 24 * 60 * 60,
+        _ => now - 24 * 60 * 60,
     }
+}
 
-    /// 清理旧数据（保留最近 30 天）
-    pub fn cleanup_old_records(&mut self) {
-        let thirty_days_ago = Utc::now().timestamp_millis() - 30 * 24 * 60 * 60 * 1000;
-        self.records.retain(|r| r.timestamp >= thirty_days_ago);
-    }
+/// 在阻塞线程池中执行数据库查询和文件读写，不占用异步运行时的工作线程
+async fn run_blocking<T: Send + 'static>(
+    db: &Arc<Database>,
+    task: impl FnOnce(&Database) -> Result<T, String> + Send + 'static,
+) -> Result<T, String> {
+    let db = Arc::clone(db);
+    tokio::task::spawn_blocking(move || task(&db))
+        .await
+        .map_err(|e| format!("后台任务失败: {e}"))?
 }
 
 /// 获取使用统计摘要
 #[tauri::command]
-pub async fn get_usage_summary(period: String) -> Result<UsageSummary, String> {
-    let data = UsageData::load()?;
-    let now = Utc::now().timestamp_millis();
-    
-    let start = match period.as_str() {
-        "24h" => now - 24 * 60 * 60 * 1000,
-        "7d" => now - 7 * 24 * 60 * 60 * 1000,
-        "30d" => now - 30 * 24 * 60 * 60 * 1000,
-        _ => now - 24 * 60 * 60 * 1000,
-    };
-    
-    Ok(data.calculate_summary(start, now))
+pub async fn get_usage_summary(
+    period: String,
+    currency: Option<String>,
+    project: Option<String>,
+    db: State<'_, Arc<Database>>,
+) -> Result<UsageSummary, String> {
+    let now = Utc::now().timestamp();
+    let rate = billing::report_rate(&db, currency.as_deref()).map_err(|e| e.to_string())?;
+    let summary = db
+        .get_usage_summary(Some(period_start(&period, now)), Some(now), project.as_deref())
+        .map_err(|e| e.to_string())?;
+
+    Ok(UsageSummary {
+        total_requests: summary.total_requests,
+        total_cost: summary.total_cost.parse::<f64>().unwrap_or(0.0) * rate,
+        total_input_tokens: summary.total_input_tokens,
+        total_output_tokens: summary.total_output_tokens,
+        total_cache_creation_tokens: summary.total_cache_creation_tokens,
+        total_cache_read_tokens: summary.total_cache_read_tokens,
+        measured_tokens: summary.measured_tokens,
+        estimated_tokens: summary.estimated_tokens,
+    })
 }
 
 /// 获取使用趋势数据
 #[tauri::command]
-pub async fn get_usage_trend(period: String) -> Result<Vec<UsageTrend>, String> {
-    let data = UsageData::load()?;
-    let now = Utc::now();
-    let now_millis = now.timestamp_millis();
+pub async fn get_usage_trend(
+    period: String,
+    currency: Option<String>,
+    db: State<'_, Arc<Database>>,
+) -> Result<Vec<UsageTrend>, String> {
+    let now = Utc::now().timestamp();
+    let rate = billing::report_rate(&db, currency.as_deref()).map_err(|e| e.to_string())?;
+    let start = period_start(&period, now);
     
-    let (start_millis, interval_millis, format_fn): (i64, i64, Box<dyn Fn(DateTime<Utc>) -> String>) = match period.as_str() {
-        "24h" => {
-            // 按小时分组，过去24小时
-            let start = now_millis - 24 * 60 * 60 * 1000;
-            let interval = 60 * 60 * 1000; // 1 hour
-            let format_fn: Box<dyn Fn(DateTime<Utc>) -> String> = Box::new(|dt: DateTime<Utc>| format!("{:02}:00", dt.hour()));
-            (start, interval, format_fn)
-        },
+    let (interval, format_fn): (i64, fn(DateTime<Utc>) -> String) = match period.as_str() {
         "7d" => {
             // 按天分组，过去7天
-            let start = now_millis - 7 * 24 * 60 * 60 * 1000;
-            let interval = 24 * 60 * 60 * 1000; // 1 day
-            let format_fn: Box<dyn Fn(DateTime<Utc>) -> String> = Box::new(|dt: DateTime<Utc>| {
+            let format_fn = |dt: DateTime<Utc>| {
                 let weekday = dt.weekday();
                 match weekday {
                     chrono::Weekday::Mon => "周一".to_string(),
@@ -157,58 +119,43 @@ pub async fn get_usage_trend(period: String) -> Result<Vec<UsageTrend>, String>
                     chrono::Weekday::Sat => "周六".to_string(),
                     chrono::Weekday::Sun => "周日".to_string(),
                 }
-            });
-            (start, interval, format_fn)
+            };
+            (24 * 60 * 60, format_fn)
         },
         "30d" => {
             // 按天分组，过去30天
-            let start = now_millis - 30 * 24 * 60 * 60 * 1000;
-            let interval = 24 * 60 * 60 * 1000; // 1 day
-            let format_fn: Box<dyn Fn(DateTime<Utc>) -> String> = Box::new(|dt: DateTime<Utc>| format!("{}/{}", dt.format("%m"), dt.format("%d")));
-            (start, interval, format_fn)
+            let format_fn = |dt: DateTime<Utc>| format!("{}/{}", dt.format("%m"), dt.format("%d"));
+            (24 * 60 * 60, format_fn)
         },
         _ => {
-            let start = now_millis - 24 * 60 * 60 * 1000;
-            let interval = 60 * 60 * 1000;
-            let format_fn: Box<dyn Fn(DateTime<Utc>) -> String> = Box::new(|dt: DateTime<Utc>| format!("{:02}:00", dt.hour()));
-            (start, interval, format_fn)
+            // 按小时分组，过去24小时
+            let format_fn = |dt: DateTime<Utc>| format!("{:02}:00", dt.hour());
+            (60 * 60, format_fn)
         }
     };
 
-    // 创建时间桶
-    let mut buckets: Vec<UsageTrend> = Vec::new();
-    let mut current = start_millis;
-    
-    while current < now_millis {
-        let dt = DateTime::from_timestamp_millis(current).unwrap_or(now);
-        buckets.push(UsageTrend {
-            label: format_fn(dt),
-            timestamp: current,
-            requests: 0,
-            cost: 0.0,
-            tokens: 0,
-        });
-        current += interval_millis;
-    }
-
-    // 将记录分配到时间桶
-    let records = data.get_records_in_range(start_millis, now_millis);
-    for record in records {
-        // 找到对应的时间桶
-        for bucket in &mut buckets {
-            if record.timestamp >= bucket.timestamp && record.timestamp < bucket.timestamp + interval_millis {
-                bucket.requests += 1;
-                bucket.cost += record.cost;
-                bucket.tokens += record.input_tokens + record.output_tokens;
-                break;
+    let buckets = db
+        .get_daily_trends(start, now, interval)
+        .map_err(|e| e.to_string())?;
+
+    Ok(buckets
+        .into_iter()
+        .enumerate()
+        .map(|(i, bucket)| {
+            let bucket_start = start + i as i64 * interval;
+            let dt = DateTime::from_timestamp(bucket_start, 0).unwrap_or_else(Utc::now);
+            UsageTrend {
+                label: format_fn(dt),
+                timestamp: bucket_start * 1000,
+                requests: bucket.request_count,
+                cost: bucket.total_cost.parse::<f64>().unwrap_or(0.0) * rate,
+                tokens: bucket.total_tokens,
             }
-        }
-    }
-
-    Ok(buckets)
+        })
+        .collect())
 }
 
-/// 添加使用记录
+/// 添加使用记录（写入数据库，app_type 为 manual）
 #[tauri::command]
 pub async fn add_usage_record(
     provider_name: String,
@@ -219,9 +166,8 @@ pub async fn add_usage_record(
     cache_read_tokens: Option<u64>,
     cost: Option<f64>,
     request_type: Option<String>,
+    db: State<'_, Arc<Database>>,
 ) -> Result<(), String> {
-    let mut data = UsageData::load()?;
-    
     let record = UsageRecord {
         id: uuid::Uuid::new_v4().to_string(),
         timestamp: Utc::now().timestamp_millis(),
@@ -235,53 +181,57 @@ pub async fn add_usage_record(
         request_type: request_type.unwrap_or_else(|| "chat".to_string()),
     };
     
-    data.add_record(record);
-    data.cleanup_old_records();  // 清理旧数据
-    data.save()?;
-    
-    Ok(())
+    db.insert_usage_record(&record).map_err(|e| e.to_string())
 }
 
-/// 清除所有使用统计
+/// 清除手动添加的使用记录
+///
+/// 只删除 `add_usage_record` 写入的记录；清空全部统计请使用 clear_proxy_usage_stats
 #[tauri::command]
-pub async fn clear_usage_stats() -> Result<(), String> {
-    let data = UsageData::default();
-    data.save()
+pub async fn clear_usage_stats(db: State<'_, Arc<Database>>) -> Result<(), String> {
+    db.clear_manual_usage_records()
+        .map(|_| ())
+        .map_err(|e| e.to_string())
 }
 
 /// 获取按服务商分组的统计
 #[tauri::command]
-pub async fn get_usage_by_provider(period: String) -> Result<HashMap<String, UsageSummary>, String> {
-    let data = UsageData::load()?;
-    let now = Utc::now().timestamp_millis();
-    
-    let start = match period.as_str() {
-        "24h" => now - 24 * 60 * 60 * 1000,
-        "7d" => now - 7 * 24 * 60 * 60 * 1000,
-        "30d" => now - 30 * 24 * 60 * 60 * 1000,
-        _ => now - 24 * 60 * 60 * 1000,
-    };
-    
-    let records = data.get_records_in_range(start, now);
-    let mut by_provider: HashMap<String, Vec<&UsageRecord>> = HashMap::new();
-    
-    for record in records {
-        by_provider
-            .entry(record.provider_name.clone())
-            .or_insert_with(Vec::new)
-            .push(record);
-    }
-    
+pub async fn get_usage_by_provider(
+    period: String,
+    currency: Option<String>,
+    db: State<'_, Arc<Database>>,
+) -> Result<HashMap<String, UsageSummary>, String> {
+    let now = Utc::now().timestamp();
+    let rate = billing::report_rate(&db, currency.as_deref()).map_err(|e| e.to_string())?;
+    let stats = db
+        .get_provider_stats(Some(period_start(&period, now)), Some(now))
+        .map_err(|e| e.to_string())?;
+    
+    // 同名服务商（不同 ID）合并统计
     let mut result: HashMap<String, UsageSummary> = HashMap::new();
-    for (provider, records) in by_provider {
-        result.insert(provider, UsageSummary {
-            total_requests: records.len() as u64,
-            total_cost: records.iter().map(|r| r.cost).sum(),
-            total_input_tokens: records.iter().map(|r| r.input_tokens).sum(),
-            total_output_tokens: records.iter().map(|r| r.output_tokens).sum(),
-            total_cache_creation_tokens: records.iter().map(|r| r.cache_creation_tokens).sum(),
-            total_cache_read_tokens: records.iter().map(|r| r.cache_read_tokens).sum(),
+    for stat in stats {
+        let summary = result.entry(stat.provider_name).or_insert(UsageSummary {
+            total_requests: 0,
+            total_cost: 0.0,
+            total_input_tokens: 0,
+            total_output_tokens: 0,
+            total_cache_creation_tokens: 0,
+            total_cache_read_tokens: 0,
+            measured_tokens: 0,
+            estimated_tokens: 0,
         });
+        summary.total_requests += stat.request_count;
+        summary.total_cost += stat.total_cost.parse::<f64>().unwrap_or(0.0) * rate;
+        summary.total_input_tokens += stat.total_input_tokens;
+        summary.total_output_tokens += stat.total_output_tokens;
+        summary.total_cache_creation_tokens += stat.total_cache_creation_tokens;
+        summary.total_cache_read_tokens += stat.total_cache_read_tokens;
+        summary.estimated_tokens += stat.estimated_tokens;
+        summary.measured_tokens += (stat.total_input_tokens
+            + stat.total_output_tokens
+            + stat.total_cache_creation_tokens
+            + stat.total_cache_read_tokens)
+            .saturating_sub(stat.estimated_tokens);
     }
     
     Ok(result)
@@ -301,16 +251,21 @@ pub struct ModelPricing {
     pub output_cost_per_million: String,
     pub cache_read_cost_per_million: String,
     pub cache_creation_cost_per_million: String,
+    pub max_input_tokens: Option<i64>,
+    pub max_output_tokens: Option<i64>,
+    /// 定价来源: builtin | manual | catalog
+    pub source: String,
 }
 
 /// 获取所有模型定价
 #[tauri::command]
 pub async fn get_model_pricing_list(db: State<'_, Arc<Database>>) -> Result<Vec<ModelPricing>, String> {
-    let conn = db.conn.lock().map_err(|e| format!("获取数据库锁失败: {e}"))?;
+    let conn = db.read_conn().map_err(|e| e.to_string())?;
     
     let mut stmt = conn.prepare(
         "SELECT model_id, display_name, input_cost_per_million, output_cost_per_million,
-                cache_read_cost_per_million, cache_creation_cost_per_million
+                cache_read_cost_per_million, cache_creation_cost_per_million,
+                max_input_tokens, max_output_tokens, source
          FROM model_pricing ORDER BY display_name"
     ).map_err(|e| format!("准备查询失败: {e}"))?;
     
@@ -322,6 +277,9 @@ pub async fn get_model_pricing_list(db: State<'_, Arc<Database>>) -> Result<Vec<
             output_cost_per_million: row.get(3)?,
             cache_read_cost_per_million: row.get(4)?,
             cache_creation_cost_per_million: row.get(5)?,
+            max_input_tokens: row.get(6)?,
+            max_output_tokens: row.get(7)?,
+            source: row.get(8)?,
         })
     }).map_err(|e| format!("查询失败: {e}"))?;
     
@@ -350,9 +308,11 @@ pub async fn update_model_pricing(
             input_cost_per_million = ?2,
             output_cost_per_million = ?3,
             cache_read_cost_per_million = ?4,
-            cache_creation_cost_per_million = ?5
+            cache_creation_cost_per_million = ?5,
+            source = 'manual',
+            updated_at = ?6
          WHERE model_id = ?1",
-        rusqlite::params![model_id, input_cost, output_cost, cache_read_cost, cache_creation_cost],
+        rusqlite::params![model_id, input_cost, output_cost, cache_read_cost, cache_creation_cost, Utc::now().timestamp()],
     ).map_err(|e| format!("更新失败: {e}"))?;
     
     Ok(())
@@ -374,9 +334,9 @@ pub async fn add_model_pricing(
     conn.execute(
         "INSERT OR REPLACE INTO model_pricing (
             model_id, display_name, input_cost_per_million, output_cost_per_million,
-            cache_read_cost_per_million, cache_creation_cost_per_million
-        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-        rusqlite::params![model_id, display_name, input_cost, output_cost, cache_read_cost, cache_creation_cost],
+            cache_read_cost_per_million, cache_creation_cost_per_million, source, updated_at
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, 'manual', ?7)",
+        rusqlite::params![model_id, display_name, input_cost, output_cost, cache_read_cost, cache_creation_cost, Utc::now().timestamp()],
     ).map_err(|e| format!("添加失败: {e}"))?;
     
     Ok(())
@@ -455,6 +415,308 @@ pub async fn reset_model_pricing(db: State<'_, Arc<Database>>) -> Result<(), Str
     Ok(())
 }
 
+/// 从定价目录文件导入模型定价（LiteLLM JSON 或 CSV），可重复执行以刷新价格
+#[tauri::command]
+pub async fn import_model_pricing_catalog(
+    db: State<'_, Arc<Database>>,
+    path: String,
+    options: Option<PricingImportOptions>,
+) -> Result<PricingImportReport, String> {
+    let options = options.unwrap_or_default();
+    run_blocking(&db, move |db| {
+        pricing::catalog::import_file(db, std::path::Path::new(&path), &options).map_err(|e| e.to_string())
+    })
+    .await
+}
+
+/// 导出使用报表（CSV / JSON Lines / Markdown）
+#[tauri::command]
+pub async fn export_usage_report(
+    db: State<'_, Arc<Database>>,
+    options: Option<ReportOptions>,
+    format: ReportFormat,
+    path: String,
+) -> Result<ReportExportResult, String> {
+    let options = options.unwrap_or_default();
+    run_blocking(&db, move |db| {
+        report::export(db, &options, format, std::path::Path::new(&path)).map_err(|e| e.to_string())
+    })
+    .await
+}
+
+/// 缓存效率分析（命中率、缓存节省、缓存写入开销），筛选和分组同使用报表
+#[tauri::command]
+pub async fn get_cache_analytics(
+    db: State<'_, Arc<Database>>,
+    options: Option<ReportOptions>,
+) -> Result<CacheReport, String> {
+    let options = options.unwrap_or_default();
+    run_blocking(&db, move |db| report::cache::analyze(db, &options).map_err(|e| e.to_string())).await
+}
+
+/// 生产力指标（每百行改动费用、每次编辑 token、工具调用构成、思考时间占比），筛选和分组同使用报表
+#[tauri::command]
+pub async fn get_productivity_metrics(
+    db: State<'_, Arc<Database>>,
+    options: Option<ReportOptions>,
+) -> Result<ProductivityReport, String> {
+    let options = options.unwrap_or_default();
+    run_blocking(&db, move |db| report::productivity::analyze(db, &options).map_err(|e| e.to_string())).await
+}
+
+/// 分页列出会话（起止时间、模型构成、费用、工具调用和改动行数）
+#[tauri::command]
+pub async fn list_usage_sessions(
+    db: State<'_, Arc<Database>>,
+    options: Option<SessionListOptions>,
+) -> Result<SessionPage, String> {
+    let options = options.unwrap_or_default();
+    run_blocking(&db, move |db| report::sessions::list(db, &options).map_err(|e| e.to_string())).await
+}
+
+/// 单个会话的请求时间线和工具调用统计
+#[tauri::command]
+pub async fn get_usage_session_timeline(
+    db: State<'_, Arc<Database>>,
+    session_id: String,
+    currency: Option<String>,
+) -> Result<SessionTimeline, String> {
+    run_blocking(&db, move |db| {
+        report::sessions::timeline(db, &session_id, currency.as_deref())
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("会话不存在: {session_id}"))
+    })
+    .await
+}
+
+/// 预测本月月末费用
+#[tauri::command]
+pub async fn get_cost_forecast(
+    db: State<'_, Arc<Database>>,
+    currency: Option<String>,
+) -> Result<CostForecast, String> {
+    run_blocking(&db, move |db| {
+        report::forecast::build(db, currency.as_deref(), Utc::now()).map_err(|e| e.to_string())
+    })
+    .await
+}
+
+/// 检测最近的使用异常（按天 / 按小时 / 服务商 / 模型）
+#[tauri::command]
+pub async fn get_usage_anomalies(
+    db: State<'_, Arc<Database>>,
+    options: Option<AnomalyOptions>,
+) -> Result<Vec<UsageAnomaly>, String> {
+    let options = options.unwrap_or_default();
+    run_blocking(&db, move |db| report::anomaly::detect(db, &options, Utc::now()).map_err(|e| e.to_string())).await
+}
+
+/// 用量监控的检测间隔
+const USAGE_MONITOR_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// 后台用量监控：定期更新费用预测并检测异常
+///
+/// 新出现的异常通过 `usage-anomaly` 事件发送（同一异常只发送一次），费用预测通过 `cost-forecast` 事件发送，
+/// 并同步显示在托盘提示中。
+pub async fn run_usage_monitor(app: AppHandle, db: Arc<Database>) {
+    let options = AnomalyOptions {
+        days: 1,
+        ..Default::default()
+    };
+    // 已提醒的异常：去重键 -> 时间段起点
+    let mut seen: HashMap<String, i64> = HashMap::new();
+    // 最近一次提醒的异常及其提醒时间
+    let mut latest: Option<(i64, UsageAnomaly)> = None;
+    // 延后第一次检测，等待前端注册事件监听
+    let start = tokio::time::Instant::now() + Duration::from_secs(60);
+    let mut interval = tokio::time::interval_at(start, USAGE_MONITOR_INTERVAL);
+
+    loop {
+        interval.tick().await;
+        let now = Utc::now();
+
+        // 数据库查询在阻塞线程池中执行，不占用异步运行时的工作线程
+        let task_db = db.clone();
+        let task_options = options.clone();
+        let (anomalies, forecast) = match tokio::task::spawn_blocking(move || {
+            (
+                report::anomaly::detect(&task_db, &task_options, now),
+                report::forecast::build(&task_db, None, now),
+            )
+        })
+        .await
+        {
+            Ok(result) => result,
+            Err(e) => {
+                eprintln!("用量监控失败: {e}");
+                continue;
+            }
+        };
+
+        match anomalies {
+            Ok(anomalies) => {
+                for anomaly in anomalies {
+                    if let Entry::Vacant(entry) = seen.entry(anomaly.key()) {
+                        entry.insert(anomaly.period_start);
+                        let _ = app.emit("usage-anomaly", &anomaly);
+                        latest = Some((now.timestamp(), anomaly));
+                    }
+                }
+            }
+            Err(e) => eprintln!("检测使用异常失败: {e}"),
+        }
+        // 早于检测范围的异常不会再被检测到，不再需要去重
+        let horizon = now.timestamp() - (options.days as i64 + 1) * 24 * 60 * 60;
+        seen.retain(|_, period_start| *period_start >= horizon);
+        // 托盘中的异常提醒保留 24 小时
+        latest = latest.filter(|(raised_at, _)| *raised_at >= now.timestamp() - 24 * 60 * 60);
+
+        match forecast {
+            Ok(forecast) => {
+                let mut tooltip = format!(
+                    "Ai Switch\n本月已用 ${:.2}，预计 ${:.2}",
+                    forecast.month_to_date, forecast.projected_total
+                );
+                if let Some((_, anomaly)) = &latest {
+                    tooltip.push_str(&format!("\n⚠ {}", anomaly.message));
+                }
+                crate::set_tray_tooltip(&app, &tooltip);
+                let _ = app.emit("cost-forecast", &forecast);
+            }
+            Err(e) => eprintln!("预测费用失败: {e}"),
+        }
+    }
+}
+
+/// 使用记录中各模型的定价匹配情况（精确 / 别名 / 模糊 / 无定价）
+#[tauri::command]
+pub async fn get_usage_model_matches(
+    db: State<'_, Arc<Database>>,
+) -> Result<Vec<UsageModelMatch>, String> {
+    run_blocking(&db, |db| pricing::matching::usage_model_matches(db).map_err(|e| e.to_string())).await
+}
+
+/// 获取模型别名
+#[tauri::command]
+pub async fn get_model_aliases(db: State<'_, Arc<Database>>) -> Result<Vec<ModelAlias>, String> {
+    pricing::matching::list_aliases(&db).map_err(|e| e.to_string())
+}
+
+/// 将使用记录中的模型名绑定到已有定价
+#[tauri::command]
+pub async fn bind_model_alias(
+    db: State<'_, Arc<Database>>,
+    alias: String,
+    model_id: String,
+) -> Result<(), String> {
+    pricing::matching::bind_alias(&db, &alias, &model_id).map_err(|e| e.to_string())
+}
+
+/// 解除模型别名
+#[tauri::command]
+pub async fn delete_model_alias(db: State<'_, Arc<Database>>, alias: String) -> Result<(), String> {
+    pricing::matching::unbind_alias(&db, &alias).map_err(|e| e.to_string())
+}
+
+// ============================================================================
+// 服务商计费货币、倍率与汇率
+// ============================================================================
+
+/// 获取服务商计费设置
+#[tauri::command]
+pub async fn get_provider_billing(db: State<'_, Arc<Database>>) -> Result<Vec<ProviderBilling>, String> {
+    billing::list_provider_billing(&db).map_err(|e| e.to_string())
+}
+
+/// 保存服务商计费货币和倍率（之后记录的请求按新设置计费）
+#[tauri::command]
+pub async fn save_provider_billing(
+    db: State<'_, Arc<Database>>,
+    billing: ProviderBilling,
+) -> Result<(), String> {
+    billing::save_provider_billing(&db, &billing).map_err(|e| e.to_string())
+}
+
+/// 删除服务商计费设置
+#[tauri::command]
+pub async fn delete_provider_billing(
+    db: State<'_, Arc<Database>>,
+    provider_id: String,
+) -> Result<(), String> {
+    billing::delete_provider_billing(&db, &provider_id).map_err(|e| e.to_string())
+}
+
+/// 获取汇率
+#[tauri::command]
+pub async fn get_exchange_rates(db: State<'_, Arc<Database>>) -> Result<Vec<ExchangeRate>, String> {
+    billing::list_exchange_rates(&db).map_err(|e| e.to_string())
+}
+
+/// 设置汇率（1 USD 可兑换的该货币数量）
+#[tauri::command]
+pub async fn save_exchange_rate(
+    db: State<'_, Arc<Database>>,
+    currency: String,
+    rate: String,
+) -> Result<(), String> {
+    billing::save_exchange_rate(&db, &currency, &rate).map_err(|e| e.to_string())
+}
+
+/// 删除汇率
+#[tauri::command]
+pub async fn delete_exchange_rate(db: State<'_, Arc<Database>>, currency: String) -> Result<(), String> {
+    billing::delete_exchange_rate(&db, &currency).map_err(|e| e.to_string())
+}
+
+// ============================================================================
+// 定价规则（阶梯 / 时段 / 生效日期）
+// ============================================================================
+
+/// 获取定价规则（可按模型过滤）
+#[tauri::command]
+pub async fn get_pricing_rules(
+    db: State<'_, Arc<Database>>,
+    model_id: Option<String>,
+) -> Result<Vec<PricingRule>, String> {
+    pricing::rules::list_rules(&db, model_id.as_deref()).map_err(|e| e.to_string())
+}
+
+/// 新增或更新定价规则，返回规则 ID
+#[tauri::command]
+pub async fn save_pricing_rule(
+    db: State<'_, Arc<Database>>,
+    rule: PricingRule,
+) -> Result<i64, String> {
+    pricing::rules::save_rule(&db, &rule).map_err(|e| e.to_string())
+}
+
+/// 删除定价规则
+#[tauri::command]
+pub async fn delete_pricing_rule(db: State<'_, Arc<Database>>, id: i64) -> Result<(), String> {
+    pricing::rules::delete_rule(&db, id).map_err(|e| e.to_string())
+}
+
+/// 按当前定价重算历史记录成本
+///
+/// `apply` 为 false 时仅返回变更对比，不修改数据库
+#[tauri::command]
+pub async fn recalculate_usage_costs(
+    db: State<'_, Arc<Database>>,
+    filter: Option<RecalcFilter>,
+    apply: bool,
+) -> Result<RecalcReport, String> {
+    let filter = filter.unwrap_or_default();
+    run_blocking(&db, move |db| {
+        let result = if apply {
+            pricing::recalc::apply(db, &filter)
+        } else {
+            pricing::recalc::preview(db, &filter)
+        };
+        result.map_err(|e| e.to_string())
+    })
+    .await
+}
+
 // ============================================================================
 // 服务商特定模型定价
 // ============================================================================
@@ -478,7 +740,7 @@ pub async fn get_provider_model_pricing(
     db: State<'_, Arc<Database>>,
     provider_id: String,
 ) -> Result<Vec<ProviderModelPricing>, String> {
-    let conn = db.conn.lock().map_err(|e| format!("获取数据库锁失败: {e}"))?;
+    let conn = db.read_conn().map_err(|e| e.to_string())?;
     
     let mut stmt = conn.prepare(
         "SELECT id, provider_id, model_id, input_cost_per_million, output_cost_per_million,
@@ -511,7 +773,7 @@ pub async fn get_provider_model_pricing(
 pub async fn get_all_provider_pricing(
     db: State<'_, Arc<Database>>,
 ) -> Result<Vec<ProviderModelPricing>, String> {
-    let conn = db.conn.lock().map_err(|e| format!("获取数据库锁失败: {e}"))?;
+    let conn = db.read_conn().map_err(|e| e.to_string())?;
     
     let mut stmt = conn.prepare(
         "SELECT id, provider_id, model_id, input_cost_per_million, output_cost_per_million,
@@ -585,7 +847,7 @@ pub async fn delete_provider_model_pricing(
 pub async fn get_pricing_providers(
     db: State<'_, Arc<Database>>,
 ) -> Result<Vec<String>, String> {
-    let conn = db.conn.lock().map_err(|e| format!("获取数据库锁失败: {e}"))?;
+    let conn = db.read_conn().map_err(|e| e.to_string())?;
     
     let mut stmt = conn.prepare(
         "SELECT DISTINCT provider_id FROM provider_model_pricing ORDER BY provider_id"
@@ -647,7 +909,7 @@ pub struct ProviderCount {
 /// 诊断数据库数据
 #[tauri::command]
 pub async fn diagnose_usage_data(db: State<'_, Arc<Database>>) -> Result<DataDiagnostics, String> {
-    let conn = db.conn.lock().map_err(|e| format!("获取数据库锁失败: {e}"))?;
+    let conn = db.read_conn().map_err(|e| e.to_string())?;
     
     // 获取总记录数和时间范围
     let (total, min_ts, max_ts): (u32, Option<i64>, Option<i64>) = conn.query_row(
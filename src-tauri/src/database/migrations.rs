@@ -0,0 +1,759 @@
+//! 数据库版本迁移
+//!
+//! 迁移按版本号顺序执行，每个迁移在独立事务中运行，并在同一事务内更新 `user_version`。
+//! 新增表或字段时在 `MIGRATIONS` 末尾追加迁移，同时更新 `SCHEMA_VERSION`，
+//! 已发布的迁移不得修改。
+
+use super::Database;
+use crate::error::AppError;
+use rusqlite::Connection;
+use std::path::{Path, PathBuf};
+
+/// 单个迁移
+pub(crate) struct Migration {
+    /// 迁移完成后的版本号（从 1 开始连续递增）
+    pub version: i32,
+    /// 迁移说明
+    pub description: &'static str,
+    /// 迁移内容（在事务中执行）
+    pub up: fn(&Connection) -> Result<(), AppError>,
+}
+
+/// 全部迁移（按版本号升序）
+pub(crate) const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "初始表结构",
+        up: v1_initial_schema,
+    },
+    Migration {
+        version: 2,
+        description: "模型定价上下文窗口与来源",
+        up: v2_model_pricing_metadata,
+    },
+    Migration {
+        version: 3,
+        description: "定价规则（阶梯 / 时段 / 生效日期）",
+        up: v3_pricing_rules,
+    },
+    Migration {
+        version: 4,
+        description: "定价版本与使用记录的计价版本",
+        up: v4_pricing_version,
+    },
+    Migration {
+        version: 5,
+        description: "模型别名",
+        up: v5_model_aliases,
+    },
+    Migration {
+        version: 6,
+        description: "服务商计费货币、倍率与汇率",
+        up: v6_provider_billing,
+    },
+    Migration {
+        version: 7,
+        description: "使用记录的项目归属",
+        up: v7_request_project,
+    },
+    Migration {
+        version: 8,
+        description: "本地日志增量导入状态",
+        up: v8_local_import_state,
+    },
+    Migration {
+        version: 9,
+        description: "使用记录的会话 ID",
+        up: v9_request_session,
+    },
+    Migration {
+        version: 10,
+        description: "使用记录的 token 估算标记",
+        up: v10_tokens_estimated,
+    },
+    Migration {
+        version: 11,
+        description: "工具调用统计按会话和工具去重",
+        up: v11_tool_calls_unique,
+    },
+];
+
+/// 每个数据库最多保留的迁移前备份数
+const MAX_MIGRATION_BACKUPS: usize = 5;
+
+/// 将数据库迁移到 `migrations` 中的最新版本
+///
+/// - 数据库版本高于应用支持的版本时拒绝打开（防止旧版本应用破坏新数据）
+/// - `db_path` 不为空且存在待执行迁移时，先将现有数据库备份到 `db-backups/`
+pub(crate) fn run(
+    conn: &mut Connection,
+    migrations: &[Migration],
+    db_path: Option<&Path>,
+) -> Result<(), AppError> {
+    let latest = migrations.last().map(|m| m.version).unwrap_or(0);
+    let current = Database::get_user_version(conn)?;
+
+    if current > latest {
+        return Err(AppError::Database(format!(
+            "数据库版本过新（{current}），当前应用仅支持 {latest}，请升级 Ai Switch"
+        )));
+    }
+
+    let pending: Vec<&Migration> = migrations.iter().filter(|m| m.version > current).collect();
+    if pending.is_empty() {
+        return Ok(());
+    }
+
+    if let Some(path) = db_path {
+        if has_user_tables(conn)? {
+            let backup = backup_database(conn, path, current)?;
+            eprintln!("数据库迁移前已备份到: {}", backup.display());
+        }
+    }
+
+    for migration in pending {
+        let tx = conn
+            .transaction()
+            .map_err(|e| AppError::Database(format!("开启迁移事务失败: {e}")))?;
+        (migration.up)(&tx).map_err(|e| {
+            AppError::Database(format!(
+                "迁移 v{}（{}）失败: {e}",
+                migration.version, migration.description
+            ))
+        })?;
+        Database::set_user_version(&tx, migration.version)?;
+        tx.commit()
+            .map_err(|e| AppError::Database(format!("提交迁移 v{} 失败: {e}", migration.version)))?;
+    }
+
+    Ok(())
+}
+
+/// 数据库中是否已有表（全新数据库无需备份）
+fn has_user_tables(conn: &Connection) -> Result<bool, AppError> {
+    let count: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%'",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|e| AppError::Database(format!("读取表信息失败: {e}")))?;
+    Ok(count > 0)
+}
+
+/// 使用 VACUUM INTO 生成一致的数据库快照，并清理过旧的备份
+fn backup_database(conn: &Connection, db_path: &Path, version: i32) -> Result<PathBuf, AppError> {
+    let dir = db_path
+        .parent()
+        .map(|p| p.join("db-backups"))
+        .ok_or_else(|| AppError::Database("无法确定数据库备份目录".to_string()))?;
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| AppError::Database(format!("创建数据库备份目录失败: {e}")))?;
+
+    let stem = db_path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("ai-switch");
+    let backup = dir.join(format!(
+        "{stem}-v{version}-{}.db",
+        chrono::Local::now().format("%Y%m%d%H%M%S")
+    ));
+
+    conn.execute("VACUUM INTO ?1", [backup.to_string_lossy()])
+        .map_err(|e| AppError::Database(format!("迁移前备份数据库失败: {e}")))?;
+
+    prune_backups(&dir, stem);
+    Ok(backup)
+}
+
+/// 只保留最近的 MAX_MIGRATION_BACKUPS 个备份
+fn prune_backups(dir: &Path, stem: &str) {
+    let prefix = format!("{stem}-v");
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let mut backups: Vec<PathBuf> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .map(|n| n.starts_with(&prefix) && n.ends_with(".db"))
+                .unwrap_or(false)
+        })
+        .collect();
+    backups.sort_by_key(|p| p.metadata().and_then(|m| m.modified()).ok());
+    let excess = backups.len().saturating_sub(MAX_MIGRATION_BACKUPS);
+    for old in backups.into_iter().take(excess) {
+        let _ = std::fs::remove_file(old);
+    }
+}
+
+// ============================================================================
+// 迁移定义
+// ============================================================================
+
+/// v1: 初始表结构
+///
+/// 使用 IF NOT EXISTS，兼容引入版本号之前已创建过表的数据库
+fn v1_initial_schema(conn: &Connection) -> Result<(), AppError> {
+    // 1. 代理请求日志表
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS proxy_request_logs (
+            request_id TEXT PRIMARY KEY,
+            provider_id TEXT NOT NULL,
+            provider_name TEXT,
+            app_type TEXT NOT NULL,
+            model TEXT NOT NULL,
+            request_model TEXT,
+            input_tokens INTEGER NOT NULL DEFAULT 0,
+            output_tokens INTEGER NOT NULL DEFAULT 0,
+            cache_read_tokens INTEGER NOT NULL DEFAULT 0,
+            cache_creation_tokens INTEGER NOT NULL DEFAULT 0,
+            input_cost_usd TEXT NOT NULL DEFAULT '0',
+            output_cost_usd TEXT NOT NULL DEFAULT '0',
+            cache_read_cost_usd TEXT NOT NULL DEFAULT '0',
+            cache_creation_cost_usd TEXT NOT NULL DEFAULT '0',
+            total_cost_usd TEXT NOT NULL DEFAULT '0',
+            latency_ms INTEGER NOT NULL,
+            first_token_ms INTEGER,
+            status_code INTEGER NOT NULL,
+            error_message TEXT,
+            is_streaming INTEGER NOT NULL DEFAULT 0,
+            cost_multiplier TEXT NOT NULL DEFAULT '1.0',
+            created_at INTEGER NOT NULL
+        )",
+        [],
+    )
+    .map_err(|e| AppError::Database(format!("创建 proxy_request_logs 表失败: {e}")))?;
+
+    // 创建索引
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_request_logs_provider 
+         ON proxy_request_logs(provider_id, app_type)",
+        [],
+    )
+    .map_err(|e| AppError::Database(format!("创建 provider 索引失败: {e}")))?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_request_logs_created_at 
+         ON proxy_request_logs(created_at)",
+        [],
+    )
+    .map_err(|e| AppError::Database(format!("创建 created_at 索引失败: {e}")))?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_request_logs_model 
+         ON proxy_request_logs(model)",
+        [],
+    )
+    .map_err(|e| AppError::Database(format!("创建 model 索引失败: {e}")))?;
+
+    // 2. 模型定价表（默认全局定价）
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS model_pricing (
+            model_id TEXT PRIMARY KEY,
+            display_name TEXT NOT NULL,
+            input_cost_per_million TEXT NOT NULL,
+            output_cost_per_million TEXT NOT NULL,
+            cache_read_cost_per_million TEXT NOT NULL DEFAULT '0',
+            cache_creation_cost_per_million TEXT NOT NULL DEFAULT '0'
+        )",
+        [],
+    )
+    .map_err(|e| AppError::Database(format!("创建 model_pricing 表失败: {e}")))?;
+
+    // 2.1 服务商特定模型定价表
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS provider_model_pricing (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            provider_id TEXT NOT NULL,
+            model_id TEXT NOT NULL,
+            input_cost_per_million TEXT NOT NULL,
+            output_cost_per_million TEXT NOT NULL,
+            cache_read_cost_per_million TEXT NOT NULL DEFAULT '0',
+            cache_creation_cost_per_million TEXT NOT NULL DEFAULT '0',
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+            UNIQUE(provider_id, model_id)
+        )",
+        [],
+    )
+    .map_err(|e| AppError::Database(format!("创建 provider_model_pricing 表失败: {e}")))?;
+
+    // 3. 代理配置表
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS proxy_config (
+            id INTEGER PRIMARY KEY CHECK (id = 1),
+            proxy_enabled INTEGER NOT NULL DEFAULT 0,
+            listen_address TEXT NOT NULL DEFAULT '127.0.0.1',
+            listen_port INTEGER NOT NULL DEFAULT 15721,
+            takeover_claude INTEGER NOT NULL DEFAULT 0,
+            takeover_codex INTEGER NOT NULL DEFAULT 0,
+            takeover_gemini INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+        )",
+        [],
+    )
+    .map_err(|e| AppError::Database(format!("创建 proxy_config 表失败: {e}")))?;
+
+    // 初始化代理配置
+    conn.execute(
+        "INSERT OR IGNORE INTO proxy_config (id) VALUES (1)",
+        [],
+    )
+    .map_err(|e| AppError::Database(format!("初始化代理配置失败: {e}")))?;
+
+    // 4. 配置备份表
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS proxy_live_backup (
+            app_type TEXT PRIMARY KEY,
+            original_config TEXT NOT NULL,
+            backed_up_at TEXT NOT NULL
+        )",
+        [],
+    )
+    .map_err(|e| AppError::Database(format!("创建 proxy_live_backup 表失败: {e}")))?;
+
+    // 5. 会话统计表
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS session_stats (
+            session_id TEXT PRIMARY KEY,
+            source TEXT NOT NULL,
+            provider_id TEXT,
+            conversation_count INTEGER NOT NULL DEFAULT 0,
+            tool_call_count INTEGER NOT NULL DEFAULT 0,
+            files_changed INTEGER NOT NULL DEFAULT 0,
+            lines_added INTEGER NOT NULL DEFAULT 0,
+            lines_deleted INTEGER NOT NULL DEFAULT 0,
+            response_time_ms INTEGER NOT NULL DEFAULT 0,
+            thinking_time_ms INTEGER NOT NULL DEFAULT 0,
+            created_at INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL
+        )",
+        [],
+    )
+    .map_err(|e| AppError::Database(format!("创建 session_stats 表失败: {e}")))?;
+
+    // 创建 session_stats 索引
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_session_stats_source 
+         ON session_stats(source)",
+        [],
+    )
+    .map_err(|e| AppError::Database(format!("创建 session_stats source 索引失败: {e}")))?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_session_stats_created_at 
+         ON session_stats(created_at)",
+        [],
+    )
+    .map_err(|e| AppError::Database(format!("创建 session_stats created_at 索引失败: {e}")))?;
+
+    // 6. 工具调用统计表
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS tool_calls (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            session_id TEXT NOT NULL,
+            tool_name TEXT NOT NULL,
+            call_count INTEGER NOT NULL DEFAULT 1,
+            created_at INTEGER NOT NULL,
+            FOREIGN KEY (session_id) REFERENCES session_stats(session_id)
+        )",
+        [],
+    )
+    .map_err(|e| AppError::Database(format!("创建 tool_calls 表失败: {e}")))?;
+
+    // 创建 tool_calls 索引
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_tool_calls_session 
+         ON tool_calls(session_id)",
+        [],
+    )
+    .map_err(|e| AppError::Database(format!("创建 tool_calls session 索引失败: {e}")))?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_tool_calls_tool_name 
+         ON tool_calls(tool_name)",
+        [],
+    )
+    .map_err(|e| AppError::Database(format!("创建 tool_calls tool_name 索引失败: {e}")))?;
+
+    Ok(())
+}
+
+/// v2: model_pricing 增加上下文窗口、来源和更新时间
+///
+/// source 取值：builtin（内置默认）、manual（手动编辑）、catalog（定价目录导入）
+fn v2_model_pricing_metadata(conn: &Connection) -> Result<(), AppError> {
+    conn.execute_batch(
+        "ALTER TABLE model_pricing ADD COLUMN max_input_tokens INTEGER;
+         ALTER TABLE model_pricing ADD COLUMN max_output_tokens INTEGER;
+         ALTER TABLE model_pricing ADD COLUMN source TEXT NOT NULL DEFAULT 'builtin';
+         ALTER TABLE model_pricing ADD COLUMN updated_at INTEGER;",
+    )
+    .map_err(|e| AppError::Database(format!("扩展 model_pricing 表失败: {e}")))?;
+    Ok(())
+}
+
+/// v3: 定价规则表，并内置常见模型的长上下文加价档
+fn v3_pricing_rules(conn: &Connection) -> Result<(), AppError> {
+    conn.execute_batch(
+        "CREATE TABLE pricing_rules (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            model_id TEXT NOT NULL,
+            provider_id TEXT,
+            min_prompt_tokens INTEGER NOT NULL DEFAULT 0,
+            time_start TEXT,
+            time_end TEXT,
+            effective_from INTEGER,
+            effective_to INTEGER,
+            input_cost_per_million TEXT NOT NULL,
+            output_cost_per_million TEXT NOT NULL,
+            cache_read_cost_per_million TEXT NOT NULL DEFAULT '0',
+            cache_creation_cost_per_million TEXT NOT NULL DEFAULT '0',
+            priority INTEGER NOT NULL DEFAULT 0,
+            note TEXT,
+            created_at INTEGER NOT NULL
+        );
+        CREATE INDEX idx_pricing_rules_model ON pricing_rules(model_id);",
+    )
+    .map_err(|e| AppError::Database(format!("创建 pricing_rules 表失败: {e}")))?;
+
+    let long_context_tiers = [
+        ("claude-sonnet-4-20250514", "6", "22.5", "0.60", "7.50"),
+        ("claude-sonnet-4-5-20250929", "6", "22.5", "0.60", "7.50"),
+        ("gemini-2.5-pro", "2.5", "15", "0.25", "0"),
+        ("gemini-3-pro-preview", "4", "18", "0.4", "0"),
+    ];
+    for (model_id, input, output, cache_read, cache_creation) in long_context_tiers {
+        conn.execute(
+            "INSERT INTO pricing_rules (
+                model_id, min_prompt_tokens, input_cost_per_million, output_cost_per_million,
+                cache_read_cost_per_million, cache_creation_cost_per_million, note, created_at
+            ) VALUES (?1, 200000, ?2, ?3, ?4, ?5, '超过 200K 输入 token 的长上下文价格', strftime('%s', 'now'))",
+            rusqlite::params![model_id, input, output, cache_read, cache_creation],
+        )
+        .map_err(|e| AppError::Database(format!("插入内置定价规则失败: {e}")))?;
+    }
+    Ok(())
+}
+
+/// v4: 定价版本号
+///
+/// model_pricing / provider_model_pricing / pricing_rules 任意变更时由触发器递增版本号，
+/// proxy_request_logs.pricing_version 记录每条记录计费时使用的版本（NULL 表示版本记录之前的数据）
+fn v4_pricing_version(conn: &Connection) -> Result<(), AppError> {
+    conn.execute_batch(
+        "CREATE TABLE pricing_version (
+            id INTEGER PRIMARY KEY CHECK (id = 1),
+            version INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL
+        );
+        INSERT INTO pricing_version (id, version, updated_at) VALUES (1, 1, strftime('%s', 'now'));
+        ALTER TABLE proxy_request_logs ADD COLUMN pricing_version INTEGER;",
+    )
+    .map_err(|e| AppError::Database(format!("创建 pricing_version 表失败: {e}")))?;
+
+    for table in ["model_pricing", "provider_model_pricing", "pricing_rules"] {
+        create_pricing_version_triggers(conn, table)?;
+    }
+    Ok(())
+}
+
+/// 为定价相关表创建递增定价版本的触发器
+fn create_pricing_version_triggers(conn: &Connection, table: &str) -> Result<(), AppError> {
+    for event in ["INSERT", "UPDATE", "DELETE"] {
+        conn.execute_batch(&format!(
+            "CREATE TRIGGER trg_{table}_{name}_version AFTER {event} ON {table}
+             BEGIN
+                 UPDATE pricing_version
+                 SET version = version + 1, updated_at = strftime('%s', 'now')
+                 WHERE id = 1;
+             END;",
+            name = event.to_lowercase(),
+        ))
+        .map_err(|e| AppError::Database(format!("创建 {table} 定价版本触发器失败: {e}")))?;
+    }
+    Ok(())
+}
+
+/// v5: 模型别名（将使用记录中的模型名绑定到已有定价）
+fn v5_model_aliases(conn: &Connection) -> Result<(), AppError> {
+    conn.execute_batch(
+        "CREATE TABLE model_aliases (
+            alias TEXT PRIMARY KEY,
+            model_id TEXT NOT NULL,
+            created_at INTEGER NOT NULL
+        );",
+    )
+    .map_err(|e| AppError::Database(format!("创建 model_aliases 表失败: {e}")))?;
+    create_pricing_version_triggers(conn, "model_aliases")
+}
+
+/// v6: 服务商计费货币与倍率、汇率表
+fn v6_provider_billing(conn: &Connection) -> Result<(), AppError> {
+    conn.execute_batch(
+        "CREATE TABLE provider_billing (
+            provider_id TEXT PRIMARY KEY,
+            currency TEXT NOT NULL DEFAULT 'USD',
+            cost_multiplier TEXT NOT NULL DEFAULT '1',
+            updated_at INTEGER NOT NULL
+        );
+        CREATE TABLE exchange_rates (
+            currency TEXT PRIMARY KEY,
+            rate TEXT NOT NULL,
+            updated_at INTEGER NOT NULL
+        );",
+    )
+    .map_err(|e| AppError::Database(format!("创建计费设置表失败: {e}")))?;
+    create_pricing_version_triggers(conn, "provider_billing")?;
+    create_pricing_version_triggers(conn, "exchange_rates")
+}
+
+/// v7: proxy_request_logs 增加项目（git 仓库根目录或工作目录），NULL 表示未知
+fn v7_request_project(conn: &Connection) -> Result<(), AppError> {
+    conn.execute_batch(
+        "ALTER TABLE proxy_request_logs ADD COLUMN project TEXT;
+         CREATE INDEX idx_proxy_logs_project ON proxy_request_logs(project);",
+    )
+    .map_err(|e| AppError::Database(format!("增加项目字段失败: {e}")))?;
+    Ok(())
+}
+
+/// v8: 本地日志每个文件的导入位置（大小、修改时间、已解析偏移和解析上下文）
+fn v8_local_import_state(conn: &Connection) -> Result<(), AppError> {
+    conn.execute_batch(
+        "CREATE TABLE local_import_state (
+            path TEXT PRIMARY KEY,
+            source TEXT NOT NULL,
+            size INTEGER NOT NULL,
+            modified_at INTEGER NOT NULL,
+            offset INTEGER NOT NULL DEFAULT 0,
+            context TEXT,
+            updated_at INTEGER NOT NULL
+        );
+        CREATE INDEX idx_local_import_state_source ON local_import_state(source);",
+    )
+    .map_err(|e| AppError::Database(format!("创建本地导入状态表失败: {e}")))?;
+    Ok(())
+}
+
+/// v9: proxy_request_logs 增加会话 ID，NULL 表示未知
+///
+/// 本地导入的记录 ID 改为逐条稳定 ID，清空导入位置后下次导入会重新解析全部文件，
+/// 并把旧记录就地升级为新 ID（见 `local_logs::ExistingRecords`）
+fn v9_request_session(conn: &Connection) -> Result<(), AppError> {
+    conn.execute_batch(
+        "ALTER TABLE proxy_request_logs ADD COLUMN session_id TEXT;
+         CREATE INDEX idx_proxy_logs_session ON proxy_request_logs(session_id);
+         DELETE FROM local_import_state;",
+    )
+    .map_err(|e| AppError::Database(format!("增加会话 ID 字段失败: {e}")))?;
+    Ok(())
+}
+
+/// 没有用量数据的来源（Cursor 等 VSCode 系工具按文本估算，Warp 按比例拆分输入输出）的记录标记为估算，
+/// 并清除 Cursor 系来源的导入状态，下次导入时按新的分词方式重新估算
+fn v10_tokens_estimated(conn: &Connection) -> Result<(), AppError> {
+    conn.execute_batch(
+        "ALTER TABLE proxy_request_logs ADD COLUMN tokens_estimated INTEGER NOT NULL DEFAULT 0;
+         UPDATE proxy_request_logs SET tokens_estimated = 1
+         WHERE app_type IN ('cursor_local', 'windsurf_local', 'kiro_local', 'antigravity_local', 'augment_local')
+            OR (app_type = 'warp_local' AND input_tokens + output_tokens > 0);
+         DELETE FROM local_import_state
+         WHERE source IN ('cursor', 'windsurf', 'kiro', 'antigravity', 'augment');",
+    )
+    .map_err(|e| AppError::Database(format!("增加 token 估算标记失败: {e}")))?;
+    Ok(())
+}
+
+/// v11: 之前每次保存会话统计都追加一份工具调用明细，只保留每个会话每个工具最新的一条，
+/// 并按 (session_id, tool_name) 建立唯一索引
+fn v11_tool_calls_unique(conn: &Connection) -> Result<(), AppError> {
+    conn.execute_batch(
+        "DELETE FROM tool_calls WHERE id NOT IN (
+             SELECT MAX(id) FROM tool_calls GROUP BY session_id, tool_name
+         );
+         CREATE UNIQUE INDEX idx_tool_calls_session_tool ON tool_calls(session_id, tool_name);",
+    )
+    .map_err(|e| AppError::Database(format!("工具调用统计去重失败: {e}")))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::SCHEMA_VERSION;
+
+    /// 按名称排序的表/索引定义
+    fn schema_snapshot(conn: &Connection) -> Vec<(String, String, Option<String>)> {
+        let mut stmt = conn
+            .prepare(
+                "SELECT type, name, sql FROM sqlite_master
+                 WHERE name NOT LIKE 'sqlite_%' ORDER BY type, name",
+            )
+            .unwrap();
+        stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect()
+    }
+
+    /// 只执行到指定版本，模拟历史版本的数据库
+    fn build_at_version(version: i32) -> Connection {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let upto: Vec<Migration> = MIGRATIONS
+            .iter()
+            .filter(|m| m.version <= version)
+            .map(|m| Migration {
+                version: m.version,
+                description: m.description,
+                up: m.up,
+            })
+            .collect();
+        run(&mut conn, &upto, None).unwrap();
+        assert_eq!(Database::get_user_version(&conn).unwrap(), version);
+        conn
+    }
+
+    #[test]
+    fn schema_version_matches_latest_migration() {
+        assert_eq!(MIGRATIONS.last().unwrap().version, SCHEMA_VERSION);
+        for (i, m) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(m.version, i as i32 + 1, "迁移版本号必须从 1 开始连续递增");
+        }
+    }
+
+    #[test]
+    fn every_historic_version_migrates_to_latest() {
+        let mut fresh = Connection::open_in_memory().unwrap();
+        run(&mut fresh, MIGRATIONS, None).unwrap();
+        let expected = schema_snapshot(&fresh);
+
+        for version in 0..=SCHEMA_VERSION {
+            let mut conn = build_at_version(version);
+            if version >= 1 {
+                // v1 起即存在的数据在迁移后必须保留
+                conn.execute(
+                    "INSERT INTO proxy_request_logs (
+                        request_id, provider_id, app_type, model,
+                        latency_ms, status_code, created_at
+                    ) VALUES ('historic', 'p', 'claude', 'm', 1, 200, 1700000000)",
+                    [],
+                )
+                .unwrap();
+            }
+
+            run(&mut conn, MIGRATIONS, None).unwrap();
+
+            assert_eq!(Database::get_user_version(&conn).unwrap(), SCHEMA_VERSION);
+            assert_eq!(schema_snapshot(&conn), expected, "v{version} 迁移后表结构不一致");
+            if version >= 1 {
+                let count: i64 = conn
+                    .query_row(
+                        "SELECT COUNT(*) FROM proxy_request_logs WHERE request_id = 'historic'",
+                        [],
+                        |row| row.get(0),
+                    )
+                    .unwrap();
+                assert_eq!(count, 1, "v{version} 迁移后数据丢失");
+            }
+        }
+    }
+
+    #[test]
+    fn v11_keeps_latest_tool_call_per_session_and_tool() {
+        let mut conn = build_at_version(10);
+        conn.execute_batch(
+            "INSERT INTO tool_calls (session_id, tool_name, call_count, created_at) VALUES
+                 ('s1', 'edit', 1, 1000), ('s1', 'edit', 3, 1200), ('s1', 'read', 2, 1000);",
+        )
+        .unwrap();
+
+        run(&mut conn, MIGRATIONS, None).unwrap();
+
+        let mut stmt = conn
+            .prepare("SELECT tool_name, call_count FROM tool_calls ORDER BY tool_name")
+            .unwrap();
+        let rows: Vec<(String, i64)> = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(rows, vec![("edit".to_string(), 3), ("read".to_string(), 2)]);
+    }
+
+    #[test]
+    fn refuses_newer_database() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        Database::set_user_version(&conn, SCHEMA_VERSION + 1).unwrap();
+        assert!(run(&mut conn, MIGRATIONS, None).is_err());
+        assert_eq!(schema_snapshot(&conn), Vec::new());
+    }
+
+    #[test]
+    fn failed_migration_rolls_back() {
+        fn broken(conn: &Connection) -> Result<(), AppError> {
+            conn.execute("CREATE TABLE half_done (id INTEGER)", [])
+                .map_err(|e| AppError::Database(e.to_string()))?;
+            Err(AppError::Database("boom".to_string()))
+        }
+        let migrations = [
+            Migration { version: 1, description: "初始表结构", up: v1_initial_schema },
+            Migration { version: 2, description: "broken", up: broken },
+        ];
+
+        let mut conn = Connection::open_in_memory().unwrap();
+        assert!(run(&mut conn, &migrations, None).is_err());
+        assert_eq!(Database::get_user_version(&conn).unwrap(), 1);
+        let exists: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE name = 'half_done'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(exists, 0);
+    }
+
+    #[test]
+    fn backs_up_existing_database_before_migrating() {
+        fn add_table(conn: &Connection) -> Result<(), AppError> {
+            conn.execute("CREATE TABLE extra (id INTEGER)", [])
+                .map_err(|e| AppError::Database(e.to_string()))?;
+            Ok(())
+        }
+
+        let dir = std::env::temp_dir().join(format!("ai-switch-migrate-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db_path = dir.join("ai-switch.db");
+
+        let mut conn = Connection::open(&db_path).unwrap();
+        let v1 = [Migration { version: 1, description: "初始表结构", up: v1_initial_schema }];
+        run(&mut conn, &v1, Some(&db_path)).unwrap();
+        // 全新数据库不需要备份
+        assert!(!dir.join("db-backups").exists());
+
+        let v2 = [
+            Migration { version: 1, description: "初始表结构", up: v1_initial_schema },
+            Migration { version: 2, description: "extra", up: add_table },
+        ];
+        run(&mut conn, &v2, Some(&db_path)).unwrap();
+
+        let backups: Vec<_> = std::fs::read_dir(dir.join("db-backups"))
+            .unwrap()
+            .flatten()
+            .collect();
+        assert_eq!(backups.len(), 1);
+        let name = backups[0].file_name().to_string_lossy().to_string();
+        assert!(name.starts_with("ai-switch-v1-"), "{name}");
+
+        let backup = Connection::open(backups[0].path()).unwrap();
+        assert_eq!(Database::get_user_version(&backup).unwrap(), 1);
+
+        drop(conn);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
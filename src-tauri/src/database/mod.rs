@@ -1,20 +1,36 @@
 //! 数据库模块
 //!
 //! 提供 SQLite 数据库连接和操作
+//!
+//! 文件数据库使用 WAL 模式：一个写连接（`conn`）负责所有写入，
+//! 另有一组只读连接供统计查询使用，读写互不阻塞
 
+pub(crate) mod migrations;
 pub mod schema;
+pub mod usage_records;
 
 use crate::error::AppError;
-use rusqlite::Connection;
-use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
+use rusqlite::{Connection, OpenFlags};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, TryLockError};
+
+/// 数据库版本号（等于 migrations::MIGRATIONS 中最后一个迁移的版本）
+pub const SCHEMA_VERSION: i32 = 11;
 
-/// 数据库版本号
-pub const SCHEMA_VERSION: i32 = 1;
+/// 只读连接数量
+const READER_COUNT: usize = 4;
+
+/// 等待其他连接释放写锁的最长时间（毫秒）
+const BUSY_TIMEOUT_MS: u32 = 5000;
 
 /// 数据库连接封装
 pub struct Database {
+    /// 写连接（所有写入都经由此连接）
     pub conn: Arc<Mutex<Connection>>,
+    /// 只读连接池（内存数据库为空，此时读取回退到写连接）
+    readers: Vec<Mutex<Connection>>,
+    next_reader: AtomicUsize,
 }
 
 /// 获取数据库锁的宏
@@ -42,19 +58,81 @@ impl Database {
         let db_path = Self::get_db_path()?;
         let conn = Connection::open(&db_path)
             .map_err(|e| AppError::Database(format!("打开数据库失败: {e}")))?;
+        Self::configure_writer(&conn)?;
         
-        let db = Self {
+        let mut db = Self {
             conn: Arc::new(Mutex::new(conn)),
+            readers: Vec::new(),
+            next_reader: AtomicUsize::new(0),
         };
         
-        // 创建表和应用迁移
-        db.create_tables()?;
-        db.apply_migrations()?;
+        // 应用迁移（迁移前自动备份）
+        db.apply_migrations(Some(&db_path))?;
         db.ensure_model_pricing_seeded()?;
+
+        // 导入旧版 JSON 使用记录（失败时保留原文件，下次启动重试）
+        let legacy_path = db_path.with_file_name(usage_records::LEGACY_USAGE_FILE);
+        if let Err(e) = db.migrate_legacy_usage_file(&legacy_path) {
+            eprintln!("迁移旧版使用统计失败: {e}");
+        }
+
+        // 迁移完成后再打开只读连接，保证其看到最新表结构
+        db.readers = (0..READER_COUNT)
+            .map(|_| Self::open_reader(&db_path).map(Mutex::new))
+            .collect::<Result<_, _>>()?;
         
         Ok(db)
     }
 
+    /// 写连接：启用 WAL，使读取不被写入阻塞
+    fn configure_writer(conn: &Connection) -> Result<(), AppError> {
+        let mode: String = conn
+            .query_row("PRAGMA journal_mode = WAL", [], |row| row.get(0))
+            .map_err(|e| AppError::Database(format!("启用 WAL 失败: {e}")))?;
+        if !mode.eq_ignore_ascii_case("wal") {
+            eprintln!("数据库未能切换到 WAL 模式，当前模式: {mode}");
+        }
+        conn.execute_batch(&format!(
+            "PRAGMA synchronous = NORMAL; PRAGMA busy_timeout = {BUSY_TIMEOUT_MS};"
+        ))
+        .map_err(|e| AppError::Database(format!("设置数据库参数失败: {e}")))?;
+        Ok(())
+    }
+
+    /// 打开只读连接
+    fn open_reader(db_path: &Path) -> Result<Connection, AppError> {
+        let conn = Connection::open_with_flags(
+            db_path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )
+        .map_err(|e| AppError::Database(format!("打开只读连接失败: {e}")))?;
+        conn.execute_batch(&format!("PRAGMA busy_timeout = {BUSY_TIMEOUT_MS};"))
+            .map_err(|e| AppError::Database(format!("设置数据库参数失败: {e}")))?;
+        Ok(conn)
+    }
+
+    /// 获取一个只读连接
+    ///
+    /// 优先取空闲连接，全部繁忙时排队等待；没有只读连接时回退到写连接
+    pub fn read_conn(&self) -> Result<MutexGuard<'_, Connection>, AppError> {
+        if self.readers.is_empty() {
+            return Ok(lock_conn!(self.conn));
+        }
+
+        let start = self.next_reader.fetch_add(1, Ordering::Relaxed) % self.readers.len();
+        for offset in 0..self.readers.len() {
+            let reader = &self.readers[(start + offset) % self.readers.len()];
+            match reader.try_lock() {
+                Ok(guard) => return Ok(guard),
+                Err(TryLockError::WouldBlock) => continue,
+                Err(TryLockError::Poisoned(e)) => {
+                    return Err(AppError::Database(format!("获取数据库锁失败: {e}")))
+                }
+            }
+        }
+        Ok(lock_conn!(self.readers[start]))
+    }
+
     /// 创建内存数据库（用于测试）
     #[allow(dead_code)]
     pub fn memory() -> Result<Self, AppError> {
@@ -63,9 +141,11 @@ impl Database {
         
         let db = Self {
             conn: Arc::new(Mutex::new(conn)),
+            readers: Vec::new(),
+            next_reader: AtomicUsize::new(0),
         };
         
-        db.create_tables()?;
+        db.apply_migrations(None)?;
         db.ensure_model_pricing_seeded()?;
         
         Ok(db)
@@ -1,224 +1,19 @@
-//! 数据库 Schema 定义和迁移
+//! 数据库迁移入口、默认数据和查询方法
 //!
-//! 负责数据库表结构的创建和版本迁移
+//! 表结构定义及版本演进见 migrations.rs
 
-use super::{lock_conn, Database, SCHEMA_VERSION};
+use super::{lock_conn, migrations, Database};
 use crate::error::AppError;
 use rusqlite::Connection;
+use std::path::Path;
 
 impl Database {
-    /// 创建所有数据库表
-    pub(crate) fn create_tables(&self) -> Result<(), AppError> {
-        let conn = lock_conn!(self.conn);
-        Self::create_tables_on_conn(&conn)
-    }
-
-    /// 在指定连接上创建表
-    pub(crate) fn create_tables_on_conn(conn: &Connection) -> Result<(), AppError> {
-        // 1. 代理请求日志表
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS proxy_request_logs (
-                request_id TEXT PRIMARY KEY,
-                provider_id TEXT NOT NULL,
-                provider_name TEXT,
-                app_type TEXT NOT NULL,
-                model TEXT NOT NULL,
-                request_model TEXT,
-                input_tokens INTEGER NOT NULL DEFAULT 0,
-                output_tokens INTEGER NOT NULL DEFAULT 0,
-                cache_read_tokens INTEGER NOT NULL DEFAULT 0,
-                cache_creation_tokens INTEGER NOT NULL DEFAULT 0,
-                input_cost_usd TEXT NOT NULL DEFAULT '0',
-                output_cost_usd TEXT NOT NULL DEFAULT '0',
-                cache_read_cost_usd TEXT NOT NULL DEFAULT '0',
-                cache_creation_cost_usd TEXT NOT NULL DEFAULT '0',
-                total_cost_usd TEXT NOT NULL DEFAULT '0',
-                latency_ms INTEGER NOT NULL,
-                first_token_ms INTEGER,
-                status_code INTEGER NOT NULL,
-                error_message TEXT,
-                is_streaming INTEGER NOT NULL DEFAULT 0,
-                cost_multiplier TEXT NOT NULL DEFAULT '1.0',
-                created_at INTEGER NOT NULL
-            )",
-            [],
-        )
-        .map_err(|e| AppError::Database(format!("创建 proxy_request_logs 表失败: {e}")))?;
-
-        // 创建索引
-        conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_request_logs_provider 
-             ON proxy_request_logs(provider_id, app_type)",
-            [],
-        )
-        .map_err(|e| AppError::Database(format!("创建 provider 索引失败: {e}")))?;
-
-        conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_request_logs_created_at 
-             ON proxy_request_logs(created_at)",
-            [],
-        )
-        .map_err(|e| AppError::Database(format!("创建 created_at 索引失败: {e}")))?;
-
-        conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_request_logs_model 
-             ON proxy_request_logs(model)",
-            [],
-        )
-        .map_err(|e| AppError::Database(format!("创建 model 索引失败: {e}")))?;
-
-        // 2. 模型定价表（默认全局定价）
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS model_pricing (
-                model_id TEXT PRIMARY KEY,
-                display_name TEXT NOT NULL,
-                input_cost_per_million TEXT NOT NULL,
-                output_cost_per_million TEXT NOT NULL,
-                cache_read_cost_per_million TEXT NOT NULL DEFAULT '0',
-                cache_creation_cost_per_million TEXT NOT NULL DEFAULT '0'
-            )",
-            [],
-        )
-        .map_err(|e| AppError::Database(format!("创建 model_pricing 表失败: {e}")))?;
-
-        // 2.1 服务商特定模型定价表
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS provider_model_pricing (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                provider_id TEXT NOT NULL,
-                model_id TEXT NOT NULL,
-                input_cost_per_million TEXT NOT NULL,
-                output_cost_per_million TEXT NOT NULL,
-                cache_read_cost_per_million TEXT NOT NULL DEFAULT '0',
-                cache_creation_cost_per_million TEXT NOT NULL DEFAULT '0',
-                created_at TEXT NOT NULL DEFAULT (datetime('now')),
-                updated_at TEXT NOT NULL DEFAULT (datetime('now')),
-                UNIQUE(provider_id, model_id)
-            )",
-            [],
-        )
-        .map_err(|e| AppError::Database(format!("创建 provider_model_pricing 表失败: {e}")))?;
-
-        // 3. 代理配置表
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS proxy_config (
-                id INTEGER PRIMARY KEY CHECK (id = 1),
-                proxy_enabled INTEGER NOT NULL DEFAULT 0,
-                listen_address TEXT NOT NULL DEFAULT '127.0.0.1',
-                listen_port INTEGER NOT NULL DEFAULT 15721,
-                takeover_claude INTEGER NOT NULL DEFAULT 0,
-                takeover_codex INTEGER NOT NULL DEFAULT 0,
-                takeover_gemini INTEGER NOT NULL DEFAULT 0,
-                created_at TEXT NOT NULL DEFAULT (datetime('now')),
-                updated_at TEXT NOT NULL DEFAULT (datetime('now'))
-            )",
-            [],
-        )
-        .map_err(|e| AppError::Database(format!("创建 proxy_config 表失败: {e}")))?;
-
-        // 初始化代理配置
-        conn.execute(
-            "INSERT OR IGNORE INTO proxy_config (id) VALUES (1)",
-            [],
-        )
-        .map_err(|e| AppError::Database(format!("初始化代理配置失败: {e}")))?;
-
-        // 4. 配置备份表
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS proxy_live_backup (
-                app_type TEXT PRIMARY KEY,
-                original_config TEXT NOT NULL,
-                backed_up_at TEXT NOT NULL
-            )",
-            [],
-        )
-        .map_err(|e| AppError::Database(format!("创建 proxy_live_backup 表失败: {e}")))?;
-
-        // 5. 会话统计表
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS session_stats (
-                session_id TEXT PRIMARY KEY,
-                source TEXT NOT NULL,
-                provider_id TEXT,
-                conversation_count INTEGER NOT NULL DEFAULT 0,
-                tool_call_count INTEGER NOT NULL DEFAULT 0,
-                files_changed INTEGER NOT NULL DEFAULT 0,
-                lines_added INTEGER NOT NULL DEFAULT 0,
-                lines_deleted INTEGER NOT NULL DEFAULT 0,
-                response_time_ms INTEGER NOT NULL DEFAULT 0,
-                thinking_time_ms INTEGER NOT NULL DEFAULT 0,
-                created_at INTEGER NOT NULL,
-                updated_at INTEGER NOT NULL
-            )",
-            [],
-        )
-        .map_err(|e| AppError::Database(format!("创建 session_stats 表失败: {e}")))?;
-
-        // 创建 session_stats 索引
-        conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_session_stats_source 
-             ON session_stats(source)",
-            [],
-        )
-        .map_err(|e| AppError::Database(format!("创建 session_stats source 索引失败: {e}")))?;
-
-        conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_session_stats_created_at 
-             ON session_stats(created_at)",
-            [],
-        )
-        .map_err(|e| AppError::Database(format!("创建 session_stats created_at 索引失败: {e}")))?;
-
-        // 6. 工具调用统计表
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS tool_calls (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                session_id TEXT NOT NULL,
-                tool_name TEXT NOT NULL,
-                call_count INTEGER NOT NULL DEFAULT 1,
-                created_at INTEGER NOT NULL,
-                FOREIGN KEY (session_id) REFERENCES session_stats(session_id)
-            )",
-            [],
-        )
-        .map_err(|e| AppError::Database(format!("创建 tool_calls 表失败: {e}")))?;
-
-        // 创建 tool_calls 索引
-        conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_tool_calls_session 
-             ON tool_calls(session_id)",
-            [],
-        )
-        .map_err(|e| AppError::Database(format!("创建 tool_calls session 索引失败: {e}")))?;
-
-        conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_tool_calls_tool_name 
-             ON tool_calls(tool_name)",
-            [],
-        )
-        .map_err(|e| AppError::Database(format!("创建 tool_calls tool_name 索引失败: {e}")))?;
-
-        Ok(())
-    }
-
     /// 应用数据库迁移
-    pub(crate) fn apply_migrations(&self) -> Result<(), AppError> {
-        let conn = lock_conn!(self.conn);
-        
-        let version = Self::get_user_version(&conn)?;
-        
-        if version > SCHEMA_VERSION {
-            return Err(AppError::Database(format!(
-                "数据库版本过新（{version}），当前应用仅支持 {SCHEMA_VERSION}"
-            )));
-        }
-
-        if version < SCHEMA_VERSION {
-            // 执行迁移
-            Self::set_user_version(&conn, SCHEMA_VERSION)?;
-        }
-
-        Ok(())
+    ///
+    /// `backup_path` 为数据库文件路径时，迁移前会自动备份（内存数据库传 None）
+    pub(crate) fn apply_migrations(&self, backup_path: Option<&Path>) -> Result<(), AppError> {
+        let mut conn = lock_conn!(self.conn);
+        migrations::run(&mut conn, migrations::MIGRATIONS, backup_path)
     }
 
     /// 确保模型定价数据已初始化
@@ -288,12 +83,12 @@ impl Database {
 
     // --- 辅助方法 ---
 
-    fn get_user_version(conn: &Connection) -> Result<i32, AppError> {
+    pub(crate) fn get_user_version(conn: &Connection) -> Result<i32, AppError> {
         conn.query_row("PRAGMA user_version;", [], |row| row.get(0))
             .map_err(|e| AppError::Database(format!("读取 user_version 失败: {e}")))
     }
 
-    fn set_user_version(conn: &Connection, version: i32) -> Result<(), AppError> {
+    pub(crate) fn set_user_version(conn: &Connection, version: i32) -> Result<(), AppError> {
         let sql = format!("PRAGMA user_version = {version};");
         conn.execute(&sql, [])
             .map_err(|e| AppError::Database(format!("写入 user_version 失败: {e}")))?;
@@ -318,6 +113,10 @@ pub struct UsageSummary {
     pub total_output_tokens: u64,
     pub total_cache_creation_tokens: u64,
     pub total_cache_read_tokens: u64,
+    /// 来自用量数据的 token（输入 + 输出 + 缓存）
+    pub measured_tokens: u64,
+    /// 按文本估算的 token（没有用量数据的本地日志）
+    pub estimated_tokens: u64,
     pub success_rate: f32,
 }
 
@@ -379,10 +178,29 @@ pub struct ProviderStats {
     pub total_output_tokens: u64,
     pub total_cache_creation_tokens: u64,
     pub total_cache_read_tokens: u64,
+    /// 按文本估算的 token（输入 + 输出 + 缓存）
+    pub estimated_tokens: u64,
     pub total_cost: String,
     pub success_rate: f32,
 }
 
+/// 项目统计
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectStats {
+    /// 项目路径，None 表示未归属项目的记录
+    pub project: Option<String>,
+    /// 显示名称（路径最后一段）
+    pub project_name: String,
+    pub request_count: u64,
+    pub total_tokens: u64,
+    pub total_input_tokens: u64,
+    pub total_output_tokens: u64,
+    pub total_cache_creation_tokens: u64,
+    pub total_cache_read_tokens: u64,
+    pub total_cost: String,
+}
+
 /// 代理配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -433,23 +251,51 @@ impl Default for ProxyConfigDb {
     }
 }
 
-impl Database {
-    /// 获取使用量汇总
-    pub fn get_usage_summary(&self, start_ts: Option<i64>, end_ts: Option<i64>) -> Result<UsageSummary, AppError> {
-        let conn = lock_conn!(self.conn);
+/// 使用记录的通用筛选条件，返回 (WHERE 子句, 参数)
+fn usage_filter(
+    start_ts: Option<i64>,
+    end_ts: Option<i64>,
+    provider_id: Option<&str>,
+    project: Option<&str>,
+) -> (String, Vec<rusqlite::types::Value>) {
+    let mut conditions: Vec<&str> = Vec::new();
+    let mut params: Vec<rusqlite::types::Value> = Vec::new();
+
+    if let Some(start) = start_ts {
+        conditions.push("created_at >= ?");
+        params.push(start.into());
+    }
+    if let Some(end) = end_ts {
+        conditions.push("created_at <= ?");
+        params.push(end.into());
+    }
+    if let Some(pid) = provider_id {
+        conditions.push("provider_id = ?");
+        params.push(pid.to_string().into());
+    }
+    if let Some(project) = project {
+        conditions.push("project = ?");
+        params.push(project.to_string().into());
+    }
 
-        let (where_clause, params): (String, Vec<i64>) = match (start_ts, end_ts) {
-            (Some(start), Some(end)) => {
-                ("WHERE created_at >= ?1 AND created_at <= ?2".to_string(), vec![start, end])
-            }
-            (Some(start), None) => {
-                ("WHERE created_at >= ?1".to_string(), vec![start])
-            }
-            (None, Some(end)) => {
-                ("WHERE created_at <= ?1".to_string(), vec![end])
-            }
-            (None, None) => (String::new(), vec![]),
-        };
+    let where_clause = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", conditions.join(" AND "))
+    };
+    (where_clause, params)
+}
+
+impl Database {
+    /// 获取使用量汇总（可按项目筛选）
+    pub fn get_usage_summary(
+        &self,
+        start_ts: Option<i64>,
+        end_ts: Option<i64>,
+        project: Option<&str>,
+    ) -> Result<UsageSummary, AppError> {
+        let conn = self.read_conn()?;
+        let (where_clause, params) = usage_filter(start_ts, end_ts, None, project);
 
         let sql = format!(
             "SELECT
@@ -459,92 +305,43 @@ impl Database {
                 COALESCE(SUM(output_tokens), 0) as total_output_tokens,
                 COALESCE(SUM(cache_creation_tokens), 0) as total_cache_creation_tokens,
                 COALESCE(SUM(cache_read_tokens), 0) as total_cache_read_tokens,
-                COALESCE(SUM(CASE WHEN status_code >= 200 AND status_code < 300 THEN 1 ELSE 0 END), 0) as success_count
+                COALESCE(SUM(CASE WHEN status_code >= 200 AND status_code < 300 THEN 1 ELSE 0 END), 0) as success_count,
+                COALESCE(SUM(CASE WHEN tokens_estimated = 1
+                    THEN input_tokens + output_tokens + cache_read_tokens + cache_creation_tokens ELSE 0 END), 0) as estimated_tokens
             FROM proxy_request_logs
             {where_clause}"
         );
 
-        let result = if params.is_empty() {
-            conn.query_row(&sql, [], |row| {
-                let total_requests: i64 = row.get(0)?;
-                let total_cost: f64 = row.get(1)?;
-                let total_input_tokens: i64 = row.get(2)?;
-                let total_output_tokens: i64 = row.get(3)?;
-                let total_cache_creation_tokens: i64 = row.get(4)?;
-                let total_cache_read_tokens: i64 = row.get(5)?;
-                let success_count: i64 = row.get(6)?;
-
-                let success_rate = if total_requests > 0 {
-                    (success_count as f32 / total_requests as f32) * 100.0
-                } else {
-                    0.0
-                };
-
-                Ok(UsageSummary {
-                    total_requests: total_requests as u64,
-                    total_cost: format!("{total_cost:.6}"),
-                    total_input_tokens: total_input_tokens as u64,
-                    total_output_tokens: total_output_tokens as u64,
-                    total_cache_creation_tokens: total_cache_creation_tokens as u64,
-                    total_cache_read_tokens: total_cache_read_tokens as u64,
-                    success_rate,
-                })
-            })
-        } else if params.len() == 1 {
-            conn.query_row(&sql, [params[0]], |row| {
-                let total_requests: i64 = row.get(0)?;
-                let total_cost: f64 = row.get(1)?;
-                let total_input_tokens: i64 = row.get(2)?;
-                let total_output_tokens: i64 = row.get(3)?;
-                let total_cache_creation_tokens: i64 = row.get(4)?;
-                let total_cache_read_tokens: i64 = row.get(5)?;
-                let success_count: i64 = row.get(6)?;
-
-                let success_rate = if total_requests > 0 {
-                    (success_count as f32 / total_requests as f32) * 100.0
-                } else {
-                    0.0
-                };
-
-                Ok(UsageSummary {
-                    total_requests: total_requests as u64,
-                    total_cost: format!("{total_cost:.6}"),
-                    total_input_tokens: total_input_tokens as u64,
-                    total_output_tokens: total_output_tokens as u64,
-                    total_cache_creation_tokens: total_cache_creation_tokens as u64,
-                    total_cache_read_tokens: total_cache_read_tokens as u64,
-                    success_rate,
-                })
-            })
-        } else {
-            conn.query_row(&sql, [params[0], params[1]], |row| {
-                let total_requests: i64 = row.get(0)?;
-                let total_cost: f64 = row.get(1)?;
-                let total_input_tokens: i64 = row.get(2)?;
-                let total_output_tokens: i64 = row.get(3)?;
-                let total_cache_creation_tokens: i64 = row.get(4)?;
-                let total_cache_read_tokens: i64 = row.get(5)?;
-                let success_count: i64 = row.get(6)?;
-
-                let success_rate = if total_requests > 0 {
-                    (success_count as f32 / total_requests as f32) * 100.0
-                } else {
-                    0.0
-                };
-
-                Ok(UsageSummary {
-                    total_requests: total_requests as u64,
-                    total_cost: format!("{total_cost:.6}"),
-                    total_input_tokens: total_input_tokens as u64,
-                    total_output_tokens: total_output_tokens as u64,
-                    total_cache_creation_tokens: total_cache_creation_tokens as u64,
-                    total_cache_read_tokens: total_cache_read_tokens as u64,
-                    success_rate,
-                })
-            })
-        };
+        conn.query_row(&sql, rusqlite::params_from_iter(params), |row| {
+            let total_requests: i64 = row.get(0)?;
+            let total_cost: f64 = row.get(1)?;
+            let total_input_tokens: i64 = row.get(2)?;
+            let total_output_tokens: i64 = row.get(3)?;
+            let total_cache_creation_tokens: i64 = row.get(4)?;
+            let total_cache_read_tokens: i64 = row.get(5)?;
+            let success_count: i64 = row.get(6)?;
+            let estimated_tokens = row.get::<_, i64>(7)? as u64;
+            let total_tokens = (total_input_tokens + total_output_tokens + total_cache_creation_tokens + total_cache_read_tokens) as u64;
+
+            let success_rate = if total_requests > 0 {
+                (success_count as f32 / total_requests as f32) * 100.0
+            } else {
+                0.0
+            };
 
-        result.map_err(|e| AppError::Database(format!("查询使用量汇总失败: {e}")))
+            Ok(UsageSummary {
+                total_requests: total_requests as u64,
+                total_cost: format!("{total_cost:.6}"),
+                total_input_tokens: total_input_tokens as u64,
+                total_output_tokens: total_output_tokens as u64,
+                total_cache_creation_tokens: total_cache_creation_tokens as u64,
+                total_cache_read_tokens: total_cache_read_tokens as u64,
+                measured_tokens: total_tokens.saturating_sub(estimated_tokens),
+                estimated_tokens,
+                success_rate,
+            })
+        })
+        .map_err(|e| AppError::Database(format!("查询使用量汇总失败: {e}")))
     }
 
     /// 获取使用趋势
@@ -554,8 +351,9 @@ impl Database {
         end_ts: Option<i64>,
         period: &str,
         provider_id: Option<&str>,
+        project: Option<&str>,
     ) -> Result<Vec<UsageTrend>, AppError> {
-        let conn = lock_conn!(self.conn);
+        let conn = self.read_conn()?;
 
         // 根据时间段决定分组粒度
         let (group_format, _date_format) = match period {
@@ -566,27 +364,7 @@ impl Database {
             _ => ("%Y-%m-%d", "day"),
         };
 
-        let mut conditions: Vec<String> = Vec::new();
-        let mut params: Vec<rusqlite::types::Value> = Vec::new();
-
-        if let Some(start) = start_ts {
-            conditions.push("created_at >= ?".to_string());
-            params.push(start.into());
-        }
-        if let Some(end) = end_ts {
-            conditions.push("created_at <= ?".to_string());
-            params.push(end.into());
-        }
-        if let Some(pid) = provider_id {
-            conditions.push("provider_id = ?".to_string());
-            params.push(pid.to_string().into());
-        }
-
-        let where_clause = if conditions.is_empty() {
-            String::new()
-        } else {
-            format!("WHERE {}", conditions.join(" AND "))
-        };
+        let (where_clause, params) = usage_filter(start_ts, end_ts, provider_id, project);
 
         let sql = format!(
             "SELECT
@@ -659,8 +437,9 @@ impl Database {
         end_ts: Option<i64>,
         period: &str,
         provider_id: Option<&str>,
+        project: Option<&str>,
     ) -> Result<Vec<ModelTrendData>, AppError> {
-        let conn = lock_conn!(self.conn);
+        let conn = self.read_conn()?;
 
         // 根据时间段决定分组粒度
         let group_format = match period {
@@ -671,27 +450,7 @@ impl Database {
             _ => "%Y-%m-%d",
         };
 
-        let mut conditions: Vec<String> = Vec::new();
-        let mut params: Vec<rusqlite::types::Value> = Vec::new();
-
-        if let Some(start) = start_ts {
-            conditions.push("created_at >= ?".to_string());
-            params.push(start.into());
-        }
-        if let Some(end) = end_ts {
-            conditions.push("created_at <= ?".to_string());
-            params.push(end.into());
-        }
-        if let Some(pid) = provider_id {
-            conditions.push("provider_id = ?".to_string());
-            params.push(pid.to_string().into());
-        }
-
-        let where_clause = if conditions.is_empty() {
-            String::new()
-        } else {
-            format!("WHERE {}", conditions.join(" AND "))
-        };
+        let (where_clause, params) = usage_filter(start_ts, end_ts, provider_id, project);
 
         // 查询每个时间段、每个模型的使用量
         let sql = format!(
@@ -756,7 +515,7 @@ impl Database {
 
     /// 获取每日趋势
     pub fn get_daily_trends(&self, start_ts: i64, end_ts: i64, bucket_seconds: i64) -> Result<Vec<DailyStats>, AppError> {
-        let conn = lock_conn!(self.conn);
+        let conn = self.read_conn()?;
 
         let sql = "
             SELECT
@@ -826,7 +585,7 @@ impl Database {
 
     /// 获取 Provider 统计
     pub fn get_provider_stats(&self, start_ts: Option<i64>, end_ts: Option<i64>) -> Result<Vec<ProviderStats>, AppError> {
-        let conn = lock_conn!(self.conn);
+        let conn = self.read_conn()?;
 
         let (where_clause, params): (String, Vec<i64>) = match (start_ts, end_ts) {
             (Some(start), Some(end)) => {
@@ -852,7 +611,9 @@ impl Database {
                 COALESCE(SUM(cache_creation_tokens), 0) as total_cache_creation_tokens,
                 COALESCE(SUM(cache_read_tokens), 0) as total_cache_read_tokens,
                 COALESCE(SUM(CAST(total_cost_usd AS REAL)), 0) as total_cost,
-                COALESCE(SUM(CASE WHEN status_code >= 200 AND status_code < 300 THEN 1 ELSE 0 END), 0) as success_count
+                COALESCE(SUM(CASE WHEN status_code >= 200 AND status_code < 300 THEN 1 ELSE 0 END), 0) as success_count,
+                COALESCE(SUM(CASE WHEN tokens_estimated = 1
+                    THEN input_tokens + output_tokens + cache_read_tokens + cache_creation_tokens ELSE 0 END), 0) as estimated_tokens
             FROM proxy_request_logs
             {where_clause}
             GROUP BY provider_id
@@ -884,6 +645,7 @@ impl Database {
                 total_output_tokens: row.get::<_, i64>(5).map_err(|e| AppError::Database(format!("读取字段失败: {e}")))? as u64,
                 total_cache_creation_tokens: row.get::<_, i64>(6).map_err(|e| AppError::Database(format!("读取字段失败: {e}")))? as u64,
                 total_cache_read_tokens: row.get::<_, i64>(7).map_err(|e| AppError::Database(format!("读取字段失败: {e}")))? as u64,
+                estimated_tokens: row.get::<_, i64>(10).map_err(|e| AppError::Database(format!("读取字段失败: {e}")))? as u64,
                 total_cost: format!("{:.6}", row.get::<_, f64>(8).map_err(|e| AppError::Database(format!("读取字段失败: {e}")))?),
                 success_rate,
             });
@@ -892,9 +654,56 @@ impl Database {
         Ok(stats)
     }
 
+    /// 获取按项目分组的统计（按费用降序）
+    pub fn get_project_stats(&self, start_ts: Option<i64>, end_ts: Option<i64>) -> Result<Vec<ProjectStats>, AppError> {
+        let conn = self.read_conn()?;
+        let (where_clause, params) = usage_filter(start_ts, end_ts, None, None);
+
+        let sql = format!(
+            "SELECT
+                project,
+                COUNT(*) as request_count,
+                COALESCE(SUM(input_tokens + output_tokens), 0) as total_tokens,
+                COALESCE(SUM(input_tokens), 0) as total_input_tokens,
+                COALESCE(SUM(output_tokens), 0) as total_output_tokens,
+                COALESCE(SUM(cache_creation_tokens), 0) as total_cache_creation_tokens,
+                COALESCE(SUM(cache_read_tokens), 0) as total_cache_read_tokens,
+                COALESCE(SUM(CAST(total_cost_usd AS REAL)), 0) as total_cost
+            FROM proxy_request_logs
+            {where_clause}
+            GROUP BY project
+            ORDER BY total_cost DESC"
+        );
+
+        let mut stmt = conn.prepare(&sql)
+            .map_err(|e| AppError::Database(format!("准备查询失败: {e}")))?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(params), |row| {
+            let project: Option<String> = row.get(0)?;
+            let count = |idx: usize| -> rusqlite::Result<u64> { Ok(row.get::<_, i64>(idx)?.max(0) as u64) };
+            Ok(ProjectStats {
+                project_name: project
+                    .as_deref()
+                    .map(crate::project::project_name)
+                    .unwrap_or("未归属")
+                    .to_string(),
+                project,
+                request_count: count(1)?,
+                total_tokens: count(2)?,
+                total_input_tokens: count(3)?,
+                total_output_tokens: count(4)?,
+                total_cache_creation_tokens: count(5)?,
+                total_cache_read_tokens: count(6)?,
+                total_cost: format!("{:.6}", row.get::<_, f64>(7)?),
+            })
+        }).map_err(|e| AppError::Database(format!("查询项目统计失败: {e}")))?;
+
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::Database(format!("读取行失败: {e}")))
+    }
+
     /// 获取代理配置
     pub fn get_proxy_config(&self) -> Result<ProxyConfigDb, AppError> {
-        let conn = lock_conn!(self.conn);
+        let conn = self.read_conn()?;
 
         conn.query_row(
             "SELECT proxy_enabled, listen_address, listen_port, takeover_claude, takeover_codex, takeover_gemini
@@ -958,7 +767,7 @@ impl Database {
 
     /// 获取配置备份
     pub fn get_live_backup(&self, app_type: &str) -> Result<Option<String>, AppError> {
-        let conn = lock_conn!(self.conn);
+        let conn = self.read_conn()?;
 
         let result = conn.query_row(
             "SELECT original_config FROM proxy_live_backup WHERE app_type = ?1",
@@ -987,6 +796,8 @@ impl Database {
     }
 
     /// 清除所有使用统计
+    ///
+    /// 删除全部请求日志（代理、本地导入和手动记录）、会话统计及本地导入位置
     pub fn clear_usage_stats(&self) -> Result<(), AppError> {
         let conn = lock_conn!(self.conn);
 
@@ -1002,6 +813,10 @@ impl Database {
         conn.execute("DELETE FROM session_stats", [])
             .map_err(|e| AppError::Database(format!("清除会话统计失败: {e}")))?;
 
+        // 清除本地日志导入位置，之后重新导入时完整解析
+        conn.execute("DELETE FROM local_import_state", [])
+            .map_err(|e| AppError::Database(format!("清除本地导入状态失败: {e}")))?;
+
         Ok(())
     }
 
@@ -1089,7 +904,10 @@ impl Database {
 
         conn.execute(
             "INSERT INTO tool_calls (session_id, tool_name, call_count, created_at)
-             VALUES (?1, ?2, ?3, ?4)",
+             VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(session_id, tool_name) DO UPDATE SET
+                call_count = call_count + excluded.call_count,
+                created_at = excluded.created_at",
             rusqlite::params![session_id, tool_name, call_count, created_at],
         )
         .map_err(|e| AppError::Database(format!("插入工具调用记录失败: {e}")))?;
@@ -1104,7 +922,7 @@ impl Database {
         end_ts: Option<i64>,
         provider_id: Option<&str>,
     ) -> Result<SessionStatsSummary, AppError> {
-        let conn = lock_conn!(self.conn);
+        let conn = self.read_conn()?;
 
         let mut conditions: Vec<String> = Vec::new();
         let mut params: Vec<rusqlite::types::Value> = Vec::new();
@@ -1190,7 +1008,7 @@ impl Database {
         end_ts: Option<i64>,
         provider_id: Option<&str>,
     ) -> Result<Vec<ToolCallStats>, AppError> {
-        let conn = lock_conn!(self.conn);
+        let conn = self.read_conn()?;
 
         let mut conditions: Vec<String> = Vec::new();
         let mut params: Vec<rusqlite::types::Value> = Vec::new();
@@ -1266,7 +1084,7 @@ impl Database {
 
     /// 检查会话统计是否存在
     pub fn session_stats_exists(&self, session_id: &str) -> bool {
-        let conn = match self.conn.lock() {
+        let conn = match self.read_conn() {
             Ok(c) => c,
             Err(_) => return false,
         };
@@ -0,0 +1,200 @@
+//! 手动使用记录及旧版 JSON 存储迁移
+//!
+//! 早期版本把 `add_usage_record` 写入的记录保存在 ~/.config/opencode/usage_stats.json，
+//! 现在统一写入 proxy_request_logs（app_type 为 `manual`）。
+//! 首次打开数据库时导入旧文件，导入成功后将其重命名为 usage_stats.json.migrated
+
+use super::{lock_conn, Database};
+use crate::error::AppError;
+use crate::pricing::SOURCE_COST_VERSION;
+use rusqlite::{Connection, TransactionBehavior};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// 手动记录的 app_type
+pub const MANUAL_APP_TYPE: &str = "manual";
+
+/// 旧版 JSON 存储文件名（与数据库位于同一目录）
+pub(crate) const LEGACY_USAGE_FILE: &str = "usage_stats.json";
+
+/// 单条使用记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageRecord {
+    pub id: String,
+    pub timestamp: i64,  // Unix timestamp in milliseconds
+    pub provider_name: String,
+    pub model: String,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_creation_tokens: u64,
+    pub cache_read_tokens: u64,
+    pub cost: f64,  // in USD
+    pub request_type: String,  // "chat", "completion", etc.
+}
+
+/// 旧版 JSON 文件结构
+#[derive(Debug, Default, Deserialize)]
+struct LegacyUsageData {
+    #[serde(default)]
+    records: Vec<UsageRecord>,
+}
+
+impl Database {
+    /// 写入一条手动使用记录
+    pub fn insert_usage_record(&self, record: &UsageRecord) -> Result<(), AppError> {
+        let conn = lock_conn!(self.conn);
+        insert_record(&conn, record)?;
+        Ok(())
+    }
+
+    /// 删除所有手动使用记录，返回删除的条数
+    ///
+    /// 只清除 `add_usage_record` 写入的记录，代理日志和本地导入的记录保持不变
+    pub fn clear_manual_usage_records(&self) -> Result<usize, AppError> {
+        let conn = lock_conn!(self.conn);
+        conn.execute(
+            "DELETE FROM proxy_request_logs WHERE app_type = ?1",
+            [MANUAL_APP_TYPE],
+        )
+        .map_err(|e| AppError::Database(format!("清除手动使用记录失败: {e}")))
+    }
+
+    /// 导入旧版 JSON 使用记录，返回新导入的条数
+    ///
+    /// 以 `manual-<id>` 作为 request_id，重复导入不会产生重复记录
+    pub(crate) fn migrate_legacy_usage_file(&self, path: &Path) -> Result<u32, AppError> {
+        if !path.exists() {
+            return Ok(0);
+        }
+
+        let content = std::fs::read_to_string(path)?;
+        let data: LegacyUsageData = if content.trim().is_empty() {
+            LegacyUsageData::default()
+        } else {
+            serde_json::from_str(&content)
+                .map_err(|e| AppError::Database(format!("解析 {LEGACY_USAGE_FILE} 失败: {e}")))?
+        };
+
+        let mut imported = 0u32;
+        {
+            let mut conn = lock_conn!(self.conn);
+            let tx = conn
+                .transaction_with_behavior(TransactionBehavior::Immediate)
+                .map_err(|e| AppError::Database(format!("开启事务失败: {e}")))?;
+            for record in &data.records {
+                imported += insert_record(&tx, record)? as u32;
+            }
+            tx.commit()
+                .map_err(|e| AppError::Database(format!("提交事务失败: {e}")))?;
+        }
+
+        let mut migrated = path.as_os_str().to_owned();
+        migrated.push(".migrated");
+        std::fs::rename(path, migrated)?;
+        Ok(imported)
+    }
+}
+
+/// 插入记录（已存在时忽略），返回实际插入的行数
+///
+/// 成本由用户提供，不参与历史重算
+fn insert_record(conn: &Connection, record: &UsageRecord) -> Result<usize, AppError> {
+    let cost = Decimal::try_from(record.cost).unwrap_or(Decimal::ZERO);
+    let zero = Decimal::ZERO.to_string();
+
+    conn.execute(
+        "INSERT OR IGNORE INTO proxy_request_logs (
+            request_id, provider_id, provider_name, app_type, model, request_model,
+            input_tokens, output_tokens, cache_read_tokens, cache_creation_tokens,
+            input_cost_usd, output_cost_usd, cache_read_cost_usd, cache_creation_cost_usd, total_cost_usd,
+            latency_ms, status_code, is_streaming, pricing_version, created_at
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?11, ?11, ?11, ?12, 0, 200, 0, ?13, ?14)",
+        rusqlite::params![
+            format!("{MANUAL_APP_TYPE}-{}", record.id),
+            record.provider_name,
+            record.provider_name,
+            MANUAL_APP_TYPE,
+            record.model,
+            record.request_type,
+            record.input_tokens as i64,
+            record.output_tokens as i64,
+            record.cache_read_tokens as i64,
+            record.cache_creation_tokens as i64,
+            zero,
+            cost.to_string(),
+            SOURCE_COST_VERSION,
+            record.timestamp / 1000,
+        ],
+    )
+    .map_err(|e| AppError::Database(format!("插入使用记录失败: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn legacy_file_is_imported_once_and_renamed() {
+        let dir = std::env::temp_dir().join(format!("ai-switch-legacy-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(LEGACY_USAGE_FILE);
+        std::fs::write(
+            &path,
+            r#"{"records":[
+                {"id":"a","timestamp":1700000000000,"provider_name":"OpenRouter","model":"gpt-4o",
+                 "input_tokens":100,"output_tokens":20,"cache_creation_tokens":0,"cache_read_tokens":5,
+                 "cost":0.0125,"request_type":"chat"},
+                {"id":"b","timestamp":1700000600000,"provider_name":"OpenRouter","model":"gpt-4o",
+                 "input_tokens":10,"output_tokens":2,"cache_creation_tokens":0,"cache_read_tokens":0,
+                 "cost":0.001,"request_type":"chat"}
+            ]}"#,
+        )
+        .unwrap();
+
+        let db = Database::memory().unwrap();
+        assert_eq!(db.migrate_legacy_usage_file(&path).unwrap(), 2);
+        assert!(!path.exists());
+        assert!(dir.join("usage_stats.json.migrated").exists());
+        // 文件已重命名，再次调用为空操作
+        assert_eq!(db.migrate_legacy_usage_file(&path).unwrap(), 0);
+
+        let summary = db.get_usage_summary(None, None, None).unwrap();
+        assert_eq!(summary.total_requests, 2);
+        assert_eq!(summary.total_input_tokens, 110);
+        assert_eq!(summary.total_cost.parse::<f64>().unwrap(), 0.0135);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn clearing_manual_records_keeps_other_logs() {
+        let db = Database::memory().unwrap();
+        let record = UsageRecord {
+            id: "a".to_string(),
+            timestamp: 1_700_000_000_000,
+            provider_name: "OpenRouter".to_string(),
+            model: "gpt-4o".to_string(),
+            input_tokens: 100,
+            output_tokens: 20,
+            cache_creation_tokens: 0,
+            cache_read_tokens: 0,
+            cost: 0.01,
+            request_type: "chat".to_string(),
+        };
+        db.insert_usage_record(&record).unwrap();
+        {
+            let conn = db.conn.lock().unwrap();
+            conn.execute(
+                "INSERT INTO proxy_request_logs (request_id, provider_id, app_type, model, latency_ms, status_code, created_at)
+                 VALUES ('proxy-1', 'p', 'claude', 'claude-sonnet-4', 100, 200, 1700000000)",
+                [],
+            )
+            .unwrap();
+        }
+
+        assert_eq!(db.clear_manual_usage_records().unwrap(), 1);
+        let summary = db.get_usage_summary(None, None, None).unwrap();
+        assert_eq!(summary.total_requests, 1);
+    }
+}
@@ -1,11 +1,16 @@
 // Ai Switch Tauri 库入口
 // v1.6.0 - 添加对话迁移、代理统计、本地日志导入功能
 
+pub mod admin;
+pub mod cli;
 pub mod commands;
 pub mod config;
 pub mod database;
 pub mod error;
+pub mod pricing;
+pub mod project;
 pub mod proxy;
+pub mod report;
 
 use std::sync::{Arc, Mutex};
 use config::ConfigManager;
@@ -123,6 +128,15 @@ pub fn refresh_tray_menu(app: &AppHandle) {
     }
 }
 
+/// 更新托盘提示文字
+pub fn set_tray_tooltip(app: &AppHandle, text: &str) {
+    if let Some(tray_state) = app.try_state::<TrayState>() {
+        if let Ok(tray) = tray_state.0.lock() {
+            let _ = tray.set_tooltip(Some(text));
+        }
+    }
+}
+
 /// 运行 Tauri 应用
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() -> Result<(), Box<dyn std::error::Error>> {
@@ -137,6 +151,16 @@ pub fn run() -> Result<(), Box<dyn std::error::Error>> {
     // 代理服务状态
     let proxy_service_state = commands::ProxyServiceState(Arc::new(RwLock::new(None)));
     
+    // 管理 API 状态（启用时在 setup 中启动）
+    let admin_api_state = commands::AdminApiState(Arc::new(RwLock::new(None)));
+    let admin_startup = (
+        db_arc.clone(),
+        proxy_service_state.0.clone(),
+        admin_api_state.0.clone(),
+    );
+    let monitor_db = db_arc.clone();
+    let watcher_db = db_arc.clone();
+    
     tauri::Builder::default()
         // 单实例插件必须首先注册，以便在第二个实例启动时能够正确拦截
         // 这对于 Windows/Linux 上的深链接功能至关重要
@@ -165,7 +189,8 @@ pub fn run() -> Result<(), Box<dyn std::error::Error>> {
         .manage(Mutex::new(config_manager))
         .manage(db_arc)
         .manage(proxy_service_state)
-        .setup(|app| {
+        .manage(admin_api_state)
+        .setup(move |app| {
             // 创建动态托盘菜单（包含 Provider 列表）
             let menu = build_tray_menu(app.handle())?;
             
@@ -221,6 +246,33 @@ pub fn run() -> Result<(), Box<dyn std::error::Error>> {
             // 将托盘图标存储到应用状态中，防止被释放
             app.manage(TrayState(Mutex::new(tray)));
             
+            // 启动本地管理 API（仅在配置中启用时）
+            let (db, proxy, admin) = admin_startup;
+            tauri::async_runtime::spawn(async move {
+                let config = match admin::AdminApiConfig::load() {
+                    Ok(config) if config.enabled => config,
+                    Ok(_) => return,
+                    Err(e) => {
+                        eprintln!("读取管理 API 配置失败: {e}");
+                        return;
+                    }
+                };
+                let server = admin::AdminServer::new(config, db, proxy);
+                match server.start().await {
+                    Ok(addr) => {
+                        eprintln!("管理 API 已启动: http://{addr}/api/v1");
+                        *admin.write().await = Some(server);
+                    }
+                    Err(e) => eprintln!("管理 API 启动失败: {e}"),
+                }
+            });
+            
+            // 启动用量监控（费用预测、异常提醒）
+            tauri::async_runtime::spawn(commands::run_usage_monitor(app.handle().clone(), monitor_db));
+
+            // 监听本地日志目录，实时导入不经过代理的用量
+            tauri::async_runtime::spawn(commands::run_local_log_watcher(app.handle().clone(), watcher_db));
+            
             // 深链接处理说明：
             // 初始深链接和后续深链接都由前端通过 @tauri-apps/plugin-deep-link 直接处理
             // - 初始深链接：前端使用 getCurrent() API 获取
@@ -426,12 +478,37 @@ pub fn run() -> Result<(), Box<dyn std::error::Error>> {
             commands::add_usage_record,
             commands::clear_usage_stats,
             commands::get_usage_by_provider,
+            commands::export_usage_report,
+            commands::get_cache_analytics,
+            commands::get_productivity_metrics,
+            commands::list_usage_sessions,
+            commands::get_usage_session_timeline,
+            commands::get_cost_forecast,
+            commands::get_usage_anomalies,
             // Model pricing commands
             commands::get_model_pricing_list,
             commands::update_model_pricing,
             commands::add_model_pricing,
             commands::delete_model_pricing,
             commands::reset_model_pricing,
+            commands::import_model_pricing_catalog,
+            // Model alias commands
+            commands::get_usage_model_matches,
+            commands::get_model_aliases,
+            commands::bind_model_alias,
+            commands::delete_model_alias,
+            // Billing currency commands
+            commands::get_provider_billing,
+            commands::save_provider_billing,
+            commands::delete_provider_billing,
+            commands::get_exchange_rates,
+            commands::save_exchange_rate,
+            commands::delete_exchange_rate,
+            // Pricing rule commands
+            commands::get_pricing_rules,
+            commands::save_pricing_rule,
+            commands::delete_pricing_rule,
+            commands::recalculate_usage_costs,
             // Provider model pricing commands
             commands::get_provider_model_pricing,
             commands::get_all_provider_pricing,
@@ -456,7 +533,12 @@ pub fn run() -> Result<(), Box<dyn std::error::Error>> {
             commands::get_proxy_usage_trend,
             commands::get_proxy_usage_trend_by_model,
             commands::get_provider_stats,
+            commands::get_project_stats,
             commands::clear_proxy_usage_stats,
+            // Admin API commands
+            commands::get_admin_api_status,
+            commands::update_admin_api_config,
+            commands::regenerate_admin_api_token,
             // Ai Switch unified config commands
             commands::get_open_switch_providers,
             commands::get_open_switch_provider,
@@ -0,0 +1,389 @@
+//! 服务商计费货币、倍率与汇率
+//!
+//! 很多中转服务商按人民币计费并设置倍率：服务商收费 = 官方美元价格 × 倍率，单位为该服务商的计费货币。
+//! 代理记录使用量时按服务商配置换算成实际花费的美元写入 proxy_request_logs，
+//! 统计查询可按 `exchange_rates` 中的汇率换算为任意货币展示。
+//!
+//! 汇率统一以「1 USD 可兑换的该货币数量」表示（如 CNY 为 7.2），USD 固定为 1。
+
+use super::CostBreakdown;
+use crate::database::schema::{DailyStats, ModelTrendData, ProjectStats, ProviderStats, UsageSummary, UsageTrend};
+use crate::database::{lock_conn, Database};
+use crate::error::AppError;
+use rusqlite::{Connection, OptionalExtension};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// 基准货币
+pub const BASE_CURRENCY: &str = "USD";
+
+/// 服务商计费设置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderBilling {
+    pub provider_id: String,
+    /// 计费货币（ISO 4217 代码，如 USD、CNY）
+    pub currency: String,
+    /// 相对官方价格的倍率
+    pub cost_multiplier: String,
+    #[serde(default)]
+    pub updated_at: i64,
+}
+
+/// 汇率
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExchangeRate {
+    pub currency: String,
+    /// 1 USD 可兑换的该货币数量
+    pub rate: String,
+    #[serde(default)]
+    pub updated_at: i64,
+}
+
+/// 某个服务商的计费系数
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BillingFactor {
+    /// 服务商倍率（写入 proxy_request_logs.cost_multiplier）
+    pub multiplier: Decimal,
+    /// 官方美元价格换算为实际美元花费的系数（倍率 / 计费货币汇率）
+    pub usd_factor: Decimal,
+}
+
+impl Default for BillingFactor {
+    fn default() -> Self {
+        Self {
+            multiplier: Decimal::ONE,
+            usd_factor: Decimal::ONE,
+        }
+    }
+}
+
+impl CostBreakdown {
+    /// 按计费系数换算成本
+    pub fn scaled(&self, factor: Decimal) -> CostBreakdown {
+        CostBreakdown {
+            input_cost: self.input_cost * factor,
+            output_cost: self.output_cost * factor,
+            cache_read_cost: self.cache_read_cost * factor,
+            cache_creation_cost: self.cache_creation_cost * factor,
+            total_cost: self.total_cost * factor,
+        }
+    }
+}
+
+/// 规范化货币代码（三位字母，转为大写）
+pub fn normalize_currency(currency: &str) -> Result<String, AppError> {
+    let code = currency.trim().to_ascii_uppercase();
+    if code.len() != 3 || !code.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Err(AppError::Custom(format!("货币代码无效（应为三位字母，如 CNY）: {currency}")));
+    }
+    Ok(code)
+}
+
+fn parse_positive(value: &str, name: &str) -> Result<Decimal, AppError> {
+    match Decimal::from_str(value.trim()) {
+        Ok(v) if v > Decimal::ZERO => Ok(v),
+        _ => Err(AppError::Custom(format!("{name}必须为正数: {value}"))),
+    }
+}
+
+/// 查询货币汇率（USD 固定为 1）
+pub fn exchange_rate(conn: &Connection, currency: &str) -> Result<Option<Decimal>, AppError> {
+    if currency.eq_ignore_ascii_case(BASE_CURRENCY) {
+        return Ok(Some(Decimal::ONE));
+    }
+    let rate = conn
+        .query_row(
+            "SELECT rate FROM exchange_rates WHERE currency = ?1",
+            [currency.to_ascii_uppercase()],
+            |row| row.get::<_, String>(0),
+        )
+        .optional()
+        .map_err(|e| AppError::Database(format!("查询汇率失败: {e}")))?;
+    Ok(rate.and_then(|r| Decimal::from_str(&r).ok()).filter(|r| *r > Decimal::ZERO))
+}
+
+/// 服务商计费系数解析器（按服务商缓存）
+pub struct BillingResolver<'c> {
+    conn: &'c Connection,
+    cache: HashMap<String, BillingFactor>,
+}
+
+impl<'c> BillingResolver<'c> {
+    pub fn new(conn: &'c Connection) -> Self {
+        Self {
+            conn,
+            cache: HashMap::new(),
+        }
+    }
+
+    /// 未配置计费的服务商按 USD、倍率 1 处理；计费货币缺少汇率时按 1:1 换算
+    pub fn factor(&mut self, provider_id: &str) -> Result<BillingFactor, AppError> {
+        if let Some(factor) = self.cache.get(provider_id) {
+            return Ok(*factor);
+        }
+        let billing = self
+            .conn
+            .query_row(
+                "SELECT currency, cost_multiplier FROM provider_billing WHERE provider_id = ?1",
+                [provider_id],
+                |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
+            )
+            .optional()
+            .map_err(|e| AppError::Database(format!("查询服务商计费设置失败: {e}")))?;
+
+        let factor = match billing {
+            Some((currency, multiplier)) => {
+                let multiplier = Decimal::from_str(&multiplier).unwrap_or(Decimal::ONE);
+                let rate = exchange_rate(self.conn, &currency)?.unwrap_or(Decimal::ONE);
+                BillingFactor {
+                    multiplier,
+                    usd_factor: multiplier / rate,
+                }
+            }
+            None => BillingFactor::default(),
+        };
+        self.cache.insert(provider_id.to_string(), factor);
+        Ok(factor)
+    }
+}
+
+/// 查询单个服务商的计费系数
+pub fn billing_factor(conn: &Connection, provider_id: &str) -> Result<BillingFactor, AppError> {
+    BillingResolver::new(conn).factor(provider_id)
+}
+
+// ============================================================================
+// 计费设置与汇率管理
+// ============================================================================
+
+/// 列出服务商计费设置
+pub fn list_provider_billing(db: &Database) -> Result<Vec<ProviderBilling>, AppError> {
+    let conn = db.read_conn()?;
+    let mut stmt = conn
+        .prepare("SELECT provider_id, currency, cost_multiplier, updated_at FROM provider_billing ORDER BY provider_id")
+        .map_err(|e| AppError::Database(format!("准备查询失败: {e}")))?;
+    let items = stmt
+        .query_map([], |row| {
+            Ok(ProviderBilling {
+                provider_id: row.get(0)?,
+                currency: row.get(1)?,
+                cost_multiplier: row.get(2)?,
+                updated_at: row.get(3)?,
+            })
+        })
+        .map_err(|e| AppError::Database(format!("查询服务商计费设置失败: {e}")))?
+        .collect::<Result<_, _>>()
+        .map_err(|e| AppError::Database(format!("读取服务商计费设置失败: {e}")))?;
+    Ok(items)
+}
+
+/// 保存服务商计费设置（非 USD 计费时需先设置汇率）
+pub fn save_provider_billing(db: &Database, billing: &ProviderBilling) -> Result<(), AppError> {
+    let provider_id = billing.provider_id.trim();
+    if provider_id.is_empty() {
+        return Err(AppError::Custom("服务商 ID 不能为空".to_string()));
+    }
+    let currency = normalize_currency(&billing.currency)?;
+    let multiplier = parse_positive(&billing.cost_multiplier, "倍率")?;
+
+    let conn = lock_conn!(db.conn);
+    if exchange_rate(&conn, &currency)?.is_none() {
+        return Err(AppError::Custom(format!("请先设置 {currency} 的汇率")));
+    }
+    conn.execute(
+        "INSERT INTO provider_billing (provider_id, currency, cost_multiplier, updated_at)
+         VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(provider_id) DO UPDATE SET
+            currency = excluded.currency,
+            cost_multiplier = excluded.cost_multiplier,
+            updated_at = excluded.updated_at",
+        rusqlite::params![provider_id, currency, multiplier.normalize().to_string(), chrono::Utc::now().timestamp()],
+    )
+    .map_err(|e| AppError::Database(format!("保存服务商计费设置失败: {e}")))?;
+    Ok(())
+}
+
+/// 删除服务商计费设置（恢复为 USD、倍率 1）
+pub fn delete_provider_billing(db: &Database, provider_id: &str) -> Result<(), AppError> {
+    let conn = lock_conn!(db.conn);
+    conn.execute("DELETE FROM provider_billing WHERE provider_id = ?1", [provider_id])
+        .map_err(|e| AppError::Database(format!("删除服务商计费设置失败: {e}")))?;
+    Ok(())
+}
+
+/// 列出汇率
+pub fn list_exchange_rates(db: &Database) -> Result<Vec<ExchangeRate>, AppError> {
+    let conn = db.read_conn()?;
+    let mut stmt = conn
+        .prepare("SELECT currency, rate, updated_at FROM exchange_rates ORDER BY currency")
+        .map_err(|e| AppError::Database(format!("准备查询失败: {e}")))?;
+    let rates = stmt
+        .query_map([], |row| {
+            Ok(ExchangeRate {
+                currency: row.get(0)?,
+                rate: row.get(1)?,
+                updated_at: row.get(2)?,
+            })
+        })
+        .map_err(|e| AppError::Database(format!("查询汇率失败: {e}")))?
+        .collect::<Result<_, _>>()
+        .map_err(|e| AppError::Database(format!("读取汇率失败: {e}")))?;
+    Ok(rates)
+}
+
+/// 设置汇率（1 USD 可兑换的该货币数量）
+pub fn save_exchange_rate(db: &Database, currency: &str, rate: &str) -> Result<(), AppError> {
+    let currency = normalize_currency(currency)?;
+    if currency == BASE_CURRENCY {
+        return Err(AppError::Custom("USD 为基准货币，汇率固定为 1".to_string()));
+    }
+    let rate = parse_positive(rate, "汇率")?;
+
+    let conn = lock_conn!(db.conn);
+    conn.execute(
+        "INSERT INTO exchange_rates (currency, rate, updated_at) VALUES (?1, ?2, ?3)
+         ON CONFLICT(currency) DO UPDATE SET rate = excluded.rate, updated_at = excluded.updated_at",
+        rusqlite::params![currency, rate.normalize().to_string(), chrono::Utc::now().timestamp()],
+    )
+    .map_err(|e| AppError::Database(format!("保存汇率失败: {e}")))?;
+    Ok(())
+}
+
+/// 删除汇率（仍有服务商以该货币计费时拒绝删除）
+pub fn delete_exchange_rate(db: &Database, currency: &str) -> Result<(), AppError> {
+    let currency = normalize_currency(currency)?;
+    let conn = lock_conn!(db.conn);
+    let in_use: i64 = conn
+        .query_row("SELECT COUNT(*) FROM provider_billing WHERE currency = ?1", [&currency], |row| row.get(0))
+        .map_err(|e| AppError::Database(format!("查询服务商计费设置失败: {e}")))?;
+    if in_use > 0 {
+        return Err(AppError::Custom(format!("仍有 {in_use} 个服务商以 {currency} 计费，无法删除汇率")));
+    }
+    conn.execute("DELETE FROM exchange_rates WHERE currency = ?1", [&currency])
+        .map_err(|e| AppError::Database(format!("删除汇率失败: {e}")))?;
+    Ok(())
+}
+
+// ============================================================================
+// 统计结果的货币换算
+// ============================================================================
+
+/// 统计结果换算为指定货币的汇率（为空或 USD 时为 1，未设置汇率时报错）
+pub fn report_rate(db: &Database, currency: Option<&str>) -> Result<f64, AppError> {
+    let Some(currency) = currency.filter(|c| !c.trim().is_empty()) else {
+        return Ok(1.0);
+    };
+    let currency = normalize_currency(currency)?;
+    let conn = db.read_conn()?;
+    exchange_rate(&conn, &currency)?
+        .and_then(|rate| rate.to_f64())
+        .ok_or_else(|| AppError::Custom(format!("未设置 {currency} 的汇率")))
+}
+
+/// 将统计结果中的美元金额换算为其他货币
+pub trait ConvertCurrency {
+    fn convert_currency(&mut self, rate: f64);
+}
+
+fn convert_str(value: &mut String, rate: f64) {
+    *value = format!("{:.6}", value.parse::<f64>().unwrap_or(0.0) * rate);
+}
+
+impl ConvertCurrency for UsageSummary {
+    fn convert_currency(&mut self, rate: f64) {
+        convert_str(&mut self.total_cost, rate);
+    }
+}
+
+impl ConvertCurrency for DailyStats {
+    fn convert_currency(&mut self, rate: f64) {
+        convert_str(&mut self.total_cost, rate);
+    }
+}
+
+impl ConvertCurrency for ProviderStats {
+    fn convert_currency(&mut self, rate: f64) {
+        convert_str(&mut self.total_cost, rate);
+    }
+}
+
+impl ConvertCurrency for ProjectStats {
+    fn convert_currency(&mut self, rate: f64) {
+        convert_str(&mut self.total_cost, rate);
+    }
+}
+
+impl ConvertCurrency for UsageTrend {
+    fn convert_currency(&mut self, rate: f64) {
+        self.total_cost *= rate;
+    }
+}
+
+impl ConvertCurrency for ModelTrendData {
+    fn convert_currency(&mut self, rate: f64) {
+        self.total_cost *= rate;
+        for model in &mut self.models {
+            model.total_cost *= rate;
+        }
+    }
+}
+
+impl<T: ConvertCurrency> ConvertCurrency for Vec<T> {
+    fn convert_currency(&mut self, rate: f64) {
+        for item in self.iter_mut() {
+            item.convert_currency(rate);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn billing_factor_applies_multiplier_and_currency() {
+        let db = Database::memory().unwrap();
+        let billing = |provider_id: &str, currency: &str, multiplier: &str| ProviderBilling {
+            provider_id: provider_id.to_string(),
+            currency: currency.to_string(),
+            cost_multiplier: multiplier.to_string(),
+            updated_at: 0,
+        };
+
+        // 缺少汇率时拒绝以该货币计费
+        assert!(save_provider_billing(&db, &billing("relay", "cny", "0.8")).is_err());
+        save_exchange_rate(&db, "cny", "8").unwrap();
+        save_provider_billing(&db, &billing("relay", "cny", "0.8")).unwrap();
+        save_provider_billing(&db, &billing("markup", "USD", "1.5")).unwrap();
+        assert!(delete_exchange_rate(&db, "CNY").is_err());
+
+        let conn = db.conn.lock().unwrap();
+        let mut resolver = BillingResolver::new(&conn);
+        let relay = resolver.factor("relay").unwrap();
+        assert_eq!(relay.multiplier, Decimal::from_str("0.8").unwrap());
+        assert_eq!(relay.usd_factor, Decimal::from_str("0.1").unwrap());
+        assert_eq!(resolver.factor("markup").unwrap().usd_factor, Decimal::from_str("1.5").unwrap());
+        assert_eq!(resolver.factor("direct").unwrap(), BillingFactor::default());
+        drop(conn);
+
+        assert_eq!(report_rate(&db, Some("CNY")).unwrap(), 8.0);
+        assert_eq!(report_rate(&db, None).unwrap(), 1.0);
+        assert!(report_rate(&db, Some("EUR")).is_err());
+
+        let mut stats = vec![UsageTrend {
+            period: "2025-01-01".to_string(),
+            request_count: 1,
+            total_cost: 0.5,
+            input_tokens: 0,
+            output_tokens: 0,
+            top_model: None,
+        }];
+        stats.convert_currency(8.0);
+        assert_eq!(stats[0].total_cost, 4.0);
+    }
+}
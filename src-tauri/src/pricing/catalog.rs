@@ -0,0 +1,477 @@
+//! 定价目录导入
+//!
+//! 支持两种格式：
+//! - LiteLLM 的 `model_prices_and_context_window.json`（价格单位为 USD / token）
+//! - 简单 CSV：首行为表头，必填 `model_id,input,output`，
+//!   可选 `display_name,cache_read,cache_creation,max_input_tokens,max_output_tokens`
+//!   （价格单位为 USD / 百万 token）
+//!
+//! 导入以 model_id 为键 upsert `model_pricing`，可重复执行以刷新价格；
+//! 手动编辑过的定价默认不会被覆盖。
+
+use super::clean_model_id;
+use crate::database::{lock_conn, Database};
+use crate::error::AppError;
+use rusqlite::{OptionalExtension, TransactionBehavior};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+use std::str::FromStr;
+
+/// 定价目录中的一个模型
+#[derive(Debug, Clone, PartialEq)]
+pub struct CatalogEntry {
+    pub model_id: String,
+    pub display_name: String,
+    pub input_cost_per_million: Decimal,
+    pub output_cost_per_million: Decimal,
+    pub cache_read_cost_per_million: Decimal,
+    pub cache_creation_cost_per_million: Decimal,
+    pub max_input_tokens: Option<i64>,
+    pub max_output_tokens: Option<i64>,
+}
+
+/// 解析后的定价目录
+#[derive(Debug, Default)]
+pub struct ParsedCatalog {
+    pub entries: Vec<CatalogEntry>,
+    /// 缺少价格或格式错误而跳过的条目数
+    pub skipped: u32,
+}
+
+/// 导入选项
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PricingImportOptions {
+    /// 是否覆盖手动编辑过的定价
+    #[serde(default)]
+    pub overwrite_manual: bool,
+}
+
+/// 导入结果
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PricingImportReport {
+    /// 目录中的有效模型数
+    pub total: u32,
+    pub inserted: u32,
+    pub updated: u32,
+    pub unchanged: u32,
+    /// 因手动编辑而保留原价的模型数
+    pub skipped_manual: u32,
+    /// 目录中无法解析的条目数
+    pub skipped_invalid: u32,
+    /// 使用记录中出现但仍没有定价的模型
+    pub unmatched_models: Vec<String>,
+}
+
+/// 按扩展名（或内容）识别格式并解析定价目录文件
+pub fn parse_file(path: &Path) -> Result<ParsedCatalog, AppError> {
+    let content = std::fs::read_to_string(path)?;
+    let is_csv = match path.extension().and_then(|e| e.to_str()) {
+        Some(ext) if ext.eq_ignore_ascii_case("csv") => true,
+        Some(ext) if ext.eq_ignore_ascii_case("json") => false,
+        _ => !content.trim_start().starts_with('{'),
+    };
+    if is_csv {
+        parse_csv(&content)
+    } else {
+        parse_litellm(&content)
+    }
+}
+
+/// 解析 LiteLLM 定价 JSON
+///
+/// 只保留对话类模型；带服务商前缀的键（如 `openrouter/...`）清洗后与无前缀的键重复时，
+/// 以无前缀的条目为准
+pub fn parse_litellm(content: &str) -> Result<ParsedCatalog, AppError> {
+    let root: BTreeMap<String, Value> = serde_json::from_str(content)
+        .map_err(|e| AppError::Custom(format!("解析 LiteLLM 定价文件失败: {e}")))?;
+
+    let mut catalog = ParsedCatalog::default();
+    // model_id -> (条目, 是否来自无前缀的键)
+    let mut by_id: BTreeMap<String, (CatalogEntry, bool)> = BTreeMap::new();
+
+    for (key, value) in &root {
+        if key == "sample_spec" {
+            continue;
+        }
+        let mode = value.get("mode").and_then(Value::as_str);
+        if !matches!(mode, None | Some("chat") | Some("completion") | Some("responses")) {
+            continue;
+        }
+
+        let per_token = |field: &str| value.get(field).and_then(number_to_decimal);
+        let (Some(input), Some(output)) = (per_token("input_cost_per_token"), per_token("output_cost_per_token")) else {
+            catalog.skipped += 1;
+            continue;
+        };
+
+        let million = Decimal::from(1_000_000u32);
+        let model_id = clean_model_id(key);
+        if model_id.is_empty() {
+            catalog.skipped += 1;
+            continue;
+        }
+        let entry = CatalogEntry {
+            display_name: model_id.clone(),
+            model_id: model_id.clone(),
+            input_cost_per_million: (input * million).normalize(),
+            output_cost_per_million: (output * million).normalize(),
+            cache_read_cost_per_million: (per_token("cache_read_input_token_cost").unwrap_or_default() * million).normalize(),
+            cache_creation_cost_per_million: (per_token("cache_creation_input_token_cost").unwrap_or_default() * million).normalize(),
+            max_input_tokens: value
+                .get("max_input_tokens")
+                .or_else(|| value.get("max_tokens"))
+                .and_then(Value::as_i64),
+            max_output_tokens: value.get("max_output_tokens").and_then(Value::as_i64),
+        };
+
+        let unprefixed = !key.contains('/');
+        match by_id.get(&model_id) {
+            Some((_, true)) => {}
+            Some((_, false)) if !unprefixed => {}
+            _ => {
+                by_id.insert(model_id, (entry, unprefixed));
+            }
+        }
+    }
+
+    catalog.entries = by_id.into_values().map(|(entry, _)| entry).collect();
+    Ok(catalog)
+}
+
+/// 解析简单 CSV 定价表
+pub fn parse_csv(content: &str) -> Result<ParsedCatalog, AppError> {
+    let mut lines = content
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'));
+
+    let header: Vec<String> = lines
+        .next()
+        .map(|l| split_csv_line(l).into_iter().map(|h| h.to_ascii_lowercase()).collect())
+        .ok_or_else(|| AppError::Custom("CSV 定价文件为空".to_string()))?;
+    let column = |names: &[&str]| header.iter().position(|h| names.contains(&h.as_str()));
+
+    let model_col = column(&["model_id", "model"]);
+    let input_col = column(&["input", "input_cost_per_million"]);
+    let output_col = column(&["output", "output_cost_per_million"]);
+    let (Some(model_col), Some(input_col), Some(output_col)) = (model_col, input_col, output_col) else {
+        return Err(AppError::Custom(
+            "CSV 表头必须包含 model_id、input、output 列".to_string(),
+        ));
+    };
+    let name_col = column(&["display_name", "name"]);
+    let cache_read_col = column(&["cache_read", "cache_read_cost_per_million"]);
+    let cache_creation_col = column(&["cache_creation", "cache_creation_cost_per_million"]);
+    let max_input_col = column(&["max_input_tokens"]);
+    let max_output_col = column(&["max_output_tokens"]);
+
+    let mut catalog = ParsedCatalog::default();
+    let mut seen = HashSet::new();
+    for line in lines {
+        let fields = split_csv_line(line);
+        let field = |col: Option<usize>| {
+            col.and_then(|c| fields.get(c))
+                .map(|f| f.trim())
+                .filter(|f| !f.is_empty())
+        };
+        let price = |col: Option<usize>| field(col).and_then(|f| Decimal::from_str(f).ok());
+
+        let model_id = field(Some(model_col)).map(clean_model_id).unwrap_or_default();
+        let (Some(input), Some(output)) = (price(Some(input_col)), price(Some(output_col))) else {
+            catalog.skipped += 1;
+            continue;
+        };
+        if model_id.is_empty() || !seen.insert(model_id.clone()) {
+            catalog.skipped += 1;
+            continue;
+        }
+
+        catalog.entries.push(CatalogEntry {
+            display_name: field(name_col).unwrap_or(&model_id).to_string(),
+            model_id,
+            input_cost_per_million: input.normalize(),
+            output_cost_per_million: output.normalize(),
+            cache_read_cost_per_million: price(cache_read_col).unwrap_or_default().normalize(),
+            cache_creation_cost_per_million: price(cache_creation_col).unwrap_or_default().normalize(),
+            max_input_tokens: field(max_input_col).and_then(|f| f.parse().ok()),
+            max_output_tokens: field(max_output_col).and_then(|f| f.parse().ok()),
+        });
+    }
+
+    Ok(catalog)
+}
+
+/// 解析并导入定价目录文件
+pub fn import_file(
+    db: &Database,
+    path: &Path,
+    options: &PricingImportOptions,
+) -> Result<PricingImportReport, AppError> {
+    let catalog = parse_file(path)?;
+    import(db, &catalog, options)
+}
+
+/// 将解析后的定价目录写入 model_pricing
+pub fn import(
+    db: &Database,
+    catalog: &ParsedCatalog,
+    options: &PricingImportOptions,
+) -> Result<PricingImportReport, AppError> {
+    let mut report = PricingImportReport {
+        total: catalog.entries.len() as u32,
+        skipped_invalid: catalog.skipped,
+        ..Default::default()
+    };
+    let now = chrono::Utc::now().timestamp();
+
+    {
+        let mut conn = lock_conn!(db.conn);
+        let tx = conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_err(|e| AppError::Database(format!("开启事务失败: {e}")))?;
+
+        for entry in &catalog.entries {
+            let existing = tx
+                .query_row(
+                    "SELECT source, input_cost_per_million, output_cost_per_million,
+                            cache_read_cost_per_million, cache_creation_cost_per_million,
+                            max_input_tokens, max_output_tokens
+                     FROM model_pricing WHERE model_id = ?1",
+                    [&entry.model_id],
+                    |row| {
+                        let price = |idx: usize| -> rusqlite::Result<Decimal> {
+                            Ok(Decimal::from_str(&row.get::<_, String>(idx)?).unwrap_or_default())
+                        };
+                        Ok((
+                            row.get::<_, String>(0)?,
+                            price(1)? == entry.input_cost_per_million
+                                && price(2)? == entry.output_cost_per_million
+                                && price(3)? == entry.cache_read_cost_per_million
+                                && price(4)? == entry.cache_creation_cost_per_million
+                                && row.get::<_, Option<i64>>(5)? == entry.max_input_tokens
+                                && row.get::<_, Option<i64>>(6)? == entry.max_output_tokens,
+                        ))
+                    },
+                )
+                .optional()
+                .map_err(|e| AppError::Database(format!("查询模型定价失败: {e}")))?;
+
+            match existing {
+                None => {
+                    tx.execute(
+                        "INSERT INTO model_pricing (
+                            model_id, display_name, input_cost_per_million, output_cost_per_million,
+                            cache_read_cost_per_million, cache_creation_cost_per_million,
+                            max_input_tokens, max_output_tokens, source, updated_at
+                        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, 'catalog', ?9)",
+                        rusqlite::params![
+                            entry.model_id,
+                            entry.display_name,
+                            entry.input_cost_per_million.to_string(),
+                            entry.output_cost_per_million.to_string(),
+                            entry.cache_read_cost_per_million.to_string(),
+                            entry.cache_creation_cost_per_million.to_string(),
+                            entry.max_input_tokens,
+                            entry.max_output_tokens,
+                            now,
+                        ],
+                    )
+                    .map_err(|e| AppError::Database(format!("插入模型定价失败: {e}")))?;
+                    report.inserted += 1;
+                }
+                Some((source, _)) if source == "manual" && !options.overwrite_manual => {
+                    report.skipped_manual += 1;
+                }
+                Some((_, true)) => report.unchanged += 1,
+                Some(_) => {
+                    // 保留原有显示名称（内置定价的名称更友好）
+                    tx.execute(
+                        "UPDATE model_pricing SET
+                            input_cost_per_million = ?2,
+                            output_cost_per_million = ?3,
+                            cache_read_cost_per_million = ?4,
+                            cache_creation_cost_per_million = ?5,
+                            max_input_tokens = ?6,
+                            max_output_tokens = ?7,
+                            source = 'catalog',
+                            updated_at = ?8
+                         WHERE model_id = ?1",
+                        rusqlite::params![
+                            entry.model_id,
+                            entry.input_cost_per_million.to_string(),
+                            entry.output_cost_per_million.to_string(),
+                            entry.cache_read_cost_per_million.to_string(),
+                            entry.cache_creation_cost_per_million.to_string(),
+                            entry.max_input_tokens,
+                            entry.max_output_tokens,
+                            now,
+                        ],
+                    )
+                    .map_err(|e| AppError::Database(format!("更新模型定价失败: {e}")))?;
+                    report.updated += 1;
+                }
+            }
+        }
+
+        tx.commit()
+            .map_err(|e| AppError::Database(format!("提交事务失败: {e}")))?;
+    }
+
+    report.unmatched_models = super::matching::unmatched_models(db)?;
+    Ok(report)
+}
+
+/// JSON 数字转 Decimal（保留 3e-06 这类科学计数法的精确值）
+fn number_to_decimal(value: &Value) -> Option<Decimal> {
+    let text = match value {
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => s.trim().to_string(),
+        _ => return None,
+    };
+    Decimal::from_str(&text)
+        .or_else(|_| Decimal::from_scientific(&text))
+        .ok()
+}
+
+/// 拆分一行 CSV（支持双引号包裹的字段和 "" 转义）
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                current.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => fields.push(std::mem::take(&mut current).trim().to_string()),
+            _ => current.push(c),
+        }
+    }
+    fields.push(current.trim().to_string());
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LITELLM_SAMPLE: &str = r#"{
+        "sample_spec": { "input_cost_per_token": 0, "output_cost_per_token": 0 },
+        "claude-sonnet-4-20250514": {
+            "input_cost_per_token": 3e-06,
+            "output_cost_per_token": 1.5e-05,
+            "cache_read_input_token_cost": 3e-07,
+            "cache_creation_input_token_cost": 3.75e-06,
+            "max_input_tokens": 200000,
+            "max_output_tokens": 64000,
+            "mode": "chat"
+        },
+        "openrouter/anthropic/claude-sonnet-4-20250514": {
+            "input_cost_per_token": 9e-06,
+            "output_cost_per_token": 9e-05,
+            "mode": "chat"
+        },
+        "openrouter/qwen/qwen3-coder": {
+            "input_cost_per_token": 2.2e-07,
+            "output_cost_per_token": 9.5e-07,
+            "max_tokens": 262144,
+            "mode": "chat"
+        },
+        "text-embedding-3-small": { "input_cost_per_token": 2e-08, "output_cost_per_token": 0, "mode": "embedding" },
+        "free-model": { "mode": "chat" }
+    }"#;
+
+    fn dec(s: &str) -> Decimal {
+        Decimal::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn parses_litellm_catalog() {
+        let catalog = parse_litellm(LITELLM_SAMPLE).unwrap();
+        assert_eq!(catalog.skipped, 1);
+        assert_eq!(catalog.entries.len(), 2);
+
+        let sonnet = &catalog.entries[0];
+        assert_eq!(sonnet.model_id, "claude-sonnet-4-20250514");
+        assert_eq!(sonnet.input_cost_per_million, dec("3"));
+        assert_eq!(sonnet.output_cost_per_million, dec("15"));
+        assert_eq!(sonnet.cache_read_cost_per_million, dec("0.3"));
+        assert_eq!(sonnet.cache_creation_cost_per_million, dec("3.75"));
+        assert_eq!(sonnet.max_input_tokens, Some(200_000));
+
+        let qwen = &catalog.entries[1];
+        assert_eq!(qwen.model_id, "qwen3-coder");
+        assert_eq!(qwen.input_cost_per_million, dec("0.22"));
+        assert_eq!(qwen.max_input_tokens, Some(262_144));
+    }
+
+    #[test]
+    fn parses_simple_csv() {
+        let csv = "# 自定义定价\n\
+                   model_id,display_name,input,output,cache_read\n\
+                   my-model,\"My Model, Large\",1.5,6,0.15\n\
+                   broken-row,,abc,1\n";
+        let catalog = parse_csv(csv).unwrap();
+        assert_eq!(catalog.skipped, 1);
+        assert_eq!(catalog.entries.len(), 1);
+        let entry = &catalog.entries[0];
+        assert_eq!(entry.display_name, "My Model, Large");
+        assert_eq!(entry.output_cost_per_million, dec("6"));
+        assert_eq!(entry.cache_creation_cost_per_million, Decimal::ZERO);
+
+        assert!(parse_csv("model,price\nx,1").is_err());
+    }
+
+    #[test]
+    fn import_upserts_and_reports() {
+        let db = Database::memory().unwrap();
+        {
+            let conn = db.conn.lock().unwrap();
+            conn.execute(
+                "UPDATE model_pricing SET source = 'manual', input_cost_per_million = '99'
+                 WHERE model_id = 'claude-sonnet-4-20250514'",
+                [],
+            )
+            .unwrap();
+            conn.execute(
+                "INSERT INTO proxy_request_logs (request_id, provider_id, app_type, model, latency_ms, status_code, created_at)
+                 VALUES ('r1', 'p', 'claude', 'openrouter/unknown-model', 0, 200, 0)",
+                [],
+            )
+            .unwrap();
+        }
+
+        let catalog = parse_litellm(LITELLM_SAMPLE).unwrap();
+        let report = import(&db, &catalog, &PricingImportOptions::default()).unwrap();
+        assert_eq!(report.inserted, 1);
+        assert_eq!(report.skipped_manual, 1);
+        assert_eq!(report.unmatched_models, vec!["unknown-model".to_string()]);
+
+        // 再次导入：无变化
+        let report = import(&db, &catalog, &PricingImportOptions::default()).unwrap();
+        assert_eq!((report.inserted, report.updated, report.unchanged), (0, 0, 1));
+
+        // 允许覆盖手动定价
+        let options = PricingImportOptions { overwrite_manual: true };
+        let report = import(&db, &catalog, &options).unwrap();
+        assert_eq!(report.updated, 1);
+        let conn = db.read_conn().unwrap();
+        let (input, source): (String, String) = conn
+            .query_row(
+                "SELECT input_cost_per_million, source FROM model_pricing WHERE model_id = 'claude-sonnet-4-20250514'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!((input.as_str(), source.as_str()), ("3", "catalog"));
+    }
+}
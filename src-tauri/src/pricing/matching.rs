@@ -0,0 +1,394 @@
+//! 模型 ID 匹配
+//!
+//! 使用记录中的模型名经常与 model_pricing 中的 ID 不完全一致，例如：
+//! - 省略发布日期：`claude-sonnet-4-5` → `claude-sonnet-4-5-20250929`
+//! - 云厂商前缀 / 版本后缀：`us.anthropic.claude-3-5-sonnet-20241022-v2:0`（Bedrock）、
+//!   `claude-3-5-sonnet-v2@20241022`（Vertex）、`anthropic/claude-sonnet-4.5`（OpenRouter）
+//! - 工具自定义写法：`claude-4.5-sonnet-thinking`（Cursor）
+//!
+//! 按以下顺序查找定价：
+//! 1. 精确匹配（`clean_model_id` 之后）
+//! 2. 用户绑定的别名（`model_aliases`）
+//! 3. 模糊匹配：两侧都归一化为模型族键（[`family_key`]）后比较，同族多个版本时取日期最新者
+
+use super::clean_model_id;
+use crate::database::{lock_conn, Database};
+use crate::error::AppError;
+use rusqlite::{Connection, OptionalExtension};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// 云厂商模型名中以 `.` 分隔的前缀（Bedrock 跨区域推理前缀和厂商前缀）
+const DOTTED_PREFIXES: &[&str] = &[
+    "us.", "eu.", "apac.", "global.", "anthropic.", "amazon.", "meta.", "mistral.", "cohere.",
+    "ai21.", "deepseek.", "qwen.", "openai.",
+];
+
+/// Claude 的型号名
+const CLAUDE_TIERS: &[&str] = &["opus", "sonnet", "haiku"];
+
+/// 匹配方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchKind {
+    Exact,
+    Alias,
+    Fuzzy,
+}
+
+/// 模型匹配结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelMatch {
+    /// model_pricing 中的模型 ID
+    pub model_id: String,
+    pub kind: MatchKind,
+}
+
+/// 模型别名
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelAlias {
+    pub alias: String,
+    pub model_id: String,
+    pub created_at: i64,
+}
+
+/// 使用记录中某个模型的定价匹配情况
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageModelMatch {
+    pub model: String,
+    pub request_count: u64,
+    pub total_tokens: u64,
+    /// 匹配到的定价模型 ID（为空表示没有定价）
+    pub matched_model_id: Option<String>,
+    pub match_kind: Option<MatchKind>,
+}
+
+/// 模型族键
+///
+/// 统一大小写和分隔符，去掉云厂商前缀、发布日期和 `latest` 后缀；
+/// Claude 模型统一为 `claude-<型号>-<版本>`，兼容 `claude-3-5-sonnet` / `claude-4.5-sonnet` 等写法
+pub fn family_key(model_id: &str) -> String {
+    let mut id = clean_model_id(model_id).to_lowercase();
+    while let Some(rest) = DOTTED_PREFIXES.iter().find_map(|p| id.strip_prefix(p)) {
+        id = rest.to_string();
+    }
+    let id = id.replace(['.', '_'], "-");
+
+    let mut tokens: Vec<&str> = id.split('-').filter(|t| !t.is_empty()).collect();
+    // 结尾的 YYYY-MM-DD 日期（如 gpt-4o-2024-08-06）
+    if let [.., year, month, day] = tokens[..] {
+        if is_year(year) && is_digits(month, 2) && is_digits(day, 2) {
+            tokens.truncate(tokens.len() - 3);
+        }
+    }
+    tokens.retain(|t| !(is_date(t) || *t == "latest"));
+
+    if tokens.first() == Some(&"claude") {
+        if let Some(tier) = tokens.iter().copied().find(|t| CLAUDE_TIERS.contains(t)) {
+            // Claude 的 -vN 为云厂商 API 版本，不区分模型
+            let version = tokens.iter().filter(|t| is_digits(t, 1) || is_digits(t, 2));
+            let extra = tokens[1..].iter().filter(|t| {
+                **t != tier && !t.chars().all(|c| c.is_ascii_digit()) && !is_api_version(t)
+            });
+            return ["claude", tier]
+                .into_iter()
+                .chain(version.copied())
+                .chain(extra.copied())
+                .collect::<Vec<_>>()
+                .join("-");
+        }
+    }
+    tokens.join("-")
+}
+
+fn is_digits(token: &str, len: usize) -> bool {
+    token.len() == len && token.chars().all(|c| c.is_ascii_digit())
+}
+
+fn is_year(token: &str) -> bool {
+    is_digits(token, 4) && token.starts_with("20")
+}
+
+/// YYYYMMDD 形式的发布日期
+fn is_date(token: &str) -> bool {
+    is_digits(token, 8) && token.starts_with("20")
+}
+
+/// `v1`、`v2` 等 API 版本号
+fn is_api_version(token: &str) -> bool {
+    token
+        .strip_prefix('v')
+        .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// 模型匹配器
+///
+/// 模糊匹配索引在第一次精确匹配和别名匹配都失败时才加载
+pub struct ModelMatcher<'c> {
+    conn: &'c Connection,
+    /// 族键 -> 模型 ID
+    fuzzy_index: Option<HashMap<String, String>>,
+}
+
+impl<'c> ModelMatcher<'c> {
+    pub fn new(conn: &'c Connection) -> Self {
+        Self {
+            conn,
+            fuzzy_index: None,
+        }
+    }
+
+    /// 查找模型对应的定价 ID
+    pub fn find(&mut self, model: &str) -> Result<Option<ModelMatch>, AppError> {
+        let cleaned = clean_model_id(model);
+        if cleaned.is_empty() {
+            return Ok(None);
+        }
+
+        let exact = self
+            .conn
+            .query_row("SELECT 1 FROM model_pricing WHERE model_id = ?1", [&cleaned], |_| Ok(()))
+            .optional()
+            .map_err(|e| AppError::Database(format!("查询模型定价失败: {e}")))?;
+        if exact.is_some() {
+            return Ok(Some(ModelMatch {
+                model_id: cleaned,
+                kind: MatchKind::Exact,
+            }));
+        }
+
+        let alias = self
+            .conn
+            .query_row(
+                "SELECT a.model_id FROM model_aliases a
+                 JOIN model_pricing p ON p.model_id = a.model_id
+                 WHERE a.alias = ?1",
+                [&cleaned],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()
+            .map_err(|e| AppError::Database(format!("查询模型别名失败: {e}")))?;
+        if let Some(model_id) = alias {
+            return Ok(Some(ModelMatch {
+                model_id,
+                kind: MatchKind::Alias,
+            }));
+        }
+
+        let index = self.fuzzy_index()?;
+        let key = family_key(&cleaned);
+        // Cursor 等工具会在模型名后追加 -thinking，定价与原模型相同
+        let found = index
+            .get(&key)
+            .or_else(|| key.strip_suffix("-thinking").and_then(|k| index.get(k)));
+        Ok(found.map(|model_id| ModelMatch {
+            model_id: model_id.clone(),
+            kind: MatchKind::Fuzzy,
+        }))
+    }
+
+    fn fuzzy_index(&mut self) -> Result<&HashMap<String, String>, AppError> {
+        if self.fuzzy_index.is_none() {
+            let mut stmt = self
+                .conn
+                .prepare("SELECT model_id FROM model_pricing")
+                .map_err(|e| AppError::Database(format!("准备查询失败: {e}")))?;
+            let ids: Vec<String> = stmt
+                .query_map([], |row| row.get(0))
+                .map_err(|e| AppError::Database(format!("查询模型定价失败: {e}")))?
+                .collect::<Result<_, _>>()
+                .map_err(|e| AppError::Database(format!("读取模型定价失败: {e}")))?;
+
+            let mut index: HashMap<String, String> = HashMap::new();
+            for id in ids {
+                let key = family_key(&id);
+                // 同族多个版本时取 ID 最大者（即日期最新）
+                match index.get(&key) {
+                    Some(existing) if *existing >= id => {}
+                    _ => {
+                        index.insert(key, id);
+                    }
+                }
+            }
+            self.fuzzy_index = Some(index);
+        }
+        Ok(self.fuzzy_index.get_or_insert_with(HashMap::new))
+    }
+}
+
+/// 使用记录中各模型的定价匹配情况（按请求数降序）
+pub fn usage_model_matches(db: &Database) -> Result<Vec<UsageModelMatch>, AppError> {
+    let conn = db.read_conn()?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT model, COUNT(*),
+                    COALESCE(SUM(input_tokens + output_tokens + cache_read_tokens + cache_creation_tokens), 0)
+             FROM proxy_request_logs GROUP BY model",
+        )
+        .map_err(|e| AppError::Database(format!("准备查询失败: {e}")))?;
+    let rows: Vec<(String, i64, i64)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+        .map_err(|e| AppError::Database(format!("查询使用记录失败: {e}")))?
+        .collect::<Result<_, _>>()
+        .map_err(|e| AppError::Database(format!("读取使用记录失败: {e}")))?;
+
+    // 清洗后相同的模型名合并统计
+    let mut by_model: HashMap<String, (u64, u64)> = HashMap::new();
+    for (model, requests, tokens) in rows {
+        let model = clean_model_id(&model);
+        if model.is_empty() {
+            continue;
+        }
+        let stats = by_model.entry(model).or_default();
+        stats.0 += requests.max(0) as u64;
+        stats.1 += tokens.max(0) as u64;
+    }
+
+    let mut matcher = ModelMatcher::new(&conn);
+    let mut matches = Vec::with_capacity(by_model.len());
+    for (model, (request_count, total_tokens)) in by_model {
+        let found = matcher.find(&model)?;
+        matches.push(UsageModelMatch {
+            model,
+            request_count,
+            total_tokens,
+            match_kind: found.as_ref().map(|m| m.kind),
+            matched_model_id: found.map(|m| m.model_id),
+        });
+    }
+    matches.sort_by(|a, b| b.request_count.cmp(&a.request_count).then_with(|| a.model.cmp(&b.model)));
+    Ok(matches)
+}
+
+/// 使用记录中出现过、但精确 / 别名 / 模糊匹配都找不到定价的模型
+pub fn unmatched_models(db: &Database) -> Result<Vec<String>, AppError> {
+    let mut models: Vec<String> = usage_model_matches(db)?
+        .into_iter()
+        .filter(|m| m.matched_model_id.is_none())
+        .map(|m| m.model)
+        .collect();
+    models.sort();
+    Ok(models)
+}
+
+// ============================================================================
+// 别名管理
+// ============================================================================
+
+/// 列出模型别名
+pub fn list_aliases(db: &Database) -> Result<Vec<ModelAlias>, AppError> {
+    let conn = db.read_conn()?;
+    let mut stmt = conn
+        .prepare("SELECT alias, model_id, created_at FROM model_aliases ORDER BY alias")
+        .map_err(|e| AppError::Database(format!("准备查询失败: {e}")))?;
+    let aliases = stmt
+        .query_map([], |row| {
+            Ok(ModelAlias {
+                alias: row.get(0)?,
+                model_id: row.get(1)?,
+                created_at: row.get(2)?,
+            })
+        })
+        .map_err(|e| AppError::Database(format!("查询模型别名失败: {e}")))?
+        .collect::<Result<_, _>>()
+        .map_err(|e| AppError::Database(format!("读取模型别名失败: {e}")))?;
+    Ok(aliases)
+}
+
+/// 将使用记录中的模型名绑定到已有定价（已绑定时覆盖）
+pub fn bind_alias(db: &Database, alias: &str, model_id: &str) -> Result<(), AppError> {
+    let alias = clean_model_id(alias);
+    let model_id = clean_model_id(model_id);
+    if alias.is_empty() {
+        return Err(AppError::Custom("模型名不能为空".to_string()));
+    }
+    if alias == model_id {
+        return Err(AppError::Custom("不能将模型绑定到自身".to_string()));
+    }
+
+    let conn = lock_conn!(db.conn);
+    let exists = conn
+        .query_row("SELECT 1 FROM model_pricing WHERE model_id = ?1", [&model_id], |_| Ok(()))
+        .optional()
+        .map_err(|e| AppError::Database(format!("查询模型定价失败: {e}")))?;
+    if exists.is_none() {
+        return Err(AppError::Custom(format!("模型定价不存在: {model_id}")));
+    }
+
+    conn.execute(
+        "INSERT INTO model_aliases (alias, model_id, created_at) VALUES (?1, ?2, ?3)
+         ON CONFLICT(alias) DO UPDATE SET model_id = excluded.model_id, created_at = excluded.created_at",
+        rusqlite::params![alias, model_id, chrono::Utc::now().timestamp()],
+    )
+    .map_err(|e| AppError::Database(format!("保存模型别名失败: {e}")))?;
+    Ok(())
+}
+
+/// 解除模型别名
+pub fn unbind_alias(db: &Database, alias: &str) -> Result<(), AppError> {
+    let conn = lock_conn!(db.conn);
+    conn.execute("DELETE FROM model_aliases WHERE alias = ?1", [clean_model_id(alias)])
+        .map_err(|e| AppError::Database(format!("删除模型别名失败: {e}")))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn family_key_normalizes_vendor_names() {
+        let sonnet_35 = family_key("claude-3-5-sonnet-20241022");
+        for name in [
+            "us.anthropic.claude-3-5-sonnet-20241022-v2:0",
+            "claude-3-5-sonnet-v2@20241022",
+            "anthropic/claude-3.5-sonnet",
+            "claude-sonnet-3-5",
+        ] {
+            assert_eq!(family_key(name), sonnet_35, "{name}");
+        }
+        assert_eq!(family_key("claude-4.5-sonnet-thinking"), "claude-sonnet-4-5-thinking");
+        assert_eq!(family_key("gpt-4o-2024-08-06"), family_key("openai/gpt-4o"));
+        assert_eq!(family_key("google/gemini-2.5-pro"), "gemini-2-5-pro");
+        // 非 Claude 模型的版本号有实际含义，不能丢弃
+        assert_ne!(family_key("deepseek-v3"), family_key("deepseek-v3.2"));
+        assert_ne!(family_key("kimi-k2-0905"), family_key("kimi-k2"));
+    }
+
+    #[test]
+    fn matcher_prefers_exact_then_alias_then_fuzzy() {
+        let db = Database::memory().unwrap();
+        let conn = db.conn.lock().unwrap();
+        conn.execute_batch(
+            "INSERT INTO model_pricing (model_id, display_name, input_cost_per_million, output_cost_per_million)
+             VALUES ('claude-3-5-sonnet-20240620', 'Claude 3.5 Sonnet (June)', '3', '15');
+             INSERT INTO model_aliases (alias, model_id, created_at) VALUES ('my-relay-sonnet', 'claude-sonnet-4-20250514', 0);",
+        )
+        .unwrap();
+
+        let mut matcher = ModelMatcher::new(&conn);
+        let found = |matcher: &mut ModelMatcher<'_>, model: &str| {
+            matcher.find(model).unwrap().map(|m| (m.model_id, m.kind))
+        };
+        assert_eq!(
+            found(&mut matcher, "anthropic/claude-sonnet-4-5-20250929"),
+            Some(("claude-sonnet-4-5-20250929".to_string(), MatchKind::Exact))
+        );
+        assert_eq!(
+            found(&mut matcher, "my-relay-sonnet"),
+            Some(("claude-sonnet-4-20250514".to_string(), MatchKind::Alias))
+        );
+        assert_eq!(
+            found(&mut matcher, "claude-4.5-sonnet-thinking"),
+            Some(("claude-sonnet-4-5-20250929".to_string(), MatchKind::Fuzzy))
+        );
+        // 同族取日期最新的版本
+        assert_eq!(
+            found(&mut matcher, "anthropic.claude-3-5-sonnet-v2:0"),
+            Some(("claude-3-5-sonnet-20241022".to_string(), MatchKind::Fuzzy))
+        );
+        assert_eq!(found(&mut matcher, "claude-opus-4-1"), None);
+    }
+}
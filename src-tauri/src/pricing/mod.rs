@@ -0,0 +1,110 @@
+//! 模型定价模块
+//!
+//! 模型 ID 归一化与模糊匹配、成本计算、定价规则（阶梯 / 时段 / 生效日期）、
+//! 服务商计费货币与倍率、从外部定价目录导入价格，以及定价变更后的历史成本重算
+
+pub mod billing;
+pub mod catalog;
+pub mod matching;
+pub mod recalc;
+pub mod rules;
+
+pub use billing::{ConvertCurrency, ExchangeRate, ProviderBilling};
+pub use catalog::{CatalogEntry, PricingImportOptions, PricingImportReport};
+pub use matching::{ModelAlias, UsageModelMatch};
+pub use recalc::{RecalcFilter, RecalcReport};
+pub use rules::{resolve_price, PriceResolver, PricingRule};
+
+use crate::error::AppError;
+use rusqlite::Connection;
+use rust_decimal::Decimal;
+
+/// 成本由日志来源直接提供（未按定价计算）时记录的定价版本，历史重算会跳过这些记录
+pub const SOURCE_COST_VERSION: i64 = 0;
+
+/// 当前定价版本
+///
+/// 定价表、定价规则、模型别名、服务商计费设置或汇率任意变更时由数据库触发器递增，见迁移 v4 / v5 / v6
+pub fn current_version(conn: &Connection) -> Result<i64, AppError> {
+    conn.query_row("SELECT version FROM pricing_version WHERE id = 1", [], |row| row.get(0))
+        .map_err(|e| AppError::Database(format!("读取定价版本失败: {e}")))
+}
+
+/// 清洗模型 ID，用于匹配 model_pricing 中的记录
+///
+/// 去掉服务商前缀（如 "anthropic/"）和版本后缀（如 ":v2"），并将 @ 替换为 -
+pub fn clean_model_id(model_id: &str) -> String {
+    let without_prefix = model_id.rsplit_once('/').map_or(model_id, |(_, r)| r);
+    let without_suffix = without_prefix.split(':').next().unwrap_or(without_prefix);
+    without_suffix.trim().replace('@', "-")
+}
+
+/// 每百万 token 价格（USD）
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PriceRates {
+    pub input: Decimal,
+    pub output: Decimal,
+    pub cache_read: Decimal,
+    pub cache_creation: Decimal,
+}
+
+/// 单次请求的 token 用量
+///
+/// `input` 包含缓存读取部分，计费时会扣除 `cache_read`，避免重复计费
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokenCounts {
+    pub input: u64,
+    pub output: u64,
+    pub cache_read: u64,
+    pub cache_creation: u64,
+}
+
+impl TokenCounts {
+    /// 提示词总长度（用于匹配阶梯定价，如超过 200K 输入 token 的加价档）
+    pub fn prompt_tokens(&self) -> u64 {
+        self.input + self.cache_creation
+    }
+}
+
+/// 成本明细
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CostBreakdown {
+    pub input_cost: Decimal,
+    pub output_cost: Decimal,
+    pub cache_read_cost: Decimal,
+    pub cache_creation_cost: Decimal,
+    pub total_cost: Decimal,
+}
+
+impl PriceRates {
+    /// 按价格计算成本
+    pub fn cost(&self, tokens: &TokenCounts) -> CostBreakdown {
+        let million = Decimal::from(1_000_000u64);
+        let billable_input = tokens.input.saturating_sub(tokens.cache_read);
+
+        let input_cost = Decimal::from(billable_input) * self.input / million;
+        let output_cost = Decimal::from(tokens.output) * self.output / million;
+        let cache_read_cost = Decimal::from(tokens.cache_read) * self.cache_read / million;
+        let cache_creation_cost = Decimal::from(tokens.cache_creation) * self.cache_creation / million;
+
+        CostBreakdown {
+            input_cost,
+            output_cost,
+            cache_read_cost,
+            cache_creation_cost,
+            total_cost: input_cost + output_cost + cache_read_cost + cache_creation_cost,
+        }
+    }
+
+    /// 缓存读取相对按普通输入计费节省的金额：缓存读取 token ×（输入单价 − 缓存读取单价）
+    pub fn cache_savings(&self, tokens: &TokenCounts) -> Decimal {
+        Decimal::from(tokens.cache_read) * (self.input - self.cache_read).max(Decimal::ZERO)
+            / Decimal::from(1_000_000u64)
+    }
+
+    /// 缓存写入相对按普通输入计费多付的金额：缓存写入 token ×（缓存写入单价 − 输入单价）
+    pub fn cache_write_overhead(&self, tokens: &TokenCounts) -> Decimal {
+        Decimal::from(tokens.cache_creation) * (self.cache_creation - self.input).max(Decimal::ZERO)
+            / Decimal::from(1_000_000u64)
+    }
+}
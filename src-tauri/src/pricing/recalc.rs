@@ -0,0 +1,389 @@
+//! 历史成本重算
+//!
+//! 修正 model_pricing / provider_model_pricing / pricing_rules 后，按当前定价重新计算
+//! proxy_request_logs 中已有记录的成本（含服务商当前的计费货币和倍率）。先以预览模式生成变更对比，
+//! 确认后在单个事务内写入，并将每条记录的 `pricing_version` 更新为当前定价版本。
+//!
+//! 以下记录保持原成本不变：
+//! - 成本由日志来源直接提供的记录（`pricing_version` 为 [`SOURCE_COST_VERSION`]）
+//! - 版本记录之前导入的本地日志记录（`pricing_version` 为空，无法区分成本是否由日志提供），
+//!   除非指定 [`RecalcFilter::include_unversioned_local`]
+//! - 当前仍找不到定价的模型
+
+use super::billing::BillingResolver;
+use super::{current_version, PriceResolver, TokenCounts, SOURCE_COST_VERSION};
+use crate::database::{lock_conn, Database};
+use crate::error::AppError;
+use rusqlite::{Connection, TransactionBehavior};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+/// 对比结果中最多返回的单条记录数（按成本差额降序）
+const MAX_SAMPLE_CHANGES: usize = 100;
+
+/// 重算范围
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecalcFilter {
+    /// 起始时间（Unix 秒，含）
+    #[serde(default)]
+    pub start_time: Option<i64>,
+    /// 截止时间（Unix 秒，含）
+    #[serde(default)]
+    pub end_time: Option<i64>,
+    #[serde(default)]
+    pub provider_id: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    /// 记录来源（app_type，如 claude、claude_local）
+    #[serde(default)]
+    pub source: Option<String>,
+    /// 同时重算版本记录之前导入的本地日志记录（可能覆盖日志自带的成本）
+    #[serde(default)]
+    pub include_unversioned_local: bool,
+}
+
+/// 单条记录的成本变化
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecalcChange {
+    pub request_id: String,
+    pub provider_id: String,
+    pub model: String,
+    pub created_at: i64,
+    pub old_cost: String,
+    pub new_cost: String,
+    /// 原计价版本（为空表示版本记录之前计费）
+    pub old_pricing_version: Option<i64>,
+}
+
+/// 按模型汇总的成本变化
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecalcModelDiff {
+    pub model: String,
+    pub records: u32,
+    pub changed: u32,
+    pub old_cost: String,
+    pub new_cost: String,
+}
+
+/// 重算结果（预览与执行共用）
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecalcReport {
+    /// 是否已写入数据库（预览时为 false）
+    pub applied: bool,
+    /// 用于计费的定价版本
+    pub pricing_version: i64,
+    /// 范围内的记录数
+    pub matched: u32,
+    /// 成本发生变化的记录数
+    pub changed: u32,
+    /// 成本由日志来源提供而跳过的记录数
+    pub skipped_source_cost: u32,
+    /// 版本记录之前导入、成本来源未知而跳过的本地日志记录数
+    pub skipped_unversioned_local: u32,
+    /// 没有定价而保持原成本的记录数
+    pub unpriced: u32,
+    pub old_total: String,
+    pub new_total: String,
+    pub by_model: Vec<RecalcModelDiff>,
+    /// 变化最大的记录（最多 MAX_SAMPLE_CHANGES 条）
+    pub changes: Vec<RecalcChange>,
+}
+
+/// 待写入的重算结果
+struct RecalcUpdate {
+    request_id: String,
+    input_cost: Decimal,
+    output_cost: Decimal,
+    cache_read_cost: Decimal,
+    cache_creation_cost: Decimal,
+    total_cost: Decimal,
+    cost_multiplier: Decimal,
+}
+
+/// 预览重算结果（不修改数据库）
+pub fn preview(db: &Database, filter: &RecalcFilter) -> Result<RecalcReport, AppError> {
+    let conn = db.read_conn()?;
+    let (report, _) = compute(&conn, filter)?;
+    Ok(report)
+}
+
+/// 按当前定价重算并在单个事务内写入
+pub fn apply(db: &Database, filter: &RecalcFilter) -> Result<RecalcReport, AppError> {
+    let mut conn = lock_conn!(db.conn);
+    let tx = conn
+        .transaction_with_behavior(TransactionBehavior::Immediate)
+        .map_err(|e| AppError::Database(format!("开启事务失败: {e}")))?;
+
+    let (mut report, updates) = compute(&tx, filter)?;
+    {
+        let mut stmt = tx
+            .prepare(
+                "UPDATE proxy_request_logs SET
+                    input_cost_usd = ?1, output_cost_usd = ?2, cache_read_cost_usd = ?3,
+                    cache_creation_cost_usd = ?4, total_cost_usd = ?5, cost_multiplier = ?6,
+                    pricing_version = ?7
+                 WHERE request_id = ?8",
+            )
+            .map_err(|e| AppError::Database(format!("准备更新失败: {e}")))?;
+        for update in &updates {
+            stmt.execute(rusqlite::params![
+                update.input_cost.to_string(),
+                update.output_cost.to_string(),
+                update.cache_read_cost.to_string(),
+                update.cache_creation_cost.to_string(),
+                update.total_cost.to_string(),
+                update.cost_multiplier.to_string(),
+                report.pricing_version,
+                update.request_id,
+            ])
+            .map_err(|e| AppError::Database(format!("更新记录成本失败: {e}")))?;
+        }
+    }
+    tx.commit()
+        .map_err(|e| AppError::Database(format!("提交事务失败: {e}")))?;
+
+    report.applied = true;
+    Ok(report)
+}
+
+/// 计算范围内记录的新成本
+fn compute(conn: &Connection, filter: &RecalcFilter) -> Result<(RecalcReport, Vec<RecalcUpdate>), AppError> {
+    let pricing_version = current_version(conn)?;
+    let mut resolver = PriceResolver::new(conn);
+    let mut billing = BillingResolver::new(conn);
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT request_id, provider_id, model, input_tokens, output_tokens,
+                    cache_read_tokens, cache_creation_tokens, total_cost_usd, pricing_version, created_at,
+                    app_type
+             FROM proxy_request_logs
+             WHERE (?1 IS NULL OR created_at >= ?1)
+               AND (?2 IS NULL OR created_at <= ?2)
+               AND (?3 IS NULL OR provider_id = ?3)
+               AND (?4 IS NULL OR model = ?4)
+               AND (?5 IS NULL OR app_type = ?5)
+             ORDER BY created_at",
+        )
+        .map_err(|e| AppError::Database(format!("准备查询失败: {e}")))?;
+    let mut rows = stmt
+        .query(rusqlite::params![
+            filter.start_time,
+            filter.end_time,
+            filter.provider_id,
+            filter.model,
+            filter.source,
+        ])
+        .map_err(|e| AppError::Database(format!("查询使用记录失败: {e}")))?;
+
+    let mut report = RecalcReport {
+        applied: false,
+        pricing_version,
+        matched: 0,
+        changed: 0,
+        skipped_source_cost: 0,
+        skipped_unversioned_local: 0,
+        unpriced: 0,
+        old_total: String::new(),
+        new_total: String::new(),
+        by_model: Vec::new(),
+        changes: Vec::new(),
+    };
+    let mut updates = Vec::new();
+    let (mut old_total, mut new_total) = (Decimal::ZERO, Decimal::ZERO);
+    // model -> (记录数, 变化数, 原成本, 新成本)
+    let mut by_model: BTreeMap<String, (u32, u32, Decimal, Decimal)> = BTreeMap::new();
+
+    while let Some(row) = rows
+        .next()
+        .map_err(|e| AppError::Database(format!("读取使用记录失败: {e}")))?
+    {
+        let read = |e: rusqlite::Error| AppError::Database(format!("读取使用记录失败: {e}"));
+        let request_id: String = row.get(0).map_err(read)?;
+        let provider_id: String = row.get(1).map_err(read)?;
+        let model: String = row.get(2).map_err(read)?;
+        let tokens = TokenCounts {
+            input: row.get::<_, i64>(3).map_err(read)?.max(0) as u64,
+            output: row.get::<_, i64>(4).map_err(read)?.max(0) as u64,
+            cache_read: row.get::<_, i64>(5).map_err(read)?.max(0) as u64,
+            cache_creation: row.get::<_, i64>(6).map_err(read)?.max(0) as u64,
+        };
+        let old_cost = Decimal::from_str(&row.get::<_, String>(7).map_err(read)?).unwrap_or(Decimal::ZERO);
+        let old_pricing_version: Option<i64> = row.get(8).map_err(read)?;
+        let created_at: i64 = row.get(9).map_err(read)?;
+        let app_type: String = row.get(10).map_err(read)?;
+
+        report.matched += 1;
+        let rates = if old_pricing_version == Some(SOURCE_COST_VERSION) {
+            report.skipped_source_cost += 1;
+            None
+        } else if old_pricing_version.is_none() && app_type.ends_with("_local") && !filter.include_unversioned_local {
+            report.skipped_unversioned_local += 1;
+            None
+        } else {
+            let rates = resolver.resolve(Some(&provider_id), &model, &tokens, created_at)?;
+            if rates.is_none() {
+                report.unpriced += 1;
+            }
+            rates
+        };
+
+        let new_cost = match rates {
+            Some(rates) => {
+                let factor = billing.factor(&provider_id)?;
+                let cost = rates.cost(&tokens).scaled(factor.usd_factor);
+                updates.push(RecalcUpdate {
+                    request_id: request_id.clone(),
+                    input_cost: cost.input_cost,
+                    output_cost: cost.output_cost,
+                    cache_read_cost: cost.cache_read_cost,
+                    cache_creation_cost: cost.cache_creation_cost,
+                    total_cost: cost.total_cost,
+                    cost_multiplier: factor.multiplier,
+                });
+                cost.total_cost
+            }
+            None => old_cost,
+        };
+
+        old_total += old_cost;
+        new_total += new_cost;
+        let stats = by_model.entry(model.clone()).or_default();
+        stats.0 += 1;
+        stats.2 += old_cost;
+        stats.3 += new_cost;
+        if new_cost != old_cost {
+            report.changed += 1;
+            stats.1 += 1;
+            report.changes.push(RecalcChange {
+                request_id,
+                provider_id,
+                model,
+                created_at,
+                old_cost: old_cost.to_string(),
+                new_cost: new_cost.to_string(),
+                old_pricing_version,
+            });
+        }
+    }
+
+    report.changes.sort_by_key(|c| {
+        let diff = Decimal::from_str(&c.new_cost).unwrap_or_default() - Decimal::from_str(&c.old_cost).unwrap_or_default();
+        std::cmp::Reverse(diff.abs())
+    });
+    report.changes.truncate(MAX_SAMPLE_CHANGES);
+    report.old_total = old_total.to_string();
+    report.new_total = new_total.to_string();
+    report.by_model = by_model
+        .into_iter()
+        .map(|(model, (records, changed, old_cost, new_cost))| RecalcModelDiff {
+            model,
+            records,
+            changed,
+            old_cost: old_cost.to_string(),
+            new_cost: new_cost.to_string(),
+        })
+        .collect();
+
+    Ok((report, updates))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn insert(db: &Database, request_id: &str, model: &str, cost: &str, pricing_version: Option<i64>) {
+        insert_from(db, "claude", request_id, model, cost, pricing_version);
+    }
+
+    fn insert_from(db: &Database, app_type: &str, request_id: &str, model: &str, cost: &str, pricing_version: Option<i64>) {
+        let conn = db.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO proxy_request_logs (
+                request_id, provider_id, app_type, model, input_tokens, output_tokens,
+                total_cost_usd, latency_ms, status_code, pricing_version, created_at
+            ) VALUES (?1, 'p1', ?2, ?3, 100000, 0, ?4, 0, 200, ?5, 1700000000)",
+            rusqlite::params![request_id, app_type, model, cost, pricing_version],
+        )
+        .unwrap();
+    }
+
+    fn stored_cost(db: &Database, id: &str) -> (String, Option<i64>) {
+        let conn = db.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT total_cost_usd, pricing_version FROM proxy_request_logs WHERE request_id = ?1",
+            [id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn preview_leaves_rows_and_apply_recosts_with_version() {
+        let db = Database::memory().unwrap();
+        // claude-sonnet-4-20250514 内置输入价格为 3 USD / 百万 token，10 万 token 未达到 200K 加价档
+        insert(&db, "stale", "claude-sonnet-4-20250514", "1", None);
+        insert(&db, "source", "claude-sonnet-4-20250514", "9", Some(SOURCE_COST_VERSION));
+        insert(&db, "unknown", "no-such-model", "0.5", None);
+
+        let filter = RecalcFilter::default();
+        let report = preview(&db, &filter).unwrap();
+        assert!(!report.applied);
+        assert_eq!((report.matched, report.changed), (3, 1));
+        assert_eq!((report.skipped_source_cost, report.unpriced), (1, 1));
+        assert_eq!(report.old_total.parse::<f64>().unwrap(), 10.5);
+        assert_eq!(report.new_total.parse::<f64>().unwrap(), 9.8);
+        assert_eq!(report.changes[0].request_id, "stale");
+
+        let stored = |id: &str| stored_cost(&db, id);
+        assert_eq!(stored("stale"), ("1".to_string(), None));
+
+        let report = apply(&db, &filter).unwrap();
+        assert!(report.applied);
+        let (cost, version) = stored("stale");
+        assert_eq!(cost.parse::<f64>().unwrap(), 0.3);
+        assert_eq!(version, Some(report.pricing_version));
+        assert_eq!(stored("source"), ("9".to_string(), Some(SOURCE_COST_VERSION)));
+        assert_eq!(stored("unknown"), ("0.5".to_string(), None));
+
+        // 修改定价后版本递增
+        {
+            let conn = db.conn.lock().unwrap();
+            conn.execute(
+                "UPDATE model_pricing SET input_cost_per_million = '4' WHERE model_id = 'claude-sonnet-4-20250514'",
+                [],
+            )
+            .unwrap();
+        }
+        let again = preview(&db, &filter).unwrap();
+        assert!(again.pricing_version > report.pricing_version);
+        assert_eq!(again.new_total.parse::<f64>().unwrap(), 9.9);
+    }
+
+    #[test]
+    fn keeps_unversioned_local_costs_unless_opted_in() {
+        let db = Database::memory().unwrap();
+        // v4 之前导入的 Claude Code 日志记录，成本来自日志中的 costUSD
+        insert_from(&db, "claude_local", "legacy", "claude-sonnet-4-20250514", "2", None);
+
+        let report = apply(&db, &RecalcFilter::default()).unwrap();
+        assert_eq!((report.changed, report.skipped_unversioned_local), (0, 1));
+        assert_eq!(stored_cost(&db, "legacy"), ("2".to_string(), None));
+
+        let filter = RecalcFilter {
+            include_unversioned_local: true,
+            ..Default::default()
+        };
+        let report = apply(&db, &filter).unwrap();
+        assert_eq!((report.changed, report.skipped_unversioned_local), (1, 0));
+        let (cost, version) = stored_cost(&db, "legacy");
+        assert_eq!(cost.parse::<f64>().unwrap(), 0.3);
+        assert_eq!(version, Some(report.pricing_version));
+    }
+}
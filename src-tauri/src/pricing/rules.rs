@@ -0,0 +1,493 @@
+//! 定价规则
+//!
+//! 在 model_pricing / provider_model_pricing 的固定价格之上，按以下条件覆盖价格：
+//! - 阶梯：提示词超过 `min_prompt_tokens` 时整次请求按该档计价（如 200K 以上加价）
+//! - 时段：`time_start`..`time_end`（UTC，HH:MM，可跨零点，如错峰优惠）
+//! - 生效日期：`effective_from`..`effective_to`（Unix 秒，左闭右开），
+//!   按请求的 `created_at` 匹配，历史记录使用当时的价格
+//!
+//! 多条规则同时命中时，依次按 priority、服务商专属、阶梯阈值、生效起点取最高者。
+
+use super::matching::ModelMatcher;
+use super::{clean_model_id, PriceRates, TokenCounts};
+use crate::database::{lock_conn, Database};
+use crate::error::AppError;
+use rusqlite::{Connection, OptionalExtension};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// 定价规则
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PricingRule {
+    /// 规则 ID（新建时为空）
+    #[serde(default)]
+    pub id: Option<i64>,
+    pub model_id: String,
+    /// 仅对指定服务商生效（为空表示所有服务商）
+    #[serde(default)]
+    pub provider_id: Option<String>,
+    /// 提示词 token 超过该值时生效（0 表示不限）
+    #[serde(default)]
+    pub min_prompt_tokens: u64,
+    /// 每日生效时段起点（UTC，HH:MM）
+    #[serde(default)]
+    pub time_start: Option<String>,
+    /// 每日生效时段终点（UTC，HH:MM，不含）
+    #[serde(default)]
+    pub time_end: Option<String>,
+    /// 生效起始时间（Unix 秒，含）
+    #[serde(default)]
+    pub effective_from: Option<i64>,
+    /// 生效截止时间（Unix 秒，不含）
+    #[serde(default)]
+    pub effective_to: Option<i64>,
+    pub input_cost_per_million: String,
+    pub output_cost_per_million: String,
+    #[serde(default = "zero_price")]
+    pub cache_read_cost_per_million: String,
+    #[serde(default = "zero_price")]
+    pub cache_creation_cost_per_million: String,
+    #[serde(default)]
+    pub priority: i64,
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+fn zero_price() -> String {
+    "0".to_string()
+}
+
+impl PricingRule {
+    /// 校验规则字段
+    pub fn validate(&self) -> Result<(), AppError> {
+        if clean_model_id(&self.model_id).is_empty() {
+            return Err(AppError::Custom("模型 ID 不能为空".to_string()));
+        }
+        for (name, value) in [
+            ("输入价格", &self.input_cost_per_million),
+            ("输出价格", &self.output_cost_per_million),
+            ("缓存读取价格", &self.cache_read_cost_per_million),
+            ("缓存写入价格", &self.cache_creation_cost_per_million),
+        ] {
+            match Decimal::from_str(value.trim()) {
+                Ok(price) if !price.is_sign_negative() => {}
+                _ => return Err(AppError::Custom(format!("{name}无效: {value}"))),
+            }
+        }
+        match (&self.time_start, &self.time_end) {
+            (None, None) => {}
+            (Some(start), Some(end)) => {
+                let (start, end) = (parse_hhmm(start)?, parse_hhmm(end)?);
+                if start == end {
+                    return Err(AppError::Custom("时段起止时间不能相同".to_string()));
+                }
+            }
+            _ => return Err(AppError::Custom("时段需要同时设置起止时间".to_string())),
+        }
+        if let (Some(from), Some(to)) = (self.effective_from, self.effective_to) {
+            if from >= to {
+                return Err(AppError::Custom("生效起始时间必须早于截止时间".to_string()));
+            }
+        }
+        Ok(())
+    }
+
+    /// 规则价格
+    pub fn rates(&self) -> PriceRates {
+        let price = |s: &str| Decimal::from_str(s.trim()).unwrap_or(Decimal::ZERO);
+        PriceRates {
+            input: price(&self.input_cost_per_million),
+            output: price(&self.output_cost_per_million),
+            cache_read: price(&self.cache_read_cost_per_million),
+            cache_creation: price(&self.cache_creation_cost_per_million),
+        }
+    }
+
+    /// 规则是否适用于指定请求
+    pub fn matches(&self, prompt_tokens: u64, at: i64) -> bool {
+        if self.min_prompt_tokens > 0 && prompt_tokens <= self.min_prompt_tokens {
+            return false;
+        }
+        if self.effective_from.is_some_and(|from| at < from) || self.effective_to.is_some_and(|to| at >= to) {
+            return false;
+        }
+        if let (Some(start), Some(end)) = (&self.time_start, &self.time_end) {
+            let (Ok(start), Ok(end)) = (parse_hhmm(start), parse_hhmm(end)) else {
+                return false;
+            };
+            let minute = (at.rem_euclid(86_400) / 60) as u16;
+            let in_window = if start < end {
+                minute >= start && minute < end
+            } else {
+                minute >= start || minute < end
+            };
+            if !in_window {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row.get(0)?,
+            model_id: row.get(1)?,
+            provider_id: row.get(2)?,
+            min_prompt_tokens: row.get::<_, i64>(3)?.max(0) as u64,
+            time_start: row.get(4)?,
+            time_end: row.get(5)?,
+            effective_from: row.get(6)?,
+            effective_to: row.get(7)?,
+            input_cost_per_million: row.get(8)?,
+            output_cost_per_million: row.get(9)?,
+            cache_read_cost_per_million: row.get(10)?,
+            cache_creation_cost_per_million: row.get(11)?,
+            priority: row.get(12)?,
+            note: row.get(13)?,
+        })
+    }
+}
+
+const RULE_COLUMNS: &str = "id, model_id, provider_id, min_prompt_tokens, time_start, time_end,
+    effective_from, effective_to, input_cost_per_million, output_cost_per_million,
+    cache_read_cost_per_million, cache_creation_cost_per_million, priority, note";
+
+/// "HH:MM" 转为当天的分钟数
+fn parse_hhmm(value: &str) -> Result<u16, AppError> {
+    let invalid = || AppError::Custom(format!("时间格式无效（应为 HH:MM）: {value}"));
+    let (h, m) = value.trim().split_once(':').ok_or_else(invalid)?;
+    let (h, m): (u16, u16) = (h.parse().map_err(|_| invalid())?, m.parse().map_err(|_| invalid())?);
+    if h > 23 || m > 59 {
+        return Err(invalid());
+    }
+    Ok(h * 60 + m)
+}
+
+/// 按优先级排序（命中时取第一条）
+fn sort_rules(rules: &mut [PricingRule]) {
+    rules.sort_by_key(|r| {
+        (
+            Reverse(r.priority),
+            Reverse(r.provider_id.is_some()),
+            Reverse(r.min_prompt_tokens),
+            Reverse(r.effective_from.unwrap_or(i64::MIN)),
+        )
+    });
+}
+
+// ============================================================================
+// 价格解析
+// ============================================================================
+
+/// 价格解析器
+///
+/// 按 (服务商, 模型) 缓存基础价格和规则，适合批量计费（导入、历史重算）
+pub struct PriceResolver<'c> {
+    conn: &'c Connection,
+    matcher: ModelMatcher<'c>,
+    cache: HashMap<(Option<String>, String), ModelPrices>,
+}
+
+/// 某个 (服务商, 模型) 的基础价格及按优先级排序的规则
+type ModelPrices = (Option<PriceRates>, Vec<PricingRule>);
+
+impl<'c> PriceResolver<'c> {
+    pub fn new(conn: &'c Connection) -> Self {
+        Self {
+            conn,
+            matcher: ModelMatcher::new(conn),
+            cache: HashMap::new(),
+        }
+    }
+
+    /// 解析请求适用的价格
+    ///
+    /// 基础价格优先取服务商专属定价，其次为默认模型定价（含别名和模糊匹配）；命中规则时使用规则价格
+    pub fn resolve(
+        &mut self,
+        provider_id: Option<&str>,
+        model: &str,
+        tokens: &TokenCounts,
+        at: i64,
+    ) -> Result<Option<PriceRates>, AppError> {
+        let key = (provider_id.map(str::to_string), clean_model_id(model));
+        if !self.cache.contains_key(&key) {
+            let loaded = self.load(key.0.as_deref(), &key.1)?;
+            self.cache.insert(key.clone(), loaded);
+        }
+        let (base, rules) = &self.cache[&key];
+
+        let prompt_tokens = tokens.prompt_tokens();
+        Ok(rules
+            .iter()
+            .find(|r| r.matches(prompt_tokens, at))
+            .map(PricingRule::rates)
+            .or(*base))
+    }
+
+    fn load(
+        &mut self,
+        provider_id: Option<&str>,
+        model_id: &str,
+    ) -> Result<ModelPrices, AppError> {
+        // 原始模型 ID 在前，匹配到的定价模型 ID 在后
+        let mut model_ids = vec![model_id.to_string()];
+        if let Some(found) = self.matcher.find(model_id)? {
+            if found.model_id != model_id {
+                model_ids.push(found.model_id);
+            }
+        }
+
+        let mut base = None;
+        if let Some(provider_id) = provider_id {
+            for id in &model_ids {
+                base = query_rates(
+                    self.conn,
+                    "SELECT input_cost_per_million, output_cost_per_million,
+                            cache_read_cost_per_million, cache_creation_cost_per_million
+                     FROM provider_model_pricing WHERE provider_id = ?1 AND model_id = ?2",
+                    &[provider_id, id.as_str()],
+                )?;
+                if base.is_some() {
+                    break;
+                }
+            }
+        }
+        if base.is_none() {
+            if let Some(id) = model_ids.last() {
+                base = query_rates(
+                    self.conn,
+                    "SELECT input_cost_per_million, output_cost_per_million,
+                            cache_read_cost_per_million, cache_creation_cost_per_million
+                     FROM model_pricing WHERE model_id = ?1",
+                    &[id.as_str()],
+                )?;
+            }
+        }
+
+        let mut stmt = self
+            .conn
+            .prepare(&format!(
+                "SELECT {RULE_COLUMNS} FROM pricing_rules
+                 WHERE model_id IN (?1, ?2) AND (provider_id IS NULL OR provider_id = ?3)"
+            ))
+            .map_err(|e| AppError::Database(format!("准备查询失败: {e}")))?;
+        let mut rules: Vec<PricingRule> = stmt
+            .query_map(
+                rusqlite::params![model_ids[0], model_ids.last(), provider_id],
+                PricingRule::from_row,
+            )
+            .map_err(|e| AppError::Database(format!("查询定价规则失败: {e}")))?
+            .collect::<Result<_, _>>()
+            .map_err(|e| AppError::Database(format!("读取定价规则失败: {e}")))?;
+        sort_rules(&mut rules);
+
+        Ok((base, rules))
+    }
+}
+
+fn query_rates(conn: &Connection, sql: &str, params: &[&str]) -> Result<Option<PriceRates>, AppError> {
+    conn.query_row(sql, rusqlite::params_from_iter(params), |row| {
+        let price = |idx: usize| -> rusqlite::Result<Decimal> {
+            Ok(Decimal::from_str(&row.get::<_, String>(idx)?).unwrap_or(Decimal::ZERO))
+        };
+        Ok(PriceRates {
+            input: price(0)?,
+            output: price(1)?,
+            cache_read: price(2)?,
+            cache_creation: price(3)?,
+        })
+    })
+    .optional()
+    .map_err(|e| AppError::Database(format!("查询模型定价失败: {e}")))
+}
+
+/// 解析单次请求的价格
+pub fn resolve_price(
+    conn: &Connection,
+    provider_id: Option<&str>,
+    model: &str,
+    tokens: &TokenCounts,
+    at: i64,
+) -> Result<Option<PriceRates>, AppError> {
+    PriceResolver::new(conn).resolve(provider_id, model, tokens, at)
+}
+
+// ============================================================================
+// 规则管理
+// ============================================================================
+
+/// 列出定价规则（可按模型过滤）
+pub fn list_rules(db: &Database, model_id: Option<&str>) -> Result<Vec<PricingRule>, AppError> {
+    let conn = db.read_conn()?;
+    let model_id = model_id.map(clean_model_id);
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {RULE_COLUMNS} FROM pricing_rules
+             WHERE ?1 IS NULL OR model_id = ?1
+             ORDER BY model_id, priority DESC, min_prompt_tokens, id"
+        ))
+        .map_err(|e| AppError::Database(format!("准备查询失败: {e}")))?;
+    let rules = stmt
+        .query_map([model_id], PricingRule::from_row)
+        .map_err(|e| AppError::Database(format!("查询定价规则失败: {e}")))?
+        .collect::<Result<_, _>>()
+        .map_err(|e| AppError::Database(format!("读取定价规则失败: {e}")))?;
+    Ok(rules)
+}
+
+/// 新增或更新定价规则，返回规则 ID
+pub fn save_rule(db: &Database, rule: &PricingRule) -> Result<i64, AppError> {
+    rule.validate()?;
+    let conn = lock_conn!(db.conn);
+    let model_id = clean_model_id(&rule.model_id);
+    let provider_id = rule.provider_id.as_deref().map(str::trim).filter(|p| !p.is_empty());
+    // 前 13 个参数为规则字段，最后一个为规则 ID（更新）或创建时间（新增）
+    macro_rules! rule_params {
+        ($last:expr) => {
+            rusqlite::params![
+                model_id,
+                provider_id,
+                rule.min_prompt_tokens as i64,
+                rule.time_start.as_deref().map(str::trim),
+                rule.time_end.as_deref().map(str::trim),
+                rule.effective_from,
+                rule.effective_to,
+                rule.input_cost_per_million.trim(),
+                rule.output_cost_per_million.trim(),
+                rule.cache_read_cost_per_million.trim(),
+                rule.cache_creation_cost_per_million.trim(),
+                rule.priority,
+                rule.note,
+                $last,
+            ]
+        };
+    }
+
+    match rule.id {
+        Some(id) => {
+            let updated = conn
+                .execute(
+                    "UPDATE pricing_rules SET
+                        model_id = ?1, provider_id = ?2, min_prompt_tokens = ?3,
+                        time_start = ?4, time_end = ?5, effective_from = ?6, effective_to = ?7,
+                        input_cost_per_million = ?8, output_cost_per_million = ?9,
+                        cache_read_cost_per_million = ?10, cache_creation_cost_per_million = ?11,
+                        priority = ?12, note = ?13
+                     WHERE id = ?14",
+                    rule_params!(id),
+                )
+                .map_err(|e| AppError::Database(format!("更新定价规则失败: {e}")))?;
+            if updated == 0 {
+                return Err(AppError::Custom(format!("定价规则不存在: {id}")));
+            }
+            Ok(id)
+        }
+        None => {
+            conn.execute(
+                "INSERT INTO pricing_rules (
+                    model_id, provider_id, min_prompt_tokens, time_start, time_end,
+                    effective_from, effective_to, input_cost_per_million, output_cost_per_million,
+                    cache_read_cost_per_million, cache_creation_cost_per_million, priority, note,
+                    created_at
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+                rule_params!(chrono::Utc::now().timestamp()),
+            )
+            .map_err(|e| AppError::Database(format!("添加定价规则失败: {e}")))?;
+            Ok(conn.last_insert_rowid())
+        }
+    }
+}
+
+/// 删除定价规则
+pub fn delete_rule(db: &Database, id: i64) -> Result<(), AppError> {
+    let conn = lock_conn!(db.conn);
+    conn.execute("DELETE FROM pricing_rules WHERE id = ?1", [id])
+        .map_err(|e| AppError::Database(format!("删除定价规则失败: {e}")))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(model_id: &str, input: &str) -> PricingRule {
+        PricingRule {
+            id: None,
+            model_id: model_id.to_string(),
+            provider_id: None,
+            min_prompt_tokens: 0,
+            time_start: None,
+            time_end: None,
+            effective_from: None,
+            effective_to: None,
+            input_cost_per_million: input.to_string(),
+            output_cost_per_million: "10".to_string(),
+            cache_read_cost_per_million: "0".to_string(),
+            cache_creation_cost_per_million: "0".to_string(),
+            priority: 0,
+            note: None,
+        }
+    }
+
+    fn tokens(input: u64) -> TokenCounts {
+        TokenCounts {
+            input,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn time_window_wraps_midnight() {
+        let mut r = rule("deepseek-chat", "0.135");
+        r.time_start = Some("16:30".into());
+        r.time_end = Some("00:30".into());
+        let day = 1_750_000_000 - 1_750_000_000 % 86_400;
+        assert!(r.matches(0, day + 17 * 3600));
+        assert!(r.matches(0, day + 10 * 60));
+        assert!(!r.matches(0, day + 12 * 3600));
+    }
+
+    #[test]
+    fn validate_rejects_bad_rules() {
+        assert!(rule("m", "1").validate().is_ok());
+        assert!(rule("", "1").validate().is_err());
+        assert!(rule("m", "-1").validate().is_err());
+        let mut r = rule("m", "1");
+        r.time_start = Some("25:00".into());
+        r.time_end = Some("01:00".into());
+        assert!(r.validate().is_err());
+        let mut r = rule("m", "1");
+        r.effective_from = Some(10);
+        r.effective_to = Some(10);
+        assert!(r.validate().is_err());
+    }
+
+    #[test]
+    fn resolver_applies_tiers_and_effective_dates() {
+        let db = Database::memory().unwrap();
+        // 内置规则：gemini-2.5-pro 超过 200K 输入 token 加价
+        let conn = db.conn.lock().unwrap();
+        let mut resolver = PriceResolver::new(&conn);
+        let normal = resolver.resolve(None, "gemini-2.5-pro", &tokens(1_000), 0).unwrap().unwrap();
+        let long = resolver.resolve(None, "google/gemini-2.5-pro", &tokens(250_000), 0).unwrap().unwrap();
+        assert_eq!(normal.input, Decimal::from_str("1.25").unwrap());
+        assert_eq!(long.input, Decimal::from_str("2.5").unwrap());
+        drop(conn);
+
+        // 2025 年之前使用旧价格
+        let mut old = rule("gpt-5", "5");
+        old.effective_to = Some(1_735_689_600);
+        save_rule(&db, &old).unwrap();
+        let conn = db.conn.lock().unwrap();
+        let before = resolve_price(&conn, None, "gpt-5", &tokens(1), 1_700_000_000).unwrap().unwrap();
+        let after = resolve_price(&conn, None, "gpt-5", &tokens(1), 1_740_000_000).unwrap().unwrap();
+        assert_eq!(before.input, Decimal::from(5));
+        assert_eq!(after.input, Decimal::from_str("1.25").unwrap());
+        assert!(resolve_price(&conn, None, "no-such-model", &tokens(1), 0).unwrap().is_none());
+    }
+}
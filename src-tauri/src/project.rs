@@ -0,0 +1,88 @@
+//! 使用记录的项目归属
+//!
+//! 项目取会话工作目录所在的 git 仓库根目录（向上查找 `.git`）；
+//! 不在仓库中或目录已不存在（如在其他机器上导入的日志）时直接使用工作目录。
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+/// 代理请求中指定项目的请求头，值为工作目录或项目路径，非 ASCII 字符需 URL 编码（不会转发给上游）
+pub const PROJECT_HEADER: &str = "x-ai-switch-project";
+
+/// 已解析目录的缓存上限
+///
+/// 代理请求头中的路径由客户端提供，缓存满时整体清空，避免无限增长
+const CACHE_CAPACITY: usize = 1024;
+
+/// 同一会话的每条记录都会查询，缓存已解析的目录
+static CACHE: OnceLock<Mutex<HashMap<String, String>>> = OnceLock::new();
+
+/// 将工作目录解析为项目路径，空路径返回 None
+///
+/// 未缓存时需要访问文件系统，异步上下文中应在阻塞线程中调用
+pub fn project_root(cwd: &str) -> Option<String> {
+    let cwd = cwd.trim();
+    let cwd = if cwd.len() > 1 { cwd.trim_end_matches(['/', '\\']) } else { cwd };
+    if cwd.is_empty() {
+        return None;
+    }
+
+    let cache = CACHE.get_or_init(Default::default);
+    if let Some(root) = cache.lock().ok().and_then(|c| c.get(cwd).cloned()) {
+        return Some(root);
+    }
+
+    let path = Path::new(cwd);
+    let root = path
+        .ancestors()
+        .find(|dir| dir.join(".git").exists())
+        .unwrap_or(path)
+        .to_string_lossy()
+        .into_owned();
+    if let Ok(mut cache) = cache.lock() {
+        if cache.len() >= CACHE_CAPACITY {
+            cache.clear();
+        }
+        cache.insert(cwd.to_string(), root.clone());
+    }
+    Some(root)
+}
+
+/// 项目显示名称（路径最后一段）
+pub fn project_name(project: &str) -> &str {
+    project
+        .rsplit(['/', '\\'])
+        .find(|s| !s.is_empty())
+        .unwrap_or(project)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_git_root_or_keeps_cwd() {
+        let dir = std::env::temp_dir().join(format!("ai-switch-project-{}", uuid::Uuid::new_v4()));
+        let nested = dir.join("crates").join("core");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::create_dir_all(dir.join(".git")).unwrap();
+
+        let root = dir.to_string_lossy().into_owned();
+        assert_eq!(project_root(&format!("{}/", nested.display())), Some(root.clone()));
+        assert_eq!(project_name(&root), dir.file_name().unwrap().to_str().unwrap());
+        // 已不存在的目录保持原样
+        assert_eq!(project_root("/nonexistent/work/app"), Some("/nonexistent/work/app".to_string()));
+        assert_eq!(project_root("  "), None);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn cache_is_bounded() {
+        for i in 0..CACHE_CAPACITY + 10 {
+            project_root(&format!("/nonexistent/cache/{i}"));
+        }
+        assert!(CACHE.get().unwrap().lock().unwrap().len() <= CACHE_CAPACITY);
+    }
+}
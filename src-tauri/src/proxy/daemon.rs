@@ -0,0 +1,328 @@
+//! 无界面代理守护进程
+//!
+//! 不依赖 Tauri 窗口，直接使用与桌面端相同的数据库启动代理服务器，
+//! 适用于服务器、CI 或 systemd/launchd 托管场景。
+//!
+//! 配置来源（后者覆盖前者）：
+//! 1. 数据库中的 proxy_config（监听地址/端口）
+//! 2. JSON 配置文件（`--config` 指定，或 `AI_SWITCH_PROXY_CONFIG`，默认 `~/.ai-switch/proxy-daemon.json`）
+//! 3. 环境变量 `AI_SWITCH_PROXY_ADDRESS` / `AI_SWITCH_PROXY_PORT` /
+//!    `AI_SWITCH_PROXY_TAKEOVER` / `AI_SWITCH_PROXY_RESTORE`
+
+use super::{ProxyConfig, ProxyService};
+use crate::database::schema::ProxyConfigDb;
+use crate::database::Database;
+use crate::error::AppError;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// 支持接管的应用
+const TAKEOVER_APPS: [&str; 3] = ["claude", "codex", "gemini"];
+
+/// 守护进程配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DaemonConfig {
+    /// 监听地址（为空时使用数据库配置）
+    pub listen_address: Option<String>,
+    /// 监听端口（为空时使用数据库配置）
+    pub listen_port: Option<u16>,
+    /// 启动时接管的应用（claude / codex / gemini）
+    pub takeover: Vec<String>,
+    /// 退出时是否恢复被接管的配置
+    pub restore_on_exit: bool,
+}
+
+impl Default for DaemonConfig {
+    fn default() -> Self {
+        Self {
+            listen_address: None,
+            listen_port: None,
+            takeover: Vec::new(),
+            restore_on_exit: true,
+        }
+    }
+}
+
+impl DaemonConfig {
+    /// 默认配置文件路径
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::home_dir().map(|home| home.join(".ai-switch").join("proxy-daemon.json"))
+    }
+
+    /// 守护进程 PID 文件路径（供 CLI 停止守护进程）
+    pub fn pid_path() -> Option<PathBuf> {
+        dirs::home_dir().map(|home| home.join(".ai-switch").join("proxy-daemon.pid"))
+    }
+
+    /// 实际监听地址：配置文件 / 环境变量优先，否则使用数据库配置
+    pub fn listen_on(&self, db_config: &ProxyConfigDb) -> (String, u16) {
+        (
+            self.listen_address
+                .clone()
+                .unwrap_or_else(|| db_config.listen_address.clone()),
+            self.listen_port.unwrap_or(db_config.listen_port),
+        )
+    }
+
+    /// 加载配置：配置文件 + 环境变量覆盖
+    pub fn load(path: Option<&Path>) -> Result<Self, AppError> {
+        let explicit = path
+            .map(Path::to_path_buf)
+            .or_else(|| std::env::var_os("AI_SWITCH_PROXY_CONFIG").map(PathBuf::from));
+
+        let mut config = match explicit {
+            // 显式指定的配置文件必须存在
+            Some(path) => Self::from_file(&path)?,
+            None => match Self::default_path().filter(|p| p.exists()) {
+                Some(path) => Self::from_file(&path)?,
+                None => Self::default(),
+            },
+        };
+
+        config.apply_env_with(|key| std::env::var(key).ok())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// 从 JSON 文件读取配置
+    fn from_file(path: &Path) -> Result<Self, AppError> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| AppError::Custom(format!("读取配置文件 {} 失败: {e}", path.display())))?;
+        serde_json::from_str(&content)
+            .map_err(|e| AppError::Custom(format!("解析配置文件 {} 失败: {e}", path.display())))
+    }
+
+    /// 应用环境变量覆盖（通过闭包读取，便于测试）
+    fn apply_env_with<F>(&mut self, get: F) -> Result<(), AppError>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        if let Some(address) = get("AI_SWITCH_PROXY_ADDRESS").filter(|v| !v.trim().is_empty()) {
+            self.listen_address = Some(address.trim().to_string());
+        }
+        if let Some(port) = get("AI_SWITCH_PROXY_PORT").filter(|v| !v.trim().is_empty()) {
+            let port = port
+                .trim()
+                .parse::<u16>()
+                .map_err(|e| AppError::Custom(format!("AI_SWITCH_PROXY_PORT 无效: {e}")))?;
+            self.listen_port = Some(port);
+        }
+        if let Some(apps) = get("AI_SWITCH_PROXY_TAKEOVER") {
+            self.takeover = apps
+                .split(',')
+                .map(|s| s.trim().to_lowercase())
+                .filter(|s| !s.is_empty())
+                .collect();
+        }
+        if let Some(restore) = get("AI_SWITCH_PROXY_RESTORE") {
+            self.restore_on_exit = !matches!(
+                restore.trim().to_lowercase().as_str(),
+                "0" | "false" | "no" | "off"
+            );
+        }
+        Ok(())
+    }
+
+    /// 校验接管应用列表
+    pub fn validate(&self) -> Result<(), AppError> {
+        for app in &self.takeover {
+            if !TAKEOVER_APPS.contains(&app.as_str()) {
+                return Err(AppError::Custom(format!(
+                    "不支持接管的应用: {app}（可选: {}）",
+                    TAKEOVER_APPS.join(", ")
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// PID 文件内容：守护进程 PID 及其实际监听地址
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DaemonState {
+    pub pid: u32,
+    /// 旧版 PID 文件只有 PID，没有监听地址
+    #[serde(default)]
+    pub address: Option<String>,
+    #[serde(default)]
+    pub port: Option<u16>,
+}
+
+impl DaemonState {
+    /// 读取 PID 文件（兼容只含 PID 的旧格式）
+    pub fn read(path: &Path) -> Option<Self> {
+        Self::parse(&std::fs::read_to_string(path).ok()?)
+    }
+
+    fn parse(content: &str) -> Option<Self> {
+        let content = content.trim();
+        serde_json::from_str(content).ok().or_else(|| {
+            content.parse().ok().map(|pid| Self {
+                pid,
+                address: None,
+                port: None,
+            })
+        })
+    }
+
+    fn write(&self, path: &Path) -> std::io::Result<()> {
+        std::fs::write(path, serde_json::to_string(self).unwrap_or_default())
+    }
+}
+
+/// 运行守护进程，直到收到 SIGINT/SIGTERM
+pub async fn run(config: DaemonConfig) -> Result<(), AppError> {
+    let db = Arc::new(Database::open()?);
+
+    let (listen_address, listen_port) = config.listen_on(&db.get_proxy_config()?);
+    let listen = ProxyConfig {
+        listen_address,
+        listen_port,
+        enable_logging: true,
+    };
+
+    let service = ProxyService::with_listen_config(db, listen);
+
+    let info = if config.takeover.is_empty() {
+        service.start().await?
+    } else {
+        let apps: Vec<&str> = config.takeover.iter().map(|s| s.as_str()).collect();
+        service.start_with_takeover(&apps).await?
+    };
+
+    let pid_path = DaemonConfig::pid_path();
+    if let Some(path) = &pid_path {
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        let state = DaemonState {
+            pid: std::process::id(),
+            address: Some(info.address.clone()),
+            port: Some(info.port),
+        };
+        if let Err(e) = state.write(path) {
+            eprintln!("写入 PID 文件失败: {e}");
+        }
+    }
+
+    eprintln!("代理已启动: http://{}:{}", info.address, info.port);
+    if !config.takeover.is_empty() {
+        eprintln!("已接管: {}", config.takeover.join(", "));
+    }
+
+    shutdown_signal().await;
+    eprintln!("收到退出信号，正在停止代理...");
+
+    if let Some(path) = &pid_path {
+        let _ = std::fs::remove_file(path);
+    }
+
+    if !config.takeover.is_empty() && config.restore_on_exit {
+        service.stop_with_restore().await?;
+    } else {
+        service.stop().await?;
+    }
+
+    eprintln!("代理已停止");
+    Ok(())
+}
+
+/// 等待 Ctrl+C 或 SIGTERM
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            eprintln!("监听 Ctrl+C 失败: {e}");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut sig) => {
+                sig.recv().await;
+            }
+            Err(e) => {
+                eprintln!("监听 SIGTERM 失败: {e}");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn parses_partial_config_file() {
+        let config: DaemonConfig =
+            serde_json::from_str(r#"{ "listenPort": 18080, "takeover": ["claude"] }"#).unwrap();
+        assert_eq!(config.listen_port, Some(18080));
+        assert_eq!(config.listen_address, None);
+        assert_eq!(config.takeover, vec!["claude".to_string()]);
+        assert!(config.restore_on_exit);
+    }
+
+    #[test]
+    fn env_overrides_file_values() {
+        let env: HashMap<&str, &str> = HashMap::from([
+            ("AI_SWITCH_PROXY_ADDRESS", "0.0.0.0"),
+            ("AI_SWITCH_PROXY_PORT", "15800"),
+            ("AI_SWITCH_PROXY_TAKEOVER", "Claude, codex,"),
+            ("AI_SWITCH_PROXY_RESTORE", "false"),
+        ]);
+        let mut config = DaemonConfig {
+            listen_port: Some(1),
+            ..Default::default()
+        };
+        config
+            .apply_env_with(|k| env.get(k).map(|v| v.to_string()))
+            .unwrap();
+
+        assert_eq!(config.listen_address.as_deref(), Some("0.0.0.0"));
+        assert_eq!(config.listen_port, Some(15800));
+        assert_eq!(config.takeover, vec!["claude".to_string(), "codex".to_string()]);
+        assert!(!config.restore_on_exit);
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn reads_state_and_legacy_pid_file() {
+        let state = DaemonState {
+            pid: 4242,
+            address: Some("0.0.0.0".to_string()),
+            port: Some(15800),
+        };
+        let json = serde_json::to_string(&state).unwrap();
+        assert_eq!(DaemonState::parse(&json), Some(state));
+
+        let legacy = DaemonState::parse("4242\n").unwrap();
+        assert_eq!((legacy.pid, legacy.address, legacy.port), (4242, None, None));
+        assert_eq!(DaemonState::parse("not a pid"), None);
+    }
+
+    #[test]
+    fn rejects_invalid_port_and_app() {
+        let mut config = DaemonConfig::default();
+        assert!(config
+            .apply_env_with(|k| (k == "AI_SWITCH_PROXY_PORT").then(|| "abc".to_string()))
+            .is_err());
+
+        config.takeover = vec!["opencode".to_string()];
+        assert!(config.validate().is_err());
+    }
+}
@@ -5,6 +5,7 @@
 use super::server::ProxyState;
 use super::types::*;
 use super::usage::{log_usage, TokenUsage};
+use crate::project::{project_root, PROJECT_HEADER};
 use axum::{
     body::Body,
     extract::{Path, State},
@@ -34,6 +35,7 @@ pub async fn get_status(State(state): State<ProxyState>) -> Result<Json<ProxySta
     if let Some(start) = *state.start_time.read().await {
         status.uptime_seconds = start.elapsed().as_secs();
     }
+    status.pid = std::process::id();
     
     Ok(Json(status))
 }
@@ -45,6 +47,7 @@ pub async fn handle_claude(
     Json(body): Json<Value>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
     let start_time = Instant::now();
+    let project = get_project(&headers).await;
     let is_stream = body.get("stream").and_then(|v| v.as_bool()).unwrap_or(false);
     let model = body.get("model").and_then(|v| v.as_str()).unwrap_or("unknown").to_string();
 
@@ -68,7 +71,7 @@ pub async fn handle_claude(
     // 复制其他头部
     for (key, value) in headers.iter() {
         let key_str = key.as_str().to_lowercase();
-        if !["host", "content-length", "x-api-key", "authorization"].contains(&key_str.as_str()) {
+        if !["host", "content-length", "x-api-key", "authorization", PROJECT_HEADER].contains(&key_str.as_str()) {
             if let Ok(v) = value.to_str() {
                 req_builder = req_builder.header(key.as_str(), v);
             }
@@ -127,6 +130,7 @@ pub async fn handle_claude(
                     usage,
                     latency_ms,
                     status_code.as_u16(),
+                    project.as_deref(),
                 );
             }
         }
@@ -145,6 +149,7 @@ pub async fn handle_codex(
     Json(body): Json<Value>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
     let start_time = Instant::now();
+    let project = get_project(&headers).await;
     let is_stream = body.get("stream").and_then(|v| v.as_bool()).unwrap_or(false);
     let model = body.get("model").and_then(|v| v.as_str()).unwrap_or("unknown").to_string();
 
@@ -167,7 +172,7 @@ pub async fn handle_codex(
     // 复制其他头部
     for (key, value) in headers.iter() {
         let key_str = key.as_str().to_lowercase();
-        if !["host", "content-length", "authorization"].contains(&key_str.as_str()) {
+        if !["host", "content-length", "authorization", PROJECT_HEADER].contains(&key_str.as_str()) {
             if let Ok(v) = value.to_str() {
                 req_builder = req_builder.header(key.as_str(), v);
             }
@@ -220,6 +225,7 @@ pub async fn handle_codex(
                     usage,
                     latency_ms,
                     status_code.as_u16(),
+                    project.as_deref(),
                 );
             }
         }
@@ -238,6 +244,7 @@ pub async fn handle_codex_responses(
     Json(body): Json<Value>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
     let start_time = Instant::now();
+    let project = get_project(&headers).await;
     let is_stream = body.get("stream").and_then(|v| v.as_bool()).unwrap_or(false);
     let model = body.get("model").and_then(|v| v.as_str()).unwrap_or("unknown").to_string();
 
@@ -257,7 +264,7 @@ pub async fn handle_codex_responses(
 
     for (key, value) in headers.iter() {
         let key_str = key.as_str().to_lowercase();
-        if !["host", "content-length", "authorization"].contains(&key_str.as_str()) {
+        if !["host", "content-length", "authorization", PROJECT_HEADER].contains(&key_str.as_str()) {
             if let Ok(v) = value.to_str() {
                 req_builder = req_builder.header(key.as_str(), v);
             }
@@ -307,6 +314,7 @@ pub async fn handle_codex_responses(
                     usage,
                     latency_ms,
                     status_code.as_u16(),
+                    project.as_deref(),
                 );
             }
         }
@@ -326,6 +334,7 @@ pub async fn handle_gemini(
     Json(body): Json<Value>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
     let start_time = Instant::now();
+    let project = get_project(&headers).await;
     
     // 从路径提取模型名称
     let model = extract_gemini_model(&path).unwrap_or("unknown".to_string());
@@ -348,7 +357,7 @@ pub async fn handle_gemini(
 
     for (key, value) in headers.iter() {
         let key_str = key.as_str().to_lowercase();
-        if !["host", "content-length"].contains(&key_str.as_str()) {
+        if !["host", "content-length", PROJECT_HEADER].contains(&key_str.as_str()) {
             if let Ok(v) = value.to_str() {
                 req_builder = req_builder.header(key.as_str(), v);
             }
@@ -386,6 +395,7 @@ pub async fn handle_gemini(
                 usage,
                 latency_ms,
                 status_code.as_u16(),
+                project.as_deref(),
             );
         }
     }
@@ -451,6 +461,18 @@ fn get_gemini_config(headers: &HeaderMap) -> (String, String) {
     (api_key, base_url)
 }
 
+/// 从请求头获取项目（可选，用于按项目统计；非 ASCII 路径需 URL 编码）
+///
+/// 查找仓库根目录需要访问文件系统，在阻塞线程中执行
+async fn get_project(headers: &HeaderMap) -> Option<String> {
+    let value = headers.get(PROJECT_HEADER)?.to_str().ok()?;
+    let path = urlencoding::decode(value).ok()?.into_owned();
+    tokio::task::spawn_blocking(move || project_root(&path))
+        .await
+        .ok()
+        .flatten()
+}
+
 /// 从 Gemini API 路径提取模型名称
 fn extract_gemini_model(path: &str) -> Option<String> {
     // 路径格式: models/{model}:generateContent
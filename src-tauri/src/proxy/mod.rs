@@ -2,6 +2,7 @@
 //!
 //! 提供本地 HTTP 代理服务，拦截 CLI 工具的 API 请求并记录使用量
 
+pub mod daemon;
 pub mod handlers;
 pub mod server;
 pub mod service;
@@ -12,8 +12,9 @@ use axum::{
 };
 use std::net::SocketAddr;
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use tokio::sync::{oneshot, RwLock};
+use tokio::task::JoinHandle;
 
 /// 代理服务器状态（共享）
 #[derive(Clone)]
@@ -29,8 +30,12 @@ pub struct ProxyServer {
     config: ProxyConfig,
     state: ProxyState,
     shutdown_tx: Arc<RwLock<Option<oneshot::Sender<()>>>>,
+    serve_handle: Arc<RwLock<Option<JoinHandle<()>>>>,
 }
 
+/// 停止时等待进行中请求完成的最长时间
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
 impl ProxyServer {
     /// 创建新的代理服务器
     pub fn new(config: ProxyConfig, db: Arc<Database>) -> Self {
@@ -45,6 +50,7 @@ impl ProxyServer {
             config,
             state,
             shutdown_tx: Arc::new(RwLock::new(None)),
+            serve_handle: Arc::new(RwLock::new(None)),
         }
     }
 
@@ -86,7 +92,7 @@ impl ProxyServer {
 
         // 启动服务器
         let state = self.state.clone();
-        tokio::spawn(async move {
+        let handle = tokio::spawn(async move {
             axum::serve(listener, app)
                 .with_graceful_shutdown(async {
                     shutdown_rx.await.ok();
@@ -98,6 +104,7 @@ impl ProxyServer {
             state.status.write().await.running = false;
             *state.start_time.write().await = None;
         });
+        *self.serve_handle.write().await = Some(handle);
 
         Ok(ProxyServerInfo {
             address: self.config.listen_address.clone(),
@@ -110,6 +117,10 @@ impl ProxyServer {
     pub async fn stop(&self) -> Result<(), AppError> {
         if let Some(tx) = self.shutdown_tx.write().await.take() {
             let _ = tx.send(());
+            // 等待进行中的请求处理完毕，超时则放弃等待
+            if let Some(handle) = self.serve_handle.write().await.take() {
+                let _ = tokio::time::timeout(SHUTDOWN_TIMEOUT, handle).await;
+            }
             Ok(())
         } else {
             Err(AppError::Proxy("代理服务器未运行".to_string()))
@@ -124,6 +135,7 @@ impl ProxyServer {
         if let Some(start) = *self.state.start_time.read().await {
             status.uptime_seconds = start.elapsed().as_secs();
         }
+        status.pid = std::process::id();
 
         status
     }
@@ -16,6 +16,8 @@ const PROXY_TOKEN_PLACEHOLDER: &str = "PROXY_MANAGED";
 pub struct ProxyService {
     db: Arc<Database>,
     server: Arc<RwLock<Option<ProxyServer>>>,
+    /// 监听配置覆盖（为空时使用数据库中的 proxy_config）
+    listen_override: Option<ProxyConfig>,
 }
 
 impl ProxyService {
@@ -24,6 +26,16 @@ impl ProxyService {
         Self {
             db,
             server: Arc::new(RwLock::new(None)),
+            listen_override: None,
+        }
+    }
+
+    /// 创建使用指定监听配置的代理服务（用于无界面守护进程）
+    pub fn with_listen_config(db: Arc<Database>, config: ProxyConfig) -> Self {
+        Self {
+            db,
+            server: Arc::new(RwLock::new(None)),
+            listen_override: Some(config),
         }
     }
 
@@ -31,10 +43,13 @@ impl ProxyService {
     pub async fn start(&self) -> Result<ProxyServerInfo, AppError> {
         let config_db = self.db.get_proxy_config()?;
         
-        let config = ProxyConfig {
-            listen_address: config_db.listen_address.clone(),
-            listen_port: config_db.listen_port,
-            enable_logging: true,
+        let config = match &self.listen_override {
+            Some(config) => config.clone(),
+            None => ProxyConfig {
+                listen_address: config_db.listen_address.clone(),
+                listen_port: config_db.listen_port,
+                enable_logging: true,
+            },
         };
 
         let server = ProxyServer::new(config, self.db.clone());
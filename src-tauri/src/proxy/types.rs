@@ -41,6 +41,9 @@ pub struct ProxyStatus {
     pub failed_requests: u64,
     /// 运行时间（秒）
     pub uptime_seconds: u64,
+    /// 代理所在进程的 PID（CLI 停止守护进程前据此核对 PID 文件）
+    #[serde(default)]
+    pub pid: u32,
 }
 
 /// 代理服务器信息
@@ -3,30 +3,11 @@
 use super::parser::TokenUsage;
 use crate::database::{lock_conn, Database};
 use crate::error::AppError;
+use crate::pricing::billing::billing_factor;
+use crate::pricing::{self, resolve_price, TokenCounts};
 use crate::proxy::types::AppType;
-use rust_decimal::Decimal;
-use std::str::FromStr;
 use std::time::SystemTime;
 
-/// 模型定价信息
-#[derive(Debug, Clone)]
-pub struct ModelPricing {
-    pub input_cost_per_million: Decimal,
-    pub output_cost_per_million: Decimal,
-    pub cache_read_cost_per_million: Decimal,
-    pub cache_creation_cost_per_million: Decimal,
-}
-
-/// 成本明细
-#[derive(Debug, Clone)]
-pub struct CostBreakdown {
-    pub input_cost: Decimal,
-    pub output_cost: Decimal,
-    pub cache_read_cost: Decimal,
-    pub cache_creation_cost: Decimal,
-    pub total_cost: Decimal,
-}
-
 /// 记录使用量到数据库
 pub fn log_usage(
     db: &Database,
@@ -37,28 +18,38 @@ pub fn log_usage(
     usage: TokenUsage,
     latency_ms: u64,
     status_code: u16,
+    project: Option<&str>,
 ) -> Result<(), AppError> {
     let conn = lock_conn!(db.conn);
 
-    // 获取模型定价
-    let pricing = get_model_pricing(&conn, model)?;
-    
-    // 计算成本
-    let cost = calculate_cost(&usage, pricing.as_ref());
-
     let request_id = uuid::Uuid::new_v4().to_string();
     let created_at = SystemTime::now()
         .duration_since(SystemTime::UNIX_EPOCH)
         .map(|d| d.as_secs() as i64)
         .unwrap_or(0);
 
+    // 按服务商、请求时间和提示词长度解析价格（含阶梯 / 时段规则）并计算成本，与历史重算一致
+    let tokens = TokenCounts {
+        input: usage.input_tokens as u64,
+        output: usage.output_tokens as u64,
+        cache_read: usage.cache_read_tokens as u64,
+        cache_creation: usage.cache_creation_tokens as u64,
+    };
+    let (cost, pricing_version) = match resolve_price(&conn, Some(provider_id), model, &tokens, created_at)? {
+        Some(rates) => (rates.cost(&tokens), Some(pricing::current_version(&conn)?)),
+        None => (Default::default(), None),
+    };
+    // 按服务商的计费货币和倍率换算为实际花费的美元
+    let billing = billing_factor(&conn, provider_id)?;
+    let cost = cost.scaled(billing.usd_factor);
+
     conn.execute(
         "INSERT INTO proxy_request_logs (
             request_id, provider_id, provider_name, app_type, model,
             input_tokens, output_tokens, cache_read_tokens, cache_creation_tokens,
             input_cost_usd, output_cost_usd, cache_read_cost_usd, cache_creation_cost_usd, total_cost_usd,
-            latency_ms, status_code, is_streaming, created_at
-        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)",
+            latency_ms, status_code, is_streaming, cost_multiplier, pricing_version, project, created_at
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21)",
         rusqlite::params![
             request_id,
             provider_id,
@@ -77,6 +68,9 @@ pub fn log_usage(
             latency_ms as i64,
             status_code as i64,
             0, // is_streaming
+            billing.multiplier.to_string(),
+            pricing_version,
+            project,
             created_at,
         ],
     )
@@ -85,90 +79,48 @@ pub fn log_usage(
     Ok(())
 }
 
-/// 获取模型定价
-fn get_model_pricing(conn: &rusqlite::Connection, model_id: &str) -> Result<Option<ModelPricing>, AppError> {
-    // 清洗模型名称
-    let cleaned = clean_model_id(model_id);
-
-    let result = conn.query_row(
-        "SELECT input_cost_per_million, output_cost_per_million,
-                cache_read_cost_per_million, cache_creation_cost_per_million
-         FROM model_pricing WHERE model_id = ?1",
-        [&cleaned],
-        |row| {
-            Ok((
-                row.get::<_, String>(0)?,
-                row.get::<_, String>(1)?,
-                row.get::<_, String>(2)?,
-                row.get::<_, String>(3)?,
-            ))
-        },
-    );
-
-    match result {
-        Ok((input, output, cache_read, cache_creation)) => {
-            Ok(Some(ModelPricing {
-                input_cost_per_million: Decimal::from_str(&input).unwrap_or(Decimal::ZERO),
-                output_cost_per_million: Decimal::from_str(&output).unwrap_or(Decimal::ZERO),
-                cache_read_cost_per_million: Decimal::from_str(&cache_read).unwrap_or(Decimal::ZERO),
-                cache_creation_cost_per_million: Decimal::from_str(&cache_creation).unwrap_or(Decimal::ZERO),
-            }))
-        }
-        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-        Err(e) => Err(AppError::Database(format!("查询模型定价失败: {e}"))),
-    }
-}
-
-/// 清洗模型 ID
-fn clean_model_id(model_id: &str) -> String {
-    // 去前缀（如 "anthropic/"）
-    let without_prefix = model_id
-        .rsplit_once('/')
-        .map_or(model_id, |(_, r)| r);
-    
-    // 去后缀（如 ":v2"）
-    let without_suffix = without_prefix
-        .split(':')
-        .next()
-        .unwrap_or(without_prefix);
-    
-    // @ 替换为 -
-    without_suffix.trim().replace('@', "-")
-}
-
-/// 计算成本
-fn calculate_cost(usage: &TokenUsage, pricing: Option<&ModelPricing>) -> CostBreakdown {
-    let pricing = match pricing {
-        Some(p) => p,
-        None => {
-            return CostBreakdown {
-                input_cost: Decimal::ZERO,
-                output_cost: Decimal::ZERO,
-                cache_read_cost: Decimal::ZERO,
-                cache_creation_cost: Decimal::ZERO,
-                total_cost: Decimal::ZERO,
-            };
-        }
-    };
-
-    let million = Decimal::from(1_000_000u64);
-
-    // 计算各项成本
-    // 注意：input_tokens 需要扣除 cache_read_tokens（避免缓存部分被重复计费）
-    let billable_input_tokens = (usage.input_tokens as u64).saturating_sub(usage.cache_read_tokens as u64);
-    
-    let input_cost = Decimal::from(billable_input_tokens) * pricing.input_cost_per_million / million;
-    let output_cost = Decimal::from(usage.output_tokens as u64) * pricing.output_cost_per_million / million;
-    let cache_read_cost = Decimal::from(usage.cache_read_tokens as u64) * pricing.cache_read_cost_per_million / million;
-    let cache_creation_cost = Decimal::from(usage.cache_creation_tokens as u64) * pricing.cache_creation_cost_per_million / million;
-
-    let total_cost = input_cost + output_cost + cache_read_cost + cache_creation_cost;
-
-    CostBreakdown {
-        input_cost,
-        output_cost,
-        cache_read_cost,
-        cache_creation_cost,
-        total_cost,
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pricing::recalc::{self, RecalcFilter};
+    use crate::pricing::rules::{save_rule, PricingRule};
+
+    #[test]
+    fn provider_scoped_rule_matches_recalc() {
+        let db = Database::memory().unwrap();
+        save_rule(
+            &db,
+            &PricingRule {
+                id: None,
+                model_id: "claude-sonnet-4-20250514".to_string(),
+                provider_id: Some("relay".to_string()),
+                min_prompt_tokens: 0,
+                time_start: None,
+                time_end: None,
+                effective_from: None,
+                effective_to: None,
+                input_cost_per_million: "1".to_string(),
+                output_cost_per_million: "2".to_string(),
+                cache_read_cost_per_million: "0".to_string(),
+                cache_creation_cost_per_million: "0".to_string(),
+                priority: 0,
+                note: None,
+            },
+        )
+        .unwrap();
+
+        let usage = TokenUsage {
+            input_tokens: 1_000_000,
+            output_tokens: 0,
+            cache_read_tokens: 0,
+            cache_creation_tokens: 0,
+            model: None,
+        };
+        log_usage(&db, "relay", None, AppType::Claude, "claude-sonnet-4-20250514", usage, 100, 200, None).unwrap();
+
+        let report = recalc::preview(&db, &RecalcFilter::default()).unwrap();
+        assert_eq!((report.matched, report.changed), (1, 0));
+        assert_eq!(report.old_total.parse::<f64>().unwrap(), 1.0);
+        assert_eq!(report.new_total, report.old_total);
     }
 }
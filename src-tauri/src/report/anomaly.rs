@@ -0,0 +1,342 @@
+//! 使用异常检测
+//!
+//! 将每个时间段的费用 / token 用量与滚动基线比较，偏离过大时记为异常：
+//! - 按天：与前 14 天比较
+//! - 按小时：与前 7 天同一小时比较（避开昼夜差异）
+//! - 服务商 / 模型：最近 24 小时与之前 7 天每天的用量比较
+//!
+//! 同时满足「不低于下限」「达到基线均值的倍数」「超过基线均值若干个标准差」才算异常，
+//! 基线全为 0 时只看下限。费用均以 USD 计。
+
+use crate::database::schema::DailyStats;
+use crate::database::Database;
+use crate::error::AppError;
+use chrono::{DateTime, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+const HOUR_SECONDS: i64 = 60 * 60;
+const DAY_SECONDS: i64 = 24 * HOUR_SECONDS;
+
+/// 按天检测的基线天数
+const DAILY_BASELINE: usize = 14;
+/// 按小时检测、服务商 / 模型检测的基线天数
+const WEEKLY_BASELINE: usize = 7;
+
+/// 异常的范围
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnomalyScope {
+    Day,
+    Hour,
+    Provider,
+    Model,
+}
+
+/// 异常的指标
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnomalyMetric {
+    Cost,
+    Tokens,
+}
+
+/// 检测选项
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AnomalyOptions {
+    /// 检测最近多少天的按天 / 按小时异常
+    pub days: u32,
+    /// 超过基线均值的标准差倍数
+    pub z_threshold: f64,
+    /// 至少达到基线均值的倍数
+    pub min_ratio: f64,
+    /// 费用下限（USD）
+    pub min_cost: f64,
+    /// token 下限
+    pub min_tokens: u64,
+}
+
+impl Default for AnomalyOptions {
+    fn default() -> Self {
+        Self {
+            days: 7,
+            z_threshold: 3.0,
+            min_ratio: 2.0,
+            min_cost: 1.0,
+            min_tokens: 1_000_000,
+        }
+    }
+}
+
+/// 一条异常
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageAnomaly {
+    pub scope: AnomalyScope,
+    pub metric: AnomalyMetric,
+    /// 日期、小时、服务商名或模型名
+    pub label: String,
+    /// 时间段起点（Unix 秒）
+    pub period_start: i64,
+    pub value: f64,
+    /// 基线均值
+    pub baseline: f64,
+    /// value / baseline，基线为 0 时为空
+    pub ratio: Option<f64>,
+    /// 偏离基线的标准差倍数，基线没有波动时为空
+    pub z_score: Option<f64>,
+    pub message: String,
+}
+
+impl UsageAnomaly {
+    /// 去重用的键：按天 / 按小时异常每个时间段一个，服务商 / 模型异常每天（UTC）一个
+    pub fn key(&self) -> String {
+        let period = match self.scope {
+            AnomalyScope::Day | AnomalyScope::Hour => self.period_start,
+            AnomalyScope::Provider | AnomalyScope::Model => (self.period_start + DAY_SECONDS).div_euclid(DAY_SECONDS),
+        };
+        format!("{:?}:{:?}:{}:{}", self.scope, self.metric, self.label, period)
+    }
+}
+
+/// 检测最近的使用异常，按时间段起点排序
+pub fn detect(db: &Database, options: &AnomalyOptions, now: DateTime<Utc>) -> Result<Vec<UsageAnomaly>, AppError> {
+    let now_ts = now.timestamp();
+    let days = options.days.max(1) as usize;
+    let mut anomalies = Vec::new();
+
+    // 按天
+    let today_start = now_ts - now_ts.rem_euclid(DAY_SECONDS);
+    let start = today_start - ((days - 1 + DAILY_BASELINE) * DAY_SECONDS as usize) as i64;
+    // 查询到当前时间段结束，正好位于边界时也包含当前这一桶
+    let daily = db.get_daily_trends(start, today_start + DAY_SECONDS - 1, DAY_SECONDS)?;
+    let series = metric_series(&daily);
+    for (i, stat) in daily.iter().enumerate().skip(DAILY_BASELINE) {
+        let period_start = start + i as i64 * DAY_SECONDS;
+        let label = stat.date.get(..10).unwrap_or(&stat.date).to_string();
+        for (metric, values) in &series {
+            let baseline = &values[i - DAILY_BASELINE..i];
+            anomalies.extend(check(options, AnomalyScope::Day, *metric, &label, period_start, values[i], baseline));
+        }
+    }
+
+    // 按小时，基线为前 7 天的同一小时
+    let hour_start = now_ts - now_ts.rem_euclid(HOUR_SECONDS);
+    let hours = days * 24;
+    let start = hour_start - ((hours - 1 + WEEKLY_BASELINE * 24) * HOUR_SECONDS as usize) as i64;
+    let hourly = db.get_daily_trends(start, hour_start + HOUR_SECONDS - 1, HOUR_SECONDS)?;
+    let series = metric_series(&hourly);
+    for (i, _) in hourly.iter().enumerate().skip(WEEKLY_BASELINE * 24) {
+        let period_start = start + i as i64 * HOUR_SECONDS;
+        let label = DateTime::from_timestamp(period_start, 0)
+            .map(|dt| format!("{} {:02}:00", dt.format("%Y-%m-%d"), dt.hour()))
+            .unwrap_or_default();
+        for (metric, values) in &series {
+            let baseline: Vec<f64> = (1..=WEEKLY_BASELINE).map(|d| values[i - d * 24]).collect();
+            anomalies.extend(check(options, AnomalyScope::Hour, *metric, &label, period_start, values[i], &baseline));
+        }
+    }
+
+    // 服务商 / 模型：最近 24 小时与之前 7 天
+    let conn = db.read_conn()?;
+    let period_start = now_ts - DAY_SECONDS;
+    for scope in [AnomalyScope::Provider, AnomalyScope::Model] {
+        for (label, (cost, tokens)) in dimension_series(&conn, scope, now_ts)? {
+            for (metric, values) in [(AnomalyMetric::Cost, cost), (AnomalyMetric::Tokens, tokens)] {
+                let (baseline, recent) = values.split_at(WEEKLY_BASELINE);
+                anomalies.extend(check(options, scope, metric, &label, period_start, recent[0], baseline));
+            }
+        }
+    }
+
+    anomalies.sort_by_key(|a| a.period_start);
+    Ok(anomalies)
+}
+
+/// 将趋势数据拆成费用和 token 两个序列
+fn metric_series(stats: &[DailyStats]) -> [(AnomalyMetric, Vec<f64>); 2] {
+    [
+        (
+            AnomalyMetric::Cost,
+            stats.iter().map(|s| s.total_cost.parse::<f64>().unwrap_or(0.0)).collect(),
+        ),
+        (AnomalyMetric::Tokens, stats.iter().map(|s| s.total_tokens as f64).collect()),
+    ]
+}
+
+/// 每个服务商 / 模型最近 8 个 24 小时的（费用, token）序列，最后一项为最近 24 小时
+type DimensionSeries = BTreeMap<String, (Vec<f64>, Vec<f64>)>;
+
+fn dimension_series(conn: &rusqlite::Connection, scope: AnomalyScope, now_ts: i64) -> Result<DimensionSeries, AppError> {
+    let key = match scope {
+        AnomalyScope::Provider => "COALESCE(NULLIF(provider_name, ''), provider_id)",
+        _ => "model",
+    };
+    let start = now_ts - (WEEKLY_BASELINE as i64 + 1) * DAY_SECONDS;
+    let sql = format!(
+        "SELECT {key}, CAST((created_at - ?1) / ?2 AS INTEGER) AS bucket_idx,
+                COALESCE(SUM(CAST(total_cost_usd AS REAL)), 0),
+                COALESCE(SUM(input_tokens + output_tokens), 0)
+         FROM proxy_request_logs
+         WHERE created_at > ?1 AND created_at <= ?3
+         GROUP BY 1, bucket_idx"
+    );
+    let mut stmt = conn
+        .prepare(&sql)
+        .map_err(|e| AppError::Database(format!("准备查询失败: {e}")))?;
+    let rows = stmt
+        .query_map(rusqlite::params![start, DAY_SECONDS, now_ts], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, f64>(2)?,
+                row.get::<_, i64>(3)?,
+            ))
+        })
+        .map_err(|e| AppError::Database(format!("查询使用记录失败: {e}")))?;
+
+    let mut result = DimensionSeries::new();
+    for row in rows {
+        let (label, idx, cost, tokens) = row.map_err(|e| AppError::Database(format!("读取行失败: {e}")))?;
+        // created_at = now 时落在第 8 个桶之外，并入最近 24 小时
+        let idx = (idx.max(0) as usize).min(WEEKLY_BASELINE);
+        let entry = result
+            .entry(label)
+            .or_insert_with(|| (vec![0.0; WEEKLY_BASELINE + 1], vec![0.0; WEEKLY_BASELINE + 1]));
+        entry.0[idx] += cost;
+        entry.1[idx] += tokens as f64;
+    }
+    Ok(result)
+}
+
+/// 将一个值与基线比较，构造异常
+fn check(
+    options: &AnomalyOptions,
+    scope: AnomalyScope,
+    metric: AnomalyMetric,
+    label: &str,
+    period_start: i64,
+    value: f64,
+    baseline: &[f64],
+) -> Option<UsageAnomaly> {
+    let floor = match metric {
+        AnomalyMetric::Cost => options.min_cost,
+        AnomalyMetric::Tokens => options.min_tokens as f64,
+    };
+    if baseline.is_empty() || value < floor {
+        return None;
+    }
+    let mean = baseline.iter().sum::<f64>() / baseline.len() as f64;
+    let deviation = (baseline.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / baseline.len() as f64).sqrt();
+    let ratio = (mean > 0.0).then(|| value / mean);
+    let z_score = (deviation > 0.0).then(|| (value - mean) / deviation);
+    if ratio.is_some_and(|r| r < options.min_ratio) || z_score.is_some_and(|z| z < options.z_threshold) {
+        return None;
+    }
+
+    let subject = match scope {
+        AnomalyScope::Day | AnomalyScope::Hour => label.to_string(),
+        AnomalyScope::Provider => format!("服务商 {label} 最近 24 小时"),
+        AnomalyScope::Model => format!("模型 {label} 最近 24 小时"),
+    };
+    let (name, format_value): (&str, fn(f64) -> String) = match metric {
+        AnomalyMetric::Cost => ("费用", |v| format!("${v:.2}")),
+        AnomalyMetric::Tokens => ("token 用量", |v| format!("{v:.0}")),
+    };
+    let message = match ratio {
+        Some(ratio) => format!(
+            "{subject}{name} {}，为基线 {} 的 {ratio:.1} 倍",
+            format_value(value),
+            format_value(mean)
+        ),
+        None => format!("{subject}{name} {}，基线期间没有使用", format_value(value)),
+    };
+
+    Some(UsageAnomaly {
+        scope,
+        metric,
+        label: label.to_string(),
+        period_start,
+        value,
+        baseline: mean,
+        ratio,
+        z_score,
+        message,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::fixtures::{insert, Record};
+
+    /// 2025-06-20 12:00 UTC；之前 20 天每天 10:00 花费 1~1.4，今天 10:00 首次使用 Opus 花费 9
+    fn detect_spike() -> Vec<UsageAnomaly> {
+        let db = Database::memory().unwrap();
+        let now = DateTime::from_timestamp(1750420800, 0).unwrap();
+        let today = 1750420800 - 12 * HOUR_SECONDS;
+        for day in 1..=20 {
+            let cost = format!("{:.1}", 1.0 + (day % 5) as f64 * 0.1);
+            insert(
+                &db,
+                Record {
+                    id: &format!("d{day}"),
+                    model: "claude-sonnet-4",
+                    cost: &cost,
+                    created_at: today - day * DAY_SECONDS + 10 * HOUR_SECONDS,
+                    ..Default::default()
+                },
+            );
+        }
+        insert(
+            &db,
+            Record {
+                id: "spike",
+                model: "claude-opus-4",
+                cost: "9",
+                created_at: today + 10 * HOUR_SECONDS,
+                ..Default::default()
+            },
+        );
+        detect(&db, &AnomalyOptions::default(), now).unwrap()
+    }
+
+    fn found(anomalies: &[UsageAnomaly]) -> Vec<(AnomalyScope, AnomalyMetric, &str)> {
+        anomalies.iter().map(|a| (a.scope, a.metric, a.label.as_str())).collect()
+    }
+
+    #[test]
+    fn flags_daily_and_hourly_spikes() {
+        let anomalies = detect_spike();
+        let found = found(&anomalies);
+        assert!(found.contains(&(AnomalyScope::Day, AnomalyMetric::Cost, "2025-06-20")));
+        assert!(found.contains(&(AnomalyScope::Hour, AnomalyMetric::Cost, "2025-06-20 10:00")));
+        // 之前的日子都在正常范围内
+        assert!(anomalies
+            .iter()
+            .filter(|a| matches!(a.scope, AnomalyScope::Day | AnomalyScope::Hour))
+            .all(|a| a.label.starts_with("2025-06-20")));
+    }
+
+    #[test]
+    fn flags_new_model_without_baseline() {
+        let anomalies = detect_spike();
+        // 之前没用过 Opus，基线为 0
+        let model = anomalies
+            .iter()
+            .find(|a| a.scope == AnomalyScope::Model && a.metric == AnomalyMetric::Cost)
+            .unwrap();
+        assert_eq!(model.label, "claude-opus-4");
+        assert_eq!(model.ratio, None);
+    }
+
+    #[test]
+    fn flags_provider_cost_but_not_small_token_usage() {
+        let anomalies = detect_spike();
+        let found = found(&anomalies);
+        // 服务商费用 9 与日均约 1.2 相比同样异常；token 用量低于下限，不提示
+        assert!(found.contains(&(AnomalyScope::Provider, AnomalyMetric::Cost, "My Relay")));
+        assert!(found.iter().all(|(_, metric, _)| *metric == AnomalyMetric::Cost));
+    }
+}
@@ -0,0 +1,196 @@
+//! 缓存效率分析
+//!
+//! 复用报表的筛选和分组（日 / 周 / 月 × 服务商 / 模型 / 应用 / 会话），统计提示词缓存是否真正生效：
+//! - 命中率：缓存读取 token / 提示词 token（输入 + 缓存写入，输入已包含缓存读取）
+//! - 节省：缓存读取 token ×（输入单价 − 缓存读取单价）
+//! - 写入开销：缓存写入 token ×（缓存写入单价 − 输入单价）
+//!
+//! 金额按请求时的价格和服务商计费倍率计算，并换算为报表货币。
+
+use super::{for_each_entry, report_currency, ReportOptions};
+use crate::database::Database;
+use crate::error::AppError;
+use crate::pricing::billing::BillingResolver;
+use crate::pricing::PriceResolver;
+use rust_decimal::prelude::ToPrimitive;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// 一个分组的缓存统计
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheStats {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub period: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    pub request_count: u64,
+    /// 命中缓存（缓存读取 token > 0）的请求数
+    pub cached_request_count: u64,
+    /// 无法匹配定价、不计入节省和开销的请求数
+    pub unpriced_request_count: u64,
+    /// 提示词 token（输入 + 缓存写入）
+    pub prompt_tokens: u64,
+    pub cache_read_tokens: u64,
+    pub cache_creation_tokens: u64,
+    pub hit_ratio: f64,
+    pub cost: f64,
+    pub cache_savings: f64,
+    pub write_overhead: f64,
+    /// 节省 − 写入开销
+    pub net_savings: f64,
+}
+
+impl CacheStats {
+    fn add(&mut self, other: &CacheStats) {
+        self.request_count += other.request_count;
+        self.cached_request_count += other.cached_request_count;
+        self.unpriced_request_count += other.unpriced_request_count;
+        self.prompt_tokens += other.prompt_tokens;
+        self.cache_read_tokens += other.cache_read_tokens;
+        self.cache_creation_tokens += other.cache_creation_tokens;
+        self.cost += other.cost;
+        self.cache_savings += other.cache_savings;
+        self.write_overhead += other.write_overhead;
+    }
+
+    fn finish(&mut self) {
+        self.hit_ratio = if self.prompt_tokens > 0 {
+            (self.cache_read_tokens as f64 / self.prompt_tokens as f64).min(1.0)
+        } else {
+            0.0
+        };
+        self.net_savings = self.cache_savings - self.write_overhead;
+    }
+}
+
+/// 缓存效率报告
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheReport {
+    pub options: ReportOptions,
+    pub currency: String,
+    pub rows: Vec<CacheStats>,
+    pub totals: CacheStats,
+}
+
+/// 按报表选项统计缓存效率
+pub fn analyze(db: &Database, options: &ReportOptions) -> Result<CacheReport, AppError> {
+    let (currency, rate) = report_currency(db, options)?;
+    let conn = db.read_conn()?;
+    let mut prices = PriceResolver::new(&conn);
+    let mut billing_factors = BillingResolver::new(&conn);
+
+    let mut groups: BTreeMap<(Option<String>, Option<String>), CacheStats> = BTreeMap::new();
+    for_each_entry(&conn, options, |entry| {
+        let tokens = &entry.tokens;
+        let mut item = CacheStats {
+            request_count: 1,
+            cached_request_count: u64::from(tokens.cache_read > 0),
+            prompt_tokens: tokens.prompt_tokens(),
+            cache_read_tokens: tokens.cache_read,
+            cache_creation_tokens: tokens.cache_creation,
+            cost: entry.cost.to_f64().unwrap_or(0.0) * rate,
+            ..Default::default()
+        };
+        match prices.resolve(Some(&entry.provider_id), &entry.model, tokens, entry.created_at)? {
+            Some(rates) => {
+                let factor = billing_factors.factor(&entry.provider_id)?.usd_factor;
+                item.cache_savings = (rates.cache_savings(tokens) * factor).to_f64().unwrap_or(0.0) * rate;
+                item.write_overhead = (rates.cache_write_overhead(tokens) * factor).to_f64().unwrap_or(0.0) * rate;
+            }
+            None => item.unpriced_request_count = 1,
+        }
+        groups.entry(entry.group_key(options)).or_default().add(&item);
+        Ok(())
+    })?;
+
+    let mut totals = CacheStats::default();
+    let rows: Vec<CacheStats> = groups
+        .into_iter()
+        .map(|((period, group), mut row)| {
+            totals.add(&row);
+            row.period = period;
+            row.group = group;
+            row.finish();
+            row
+        })
+        .collect();
+    totals.finish();
+
+    Ok(CacheReport {
+        options: options.clone(),
+        currency,
+        rows,
+        totals,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::fixtures::{insert, Record};
+    use crate::report::ReportDimension;
+
+    const SESSION: &str = "0b6e2f6c-1a2b-4c3d-8e9f-0123456789ab";
+
+    /// 同一会话的两次请求（先写入缓存，再命中缓存）和一条代理记录
+    fn seed(db: &Database) {
+        for (id, app_type, input, cache_read, cache_creation) in [
+            (format!("{SESSION}-1748736000"), "claude_local", 0, 0, 20_000),
+            (format!("{SESSION}-1748736060"), "claude_local", 100_000, 80_000, 0),
+            ("proxy-1".to_string(), "claude", 50_000, 0, 0),
+        ] {
+            insert(
+                db,
+                Record {
+                    id: &id,
+                    app_type,
+                    input,
+                    output: 0,
+                    cache_read,
+                    cache_creation,
+                    ..Default::default()
+                },
+            );
+        }
+    }
+
+    fn by_session(db: &Database) -> CacheReport {
+        let options = ReportOptions {
+            group_by: Some(ReportDimension::Session),
+            ..Default::default()
+        };
+        analyze(db, &options).unwrap()
+    }
+
+    #[test]
+    fn computes_hit_ratio_savings_and_overhead_per_session() {
+        let db = Database::memory().unwrap();
+        seed(&db);
+
+        let report = by_session(&db);
+        assert_eq!(report.rows.len(), 2);
+        let session = &report.rows[1];
+        assert_eq!(session.group.as_deref(), Some(SESSION));
+        assert_eq!((session.request_count, session.cached_request_count), (2, 1));
+        assert!((session.hit_ratio - 80_000.0 / 120_000.0).abs() < 1e-9);
+        // Sonnet 4：输入 3，缓存读取 0.3，缓存写入 3.75（USD / 百万 token）
+        assert!((session.cache_savings - 0.08 * 2.7).abs() < 1e-9);
+        assert!((session.write_overhead - 0.02 * 0.75).abs() < 1e-9);
+        assert!((session.net_savings - (0.216 - 0.015)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn groups_proxy_records_without_session_and_totals_all() {
+        let db = Database::memory().unwrap();
+        seed(&db);
+
+        let report = by_session(&db);
+        // 代理记录没有会话，归入空分组
+        assert_eq!(report.rows[0].group, None);
+        assert_eq!(report.rows[0].hit_ratio, 0.0);
+        assert_eq!(report.totals.request_count, 3);
+        assert!((report.totals.hit_ratio - 80_000.0 / 170_000.0).abs() < 1e-9);
+    }
+}
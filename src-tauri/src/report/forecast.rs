@@ -0,0 +1,167 @@
+//! 月末费用预测
+//!
+//! 基于 `get_daily_trends` 的每日序列：本月已发生费用 + 剩余天数 × 近 7 个完整日的日均费用。
+//! 预测区间按日均费用 ± 1 个标准差计算。
+
+use crate::database::Database;
+use crate::error::AppError;
+use crate::pricing::billing;
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use serde::Serialize;
+
+const DAY_SECONDS: i64 = 24 * 60 * 60;
+
+/// 计算日均费用使用的完整日数
+const BASELINE_DAYS: usize = 7;
+
+/// 月末费用预测
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CostForecast {
+    pub currency: String,
+    /// 月份（UTC），如 2025-06
+    pub month: String,
+    pub days_in_month: u32,
+    /// 本月已过去的天数（含今天已过去的部分）
+    pub days_elapsed: f64,
+    pub month_to_date: f64,
+    /// 预测使用的日均费用
+    pub daily_average: f64,
+    pub projected_total: f64,
+    pub projected_low: f64,
+    pub projected_high: f64,
+}
+
+/// 预测本月（UTC）月末费用
+pub fn build(db: &Database, currency: Option<&str>, now: DateTime<Utc>) -> Result<CostForecast, AppError> {
+    let currency = currency
+        .filter(|c| !c.trim().is_empty())
+        .map(billing::normalize_currency)
+        .transpose()?
+        .unwrap_or_else(|| billing::BASE_CURRENCY.to_string());
+    let rate = billing::report_rate(db, Some(&currency))?;
+
+    let month_start = NaiveDate::from_ymd_opt(now.year(), now.month(), 1)
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .map(|dt| dt.and_utc().timestamp())
+        .ok_or_else(|| AppError::Custom("无效的日期".to_string()))?;
+    let next_month = if now.month() == 12 {
+        NaiveDate::from_ymd_opt(now.year() + 1, 1, 1)
+    } else {
+        NaiveDate::from_ymd_opt(now.year(), now.month() + 1, 1)
+    }
+    .and_then(|d| d.and_hms_opt(0, 0, 0))
+    .map(|dt| dt.and_utc().timestamp())
+    .ok_or_else(|| AppError::Custom("无效的日期".to_string()))?;
+
+    // 多取上月末 7 天，月初也有足够的基线；查询到今天结束，
+    // 使序列最后一项总是今天（正好零点时按 now 截止会少掉今天这一桶）
+    let series_start = month_start - BASELINE_DAYS as i64 * DAY_SECONDS;
+    let today_end = now.timestamp() - now.timestamp().rem_euclid(DAY_SECONDS) + DAY_SECONDS - 1;
+    let daily: Vec<f64> = db
+        .get_daily_trends(series_start, today_end, DAY_SECONDS)?
+        .iter()
+        .map(|d| d.total_cost.parse::<f64>().unwrap_or(0.0))
+        .collect();
+
+    let mut forecast = project(&daily, BASELINE_DAYS, now.timestamp() - month_start, next_month - month_start);
+    forecast.currency = currency;
+    forecast.month = now.format("%Y-%m").to_string();
+    for value in [
+        &mut forecast.month_to_date,
+        &mut forecast.daily_average,
+        &mut forecast.projected_total,
+        &mut forecast.projected_low,
+        &mut forecast.projected_high,
+    ] {
+        *value *= rate;
+    }
+    Ok(forecast)
+}
+
+/// 根据每日费用序列（最后一项为今天）预测月末费用
+///
+/// `month_offset` 为本月第一天在序列中的下标，`elapsed` / `month_length` 为本月已过去的秒数和本月总秒数。
+/// 日均费用取最近 7 个完整日，忽略第一次有记录之前的日期；还没有完整日时按今天的速度估算。
+fn project(daily: &[f64], month_offset: usize, elapsed: i64, month_length: i64) -> CostForecast {
+    let month_to_date: f64 = daily.iter().skip(month_offset).sum();
+    let today = daily.last().copied().unwrap_or(0.0);
+    let complete = &daily[..daily.len().saturating_sub(1)];
+    let first_active = complete.iter().position(|c| *c > 0.0).unwrap_or(complete.len());
+    let baseline = &complete[first_active.max(complete.len().saturating_sub(BASELINE_DAYS))..];
+
+    let today_elapsed = (elapsed % DAY_SECONDS) as f64 / DAY_SECONDS as f64;
+    let (daily_average, deviation) = if baseline.is_empty() {
+        let pace = if today_elapsed > 0.0 { today / today_elapsed } else { 0.0 };
+        (pace, 0.0)
+    } else {
+        let mean = baseline.iter().sum::<f64>() / baseline.len() as f64;
+        let variance = baseline.iter().map(|c| (c - mean).powi(2)).sum::<f64>() / baseline.len() as f64;
+        (mean, variance.sqrt())
+    };
+
+    let remaining = (month_length - elapsed).max(0) as f64 / DAY_SECONDS as f64;
+    CostForecast {
+        days_in_month: (month_length / DAY_SECONDS) as u32,
+        days_elapsed: elapsed as f64 / DAY_SECONDS as f64,
+        month_to_date,
+        daily_average,
+        projected_total: month_to_date + daily_average * remaining,
+        projected_low: month_to_date + (daily_average - deviation).max(0.0) * remaining,
+        projected_high: month_to_date + (daily_average + deviation) * remaining,
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::fixtures::{insert, Record};
+
+    #[test]
+    fn projects_from_recent_complete_days() {
+        // 上月末 2 天无记录，之后每天 2 / 4 交替；本月第 10 天过了一半，今天已花 1
+        let mut daily = vec![0.0, 0.0];
+        daily.extend((0..5 + 9).map(|i| if i % 2 == 0 { 2.0 } else { 4.0 }));
+        daily.push(1.0);
+        let elapsed = 9 * DAY_SECONDS + DAY_SECONDS / 2;
+        let forecast = project(&daily, 7, elapsed, 30 * DAY_SECONDS);
+
+        assert_eq!(forecast.days_in_month, 30);
+        let month_to_date: f64 = daily[7..].iter().sum();
+        assert!((forecast.month_to_date - month_to_date).abs() < 1e-9);
+        // 日均取最近 7 个完整日（不含今天）
+        let recent = &daily[daily.len() - 8..daily.len() - 1];
+        let mean = recent.iter().sum::<f64>() / 7.0;
+        assert!((forecast.daily_average - mean).abs() < 1e-9);
+        assert!((forecast.projected_total - (month_to_date + mean * 20.5)).abs() < 1e-9);
+        assert!(forecast.projected_low < forecast.projected_total);
+        assert!(forecast.projected_high > forecast.projected_total);
+
+        // 第一天使用：按今天的速度估算
+        let forecast = project(&[0.0, 0.0, 3.0], 2, DAY_SECONDS / 4, 30 * DAY_SECONDS);
+        assert!((forecast.daily_average - 12.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn keeps_today_bucket_at_midnight() {
+        let db = Database::memory().unwrap();
+        // 2025-06-09 12:00 UTC 花费 2，预测时间正好是 2025-06-10 00:00 UTC
+        insert(
+            &db,
+            Record {
+                id: "yesterday",
+                cost: "2",
+                created_at: 1749470400,
+                ..Default::default()
+            },
+        );
+        let now = DateTime::from_timestamp(1749513600, 0).unwrap();
+
+        let forecast = build(&db, None, now).unwrap();
+        assert_eq!(forecast.days_elapsed, 9.0);
+        assert!((forecast.month_to_date - 2.0).abs() < 1e-9);
+        // 6 月 9 日是完整日，计入日均
+        assert!((forecast.daily_average - 2.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,707 @@
+//! 使用报表导出
+//!
+//! 按筛选条件（时间范围、应用、服务商、模型、来源、项目）汇总 proxy_request_logs 和 session_stats，
+//! 可按 日 / 周 / 月 × 服务商 / 模型 / 应用 / 会话 / 项目 分组，导出为 CSV、JSON Lines 或 Markdown 报告。
+//! 缓存节省金额按请求时的价格计算：缓存读取 token ×（输入单价 − 缓存读取单价）。
+
+pub mod anomaly;
+pub mod cache;
+pub mod forecast;
+pub mod productivity;
+pub mod render;
+pub mod sessions;
+
+use crate::database::Database;
+use crate::error::AppError;
+use crate::pricing::billing::{self, BillingResolver};
+use crate::pricing::{PriceResolver, TokenCounts};
+use chrono::{DateTime, Datelike, Utc};
+use rusqlite::types::Value;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Markdown 报告中展示的主要模型数
+const TOP_MODEL_COUNT: usize = 10;
+
+/// 记录来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportSource {
+    /// 代理记录的请求
+    Proxy,
+    /// 从本地日志导入的记录（app_type 以 `_local` 结尾）
+    Local,
+    /// 手动添加的记录
+    Manual,
+}
+
+/// 时间分组
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportPeriod {
+    Day,
+    Week,
+    Month,
+}
+
+/// 维度分组
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportDimension {
+    Provider,
+    Model,
+    App,
+    /// 会话（仅本地日志记录，代理和手动记录归入空分组）
+    Session,
+    /// 项目（未归属项目的记录归入空分组）
+    Project,
+}
+
+/// 导出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    Csv,
+    Jsonl,
+    Markdown,
+}
+
+impl FromStr for ReportFormat {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "csv" => Ok(Self::Csv),
+            "jsonl" | "json" => Ok(Self::Jsonl),
+            "md" | "markdown" => Ok(Self::Markdown),
+            other => Err(AppError::Custom(format!("不支持的导出格式: {other}（可选: csv, jsonl, md）"))),
+        }
+    }
+}
+
+/// 报表选项
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportOptions {
+    /// 起始时间（Unix 秒，含）
+    #[serde(default)]
+    pub start_time: Option<i64>,
+    /// 截止时间（Unix 秒，含）
+    #[serde(default)]
+    pub end_time: Option<i64>,
+    /// 应用（claude、codex 等，同时包含代理记录和本地日志）
+    #[serde(default)]
+    pub app: Option<String>,
+    #[serde(default)]
+    pub provider_id: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub source: Option<ReportSource>,
+    /// 项目路径
+    #[serde(default)]
+    pub project: Option<String>,
+    #[serde(default)]
+    pub period: Option<ReportPeriod>,
+    #[serde(default)]
+    pub group_by: Option<ReportDimension>,
+    /// 费用货币（默认 USD）
+    #[serde(default)]
+    pub currency: Option<String>,
+}
+
+/// 报表中的一行（一个分组）
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportRow {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub period: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    pub request_count: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_read_tokens: u64,
+    pub cache_creation_tokens: u64,
+    /// 按文本估算的 token（包含在以上各项中）
+    pub estimated_tokens: u64,
+    pub cost: f64,
+    pub cache_savings: f64,
+}
+
+impl ReportRow {
+    fn add(&mut self, other: &ReportRow) {
+        self.request_count += other.request_count;
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cache_read_tokens += other.cache_read_tokens;
+        self.cache_creation_tokens += other.cache_creation_tokens;
+        self.estimated_tokens += other.estimated_tokens;
+        self.cost += other.cost;
+        self.cache_savings += other.cache_savings;
+    }
+
+    pub fn total_tokens(&self) -> u64 {
+        self.input_tokens + self.output_tokens + self.cache_read_tokens + self.cache_creation_tokens
+    }
+}
+
+/// 会话统计汇总（来自 session_stats）
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportSessions {
+    pub session_count: u64,
+    pub conversation_count: u64,
+    pub tool_call_count: u64,
+    pub files_changed: u64,
+    pub lines_added: u64,
+    pub lines_deleted: u64,
+}
+
+/// 使用报表
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageReport {
+    pub options: ReportOptions,
+    pub currency: String,
+    pub generated_at: i64,
+    pub rows: Vec<ReportRow>,
+    pub totals: ReportRow,
+    /// 按费用排序的主要模型
+    pub top_models: Vec<ReportRow>,
+    pub sessions: ReportSessions,
+}
+
+/// 导出结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportExportResult {
+    pub path: String,
+    pub rows: usize,
+    pub bytes: usize,
+}
+
+/// 生成使用报表
+pub fn build(db: &Database, options: &ReportOptions) -> Result<UsageReport, AppError> {
+    let (currency, rate) = report_currency(db, options)?;
+    let conn = db.read_conn()?;
+    let mut prices = PriceResolver::new(&conn);
+    let mut billing_factors = BillingResolver::new(&conn);
+
+    let mut groups: BTreeMap<(Option<String>, Option<String>), ReportRow> = BTreeMap::new();
+    let mut models: BTreeMap<String, ReportRow> = BTreeMap::new();
+    for_each_entry(&conn, options, |entry| {
+        let savings = match prices.resolve(Some(&entry.provider_id), &entry.model, &entry.tokens, entry.created_at)? {
+            Some(rates) => rates.cache_savings(&entry.tokens) * billing_factors.factor(&entry.provider_id)?.usd_factor,
+            None => Decimal::ZERO,
+        };
+
+        let mut item = ReportRow {
+            period: None,
+            group: None,
+            request_count: 1,
+            input_tokens: entry.tokens.input,
+            output_tokens: entry.tokens.output,
+            cache_read_tokens: entry.tokens.cache_read,
+            cache_creation_tokens: entry.tokens.cache_creation,
+            estimated_tokens: 0,
+            cost: entry.cost.to_f64().unwrap_or(0.0) * rate,
+            cache_savings: savings.to_f64().unwrap_or(0.0) * rate,
+        };
+        if entry.estimated {
+            item.estimated_tokens = item.total_tokens();
+        }
+
+        groups.entry(entry.group_key(options)).or_default().add(&item);
+        models.entry(entry.model).or_default().add(&item);
+        Ok(())
+    })?;
+
+    let rows: Vec<ReportRow> = groups
+        .into_iter()
+        .map(|((period, group), mut row)| {
+            row.period = period;
+            row.group = group;
+            row
+        })
+        .collect();
+    let mut totals = ReportRow::default();
+    for row in &rows {
+        totals.add(row);
+    }
+    let mut top_models: Vec<ReportRow> = models
+        .into_iter()
+        .map(|(model, mut row)| {
+            row.group = Some(model);
+            row
+        })
+        .collect();
+    top_models.sort_by(|a, b| b.cost.total_cmp(&a.cost).then_with(|| b.request_count.cmp(&a.request_count)));
+    top_models.truncate(TOP_MODEL_COUNT);
+
+    let sessions = session_summary(&conn, options)?;
+
+    Ok(UsageReport {
+        options: options.clone(),
+        currency,
+        generated_at: Utc::now().timestamp(),
+        rows,
+        totals,
+        top_models,
+        sessions,
+    })
+}
+
+/// 报表货币及其相对美元的汇率
+fn report_currency(db: &Database, options: &ReportOptions) -> Result<(String, f64), AppError> {
+    let currency = options
+        .currency
+        .as_deref()
+        .map(billing::normalize_currency)
+        .transpose()?
+        .unwrap_or_else(|| billing::BASE_CURRENCY.to_string());
+    let rate = billing::report_rate(db, Some(&currency))?;
+    Ok((currency, rate))
+}
+
+/// 一条符合筛选条件的使用记录
+struct UsageEntry {
+    provider_id: String,
+    provider_name: Option<String>,
+    app_type: String,
+    model: String,
+    request_id: String,
+    session_id: Option<String>,
+    project: Option<String>,
+    tokens: TokenCounts,
+    /// token 数为估算值
+    estimated: bool,
+    cost: Decimal,
+    created_at: i64,
+}
+
+impl UsageEntry {
+    /// 该记录所属的（时间, 维度）分组
+    fn group_key(&self, options: &ReportOptions) -> (Option<String>, Option<String>) {
+        let period = options.period.map(|p| period_label(p, self.created_at));
+        let group = options.group_by.and_then(|d| match d {
+            ReportDimension::Provider => {
+                Some(self.provider_name.clone().unwrap_or_else(|| self.provider_id.clone()))
+            }
+            ReportDimension::Model => Some(self.model.clone()),
+            ReportDimension::App => Some(self.app_type.clone()),
+            ReportDimension::Session => self
+                .session_id
+                .clone()
+                .or_else(|| session_key(&self.app_type, &self.request_id)),
+            ReportDimension::Project => self.project.clone(),
+        });
+        (period, group)
+    }
+}
+
+/// proxy_request_logs 的筛选条件，参数依次为 filter_params 返回的 ?1..?7
+const ENTRY_FILTER: &str = "(?1 IS NULL OR created_at >= ?1)
+    AND (?2 IS NULL OR created_at <= ?2)
+    AND (?3 IS NULL OR app_type = ?3 OR app_type = ?3 || '_local')
+    AND (?4 IS NULL OR provider_id = ?4)
+    AND (?5 IS NULL OR model = ?5)
+    AND (?6 IS NULL
+         OR (?6 = 'local' AND app_type LIKE '%\\_local' ESCAPE '\\')
+         OR (?6 = 'manual' AND app_type = 'manual')
+         OR (?6 = 'proxy' AND app_type != 'manual' AND app_type NOT LIKE '%\\_local' ESCAPE '\\'))
+    AND (?7 IS NULL OR project = ?7)";
+
+/// ENTRY_FILTER 的参数
+fn filter_params(options: &ReportOptions) -> impl rusqlite::Params {
+    let source = options.source.map(|s| match s {
+        ReportSource::Proxy => "proxy",
+        ReportSource::Local => "local",
+        ReportSource::Manual => "manual",
+    });
+    let text = |value: Option<&str>| value.map(|v| Value::Text(v.to_string()));
+    rusqlite::params_from_iter([
+        options.start_time.map(Value::Integer),
+        options.end_time.map(Value::Integer),
+        text(options.app.as_deref()),
+        text(options.provider_id.as_deref()),
+        text(options.model.as_deref()),
+        text(source),
+        text(options.project.as_deref()),
+    ])
+}
+
+/// 按时间顺序遍历符合筛选条件的使用记录
+fn for_each_entry(
+    conn: &rusqlite::Connection,
+    options: &ReportOptions,
+    mut f: impl FnMut(UsageEntry) -> Result<(), AppError>,
+) -> Result<(), AppError> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT provider_id, provider_name, app_type, model, request_id, input_tokens, output_tokens,
+                    cache_read_tokens, cache_creation_tokens, total_cost_usd, created_at, project, session_id,
+                    tokens_estimated
+             FROM proxy_request_logs
+             WHERE {ENTRY_FILTER}
+             ORDER BY created_at"
+        ))
+        .map_err(|e| AppError::Database(format!("准备查询失败: {e}")))?;
+    let mut rows = stmt
+        .query(filter_params(options))
+        .map_err(|e| AppError::Database(format!("查询使用记录失败: {e}")))?;
+
+    while let Some(row) = rows
+        .next()
+        .map_err(|e| AppError::Database(format!("读取使用记录失败: {e}")))?
+    {
+        let read = |e: rusqlite::Error| AppError::Database(format!("读取使用记录失败: {e}"));
+        let count = |idx: usize| -> Result<u64, AppError> { Ok(row.get::<_, i64>(idx).map_err(read)?.max(0) as u64) };
+        f(UsageEntry {
+            provider_id: row.get(0).map_err(read)?,
+            provider_name: row.get(1).map_err(read)?,
+            app_type: row.get(2).map_err(read)?,
+            model: row.get(3).map_err(read)?,
+            request_id: row.get(4).map_err(read)?,
+            session_id: row.get(12).map_err(read)?,
+            project: row.get(11).map_err(read)?,
+            tokens: TokenCounts {
+                input: count(5)?,
+                output: count(6)?,
+                cache_read: count(7)?,
+                cache_creation: count(8)?,
+            },
+            estimated: row.get(13).map_err(read)?,
+            cost: Decimal::from_str(&row.get::<_, String>(9).map_err(read)?).unwrap_or(Decimal::ZERO),
+            created_at: row.get(10).map_err(read)?,
+        })?;
+    }
+    Ok(())
+}
+
+/// 从本地日志记录的 request_id 中提取会话 ID（v9 之前导入、没有 session_id 的记录）
+///
+/// 本地日志的 request_id 形如 `{会话}-{时间戳}` 或 `{会话}-{时间戳}-{消息}`，
+/// 取第一个 10 / 13 位纯数字段（秒 / 毫秒时间戳）之前的部分；没有时间戳段的（如 Cursor 工作区、Warp 对话）整体即为会话。
+/// 代理和手动记录没有会话，返回 None。
+fn session_key(app_type: &str, request_id: &str) -> Option<String> {
+    if !app_type.ends_with("_local") {
+        return None;
+    }
+    let segments: Vec<&str> = request_id.split('-').collect();
+    let end = segments
+        .iter()
+        .skip(1)
+        .position(|s| matches!(s.len(), 10 | 13) && s.bytes().all(|b| b.is_ascii_digit()))
+        .map(|i| i + 1)
+        .unwrap_or(segments.len());
+    Some(segments[..end].join("-"))
+}
+
+/// 会话统计汇总
+///
+/// session_stats 的时间是导入时间，且没有模型和项目字段，
+/// 因此按与使用记录相同的筛选条件选出会话，只统计其中有请求记录的会话
+fn session_summary(conn: &rusqlite::Connection, options: &ReportOptions) -> Result<ReportSessions, AppError> {
+    // 会话统计只来自本地日志导入
+    if matches!(options.source, Some(ReportSource::Proxy | ReportSource::Manual)) {
+        return Ok(ReportSessions::default());
+    }
+    conn.query_row(
+        &format!(
+            "SELECT COUNT(*), COALESCE(SUM(conversation_count), 0), COALESCE(SUM(tool_call_count), 0),
+                    COALESCE(SUM(files_changed), 0), COALESCE(SUM(lines_added), 0), COALESCE(SUM(lines_deleted), 0)
+             FROM session_stats
+             WHERE session_id IN (
+                 SELECT session_id FROM proxy_request_logs WHERE session_id IS NOT NULL AND {ENTRY_FILTER}
+             )"
+        ),
+        filter_params(options),
+        |row| {
+            let count = |idx: usize| -> rusqlite::Result<u64> { Ok(row.get::<_, i64>(idx)?.max(0) as u64) };
+            Ok(ReportSessions {
+                session_count: count(0)?,
+                conversation_count: count(1)?,
+                tool_call_count: count(2)?,
+                files_changed: count(3)?,
+                lines_added: count(4)?,
+                lines_deleted: count(5)?,
+            })
+        },
+    )
+    .map_err(|e| AppError::Database(format!("查询会话统计失败: {e}")))
+}
+
+/// 时间分组标签（UTC）：日 2025-06-01，周 2025-W23（ISO 周），月 2025-06
+fn period_label(period: ReportPeriod, ts: i64) -> String {
+    let dt = DateTime::from_timestamp(ts, 0).unwrap_or_default();
+    match period {
+        ReportPeriod::Day => dt.format("%Y-%m-%d").to_string(),
+        ReportPeriod::Week => {
+            let week = dt.iso_week();
+            format!("{}-W{:02}", week.year(), week.week())
+        }
+        ReportPeriod::Month => dt.format("%Y-%m").to_string(),
+    }
+}
+
+/// 生成报表并写入文件
+pub fn export(
+    db: &Database,
+    options: &ReportOptions,
+    format: ReportFormat,
+    path: &Path,
+) -> Result<ReportExportResult, AppError> {
+    let report = build(db, options)?;
+    let content = render::render(&report, format)?;
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, &content)?;
+    Ok(ReportExportResult {
+        path: path.display().to_string(),
+        rows: report.rows.len(),
+        bytes: content.len(),
+    })
+}
+
+/// 报表测试共用的数据
+#[cfg(test)]
+pub(crate) mod fixtures {
+    use crate::database::Database;
+
+    /// 一条使用记录，未指定的字段取默认值
+    #[derive(Debug, Clone)]
+    pub(crate) struct Record<'a> {
+        pub id: &'a str,
+        pub provider_id: &'a str,
+        pub provider_name: &'a str,
+        pub app_type: &'a str,
+        pub model: &'a str,
+        pub input: i64,
+        pub output: i64,
+        pub cache_read: i64,
+        pub cache_creation: i64,
+        pub cost: &'a str,
+        /// 默认 2025-06-01 00:00 UTC
+        pub created_at: i64,
+        pub session_id: Option<&'a str>,
+        pub project: Option<&'a str>,
+    }
+
+    impl Default for Record<'_> {
+        fn default() -> Self {
+            Self {
+                id: "",
+                provider_id: "relay",
+                provider_name: "My Relay",
+                app_type: "claude",
+                model: "claude-sonnet-4-20250514",
+                input: 1000,
+                output: 100,
+                cache_read: 0,
+                cache_creation: 0,
+                cost: "0",
+                created_at: 1748736000,
+                session_id: None,
+                project: None,
+            }
+        }
+    }
+
+    /// 写入一条使用记录
+    pub(crate) fn insert(db: &Database, record: Record) {
+        let conn = db.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO proxy_request_logs (
+                request_id, provider_id, provider_name, app_type, model, input_tokens, output_tokens,
+                cache_read_tokens, cache_creation_tokens, total_cost_usd, latency_ms, status_code, created_at,
+                session_id, project
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, 0, 200, ?11, ?12, ?13)",
+            rusqlite::params![
+                record.id,
+                record.provider_id,
+                record.provider_name,
+                record.app_type,
+                record.model,
+                record.input,
+                record.output,
+                record.cache_read,
+                record.cache_creation,
+                record.cost,
+                record.created_at,
+                record.session_id,
+                record.project,
+            ],
+        )
+        .unwrap();
+    }
+
+    /// 执行会话统计、工具调用等其他测试数据的 SQL
+    pub(crate) fn execute(db: &Database, sql: &str) {
+        db.conn.lock().unwrap().execute_batch(sql).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fixtures::{execute, insert, Record};
+    use super::*;
+
+    /// 2025-05-31、2025-06-01（UTC）的三条记录，各含 100 万缓存读取 token
+    fn seed(db: &Database) {
+        for (id, app_type, model, created_at, cost) in [
+            ("a", "claude", "claude-sonnet-4-20250514", 1748649600, "0.5"),
+            ("b", "claude_local", "claude-sonnet-4-20250514", 1748736000, "0.25"),
+            ("c", "codex", "gpt-5", 1748736000, "0.125"),
+        ] {
+            insert(
+                db,
+                Record {
+                    id,
+                    app_type,
+                    model,
+                    created_at,
+                    cost,
+                    cache_read: 1_000_000,
+                    ..Default::default()
+                },
+            );
+        }
+    }
+
+    #[test]
+    fn groups_by_month_and_model() {
+        let db = Database::memory().unwrap();
+        seed(&db);
+
+        let options = ReportOptions {
+            period: Some(ReportPeriod::Month),
+            group_by: Some(ReportDimension::Model),
+            ..Default::default()
+        };
+        let report = build(&db, &options).unwrap();
+        let keys: Vec<_> = report
+            .rows
+            .iter()
+            .map(|r| (r.period.clone().unwrap(), r.group.clone().unwrap(), r.request_count))
+            .collect();
+        assert_eq!(
+            keys,
+            vec![
+                ("2025-05".to_string(), "claude-sonnet-4-20250514".to_string(), 1),
+                ("2025-06".to_string(), "claude-sonnet-4-20250514".to_string(), 1),
+                ("2025-06".to_string(), "gpt-5".to_string(), 1),
+            ]
+        );
+        assert_eq!(report.totals.request_count, 3);
+        assert!((report.totals.cost - 0.875).abs() < 1e-9);
+        assert_eq!(report.top_models[0].group.as_deref(), Some("claude-sonnet-4-20250514"));
+    }
+
+    #[test]
+    fn prices_cache_savings_at_request_time() {
+        let db = Database::memory().unwrap();
+        seed(&db);
+
+        let report = build(&db, &ReportOptions::default()).unwrap();
+        // 100 万缓存读取 token：Sonnet 4 节省 3 - 0.3，GPT-5 节省 1.25 - 0.125
+        assert!((report.totals.cache_savings - (2.7 * 2.0 + 1.125)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn filters_by_app_source_and_project() {
+        let db = Database::memory().unwrap();
+        seed(&db);
+
+        let claude_only = ReportOptions {
+            app: Some("claude".to_string()),
+            source: Some(ReportSource::Local),
+            ..Default::default()
+        };
+        let report = build(&db, &claude_only).unwrap();
+        assert_eq!(report.totals.request_count, 1);
+        assert_eq!(report.rows.len(), 1);
+
+        execute(&db, "UPDATE proxy_request_logs SET project = '/work/app' WHERE request_id IN ('a', 'b')");
+        let by_project = ReportOptions {
+            group_by: Some(ReportDimension::Project),
+            ..Default::default()
+        };
+        let groups: Vec<_> = build(&db, &by_project)
+            .unwrap()
+            .rows
+            .into_iter()
+            .map(|r| (r.group, r.request_count))
+            .collect();
+        assert_eq!(groups, vec![(None, 1), (Some("/work/app".to_string()), 2)]);
+        let one_project = ReportOptions {
+            project: Some("/work/app".to_string()),
+            ..Default::default()
+        };
+        assert!((build(&db, &one_project).unwrap().totals.cost - 0.75).abs() < 1e-9);
+    }
+
+    #[test]
+    fn session_summary_follows_record_filters() {
+        let db = Database::memory().unwrap();
+        for (id, app_type, model, created_at, project) in [
+            ("a", "claude_local", "claude-sonnet-4-20250514", 1748649600, Some("/work/app")),
+            ("b", "codex_local", "gpt-5", 1748736000, None),
+        ] {
+            let session_id = format!("s-{id}");
+            insert(
+                &db,
+                Record {
+                    id,
+                    app_type,
+                    model,
+                    created_at,
+                    project,
+                    session_id: Some(&session_id),
+                    ..Default::default()
+                },
+            );
+        }
+        // 会话统计的 created_at 是导入时间，晚于记录时间
+        execute(
+            &db,
+            "INSERT INTO session_stats (session_id, source, tool_call_count, created_at, updated_at) VALUES
+                 ('s-a', 'claude', 3, 1800000000, 1800000000),
+                 ('s-b', 'codex', 5, 1800000000, 1800000000),
+                 ('s-orphan', 'claude', 7, 1800000000, 1800000000)",
+        );
+
+        let summary = |options: ReportOptions| {
+            let s = build(&db, &options).unwrap().sessions;
+            (s.session_count, s.tool_call_count)
+        };
+        assert_eq!(summary(ReportOptions::default()), (2, 8));
+        assert_eq!(
+            summary(ReportOptions {
+                start_time: Some(1748649600),
+                end_time: Some(1748700000),
+                ..Default::default()
+            }),
+            (1, 3)
+        );
+        assert_eq!(
+            summary(ReportOptions {
+                model: Some("gpt-5".to_string()),
+                ..Default::default()
+            }),
+            (1, 5)
+        );
+        assert_eq!(
+            summary(ReportOptions {
+                project: Some("/work/app".to_string()),
+                ..Default::default()
+            }),
+            (1, 3)
+        );
+    }
+}
@@ -0,0 +1,360 @@
+//! 生产力指标
+//!
+//! 将会话的代码改动（session_stats）与会话内请求的 token 和费用对应起来，比较各助手完成实际工作的效率：
+//! - 每百行改动费用：费用 / (新增 + 删除行数) × 100
+//! - 每次编辑 token：token / 编辑类工具调用次数（Write、StrReplace 等；日志不记录编辑是否被撤销，按执行次数计）
+//! - 工具调用构成：各工具的调用次数占比
+//! - 思考时间占比：思考时间 / (思考 + 响应时间)
+//!
+//! 筛选和分组同使用报表（日 / 周 / 月 × 服务商 / 模型 / 应用 / 会话 / 项目）。只统计有会话统计且有请求记录的会话；
+//! 会话整体归入 token 最多的主要模型，改动行数和工具调用不在模型间拆分。
+
+use super::{period_label, report_currency, ReportDimension, ReportOptions, ReportSource};
+use crate::database::Database;
+use crate::error::AppError;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// 编辑类工具（本地日志导入时已归一化工具名）
+const EDIT_TOOLS: &[&str] = &["Write", "StrReplace", "MultiEdit", "NotebookEdit", "apply_patch"];
+
+/// 一个工具的调用次数和占比
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolShare {
+    pub tool_name: String,
+    pub call_count: u64,
+    pub share: f64,
+}
+
+/// 一个分组的生产力指标
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProductivityRow {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub period: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    pub session_count: u64,
+    pub request_count: u64,
+    pub total_tokens: u64,
+    pub cost: f64,
+    pub files_changed: u64,
+    pub lines_added: u64,
+    pub lines_deleted: u64,
+    /// 编辑类工具调用次数
+    pub edit_count: u64,
+    pub tool_call_count: u64,
+    pub response_time_ms: u64,
+    pub thinking_time_ms: u64,
+    /// 以下指标在分母为 0 时为 None
+    pub cost_per_100_lines: Option<f64>,
+    pub tokens_per_edit: Option<f64>,
+    pub thinking_ratio: Option<f64>,
+    /// 按调用次数从高到低
+    pub tool_mix: Vec<ToolShare>,
+    #[serde(skip)]
+    tools: BTreeMap<String, u64>,
+}
+
+impl ProductivityRow {
+    fn add(&mut self, other: &ProductivityRow) {
+        self.session_count += other.session_count;
+        self.request_count += other.request_count;
+        self.total_tokens += other.total_tokens;
+        self.cost += other.cost;
+        self.files_changed += other.files_changed;
+        self.lines_added += other.lines_added;
+        self.lines_deleted += other.lines_deleted;
+        self.edit_count += other.edit_count;
+        self.tool_call_count += other.tool_call_count;
+        self.response_time_ms += other.response_time_ms;
+        self.thinking_time_ms += other.thinking_time_ms;
+        for (tool, count) in &other.tools {
+            *self.tools.entry(tool.clone()).or_default() += count;
+        }
+    }
+
+    fn finish(&mut self) {
+        let lines = self.lines_added + self.lines_deleted;
+        self.cost_per_100_lines = (lines > 0).then(|| self.cost / lines as f64 * 100.0);
+        self.tokens_per_edit = (self.edit_count > 0).then(|| self.total_tokens as f64 / self.edit_count as f64);
+        let time = self.thinking_time_ms + self.response_time_ms;
+        self.thinking_ratio = (time > 0).then(|| self.thinking_time_ms as f64 / time as f64);
+
+        let mut tool_mix: Vec<ToolShare> = self
+            .tools
+            .iter()
+            .map(|(tool_name, &call_count)| ToolShare {
+                tool_name: tool_name.clone(),
+                call_count,
+                share: call_count as f64 / self.tool_call_count.max(1) as f64,
+            })
+            .collect();
+        tool_mix.sort_by(|a, b| b.call_count.cmp(&a.call_count).then_with(|| a.tool_name.cmp(&b.tool_name)));
+        self.tool_mix = tool_mix;
+    }
+}
+
+/// 生产力报告
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProductivityReport {
+    pub options: ReportOptions,
+    pub currency: String,
+    pub rows: Vec<ProductivityRow>,
+    pub totals: ProductivityRow,
+}
+
+/// 会话在筛选范围内的请求用量（按模型）
+struct SessionModelUsage {
+    session_id: String,
+    model: String,
+    provider: String,
+    app_type: String,
+    project: Option<String>,
+    started_at: i64,
+    request_count: u64,
+    total_tokens: u64,
+    cost: f64,
+}
+
+/// 按报表选项统计生产力指标
+pub fn analyze(db: &Database, options: &ReportOptions) -> Result<ProductivityReport, AppError> {
+    let (currency, rate) = report_currency(db, options)?;
+    let conn = db.read_conn()?;
+
+    // 会话统计只来自本地日志导入
+    let usages = if matches!(options.source, Some(ReportSource::Proxy | ReportSource::Manual)) {
+        Vec::new()
+    } else {
+        load_usage(&conn, options)?
+    };
+    let mut sessions: HashMap<String, Vec<SessionModelUsage>> = HashMap::new();
+    for usage in usages {
+        sessions.entry(usage.session_id.clone()).or_default().push(usage);
+    }
+    let tools = load_tool_calls(&conn)?;
+
+    let read = |e: rusqlite::Error| AppError::Database(format!("查询会话统计失败: {e}"));
+    let mut stmt = conn
+        .prepare(
+            "SELECT files_changed, lines_added, lines_deleted, response_time_ms, thinking_time_ms
+             FROM session_stats WHERE session_id = ?1",
+        )
+        .map_err(read)?;
+
+    let mut groups: BTreeMap<(Option<String>, Option<String>), ProductivityRow> = BTreeMap::new();
+    for (session_id, models) in &sessions {
+        let Some(primary) = models
+            .iter()
+            .max_by(|a, b| a.total_tokens.cmp(&b.total_tokens).then_with(|| b.model.cmp(&a.model)))
+        else {
+            continue;
+        };
+        if options.model.as_ref().is_some_and(|m| *m != primary.model) {
+            continue;
+        }
+
+        let mut item = stmt
+            .query_row([session_id], |row| {
+                let count = |idx: usize| -> rusqlite::Result<u64> { Ok(row.get::<_, i64>(idx)?.max(0) as u64) };
+                Ok(ProductivityRow {
+                    session_count: 1,
+                    files_changed: count(0)?,
+                    lines_added: count(1)?,
+                    lines_deleted: count(2)?,
+                    response_time_ms: count(3)?,
+                    thinking_time_ms: count(4)?,
+                    ..Default::default()
+                })
+            })
+            .map_err(read)?;
+        for usage in models {
+            item.request_count += usage.request_count;
+            item.total_tokens += usage.total_tokens;
+            item.cost += usage.cost * rate;
+        }
+        for (tool, count) in tools.get(session_id).into_iter().flatten() {
+            item.tool_call_count += count;
+            if EDIT_TOOLS.iter().any(|t| t.eq_ignore_ascii_case(tool)) {
+                item.edit_count += count;
+            }
+            *item.tools.entry(tool.clone()).or_default() += count;
+        }
+
+        let started_at = models.iter().map(|m| m.started_at).min().unwrap_or(primary.started_at);
+        let period = options.period.map(|p| period_label(p, started_at));
+        let group = options.group_by.and_then(|d| match d {
+            ReportDimension::Provider => Some(primary.provider.clone()),
+            ReportDimension::Model => Some(primary.model.clone()),
+            ReportDimension::App => Some(primary.app_type.clone()),
+            ReportDimension::Session => Some(session_id.clone()),
+            ReportDimension::Project => models.iter().find_map(|m| m.project.clone()),
+        });
+        groups.entry((period, group)).or_default().add(&item);
+    }
+
+    let mut totals = ProductivityRow::default();
+    let rows: Vec<ProductivityRow> = groups
+        .into_iter()
+        .map(|((period, group), mut row)| {
+            totals.add(&row);
+            row.period = period;
+            row.group = group;
+            row.finish();
+            row
+        })
+        .collect();
+    totals.finish();
+
+    Ok(ProductivityReport {
+        options: options.clone(),
+        currency,
+        rows,
+        totals,
+    })
+}
+
+/// 有会话统计的会话在筛选范围内的请求用量（模型筛选在确定主要模型后进行）
+fn load_usage(conn: &rusqlite::Connection, options: &ReportOptions) -> Result<Vec<SessionModelUsage>, AppError> {
+    let read = |e: rusqlite::Error| AppError::Database(format!("查询会话用量失败: {e}"));
+    let mut stmt = conn
+        .prepare(
+            "SELECT r.session_id, r.model, COALESCE(MAX(r.provider_name), r.provider_id), MIN(r.app_type), MAX(r.project),
+                    MIN(r.created_at), COUNT(*),
+                    COALESCE(SUM(r.input_tokens + r.output_tokens + r.cache_read_tokens + r.cache_creation_tokens), 0),
+                    COALESCE(SUM(CAST(r.total_cost_usd AS REAL)), 0)
+             FROM proxy_request_logs r
+             JOIN session_stats s ON s.session_id = r.session_id
+             WHERE (?1 IS NULL OR r.created_at >= ?1)
+               AND (?2 IS NULL OR r.created_at <= ?2)
+               AND (?3 IS NULL OR r.app_type = ?3 OR r.app_type = ?3 || '_local')
+               AND (?4 IS NULL OR r.provider_id = ?4)
+               AND (?5 IS NULL OR r.project = ?5)
+             GROUP BY r.session_id, r.model",
+        )
+        .map_err(read)?;
+    let rows = stmt
+        .query_map(
+            rusqlite::params![options.start_time, options.end_time, options.app, options.provider_id, options.project],
+            |row| {
+                Ok(SessionModelUsage {
+                    session_id: row.get(0)?,
+                    model: row.get(1)?,
+                    provider: row.get(2)?,
+                    app_type: row.get(3)?,
+                    project: row.get(4)?,
+                    started_at: row.get(5)?,
+                    request_count: row.get::<_, i64>(6)?.max(0) as u64,
+                    total_tokens: row.get::<_, i64>(7)?.max(0) as u64,
+                    cost: row.get(8)?,
+                })
+            },
+        )
+        .map_err(read)?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(read)?;
+    Ok(rows)
+}
+
+/// 各会话的工具调用次数
+fn load_tool_calls(conn: &rusqlite::Connection) -> Result<HashMap<String, Vec<(String, u64)>>, AppError> {
+    let read = |e: rusqlite::Error| AppError::Database(format!("查询工具调用失败: {e}"));
+    let mut stmt = conn
+        .prepare(
+            "SELECT session_id, tool_name, call_count FROM tool_calls",
+        )
+        .map_err(read)?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, i64>(2)?.max(0) as u64,
+            ))
+        })
+        .map_err(read)?;
+    let mut tools: HashMap<String, Vec<(String, u64)>> = HashMap::new();
+    for row in rows {
+        let (session_id, tool_name, count) = row.map_err(read)?;
+        tools.entry(session_id).or_default().push((tool_name, count));
+    }
+    Ok(tools)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::fixtures::{execute, insert, Record};
+
+    /// 会话 s1 主要使用 Sonnet，会话 s2 使用 GPT-5，按模型分组分析
+    fn analyze_by_model() -> ProductivityReport {
+        let db = Database::memory().unwrap();
+        for (id, session_id, model, input, cost) in [
+            ("a1", "s1", "claude-sonnet-4", 9_000, "1.5"),
+            ("a2", "s1", "claude-haiku-4", 1_000, "0.5"),
+            ("b1", "s2", "gpt-5", 4_000, "1"),
+        ] {
+            insert(
+                &db,
+                Record {
+                    id,
+                    model,
+                    input,
+                    cost,
+                    session_id: Some(session_id),
+                    provider_id: "claude_local",
+                    provider_name: "Claude Code",
+                    app_type: "claude_local",
+                    output: 0,
+                    ..Default::default()
+                },
+            );
+        }
+        execute(
+            &db,
+            "INSERT INTO session_stats (session_id, source, files_changed, lines_added, lines_deleted,
+                 response_time_ms, thinking_time_ms, created_at, updated_at) VALUES
+                 ('s1', 'claude', 2, 150, 50, 3000, 1000, 0, 0),
+                 ('s2', 'codex', 1, 10, 0, 1000, 0, 0, 0);
+             INSERT INTO tool_calls (session_id, tool_name, call_count, created_at) VALUES
+                 ('s1', 'StrReplace', 3, 1), ('s1', 'Read', 1, 1),
+                 ('s2', 'Shell', 2, 0);",
+        );
+
+        let options = ReportOptions {
+            group_by: Some(ReportDimension::Model),
+            ..Default::default()
+        };
+        analyze(&db, &options).unwrap()
+    }
+
+    #[test]
+    fn groups_sessions_by_primary_model() {
+        let report = analyze_by_model();
+        let groups: Vec<_> = report.rows.iter().map(|r| r.group.as_deref().unwrap()).collect();
+        assert_eq!(groups, vec!["claude-sonnet-4", "gpt-5"]);
+        assert_eq!(report.totals.session_count, 2);
+        assert_eq!(report.totals.lines_added, 160);
+    }
+
+    #[test]
+    fn derives_cost_token_and_thinking_metrics() {
+        let report = analyze_by_model();
+        let sonnet = &report.rows[0];
+        assert_eq!((sonnet.request_count, sonnet.total_tokens, sonnet.edit_count), (2, 10_000, 3));
+        assert!((sonnet.cost_per_100_lines.unwrap() - 1.0).abs() < 1e-9);
+        assert_eq!(sonnet.tokens_per_edit, Some(10_000.0 / 3.0));
+        assert_eq!(sonnet.thinking_ratio, Some(0.25));
+        // 没有编辑类工具调用时不计算每次编辑的 token
+        assert_eq!(report.rows[1].tokens_per_edit, None);
+    }
+
+    #[test]
+    fn reports_tool_mix() {
+        let report = analyze_by_model();
+        let mix: Vec<_> = report.rows[0].tool_mix.iter().map(|t| (t.tool_name.as_str(), t.call_count)).collect();
+        assert_eq!(mix, vec![("StrReplace", 3), ("Read", 1)]);
+    }
+}
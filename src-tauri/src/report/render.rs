@@ -0,0 +1,210 @@
+//! 报表渲染（CSV / JSON Lines / Markdown）
+
+use super::{ReportFormat, ReportRow, UsageReport};
+use crate::error::AppError;
+use chrono::DateTime;
+use std::fmt::Write;
+
+/// 按格式渲染报表
+pub fn render(report: &UsageReport, format: ReportFormat) -> Result<String, AppError> {
+    match format {
+        ReportFormat::Csv => Ok(csv(report)),
+        ReportFormat::Jsonl => jsonl(report),
+        ReportFormat::Markdown => Ok(markdown(report)),
+    }
+}
+
+/// CSV：每个分组一行，首行为表头
+pub fn csv(report: &UsageReport) -> String {
+    let mut out = String::from(
+        "period,group,request_count,input_tokens,output_tokens,cache_read_tokens,cache_creation_tokens,currency,cost,cache_savings,estimated_tokens\n",
+    );
+    for row in &report.rows {
+        let fields = [
+            csv_field(row.period.as_deref().unwrap_or("")),
+            csv_field(row.group.as_deref().unwrap_or("")),
+            row.request_count.to_string(),
+            row.input_tokens.to_string(),
+            row.output_tokens.to_string(),
+            row.cache_read_tokens.to_string(),
+            row.cache_creation_tokens.to_string(),
+            report.currency.clone(),
+            format!("{:.6}", row.cost),
+            format!("{:.6}", row.cache_savings),
+            row.estimated_tokens.to_string(),
+        ];
+        out.push_str(&fields.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+/// JSON Lines：每个分组一个 JSON 对象
+pub fn jsonl(report: &UsageReport) -> Result<String, AppError> {
+    let mut out = String::new();
+    for row in &report.rows {
+        let mut value = serde_json::to_value(row)
+            .map_err(|e| AppError::Custom(format!("序列化报表失败: {e}")))?;
+        value["currency"] = serde_json::Value::String(report.currency.clone());
+        out.push_str(&value.to_string());
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// Markdown 汇总报告
+pub fn markdown(report: &UsageReport) -> String {
+    let currency = &report.currency;
+    let options = &report.options;
+    let date = |ts: Option<i64>| {
+        ts.and_then(|t| DateTime::from_timestamp(t, 0))
+            .map(|dt| dt.format("%Y-%m-%d").to_string())
+    };
+    let range = match (date(options.start_time), date(options.end_time)) {
+        (Some(start), Some(end)) => format!("{start} ~ {end}"),
+        (Some(start), None) => format!("{start} 起"),
+        (None, Some(end)) => format!("截至 {end}"),
+        (None, None) => "全部".to_string(),
+    };
+
+    let mut out = String::new();
+    let _ = writeln!(out, "# 使用报告\n");
+    let _ = writeln!(out, "- 时间范围：{range}");
+    for (name, value) in [
+        ("应用", options.app.as_deref()),
+        ("服务商", options.provider_id.as_deref()),
+        ("模型", options.model.as_deref()),
+    ] {
+        if let Some(value) = value {
+            let _ = writeln!(out, "- {name}：{value}");
+        }
+    }
+    if let Some(generated) = DateTime::from_timestamp(report.generated_at, 0) {
+        let _ = writeln!(out, "- 生成时间：{} UTC", generated.format("%Y-%m-%d %H:%M"));
+    }
+
+    let totals = &report.totals;
+    let _ = writeln!(out, "\n## 汇总\n");
+    let _ = writeln!(out, "| 指标 | 数值 |\n| --- | ---: |");
+    for (name, value) in [
+        ("请求数".to_string(), totals.request_count.to_string()),
+        ("输入 tokens".to_string(), totals.input_tokens.to_string()),
+        ("输出 tokens".to_string(), totals.output_tokens.to_string()),
+        ("缓存读取 tokens".to_string(), totals.cache_read_tokens.to_string()),
+        ("缓存写入 tokens".to_string(), totals.cache_creation_tokens.to_string()),
+        (format!("总费用 ({currency})"), format!("{:.4}", totals.cost)),
+        (format!("缓存节省 ({currency})"), format!("{:.4}", totals.cache_savings)),
+    ] {
+        let _ = writeln!(out, "| {name} | {value} |");
+    }
+    if totals.estimated_tokens > 0 {
+        let _ = writeln!(out, "| 其中估算 tokens | {} |", totals.estimated_tokens);
+    }
+
+    if report.rows.iter().any(|r| r.period.is_some() || r.group.is_some()) {
+        let _ = writeln!(out, "\n## 明细\n");
+        write_rows(&mut out, &report.rows, currency, true);
+    }
+
+    if !report.top_models.is_empty() {
+        let _ = writeln!(out, "\n## 主要模型\n");
+        write_rows(&mut out, &report.top_models, currency, false);
+    }
+
+    let sessions = &report.sessions;
+    if sessions.session_count > 0 {
+        let _ = writeln!(out, "\n## 会话\n");
+        let _ = writeln!(out, "| 会话 | 对话 | 工具调用 | 修改文件 | 新增行 | 删除行 |");
+        let _ = writeln!(out, "| ---: | ---: | ---: | ---: | ---: | ---: |");
+        let _ = writeln!(
+            out,
+            "| {} | {} | {} | {} | {} | {} |",
+            sessions.session_count,
+            sessions.conversation_count,
+            sessions.tool_call_count,
+            sessions.files_changed,
+            sessions.lines_added,
+            sessions.lines_deleted
+        );
+    }
+    out
+}
+
+fn write_rows(out: &mut String, rows: &[ReportRow], currency: &str, with_period: bool) {
+    let period_header = if with_period { "| 时间 " } else { "" };
+    let period_align = if with_period { "| --- " } else { "" };
+    let _ = writeln!(
+        out,
+        "{period_header}| 分组 | 请求 | tokens | 费用 ({currency}) | 缓存节省 ({currency}) |"
+    );
+    let _ = writeln!(out, "{period_align}| --- | ---: | ---: | ---: | ---: |");
+    for row in rows {
+        let period = if with_period {
+            format!("| {} ", row.period.as_deref().unwrap_or("-"))
+        } else {
+            String::new()
+        };
+        let _ = writeln!(
+            out,
+            "{period}| {} | {} | {} | {:.4} | {:.4} |",
+            markdown_cell(row.group.as_deref().unwrap_or("-")),
+            row.request_count,
+            row.total_tokens(),
+            row.cost,
+            row.cache_savings
+        );
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn markdown_cell(value: &str) -> String {
+    value.replace('|', "\\|")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::{ReportOptions, ReportSessions};
+
+    #[test]
+    fn renders_all_formats() {
+        let row = ReportRow {
+            period: Some("2025-06".to_string()),
+            group: Some("a,\"b\"".to_string()),
+            request_count: 2,
+            input_tokens: 10,
+            cost: 1.5,
+            ..Default::default()
+        };
+        let report = UsageReport {
+            options: ReportOptions::default(),
+            currency: "USD".to_string(),
+            generated_at: 0,
+            rows: vec![row.clone()],
+            totals: row.clone(),
+            top_models: vec![row],
+            sessions: ReportSessions::default(),
+        };
+
+        let csv = csv(&report);
+        assert_eq!(
+            csv.lines().nth(1),
+            Some("2025-06,\"a,\"\"b\"\"\",2,10,0,0,0,USD,1.500000,0.000000,0")
+        );
+
+        let line: serde_json::Value = serde_json::from_str(jsonl(&report).unwrap().trim()).unwrap();
+        assert_eq!(line["requestCount"], 2);
+        assert_eq!(line["currency"], "USD");
+
+        let md = markdown(&report);
+        assert!(md.contains("| 总费用 (USD) | 1.5000 |"));
+        assert!(md.contains("| 2025-06 | a,\"b\" | 2 | 10 | 1.5000 | 0.0000 |"));
+    }
+}
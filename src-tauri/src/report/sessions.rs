@@ -0,0 +1,452 @@
+//! 会话浏览
+//!
+//! 按 proxy_request_logs.session_id 聚合单个会话：起止时间、模型构成、token、费用，
+//! 并关联 session_stats（工具调用、改动行数）。会话时间线列出会话内的每个请求和工具调用统计。
+//! 没有 session_id 的记录（代理、手动记录和 v9 之前导入、尚未升级的本地记录）不属于任何会话。
+
+use super::{report_currency, ReportOptions};
+use crate::database::Database;
+use crate::error::AppError;
+use serde::{Deserialize, Serialize};
+
+/// 默认每页会话数
+const DEFAULT_PAGE_SIZE: u32 = 50;
+
+/// 会话列表的筛选和分页
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SessionListOptions {
+    /// 起始时间（Unix 秒，含），只统计此后的请求
+    pub start_time: Option<i64>,
+    /// 截止时间（Unix 秒，含）
+    pub end_time: Option<i64>,
+    /// 应用（claude、codex 等）
+    pub app: Option<String>,
+    /// 项目路径
+    pub project: Option<String>,
+    /// 只列出使用过该模型的会话
+    pub model: Option<String>,
+    /// 按会话 ID 或项目路径模糊搜索
+    pub search: Option<String>,
+    pub offset: Option<u32>,
+    /// 每页数量（默认 50）
+    pub limit: Option<u32>,
+    /// 费用货币（默认 USD）
+    pub currency: Option<String>,
+}
+
+/// 会话中一个模型的用量
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionModel {
+    pub model: String,
+    pub request_count: u64,
+    pub total_tokens: u64,
+    pub cost: f64,
+}
+
+/// 会话概要
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionSummary {
+    pub session_id: String,
+    /// 记录的应用类型（如 claude_local）
+    pub app_type: String,
+    pub project: Option<String>,
+    pub started_at: i64,
+    pub ended_at: i64,
+    pub request_count: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_read_tokens: u64,
+    pub cache_creation_tokens: u64,
+    pub cost: f64,
+    /// 按费用从高到低排列
+    pub models: Vec<SessionModel>,
+    /// 以下来自 session_stats，没有会话统计时为 0
+    pub conversation_count: u64,
+    pub tool_call_count: u64,
+    pub files_changed: u64,
+    pub lines_added: u64,
+    pub lines_deleted: u64,
+}
+
+/// 一页会话（按最后活动时间倒序）
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionPage {
+    pub currency: String,
+    /// 符合筛选条件的会话总数
+    pub total: u64,
+    pub offset: u32,
+    pub limit: u32,
+    pub sessions: Vec<SessionSummary>,
+}
+
+/// 会话内的一个请求
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionRequest {
+    pub request_id: String,
+    pub created_at: i64,
+    pub provider_id: String,
+    pub model: String,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_read_tokens: u64,
+    pub cache_creation_tokens: u64,
+    pub cost: f64,
+    pub latency_ms: u64,
+}
+
+/// 会话内某个工具的调用次数
+///
+/// 本地日志只记录每个会话各工具的调用总数，没有单次调用的时间，`recorded_at` 为统计导入时间。
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionToolCall {
+    pub tool_name: String,
+    pub call_count: u64,
+    pub recorded_at: i64,
+}
+
+/// 会话时间线
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionTimeline {
+    pub currency: String,
+    pub session: SessionSummary,
+    /// 按时间顺序
+    pub requests: Vec<SessionRequest>,
+    /// 按调用次数从高到低
+    pub tool_calls: Vec<SessionToolCall>,
+}
+
+/// 按会话聚合请求的公共查询，?1-?6 依次为起止时间、应用、项目、模型、搜索词
+const SESSION_CTE: &str = "
+    WITH sessions AS (
+        SELECT session_id,
+               MIN(app_type) AS app_type,
+               MAX(project) AS project,
+               MIN(created_at) AS started_at,
+               MAX(created_at) AS ended_at,
+               COUNT(*) AS request_count,
+               COALESCE(SUM(input_tokens), 0) AS input_tokens,
+               COALESCE(SUM(output_tokens), 0) AS output_tokens,
+               COALESCE(SUM(cache_read_tokens), 0) AS cache_read_tokens,
+               COALESCE(SUM(cache_creation_tokens), 0) AS cache_creation_tokens,
+               COALESCE(SUM(CAST(total_cost_usd AS REAL)), 0) AS cost
+        FROM proxy_request_logs
+        WHERE session_id IS NOT NULL
+          AND (?1 IS NULL OR created_at >= ?1)
+          AND (?2 IS NULL OR created_at <= ?2)
+          AND (?3 IS NULL OR app_type = ?3 OR app_type = ?3 || '_local')
+          AND (?4 IS NULL OR project = ?4)
+        GROUP BY session_id
+        HAVING (?5 IS NULL OR SUM(model = ?5) > 0)
+           AND (?6 IS NULL OR session_id LIKE ?6 OR COALESCE(MAX(project), '') LIKE ?6)
+    )";
+
+const SESSION_COLUMNS: &str = "
+    s.session_id, s.app_type, s.project, s.started_at, s.ended_at, s.request_count,
+    s.input_tokens, s.output_tokens, s.cache_read_tokens, s.cache_creation_tokens, s.cost,
+    COALESCE(st.conversation_count, 0), COALESCE(st.tool_call_count, 0), COALESCE(st.files_changed, 0),
+    COALESCE(st.lines_added, 0), COALESCE(st.lines_deleted, 0)";
+
+/// 列出会话（按最后活动时间倒序分页）
+pub fn list(db: &Database, options: &SessionListOptions) -> Result<SessionPage, AppError> {
+    let (currency, rate) = currency_rate(db, options.currency.as_deref())?;
+    let conn = db.read_conn()?;
+    let search = options
+        .search
+        .as_deref()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| format!("%{s}%"));
+    let filters = rusqlite::params![
+        options.start_time,
+        options.end_time,
+        options.app,
+        options.project,
+        options.model,
+        search,
+    ];
+
+    let total: i64 = conn
+        .query_row(&format!("{SESSION_CTE} SELECT COUNT(*) FROM sessions"), filters, |row| row.get(0))
+        .map_err(|e| AppError::Database(format!("统计会话数失败: {e}")))?;
+
+    let offset = options.offset.unwrap_or(0);
+    let limit = options.limit.filter(|l| *l > 0).unwrap_or(DEFAULT_PAGE_SIZE);
+    let sql = format!(
+        "{SESSION_CTE}
+         SELECT {SESSION_COLUMNS}
+         FROM sessions s LEFT JOIN session_stats st ON st.session_id = s.session_id
+         ORDER BY s.ended_at DESC, s.session_id
+         LIMIT {limit} OFFSET {offset}"
+    );
+    let mut sessions = query_sessions(&conn, &sql, filters, rate)?;
+    for session in &mut sessions {
+        session.models = session_models(&conn, &session.session_id, options, rate)?;
+    }
+
+    Ok(SessionPage {
+        currency,
+        total: total.max(0) as u64,
+        offset,
+        limit,
+        sessions,
+    })
+}
+
+/// 单个会话的时间线，会话不存在时返回 None
+pub fn timeline(db: &Database, session_id: &str, currency: Option<&str>) -> Result<Option<SessionTimeline>, AppError> {
+    let (currency, rate) = currency_rate(db, currency)?;
+    let conn = db.read_conn()?;
+
+    let sql = format!(
+        "{SESSION_CTE}
+         SELECT {SESSION_COLUMNS}
+         FROM sessions s LEFT JOIN session_stats st ON st.session_id = s.session_id
+         WHERE s.session_id = ?7"
+    );
+    let params = rusqlite::params![
+        None::<i64>,
+        None::<i64>,
+        None::<String>,
+        None::<String>,
+        None::<String>,
+        None::<String>,
+        session_id,
+    ];
+    let Some(mut session) = query_sessions(&conn, &sql, params, rate)?.pop() else {
+        return Ok(None);
+    };
+    session.models = session_models(&conn, session_id, &SessionListOptions::default(), rate)?;
+
+    let read = |e: rusqlite::Error| AppError::Database(format!("查询会话请求失败: {e}"));
+    let mut stmt = conn
+        .prepare(
+            "SELECT request_id, created_at, provider_id, model, input_tokens, output_tokens,
+                    cache_read_tokens, cache_creation_tokens, CAST(total_cost_usd AS REAL), latency_ms
+             FROM proxy_request_logs
+             WHERE session_id = ?1
+             ORDER BY created_at, request_id",
+        )
+        .map_err(read)?;
+    let requests = stmt
+        .query_map([session_id], |row| {
+            Ok(SessionRequest {
+                request_id: row.get(0)?,
+                created_at: row.get(1)?,
+                provider_id: row.get(2)?,
+                model: row.get(3)?,
+                input_tokens: count(row, 4)?,
+                output_tokens: count(row, 5)?,
+                cache_read_tokens: count(row, 6)?,
+                cache_creation_tokens: count(row, 7)?,
+                cost: row.get::<_, Option<f64>>(8)?.unwrap_or(0.0) * rate,
+                latency_ms: count(row, 9)?,
+            })
+        })
+        .map_err(read)?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(read)?;
+
+    // 每次导入都会为会话重新写入工具调用统计，每个工具取最近一次的次数
+    let read = |e: rusqlite::Error| AppError::Database(format!("查询工具调用失败: {e}"));
+    let mut stmt = conn
+        .prepare(
+            "SELECT tool_name, call_count, created_at
+             FROM tool_calls
+             WHERE session_id = ?1
+             ORDER BY call_count DESC, tool_name",
+        )
+        .map_err(read)?;
+    let tool_calls = stmt
+        .query_map([session_id], |row| {
+            Ok(SessionToolCall {
+                tool_name: row.get(0)?,
+                call_count: count(row, 1)?,
+                recorded_at: row.get(2)?,
+            })
+        })
+        .map_err(read)?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(read)?;
+
+    Ok(Some(SessionTimeline {
+        currency,
+        session,
+        requests,
+        tool_calls,
+    }))
+}
+
+fn currency_rate(db: &Database, currency: Option<&str>) -> Result<(String, f64), AppError> {
+    report_currency(
+        db,
+        &ReportOptions {
+            currency: currency.map(str::to_string),
+            ..Default::default()
+        },
+    )
+}
+
+fn count(row: &rusqlite::Row, idx: usize) -> rusqlite::Result<u64> {
+    Ok(row.get::<_, Option<i64>>(idx)?.unwrap_or(0).max(0) as u64)
+}
+
+fn query_sessions(
+    conn: &rusqlite::Connection,
+    sql: &str,
+    params: impl rusqlite::Params,
+    rate: f64,
+) -> Result<Vec<SessionSummary>, AppError> {
+    let read = |e: rusqlite::Error| AppError::Database(format!("查询会话失败: {e}"));
+    let mut stmt = conn.prepare(sql).map_err(read)?;
+    let sessions = stmt
+        .query_map(params, |row| {
+            Ok(SessionSummary {
+                session_id: row.get(0)?,
+                app_type: row.get(1)?,
+                project: row.get(2)?,
+                started_at: row.get(3)?,
+                ended_at: row.get(4)?,
+                request_count: count(row, 5)?,
+                input_tokens: count(row, 6)?,
+                output_tokens: count(row, 7)?,
+                cache_read_tokens: count(row, 8)?,
+                cache_creation_tokens: count(row, 9)?,
+                cost: row.get::<_, f64>(10)? * rate,
+                models: Vec::new(),
+                conversation_count: count(row, 11)?,
+                tool_call_count: count(row, 12)?,
+                files_changed: count(row, 13)?,
+                lines_added: count(row, 14)?,
+                lines_deleted: count(row, 15)?,
+            })
+        })
+        .map_err(read)?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(read)?;
+    Ok(sessions)
+}
+
+/// 会话的模型构成（与列表使用相同的时间筛选）
+fn session_models(
+    conn: &rusqlite::Connection,
+    session_id: &str,
+    options: &SessionListOptions,
+    rate: f64,
+) -> Result<Vec<SessionModel>, AppError> {
+    let read = |e: rusqlite::Error| AppError::Database(format!("查询会话模型失败: {e}"));
+    let mut stmt = conn
+        .prepare_cached(
+            "SELECT model, COUNT(*),
+                    COALESCE(SUM(input_tokens + output_tokens + cache_read_tokens + cache_creation_tokens), 0),
+                    COALESCE(SUM(CAST(total_cost_usd AS REAL)), 0)
+             FROM proxy_request_logs
+             WHERE session_id = ?1
+               AND (?2 IS NULL OR created_at >= ?2)
+               AND (?3 IS NULL OR created_at <= ?3)
+             GROUP BY model",
+        )
+        .map_err(read)?;
+    let mut models = stmt
+        .query_map(rusqlite::params![session_id, options.start_time, options.end_time], |row| {
+            Ok(SessionModel {
+                model: row.get(0)?,
+                request_count: count(row, 1)?,
+                total_tokens: count(row, 2)?,
+                cost: row.get::<_, f64>(3)? * rate,
+            })
+        })
+        .map_err(read)?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(read)?;
+    models.sort_by(|a, b| b.cost.total_cmp(&a.cost).then_with(|| b.request_count.cmp(&a.request_count)));
+    Ok(models)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::fixtures::{execute, insert, Record};
+
+    /// 会话 s1（两条记录，含会话统计和工具调用）、s2 和一条没有会话的记录
+    fn seed() -> Database {
+        let db = Database::memory().unwrap();
+        for (id, session_id, model, cost, created_at) in [
+            ("a1", Some("s1"), "claude-sonnet-4", "0.5", 1_000),
+            ("a2", Some("s1"), "claude-opus-4", "2", 1_100),
+            ("b1", Some("s2"), "claude-sonnet-4", "0.1", 2_000),
+            ("proxy", None, "claude-sonnet-4", "9", 3_000),
+        ] {
+            insert(
+                &db,
+                Record {
+                    id,
+                    session_id,
+                    model,
+                    cost,
+                    created_at,
+                    provider_id: "anthropic",
+                    provider_name: "Anthropic",
+                    app_type: "claude_local",
+                    input: 100,
+                    output: 10,
+                    project: Some("/work/app"),
+                    ..Default::default()
+                },
+            );
+        }
+        execute(
+            &db,
+            "INSERT INTO session_stats (session_id, source, tool_call_count, lines_added, created_at, updated_at)
+                 VALUES ('s1', 'claude', 3, 40, 1000, 1000);
+             INSERT INTO tool_calls (session_id, tool_name, call_count, created_at) VALUES
+                 ('s1', 'edit', 2, 1200), ('s1', 'read', 1, 1200);",
+        );
+        db
+    }
+
+    #[test]
+    fn lists_sessions_newest_first_with_stats() {
+        let db = seed();
+        let page = list(&db, &SessionListOptions::default()).unwrap();
+        assert_eq!(page.total, 2);
+        let ids: Vec<_> = page.sessions.iter().map(|s| s.session_id.as_str()).collect();
+        assert_eq!(ids, vec!["s2", "s1"]);
+        let s1 = &page.sessions[1];
+        assert_eq!((s1.request_count, s1.started_at, s1.ended_at), (2, 1_000, 1_100));
+        assert_eq!((s1.tool_call_count, s1.lines_added), (3, 40));
+        assert_eq!(s1.models[0].model, "claude-opus-4");
+    }
+
+    #[test]
+    fn filters_sessions_by_model() {
+        let db = seed();
+        let filtered = list(
+            &db,
+            &SessionListOptions {
+                model: Some("claude-opus-4".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(filtered.total, 1);
+        assert_eq!(filtered.sessions[0].session_id, "s1");
+    }
+
+    #[test]
+    fn builds_timeline_with_tool_calls() {
+        let db = seed();
+        let timeline = timeline(&db, "s1", None).unwrap().unwrap();
+        let requests: Vec<_> = timeline.requests.iter().map(|r| r.request_id.as_str()).collect();
+        assert_eq!(requests, vec!["a1", "a2"]);
+        let tools: Vec<_> = timeline.tool_calls.iter().map(|t| (t.tool_name.as_str(), t.call_count)).collect();
+        assert_eq!(tools, vec![("edit", 2), ("read", 1)]);
+        assert!(super::timeline(&db, "missing", None).unwrap().is_none());
+    }
+}
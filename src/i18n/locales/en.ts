@@ -745,6 +745,7 @@ export default {
     textTokens: 'Text Tokens',
     cursorTokenTooltip: 'Actual Tokens = Text Tokens + Code Context + Cache (Local only records text tokens)',
     cacheTokens: 'Cache Tokens',
+    estimatedTokens: 'Estimated',
     cacheCreation: 'Creation',
     cacheHit: 'Hits',
     totalDuration: 'Total Time',
@@ -744,6 +744,7 @@ export default {
     textTokens: 'テキストトークン',
     cursorTokenTooltip: '実際のトークン = テキストトークン + コードコンテキスト + キャッシュ等（ローカルはテキストトークンのみ記録）',
     cacheTokens: 'キャッシュトークン',
+    estimatedTokens: '推定',
     cacheCreation: '作成',
     cacheHit: 'ヒット',
     totalDuration: '累計時間',
@@ -750,6 +750,7 @@ export default {
     textTokens: '文本Token数',
     cursorTokenTooltip: '实际Token = 文本Token + 代码上下文Token + 缓存等（本地仅记录文本Token）',
     cacheTokens: '缓存 Token',
+    estimatedTokens: '估算',
     cacheCreation: '创建',
     cacheHit: '命中',
     totalDuration: '累计耗时',